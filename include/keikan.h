@@ -0,0 +1,72 @@
+/* keikan's c interface, see src/ffi.rs for the rules */
+
+#ifndef KEIKAN_H
+#define KEIKAN_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define KEIKAN_OK            0
+#define KEIKAN_NULL         -1
+#define KEIKAN_TOO_SMALL    -2
+#define KEIKAN_WRONG_KIND   -3
+#define KEIKAN_BAD_ARGUMENT -4
+#define KEIKAN_BAD_SCENE    -5
+
+#define KEIKAN_MARCH 0
+#define KEIKAN_TRACE 1
+
+#define KEIKAN_PATH          0
+#define KEIKAN_BIDIRECTIONAL 1
+#define KEIKAN_SPPM          2
+
+typedef struct KeikanScene KeikanScene;
+
+typedef struct KeikanVec3 {
+    double x, y, z;
+} KeikanVec3;
+
+typedef struct KeikanMaterial {
+    KeikanVec3 color;
+    double emission;
+    double metallic;
+    double specular;
+    double roughness;
+    double transmission;
+    double ior;
+} KeikanMaterial;
+
+/* a fov of 0 keeps the default */
+KeikanScene *keikan_scene_new(KeikanVec3 from, KeikanVec3 to, KeikanVec3 up, double fov);
+KeikanScene *keikan_scene_load(const char *path);
+void keikan_scene_free(KeikanScene *scene);
+
+int keikan_scene_set_samples(KeikanScene *scene, uint32_t samples);
+int keikan_scene_set_integrator(KeikanScene *scene, int integrator);
+
+int keikan_scene_add_sphere(KeikanScene *scene, int kind, KeikanVec3 center, double radius, KeikanMaterial material);
+int keikan_scene_add_plane(KeikanScene *scene, int kind, KeikanVec3 position, KeikanVec3 normal, KeikanMaterial material);
+int keikan_scene_add_cuboid(KeikanScene *scene, int kind, KeikanVec3 center, KeikanVec3 size, double radius, KeikanMaterial material);
+int keikan_scene_add_torus(KeikanScene *scene, int kind, KeikanVec3 center, double major, double minor, KeikanMaterial material);
+int keikan_scene_add_mandelbulb(KeikanScene *scene, int kind, KeikanVec3 center, double power, uint32_t iterations, KeikanMaterial material);
+int keikan_scene_add_mesh(
+    KeikanScene *scene,
+    const double *vertices, size_t vertex_count,
+    const uint32_t *indices, size_t index_count,
+    KeikanMaterial material
+);
+int keikan_scene_add_light(KeikanScene *scene, KeikanVec3 position, double radius, KeikanVec3 color, double strength);
+
+/* buffers hold width * height * 3 values, rows top to bottom */
+int keikan_render_rgb8(const KeikanScene *scene, size_t width, size_t height, uint8_t *buffer, size_t length);
+int keikan_render_rgbf(const KeikanScene *scene, size_t width, size_t height, float *buffer, size_t length);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
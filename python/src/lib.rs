@@ -0,0 +1,349 @@
+// explicit returns and field names are the house style
+#![allow(clippy::needless_return, clippy::redundant_field_names)]
+
+use pyo3::prelude::*;
+use pyo3::exceptions::{ PyFileNotFoundError, PyIOError, PyValueError };
+
+use keikan::structures::float::Float;
+use keikan::structures::vec3::Vec3;
+use keikan::structures::camera::Camera;
+use keikan::structures::material;
+use keikan::structures::photon_map::Emitter;
+use keikan::structures::scene;
+use keikan::render::{ try_render_image, Integrator };
+use keikan::objects::sphere::Sphere;
+use keikan::objects::plane::Plane;
+use keikan::objects::cuboid::Cuboid;
+use keikan::objects::torus::Torus;
+use keikan::objects::cylinder::Cylinder;
+use keikan::objects::capsule::Capsule;
+use keikan::objects::cone::Cone;
+use keikan::objects::hex_prism::HexPrism;
+use keikan::objects::mandelbulb::Mandelbulb;
+use keikan::objects::julia::Julia;
+use keikan::objects::menger::Menger;
+use keikan::objects::disk::Disk;
+use keikan::objects::quad::Quad;
+use keikan::objects::triangle::Triangle;
+use keikan::objects::primitive::{ MarchPrimitive, TracePrimitive };
+use keikan::write;
+use keikan::error::{ self, Error };
+
+// scenes scripted from python. vectors are plain (x, y, z) tuples:
+//
+//     import keikan
+//     gold = keikan.Material(color=(0.9, 0.8, 0.5), metallic=1.0)
+//     scene = keikan.Scene((-2, 1, 4), (0, 0, 0))
+//     scene.add_march(keikan.mandelbulb((0, 0, 0), 8.0, 10, gold))
+//     scene.add_trace(keikan.plane((0, -1, 0), (0, 1, 0)))
+//     keikan.Renderer(400, 200).save(scene, "render.png")
+
+type Tuple = (Float, Float, Float);
+
+fn vec3(v: Tuple) -> Vec3 {
+    Vec3::new(v.0, v.1, v.2)
+}
+
+fn tuple(v: Vec3) -> Tuple {
+    (v.x, v.y, v.z)
+}
+
+// the python exception closest to each of keikan's errors
+fn raise(error: Error) -> PyErr {
+    match error {
+        Error::NotFound(_) => PyFileNotFoundError::new_err(error.to_string()),
+        Error::Invalid(_) | Error::Resolution(_) | Error::Scene(_) => PyValueError::new_err(error.to_string()),
+        _ => PyIOError::new_err(error.to_string()),
+    }
+}
+
+#[pyclass]
+#[derive(Clone, Copy)]
+pub struct Material {
+    inner: material::Material,
+}
+
+#[pymethods]
+impl Material {
+    #[new]
+    #[pyo3(signature = (color = (0.8, 0.8, 0.8), emission = 0.0, metallic = 0.0, specular = 0.0, roughness = 0.5, transmission = 0.0, ior = 1.5))]
+    fn new(color: Tuple, emission: Float, metallic: Float, specular: Float, roughness: Float, transmission: Float, ior: Float) -> Material {
+        Material {
+            inner: material::Material {
+                color: vec3(color),
+                emission: emission,
+                metallic: metallic,
+                specular: specular,
+                roughness: roughness,
+                transmission: transmission,
+                ior: ior,
+                ..material::Material::blank()
+            },
+        }
+    }
+
+    #[getter] fn color(&self) -> Tuple { tuple(self.inner.color) }
+    #[setter] fn set_color(&mut self, color: Tuple) { self.inner.color = vec3(color); }
+    #[getter] fn emission(&self) -> Float { self.inner.emission }
+    #[setter] fn set_emission(&mut self, value: Float) { self.inner.emission = value; }
+    #[getter] fn metallic(&self) -> Float { self.inner.metallic }
+    #[setter] fn set_metallic(&mut self, value: Float) { self.inner.metallic = value; }
+    #[getter] fn specular(&self) -> Float { self.inner.specular }
+    #[setter] fn set_specular(&mut self, value: Float) { self.inner.specular = value; }
+    #[getter] fn roughness(&self) -> Float { self.inner.roughness }
+    #[setter] fn set_roughness(&mut self, value: Float) { self.inner.roughness = value; }
+    #[getter] fn transmission(&self) -> Float { self.inner.transmission }
+    #[setter] fn set_transmission(&mut self, value: Float) { self.inner.transmission = value; }
+    #[getter] fn ior(&self) -> Float { self.inner.ior }
+    #[setter] fn set_ior(&mut self, value: Float) { self.inner.ior = value; }
+}
+
+fn grey() -> Material {
+    Material::new((0.8, 0.8, 0.8), 0.0, 0.0, 0.0, 0.5, 0.0, 1.5)
+}
+
+// one of the built in shapes, marched, traced, or either
+#[pyclass]
+#[derive(Clone, Copy)]
+pub struct Shape {
+    march: Option<MarchPrimitive>,
+    trace: Option<TracePrimitive>,
+}
+
+fn both<T: Copy + Into<MarchPrimitive> + Into<TracePrimitive>>(shape: T) -> Shape {
+    Shape { march: Some(shape.into()), trace: Some(shape.into()) }
+}
+
+fn marched(shape: impl Into<MarchPrimitive>) -> Shape {
+    Shape { march: Some(shape.into()), trace: None }
+}
+
+fn traced(shape: impl Into<TracePrimitive>) -> Shape {
+    Shape { march: None, trace: Some(shape.into()) }
+}
+
+fn pick(material: Option<Material>) -> material::Material {
+    material.unwrap_or_else(grey).inner
+}
+
+#[pyfunction]
+#[pyo3(signature = (position, radius, material = None))]
+fn sphere(position: Tuple, radius: Float, material: Option<Material>) -> Shape {
+    both(Sphere::new(vec3(position), radius, pick(material)))
+}
+
+#[pyfunction]
+#[pyo3(signature = (position, normal, material = None))]
+fn plane(position: Tuple, normal: Tuple, material: Option<Material>) -> Shape {
+    both(Plane::new(vec3(position), vec3(normal), pick(material)))
+}
+
+// rounded edges only show up when marched
+#[pyfunction]
+#[pyo3(signature = (position, size, radius = 0.0, material = None))]
+fn cuboid(position: Tuple, size: Tuple, radius: Float, material: Option<Material>) -> Shape {
+    both(Cuboid::rounded(vec3(position), vec3(size), radius, pick(material)))
+}
+
+#[pyfunction]
+#[pyo3(signature = (position, major, minor, material = None))]
+fn torus(position: Tuple, major: Float, minor: Float, material: Option<Material>) -> Shape {
+    marched(Torus::new(vec3(position), major, minor, pick(material)))
+}
+
+#[pyfunction]
+#[pyo3(signature = (position, radius, height, material = None))]
+fn cylinder(position: Tuple, radius: Float, height: Float, material: Option<Material>) -> Shape {
+    marched(Cylinder::new(vec3(position), radius, height, pick(material)))
+}
+
+#[pyfunction]
+#[pyo3(signature = (start, end, radius, material = None))]
+fn capsule(start: Tuple, end: Tuple, radius: Float, material: Option<Material>) -> Shape {
+    marched(Capsule::new(vec3(start), vec3(end), radius, pick(material)))
+}
+
+#[pyfunction]
+#[pyo3(signature = (position, radius, height, material = None))]
+fn cone(position: Tuple, radius: Float, height: Float, material: Option<Material>) -> Shape {
+    marched(Cone::new(vec3(position), radius, height, pick(material)))
+}
+
+#[pyfunction]
+#[pyo3(signature = (position, radius, height, material = None))]
+fn hex_prism(position: Tuple, radius: Float, height: Float, material: Option<Material>) -> Shape {
+    marched(HexPrism::new(vec3(position), radius, height, pick(material)))
+}
+
+#[pyfunction]
+#[pyo3(signature = (position, power = 8.0, iterations = 10, material = None))]
+fn mandelbulb(position: Tuple, power: Float, iterations: usize, material: Option<Material>) -> Shape {
+    marched(Mandelbulb::new(vec3(position), power, iterations, pick(material)))
+}
+
+#[pyfunction]
+#[pyo3(signature = (position, c, iterations = 10, material = None))]
+fn julia(position: Tuple, c: [Float; 4], iterations: usize, material: Option<Material>) -> Shape {
+    marched(Julia::new(vec3(position), c, iterations, pick(material)))
+}
+
+#[pyfunction]
+#[pyo3(signature = (position, size, iterations = 4, material = None))]
+fn menger(position: Tuple, size: Float, iterations: usize, material: Option<Material>) -> Shape {
+    marched(Menger::new(vec3(position), size, iterations, pick(material)))
+}
+
+#[pyfunction]
+#[pyo3(signature = (position, normal, radius, material = None))]
+fn disk(position: Tuple, normal: Tuple, radius: Float, material: Option<Material>) -> Shape {
+    traced(Disk::new(vec3(position), vec3(normal), radius, pick(material)))
+}
+
+#[pyfunction]
+#[pyo3(signature = (corner, u, v, material = None))]
+fn quad(corner: Tuple, u: Tuple, v: Tuple, material: Option<Material>) -> Shape {
+    traced(Quad::new(vec3(corner), vec3(u), vec3(v), pick(material)))
+}
+
+#[pyfunction]
+#[pyo3(signature = (a, b, c, material = None))]
+fn triangle(a: Tuple, b: Tuple, c: Tuple, material: Option<Material>) -> Shape {
+    traced(Triangle::new(vec3(a), vec3(b), vec3(c), pick(material)))
+}
+
+#[pyclass]
+pub struct Scene {
+    inner: scene::Scene,
+}
+
+#[pymethods]
+impl Scene {
+    #[new]
+    #[pyo3(signature = (origin, target, up = (0.0, 1.0, 0.0), fov = None))]
+    fn new(origin: Tuple, target: Tuple, up: Tuple, fov: Option<Float>) -> Scene {
+        let mut camera = Camera::new(vec3(origin), vec3(target), vec3(up));
+        if let Some(fov) = fov { camera.fov = fov; }
+        return Scene { inner: scene::Scene::new(camera) };
+    }
+
+    // a json scene file, see keikan::import::scene_file
+    #[staticmethod]
+    fn load(path: &str) -> PyResult<Scene> {
+        let inner = scene::Scene::from_file(path).map_err(raise)?;
+        return Ok(Scene { inner: inner });
+    }
+
+    fn add_march(&mut self, shape: &Shape) -> PyResult<()> {
+        let shape = shape.march.ok_or_else(|| PyValueError::new_err("this shape can only be traced"))?;
+        self.inner.add_march(shape);
+        return Ok(());
+    }
+
+    fn add_trace(&mut self, shape: &Shape) -> PyResult<()> {
+        let shape = shape.trace.ok_or_else(|| PyValueError::new_err("this shape can only be marched"))?;
+        self.inner.add_trace(shape);
+        return Ok(());
+    }
+
+    // a glowing ball the camera sees and the bidirectional integrator starts paths from
+    #[pyo3(signature = (position, radius, color = (1.0, 1.0, 1.0), strength = 1.0))]
+    fn add_light(&mut self, position: Tuple, radius: Float, color: Tuple, strength: Float) {
+        let glow = Material::new(color, strength, 0.0, 0.0, 0.5, 0.0, 1.5);
+        self.inner.add_trace(Sphere::new(vec3(position), radius, glow.inner));
+        self.inner.emitters.push(Emitter::sphere(vec3(position), radius, vec3(color) * strength));
+    }
+
+    #[getter]
+    fn integrator(&self) -> &'static str {
+        match self.inner.integrator {
+            Integrator::Path => "path",
+            Integrator::Bidirectional => "bidirectional",
+            Integrator::Sppm => "sppm",
+        }
+    }
+
+    #[setter]
+    fn set_integrator(&mut self, name: &str) -> PyResult<()> {
+        self.inner.integrator = match name {
+            "path" => Integrator::Path,
+            "bidirectional" => Integrator::Bidirectional,
+            "sppm" => Integrator::Sppm,
+            _ => return Err(PyValueError::new_err(format!("no integrator called {}", name))),
+        };
+        return Ok(());
+    }
+
+    #[getter] fn samples(&self) -> u32 { self.inner.samples }
+    #[setter] fn set_samples(&mut self, samples: u32) { self.inner.samples = samples.max(1); }
+
+    fn __len__(&self) -> usize {
+        self.inner.march.len() + self.inner.trace.len()
+    }
+}
+
+// renders scenes at one resolution. the gil is let go while rendering,
+// so other python threads keep going.
+#[pyclass]
+pub struct Renderer {
+    #[pyo3(get)]
+    width: usize,
+    #[pyo3(get)]
+    height: usize,
+}
+
+#[pymethods]
+impl Renderer {
+    #[new]
+    fn new(width: usize, height: usize) -> PyResult<Renderer> {
+        let [width, height] = error::resolution([width, height]).map_err(raise)?;
+        return Ok(Renderer { width: width, height: height });
+    }
+
+    // rows top to bottom of linear (r, g, b) tuples. a scene that can't be
+    // rendered, see Scene::check, raises a ValueError saying why.
+    fn render(&self, py: Python, scene: &Scene) -> PyResult<Vec<Vec<Tuple>>> {
+        let scene = &scene.inner;
+        let (image, _) = py.allow_threads(|| try_render_image(scene, [self.width, self.height])).map_err(raise)?;
+        return Ok(image.into_iter().map(|row| row.into_iter().map(tuple).collect()).collect());
+    }
+
+    // rgb bytes rows top to bottom, tone mapped the same way as saved pngs.
+    // numpy.frombuffer(data, numpy.uint8).reshape(height, width, 3) makes an image of it.
+    fn render_bytes(&self, py: Python, scene: &Scene) -> PyResult<Vec<u8>> {
+        let scene = &scene.inner;
+        let (image, _) = py.allow_threads(|| try_render_image(scene, [self.width, self.height])).map_err(raise)?;
+        return Ok(image.iter().flatten().flat_map(|pixel| pixel.colorize()).collect());
+    }
+
+    fn save(&self, py: Python, scene: &Scene, path: &str) -> PyResult<()> {
+        let scene = &scene.inner;
+        let (image, _) = py.allow_threads(|| try_render_image(scene, [self.width, self.height])).map_err(raise)?;
+        return write::png(image, path.to_string()).map_err(raise);
+    }
+}
+
+#[pymodule]
+#[pyo3(name = "keikan")]
+fn init(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Material>()?;
+    m.add_class::<Shape>()?;
+    m.add_class::<Scene>()?;
+    m.add_class::<Renderer>()?;
+
+    m.add_function(wrap_pyfunction!(sphere, m)?)?;
+    m.add_function(wrap_pyfunction!(plane, m)?)?;
+    m.add_function(wrap_pyfunction!(cuboid, m)?)?;
+    m.add_function(wrap_pyfunction!(torus, m)?)?;
+    m.add_function(wrap_pyfunction!(cylinder, m)?)?;
+    m.add_function(wrap_pyfunction!(capsule, m)?)?;
+    m.add_function(wrap_pyfunction!(cone, m)?)?;
+    m.add_function(wrap_pyfunction!(hex_prism, m)?)?;
+    m.add_function(wrap_pyfunction!(mandelbulb, m)?)?;
+    m.add_function(wrap_pyfunction!(julia, m)?)?;
+    m.add_function(wrap_pyfunction!(menger, m)?)?;
+    m.add_function(wrap_pyfunction!(disk, m)?)?;
+    m.add_function(wrap_pyfunction!(quad, m)?)?;
+    m.add_function(wrap_pyfunction!(triangle, m)?)?;
+
+    return Ok(());
+}
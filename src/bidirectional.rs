@@ -0,0 +1,285 @@
+use crate::structures::float::Float;
+use crate::structures::float::consts::PI;
+use rand::Rng;
+
+use crate::structures::vec3::Vec3;
+use crate::structures::ray::Ray;
+use crate::structures::scene::Scene;
+use crate::structures::material::Material;
+use crate::render::{ cast_ray, offset, reflect, power, sky_light, pick_emitter };
+use crate::objects::visible::RayKind;
+
+// longest subpaths on either side, counted in surface vertices
+const CAMERA_VERTICES: usize = 4;
+const LIGHT_VERTICES: usize = 4;
+const EPSILON: Float = 0.002;
+
+// bidirectional path tracing: grow a path from the camera and one from a
+// light, then join every vertex of one to every vertex of the other.
+// light that has to squeeze through a small gap is easy to find from the
+// light's side, which is where the plain path tracer falls apart.
+//
+// surfaces are treated as lambertian, or as perfect mirrors when mostly
+// metallic; mirrors can't be joined through, only bounced off.
+// lights come from `scene.emitters`, so emissive surfaces only show up
+// when the camera looks straight at them, and the sky is only found by
+// the camera's own path, which looks for an environment map's bright
+// parts from each diffuse vertex too. with a light tree the light is picked by how
+// much it's likely to matter where the camera's path starts, otherwise
+// any one as often as another. media and volumes are ignored.
+
+#[derive(Debug, Copy, Clone)]
+struct Vertex {
+    position: Vec3,
+    normal: Vec3, // facing the side the path arrived from
+    material: Material,
+    throughput: Vec3,
+    specular: bool,
+}
+
+fn is_specular(material: &Material) -> bool {
+    material.metallic >= 0.5
+}
+
+// cosine weighted around the normal
+fn sample_hemisphere(normal: Vec3, rng: &mut impl Rng) -> Vec3 {
+    let (u, v): (Float, Float) = (rng.gen(), rng.gen());
+    let radius = u.sqrt();
+    let phi = 2.0 * PI * v;
+
+    let helper = if normal.x.abs() > 0.9 { Vec3::new(0.0, 1.0, 0.0) } else { Vec3::new(1.0, 0.0, 0.0) };
+    let a = normal.cross(&helper).unit();
+    let b = normal.cross(&a);
+
+    return (a * (radius * phi.cos()) + b * (radius * phi.sin()) + normal * (1.0 - u).max(0.0).sqrt()).unit();
+}
+
+// follows a ray around the scene, adding a vertex at each surface it lands
+// on. returns whatever emission ends the walk, already weighted. `first`
+// is what kind of ray starts it off, every one after that is indirect.
+fn walk(scene: &Scene, mut ray: Ray, first: RayKind, mut throughput: Vec3, limit: usize, vertices: &mut Vec<Vertex>, rng: &mut impl Rng) -> Vec3 {
+    let mut emitted = Vec3::new(0.0, 0.0, 0.0);
+    let mut kind = first;
+
+    while vertices.len() < limit {
+        let cast = cast_ray(scene, ray, kind);
+        let (hit, distance, normal, material) = cast.unpack();
+        kind = RayKind::Indirect;
+
+        if !hit {
+            // an environment map was looked for from the last vertex too, see radiance
+            let share = match (&scene.environment_map, vertices.last()) {
+                (Some(map), Some(last)) if !last.specular => power(last.normal.dot(&ray.direction) / PI, map.pdf(ray.direction)),
+                _ => 1.0,
+            };
+            emitted = throughput * material.color * material.emission * share;
+            break;
+        }
+
+        let position = ray.point_at(&distance);
+
+        // lights absorb what lands on them, and only count if seen directly
+        // or in a mirror, since nothing else could've found them
+        if material.emission > 0.0 {
+            if vertices.iter().all(|v| v.specular) { emitted = throughput * material.color * material.emitted(cast.front_face); }
+            break;
+        }
+
+        let specular = is_specular(&material);
+        vertices.push(Vertex { position: position, normal: normal, material: material, throughput: throughput, specular: specular });
+
+        let direction = if specular { reflect(ray.direction, normal).unit() } else { sample_hemisphere(normal, rng) };
+
+        throughput = throughput * material.color;
+        ray = Ray::new(offset(position, normal, direction), direction);
+    }
+
+    return emitted;
+}
+
+fn visible(scene: &Scene, from: Vec3, to: Vec3) -> bool {
+    let offset = to - from;
+    let distance = offset.length();
+
+    return !scene.occluded(Ray::new(from, offset / distance).with_max(distance - 2.0 * EPSILON));
+}
+
+// uniform over the unit sphere
+fn sample_sphere(rng: &mut impl Rng) -> Vec3 {
+    let z = 1.0 - 2.0 * rng.gen::<Float>();
+    let radius = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * rng.gen::<Float>();
+
+    return Vec3::new(radius * phi.cos(), radius * phi.sin(), z);
+}
+
+// how many ways there are of making a path out of this chain of vertices,
+// camera end first, so each can be weighted evenly. every edge is a place
+// it could've been joined, as long as neither end is a mirror and neither
+// side would be longer than its subpaths ever get.
+fn strategies(chain: &[bool]) -> usize {
+    (1..chain.len()).filter(|t| {
+        *t <= CAMERA_VERTICES && chain.len() - t <= LIGHT_VERTICES + 1 && !chain[t - 1] && !chain[*t]
+    }).count()
+}
+
+pub fn radiance(scene: &Scene, ray: Ray, rng: &mut impl Rng) -> Vec3 {
+
+    let mut camera = vec![];
+    let mut total = walk(scene, ray, RayKind::Camera, Vec3::new(1.0, 1.0, 1.0), CAMERA_VERTICES, &mut camera, rng);
+
+    // an environment map, straight onto each diffuse vertex the walk went on
+    // from, shared with the walk's own bounces into it
+    if let Some(map) = &scene.environment_map {
+        for z in camera.iter().take(CAMERA_VERTICES - 1).filter(|z| !z.specular) {
+            total = total + z.throughput * z.material.color * sky_light(scene, map, z.position, z.normal, 1.0, rng);
+        }
+    }
+
+    if scene.emitters.is_empty() { return total; }
+
+    // pick a light and somewhere on it, dividing by the chance of each
+    let seen = camera.first().map_or(ray.origin, |vertex| vertex.position);
+    let (index, chance) = pick_emitter(scene, seen, rng.gen());
+    let emitter = scene.emitters[index];
+    let picked = 1.0 / chance;
+
+    let outward = sample_sphere(rng);
+    let origin = emitter.position + outward * emitter.radius;
+    let area = 4.0 * PI * emitter.radius * emitter.radius;
+
+    // spheres leave in a cosine lobe from their surface, points evenly all around.
+    // `alpha` is what a join straight onto the light carries, `leaving` what
+    // a walk out of it does.
+    let (alpha, leaving, direction) = if area > 0.0 {
+        let radiance = emitter.power / (area * PI);
+        (radiance * area * picked, radiance * area * PI * picked, sample_hemisphere(outward, rng))
+    } else {
+        let intensity = emitter.power / (4.0 * PI);
+        (intensity * picked, emitter.power * picked, outward)
+    };
+
+    let mut light = vec![];
+    walk(scene, Ray::new(origin + outward * EPSILON, direction), RayKind::Indirect, leaving, LIGHT_VERTICES, &mut light, rng);
+
+    for (t, z) in camera.iter().enumerate() {
+        if z.specular { continue; }
+
+        let mut chain: Vec<bool> = camera[..=t].iter().map(|v| v.specular).collect();
+
+        // join straight onto the light
+        let to_light = origin - z.position;
+        let d2 = to_light.length_squared();
+        let w = to_light / d2.sqrt();
+        let cos_z = z.normal.dot(&w);
+        let cos_y = if area > 0.0 { -outward.dot(&w) } else { 1.0 };
+
+        if cos_z > 0.0 && cos_y > 0.0 && visible(scene, offset(z.position, z.normal, w), origin) {
+            chain.push(false);
+            let weight = 1.0 / strategies(&chain) as Float;
+            chain.pop();
+
+            let contribution = z.throughput * (z.material.color / PI) * alpha * (cos_z * cos_y / d2);
+            total = total + contribution * weight;
+        }
+
+        // and onto every diffuse vertex of the light's path
+        for (s, y) in light.iter().enumerate() {
+            if y.specular { continue; }
+
+            let between = y.position - z.position;
+            let d2 = between.length_squared();
+            let w = between / d2.sqrt();
+            let cos_z = z.normal.dot(&w);
+            let cos_y = -y.normal.dot(&w);
+
+            if cos_z <= 0.0 || cos_y <= 0.0 { continue; }
+            if !visible(scene, offset(z.position, z.normal, w), offset(y.position, y.normal, w * -1.0)) { continue; }
+
+            let mut joined = chain.clone();
+            joined.extend(light[..=s].iter().rev().map(|v| v.specular));
+            joined.push(false);
+
+            let weight = 1.0 / strategies(&joined) as Float;
+            let contribution = z.throughput * (z.material.color / PI)
+                * (y.material.color / PI) * y.throughput * (cos_z * cos_y / d2);
+            total = total + contribution * weight;
+        }
+    }
+
+    return total;
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::{ strategies, radiance };
+    use crate::structures::float::Float;
+    use crate::structures::float::consts::PI;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::ray::Ray;
+    use crate::structures::camera::Camera;
+    use crate::structures::material::Material;
+    use crate::structures::photon_map::Emitter;
+    use crate::structures::light_tree::LightTree;
+    use crate::structures::scene::Scene;
+    use crate::objects::plane::Plane;
+    use crate::render::test::sunny;
+
+    #[test]
+    fn test_strategies() {
+        // two surfaces and the light can be joined either side of the middle
+        assert_eq!(strategies(&[false, false, false]), 2);
+        // a mirror rules out both joins touching it
+        assert_eq!(strategies(&[false, true, false, false]), 1);
+    }
+
+    #[test]
+    fn test_light_tree() {
+        // a floor under a grid of small lamps, most of them far off
+        let camera = Camera::new(Vec3::new(0.0, 4.0, 4.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let mut scene = Scene::new(camera);
+        scene.environment = Vec3::new(0.0, 0.0, 0.0);
+        let floor = Material { emission: 0.0, ..Material::blank() };
+        scene.add_trace(Plane::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), floor));
+        for x in -10..10 {
+            for z in -10..10 {
+                scene.emitters.push(Emitter::new(Vec3::new(x as Float * 3.0, 2.0, z as Float * 3.0), 0.0, Vec3::new(4.0, 4.0, 4.0)));
+            }
+        }
+
+        // straight down onto the floor, where all of it is direct light
+        let ray = Ray::new(Vec3::new(0.5, 3.0, 0.5), Vec3::new(0.0, -1.0, 0.0));
+        let point = Vec3::new(0.5, 0.0, 0.5);
+        let exact: Float = scene.emitters.iter().map(|emitter| {
+            let to = emitter.position - point;
+            let intensity = emitter.power.luminance() / (4.0 * PI);
+            (floor.color.luminance() / PI) * intensity * to.unit().y / to.length_squared()
+        }).sum();
+
+        // the same light either way, only much less noisy picked by the tree
+        let mut rng = rand::thread_rng();
+        let mut estimate = |scene: &Scene| {
+            let samples: Vec<Float> = (0..20000).map(|_| radiance(scene, ray, &mut rng).luminance()).collect();
+            let mean = samples.iter().sum::<Float>() / 20000.0;
+            let variance = samples.iter().map(|sample| (sample - mean) * (sample - mean)).sum::<Float>() / 20000.0;
+            (mean, variance)
+        };
+
+        let (_, even) = estimate(&scene);
+        scene.light_tree = LightTree::new(&scene.emitters);
+        let (mean, picked) = estimate(&scene);
+        assert!((mean - exact).abs() < 0.05 * exact);
+        assert!(picked < 0.25 * even);
+    }
+
+    #[test]
+    fn test_environment_map() {
+        // no emitters, only the sun in the map, found from the floor
+        let (scene, expected) = sunny();
+        let down = Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let rng = &mut rand::thread_rng();
+        let n = 4000;
+        let mean = (0..n).map(|_| radiance(&scene, down, rng).x).sum::<Float>() / n as Float;
+        assert!((mean - expected).abs() < 0.03 * expected, "{} against {}", mean, expected);
+    }
+}
@@ -0,0 +1,95 @@
+use image::{ ImageBuffer, Rgb, RgbImage };
+
+use crate::structures::vec3::Vec3;
+use crate::structures::camera::Camera;
+use crate::structures::material::Material;
+use crate::structures::scene::Scene;
+use crate::render::render_image;
+use crate::objects::sphere::Sphere;
+use crate::objects::plane::Plane;
+
+// small renders of lots of scenes side by side in one image, for looking
+// over a material library or a sweep of some procedural parameter at once.
+// make one scene per variant and hand them all to `sheet`.
+
+pub const THUMBNAIL: [usize; 2] = [64, 64];
+const GAP: u32 = 2; // pixels of background between thumbnails
+const BACKGROUND: Rgb<u8> = Rgb([24, 24, 24]);
+const SAMPLES: u32 = 4; // camera rays per pixel in `preview` scenes
+
+// a scene rendered at `size` and tone mapped the same way write::png does,
+// so it looks like a tiny version of the full render
+pub fn thumbnail(scene: &Scene, size: [usize; 2]) -> RgbImage {
+    let (image, _) = render_image(scene, size);
+    ImageBuffer::from_fn(size[0] as u32, size[1] as u32, |x, y| Rgb(image[y as usize][x as usize].colorize()))
+}
+
+// thumbnails of every scene, in rows of `columns` from the top left
+pub fn sheet(scenes: &[Scene], size: [usize; 2], columns: usize) -> RgbImage {
+    let columns = columns.clamp(1, scenes.len().max(1)) as u32;
+    let rows = (scenes.len() as u32).div_ceil(columns);
+    let (width, height) = (size[0] as u32, size[1] as u32);
+
+    let mut sheet = ImageBuffer::from_pixel(
+        columns * width + (columns + 1) * GAP,
+        rows * height + (rows + 1) * GAP,
+        BACKGROUND,
+    );
+
+    for (index, scene) in scenes.iter().enumerate() {
+        let (column, row) = (index as u32 % columns, index as u32 / columns);
+        let (left, top) = (GAP + column * (width + GAP), GAP + row * (height + GAP));
+
+        for (x, y, pixel) in thumbnail(scene, size).enumerate_pixels() {
+            sheet.put_pixel(left + x, top + y, *pixel);
+        }
+    }
+
+    return sheet;
+}
+
+// a ball of the material sitting on a grey floor under the sky
+pub fn preview(material: Material) -> Scene {
+    let floor = Material { color: Vec3::new(0.5, 0.5, 0.5), emission: 0.0, ..Material::blank() };
+    let camera = Camera::new(Vec3::new(0.0, 1.0, 3.0), Vec3::new(0.0, 0.5, 0.0), Vec3::new(0.0, 1.0, 0.0));
+
+    return Scene::builder()
+        .camera(camera)
+        .add(Sphere::new(Vec3::new(0.0, 0.5, 0.0), 0.5, material))
+        .add(Plane::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), floor))
+        .samples(SAMPLES)
+        .build();
+}
+
+// a sheet of `preview`s, one per material
+pub fn materials(materials: &[Material], size: [usize; 2], columns: usize) -> RgbImage {
+    let scenes: Vec<Scene> = materials.iter().map(|material| preview(*material)).collect();
+    return sheet(&scenes, size, columns);
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::{ sheet, materials, BACKGROUND, GAP };
+    use image::Rgb;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::material::Material;
+    use crate::structures::scene::Scene;
+
+    #[test]
+    fn test_contact_sheet() {
+        let red = Material { color: Vec3::new(1.0, 0.0, 0.0), emission: 0.0, ..Material::blank() };
+        let blue = Material { color: Vec3::new(0.0, 0.0, 1.0), ..red };
+
+        // three in rows of two, with a gap around each
+        let image = materials(&[red, blue, red], [4, 3], 2);
+        assert_eq!(image.dimensions(), (2 * 4 + 3 * GAP, 2 * 3 + 3 * GAP));
+        assert_eq!(*image.get_pixel(0, 0), BACKGROUND);
+        assert_eq!(*image.get_pixel(image.width() - 1, image.height() - 1), BACKGROUND);
+
+        // anything lit by nothing comes out black
+        let dark = Scene::builder().environment(Vec3::new(0.0, 0.0, 0.0)).samples(1).build();
+        let image = sheet(&[dark], [2, 2], 4);
+        assert_eq!(image.dimensions(), (2 + 2 * GAP, 2 + 2 * GAP));
+        assert_eq!(*image.get_pixel(GAP, GAP), Rgb([0, 0, 0]));
+    }
+}
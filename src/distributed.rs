@@ -0,0 +1,319 @@
+use std::fs::File;
+use std::io::{ BufReader, BufWriter, Error, ErrorKind, Read, Result, Write };
+use std::mem::size_of;
+use std::net::{ TcpListener, TcpStream, ToSocketAddrs };
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::scene::Scene;
+use crate::structures::stats::RenderStats;
+use crate::structures::tile::Tile;
+use crate::render::render_region;
+
+// spreading one frame over several machines. the frame is cut into units,
+// big tiles each worker renders on all its cores, and the pixels are sent
+// back to be merged. scenes are built in code, so every machine builds the
+// same one itself and only the resolution and units go over the wire.
+//
+// pixels are sent as raw Floats, so build everything with the same features.
+
+const UNIT: usize = 64; // pixels along each side of a unit
+const POLL: Duration = Duration::from_millis(10);
+
+// a unit is coming, or there are none left
+const MORE: u8 = 1;
+const DONE: u8 = 0;
+
+pub fn units(resolution: [usize; 2]) -> Vec<Tile> {
+    Tile::spiral(resolution, UNIT)
+}
+
+// a rendered unit
+#[derive(Debug, Clone)]
+pub struct Finished {
+    pub tile: Tile,
+    pub pixels: Vec<Vec3>, // rows of the tile, top to bottom
+}
+
+impl Finished {
+    pub fn render(scene: &Scene, resolution: [usize; 2], tile: Tile) -> (Finished, RenderStats) {
+        let (pixels, stats) = render_region(scene, resolution, tile);
+        return (Finished { tile: tile, pixels: pixels }, stats);
+    }
+
+    pub fn write(&self, out: &mut impl Write) -> Result<()> {
+        write_tile(out, &self.tile)?;
+        for pixel in &self.pixels {
+            for component in [pixel.x, pixel.y, pixel.z].iter() {
+                out.write_all(&component.to_le_bytes())?;
+            }
+        }
+        return out.flush();
+    }
+
+    pub fn read(input: &mut impl Read) -> Result<Finished> {
+        let tile = read_tile(input)?;
+        return Ok(Finished { tile: tile, pixels: read_pixels(input, tile)? });
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.write(&mut BufWriter::new(File::create(path)?))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Finished> {
+        Finished::read(&mut BufReader::new(File::open(path)?))
+    }
+}
+
+// puts rendered units back together into a frame. anything not covered stays black.
+pub fn merge(resolution: [usize; 2], finished: &[Finished]) -> Vec<Vec<Vec3>> {
+    let mut image = vec![vec![Vec3::new(0.0, 0.0, 0.0); resolution[0]]; resolution[1]];
+
+    for unit in finished {
+        let tile = unit.tile;
+        for (i, pixel) in unit.pixels.iter().enumerate() {
+            let (x, y) = (tile.x + i % tile.width, tile.y + i / tile.width);
+            if x < resolution[0] && y < resolution[1] { image[y][x] = *pixel; }
+        }
+    }
+
+    return image;
+}
+
+// the files way: each machine renders its share of `units` and saves them,
+// then they're gathered up and merged
+pub fn merge_files(resolution: [usize; 2], paths: &[impl AsRef<Path>]) -> Result<Vec<Vec<Vec3>>> {
+    let finished = paths.iter().map(Finished::load).collect::<Result<Vec<Finished>>>()?;
+    return Ok(merge(resolution, &finished));
+}
+
+// the tcp way: hands the frame out a unit at a time to every worker that
+// connects, and merges what comes back. a worker that drops its connection
+// has its unit given to someone else, so workers can come and go.
+pub fn coordinate(listener: TcpListener, resolution: [usize; 2]) -> Result<Vec<Vec<Vec3>>> {
+    let units = units(resolution);
+    let queue = Mutex::new((0..units.len()).rev().collect::<Vec<usize>>()); // popped center first
+    let complete = AtomicBool::new(false);
+    let (sender, receiver) = mpsc::channel();
+
+    listener.set_nonblocking(true)?;
+
+    thread::scope(|scope| {
+        let mut finished: Vec<Option<Finished>> = vec![None; units.len()];
+        let mut left = units.len();
+
+        while left > 0 {
+            let accepted = listener.accept().and_then(|(stream, _)| stream.set_nonblocking(false).map(|_| stream));
+            match accepted {
+                Ok(stream) => {
+                    let sender = sender.clone();
+                    let (units, queue, complete) = (&units, &queue, &complete);
+                    scope.spawn(move || hand_out(stream, resolution, units, queue, complete, sender));
+                },
+                Err(error) if error.kind() == ErrorKind::WouldBlock => {},
+                // the workers still connected are let go, or the scope
+                // would wait on them forever
+                Err(error) => {
+                    queue.lock().unwrap().clear();
+                    complete.store(true, Ordering::Relaxed);
+                    return Err(error);
+                },
+            }
+
+            // each unit counts once, whoever sent it
+            while let Ok((index, unit)) = receiver.recv_timeout(POLL) {
+                if finished[index].is_none() {
+                    finished[index] = Some(unit);
+                    left -= 1;
+                }
+            }
+        }
+
+        complete.store(true, Ordering::Relaxed);
+        let finished: Vec<Finished> = finished.into_iter().flatten().collect();
+        return Ok(merge(resolution, &finished));
+    })
+}
+
+// talks to one worker until the frame is done or it goes away
+fn hand_out(
+    mut stream: TcpStream,
+    resolution: [usize; 2],
+    units: &[Tile],
+    queue: &Mutex<Vec<usize>>,
+    complete: &AtomicBool,
+    results: mpsc::Sender<(usize, Finished)>,
+) {
+    loop {
+        let next = queue.lock().unwrap().pop();
+
+        let index = match next {
+            Some(index) => index,
+            None if complete.load(Ordering::Relaxed) => {
+                let _ = stream.write_all(&[DONE]);
+                return;
+            },
+            // the last units are still out, and one might come back
+            None => {
+                thread::sleep(POLL);
+                continue;
+            },
+        };
+
+        // the tile's checked before any room is made for its pixels
+        let answer = send_unit(&mut stream, resolution, units[index]).and_then(|_| {
+            let mut input = BufReader::new(&stream);
+            let tile = read_tile(&mut input)?;
+            if tile != units[index] { return Err(invalid("worker sent back the wrong unit")); }
+            return Ok(Finished { tile: tile, pixels: read_pixels(&mut input, tile)? });
+        });
+
+        match answer {
+            Ok(unit) => { let _ = results.send((index, unit)); },
+            Err(_) => {
+                queue.lock().unwrap().push(index);
+                return;
+            },
+        }
+    }
+}
+
+// connects to a coordinator and renders units for it until there are none left
+pub fn work(scene: &Scene, address: impl ToSocketAddrs) -> Result<RenderStats> {
+    let mut stream = TcpStream::connect(address)?;
+    let mut stats = RenderStats::default();
+
+    loop {
+        let mut tag = [0u8];
+        stream.read_exact(&mut tag)?;
+        if tag[0] == DONE { return Ok(stats); }
+
+        let resolution = [read_u64(&mut stream)? as usize, read_u64(&mut stream)? as usize];
+        let tile = read_tile(&mut stream)?;
+
+        let (unit, counted) = Finished::render(scene, resolution, tile);
+        stats.merge(&counted);
+        unit.write(&mut BufWriter::new(&stream))?;
+    }
+}
+
+fn send_unit(stream: &mut TcpStream, resolution: [usize; 2], tile: Tile) -> Result<()> {
+    stream.write_all(&[MORE])?;
+    write_u64(stream, resolution[0] as u64)?;
+    write_u64(stream, resolution[1] as u64)?;
+    write_tile(stream, &tile)?;
+    return stream.flush();
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+fn write_u64(out: &mut impl Write, value: u64) -> Result<()> {
+    out.write_all(&value.to_le_bytes())
+}
+
+fn read_u64(input: &mut impl Read) -> Result<u64> {
+    let mut raw = [0u8; 8];
+    input.read_exact(&mut raw)?;
+    return Ok(u64::from_le_bytes(raw));
+}
+
+fn read_float(input: &mut impl Read) -> Result<Float> {
+    let mut raw = [0u8; size_of::<Float>()];
+    input.read_exact(&mut raw)?;
+    return Ok(Float::from_le_bytes(raw));
+}
+
+fn write_tile(out: &mut impl Write, tile: &Tile) -> Result<()> {
+    for value in [tile.x, tile.y, tile.width, tile.height].iter() {
+        write_u64(out, *value as u64)?;
+    }
+    return Ok(());
+}
+
+fn read_tile(input: &mut impl Read) -> Result<Tile> {
+    let tile = Tile {
+        x: read_u64(input)? as usize,
+        y: read_u64(input)? as usize,
+        width: read_u64(input)? as usize,
+        height: read_u64(input)? as usize,
+    };
+
+    // a unit bigger than any frame is a garbled stream, not a real one
+    if tile.width.saturating_mul(tile.height) > 1 << 28 { return Err(invalid("tile too large")); }
+    return Ok(tile);
+}
+
+// room's only made for a unit's worth up front, so a tile that says it's
+// bigger than what's actually sent runs out of input first, not memory
+fn read_pixels(input: &mut impl Read, tile: Tile) -> Result<Vec<Vec3>> {
+    let mut pixels = Vec::with_capacity(tile.area().min(UNIT * UNIT));
+    for _ in 0..tile.area() {
+        pixels.push(Vec3::new(read_float(input)?, read_float(input)?, read_float(input)?));
+    }
+    return Ok(pixels);
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::net::TcpListener;
+    use std::thread;
+
+    use super::{ coordinate, work, units, merge, Finished };
+    use crate::structures::vec3::Vec3;
+    use crate::structures::camera::Camera;
+    use crate::structures::scene::Scene;
+    use crate::structures::material::Material;
+    use crate::objects::sphere::Sphere;
+
+    #[test]
+    fn test_distributed() {
+        let camera = Camera::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0));
+        let mut scene = Scene::new(camera);
+        let mut red = Material::blank();
+        red.color = Vec3::new(1.0, 0.0, 0.0);
+        scene.add_march(Sphere::new(Vec3::new(0.0, 0.0, -5.0), 1.0, red));
+
+        // wide enough for two units, short enough to be quick
+        let resolution = [80, 4];
+        assert_eq!(units(resolution).len(), 2);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let image = thread::scope(|scope| {
+            let coordinator = scope.spawn(move || coordinate(listener, resolution).unwrap());
+            // one of them may turn up after it's all done, and find nobody there
+            let workers: Vec<_> = (0..2).map(|_| scope.spawn(|| work(&scene, address))).collect();
+
+            for worker in workers { let _ = worker.join().unwrap(); }
+            coordinator.join().unwrap()
+        });
+
+        // the sphere sits in the middle, there's sky at the edges, and
+        // every pixel got something
+        assert!(image[2][40].x > image[2][40].z);
+        assert!(image[2][0].z > image[2][0].x);
+        assert!(image.iter().flatten().all(|pixel| pixel.total() > 0.0));
+
+        // and units survive being written out and read back
+        let unit = Finished::render(&scene, resolution, units(resolution)[0]).0;
+        let mut bytes = vec![];
+        unit.write(&mut bytes).unwrap();
+        let back = Finished::read(&mut bytes.as_slice()).unwrap();
+        assert_eq!(back.tile, unit.tile);
+        assert_eq!(merge(resolution, &[back])[0][unit.tile.x].x, unit.pixels[0].x);
+
+        // a huge tile with nothing after it is just cut short
+        let mut huge = vec![];
+        for value in [0u64, 0, 1 << 14, 1 << 14].iter() { huge.extend(&value.to_le_bytes()); }
+        assert!(Finished::read(&mut huge.as_slice()).is_err());
+    }
+}
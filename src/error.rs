@@ -0,0 +1,127 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{ Path, PathBuf };
+
+use crate::structures::validate::Diagnostic;
+
+// what can go wrong loading a scene, saving a render, or setting one up,
+// so whatever's embedding keikan can say what and carry on instead of it
+// panicking halfway through
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),            // reading or writing went wrong some other way
+    NotFound(PathBuf),        // a scene, or a mesh or font one refers to, isn't there
+    Invalid(String),          // a file that can't be made sense of, and why
+    Image(image::ImageError), // saving a picture
+    Resolution([usize; 2]),   // an image without any pixels
+    Scene(Vec<Diagnostic>),   // the errors Scene::validate found, see Scene::check
+    #[cfg(feature = "gltf")]
+    Gltf(::gltf::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    // a failure opening or reading `path`, saying which file it was
+    pub fn reading(path: &Path, error: io::Error) -> Error {
+        match error.kind() {
+            io::ErrorKind::NotFound => Error::NotFound(path.to_path_buf()),
+            io::ErrorKind::InvalidData => Error::Invalid(format!("{}: {}", path.display(), error)),
+            _ => Error::Io(error),
+        }
+    }
+
+    // a mistake found in the file at `path`, saying which file it was
+    pub fn in_file(self, path: &Path) -> Error {
+        match self {
+            Error::Invalid(message) => Error::Invalid(format!("{}: {}", path.display(), message)),
+            error => error,
+        }
+    }
+}
+
+// the whole of a file, for the format readers
+pub fn read(path: &Path) -> Result<Vec<u8>> {
+    fs::read(path).map_err(|error| Error::reading(path, error))
+}
+
+// renders can't be any smaller than a pixel
+pub fn resolution(resolution: [usize; 2]) -> Result<[usize; 2]> {
+    if resolution[0] == 0 || resolution[1] == 0 { return Err(Error::Resolution(resolution)); }
+    return Ok(resolution);
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(error) => write!(f, "{}", error),
+            Error::NotFound(path) => write!(f, "{} not found", path.display()),
+            Error::Invalid(message) => write!(f, "{}", message),
+            Error::Image(error) => write!(f, "{}", error),
+            Error::Resolution([width, height]) => write!(f, "can't render {}x{}, an image needs at least a pixel", width, height),
+            Error::Scene(errors) => {
+                let messages: Vec<&str> = errors.iter().map(|error| error.message.as_str()).collect();
+                write!(f, "{}", messages.join("; "))
+            },
+            #[cfg(feature = "gltf")]
+            Error::Gltf(error) => write!(f, "gltf: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(error) => Some(error),
+            Error::Image(error) => Some(error),
+            #[cfg(feature = "gltf")]
+            Error::Gltf(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+// invalid data from std is already worded to be shown
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Error {
+        match error.kind() {
+            io::ErrorKind::InvalidData => Error::Invalid(error.to_string()),
+            _ => Error::Io(error),
+        }
+    }
+}
+
+impl From<image::ImageError> for Error {
+    fn from(error: image::ImageError) -> Error {
+        Error::Image(error)
+    }
+}
+
+#[cfg(feature = "gltf")]
+impl From<::gltf::Error> for Error {
+    fn from(error: ::gltf::Error) -> Error {
+        Error::Gltf(error)
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::io;
+    use std::path::Path;
+
+    use super::{ Error, resolution };
+
+    #[test]
+    fn test_error() {
+        let path = Path::new("textures/wood.png");
+        let missing = Error::reading(path, io::Error::new(io::ErrorKind::NotFound, "gone"));
+        assert_eq!(missing.to_string(), "textures/wood.png not found");
+
+        let broken = Error::reading(path, io::Error::new(io::ErrorKind::InvalidData, "ply: truncated file"));
+        assert_eq!(broken.to_string(), "textures/wood.png: ply: truncated file");
+
+        assert!(resolution([640, 480]).is_ok());
+        assert!(matches!(resolution([640, 0]), Err(Error::Resolution([640, 0]))));
+    }
+}
@@ -0,0 +1,335 @@
+// the safety rules are the same for every function, see below
+#![allow(clippy::missing_safety_doc)]
+
+use std::ffi::CStr;
+use std::os::raw::{ c_char, c_int };
+use std::slice;
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::camera::Camera;
+use crate::structures::material::Material;
+use crate::structures::photon_map::Emitter;
+use crate::structures::scene::Scene;
+use crate::render::{ try_render_image, Integrator };
+use crate::objects::sphere::Sphere;
+use crate::objects::plane::Plane;
+use crate::objects::cuboid::Cuboid;
+use crate::objects::torus::Torus;
+use crate::objects::mandelbulb::Mandelbulb;
+use crate::objects::mesh::Mesh;
+use crate::objects::primitive::{ MarchPrimitive, TracePrimitive };
+
+// a c interface for embedding keikan, declared in include/keikan.h. build a
+// library with `cargo rustc --release --lib --crate-type cdylib` (or
+// staticlib) and link against it.
+//
+// scenes are opaque pointers from keikan_scene_new or keikan_scene_load,
+// and belong to the caller until handed to keikan_scene_free. pointers
+// passed in must be valid for what they point at, buffers for as long as
+// they say they are, and a scene mustn't be used from two threads at once.
+// numbers are doubles whatever Float is.
+//
+// everything returns one of these, or a pointer that's null on failure.
+
+pub const KEIKAN_OK: c_int = 0;
+pub const KEIKAN_NULL: c_int = -1;        // a pointer that can't be null was
+pub const KEIKAN_TOO_SMALL: c_int = -2;   // the buffer can't fit the image
+pub const KEIKAN_WRONG_KIND: c_int = -3;  // that shape can't be added that way
+pub const KEIKAN_BAD_ARGUMENT: c_int = -4;
+pub const KEIKAN_BAD_SCENE: c_int = -5;   // it would render black or broken, see Scene::check
+
+// add shapes to the marcher or the tracer
+pub const KEIKAN_MARCH: c_int = 0;
+pub const KEIKAN_TRACE: c_int = 1;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct KeikanVec3 {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl From<KeikanVec3> for Vec3 {
+    fn from(v: KeikanVec3) -> Vec3 {
+        Vec3::new(v.x as Float, v.y as Float, v.z as Float)
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct KeikanMaterial {
+    pub color: KeikanVec3,
+    pub emission: f64,
+    pub metallic: f64,
+    pub specular: f64,
+    pub roughness: f64,
+    pub transmission: f64,
+    pub ior: f64,
+}
+
+impl From<KeikanMaterial> for Material {
+    fn from(m: KeikanMaterial) -> Material {
+        Material {
+            color: m.color.into(),
+            emission: m.emission as Float,
+            metallic: m.metallic as Float,
+            specular: m.specular as Float,
+            roughness: m.roughness as Float,
+            transmission: m.transmission as Float,
+            ior: m.ior as Float,
+            ..Material::blank()
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn keikan_scene_new(from: KeikanVec3, to: KeikanVec3, up: KeikanVec3, fov: f64) -> *mut Scene {
+    let mut camera = Camera::new(from.into(), to.into(), up.into());
+    if fov > 0.0 { camera.fov = fov as Float; }
+    return Box::into_raw(Box::new(Scene::new(camera)));
+}
+
+// a json scene file, see import::scene_file
+#[no_mangle]
+pub unsafe extern "C" fn keikan_scene_load(path: *const c_char) -> *mut Scene {
+    if path.is_null() { return std::ptr::null_mut(); }
+
+    let loaded = CStr::from_ptr(path).to_str().ok().and_then(|path| Scene::from_file(path).ok());
+    match loaded {
+        Some(scene) => Box::into_raw(Box::new(scene)),
+        None => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn keikan_scene_free(scene: *mut Scene) {
+    if !scene.is_null() { drop(Box::from_raw(scene)); }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn keikan_scene_set_samples(scene: *mut Scene, samples: u32) -> c_int {
+    let scene = match scene.as_mut() { Some(scene) => scene, None => return KEIKAN_NULL };
+    scene.samples = samples.max(1);
+    return KEIKAN_OK;
+}
+
+// 0 for paths from the camera, 1 for bidirectional, 2 for sppm
+#[no_mangle]
+pub unsafe extern "C" fn keikan_scene_set_integrator(scene: *mut Scene, integrator: c_int) -> c_int {
+    let scene = match scene.as_mut() { Some(scene) => scene, None => return KEIKAN_NULL };
+    scene.integrator = match integrator {
+        0 => Integrator::Path,
+        1 => Integrator::Bidirectional,
+        2 => Integrator::Sppm,
+        _ => return KEIKAN_BAD_ARGUMENT,
+    };
+    return KEIKAN_OK;
+}
+
+// adds a built in shape as marched or traced, if it can be
+unsafe fn add(scene: *mut Scene, kind: c_int, march: Option<MarchPrimitive>, trace: Option<TracePrimitive>) -> c_int {
+    let scene = match scene.as_mut() { Some(scene) => scene, None => return KEIKAN_NULL };
+
+    match (kind, march, trace) {
+        (KEIKAN_MARCH, Some(shape), _) => scene.add_march(shape),
+        (KEIKAN_TRACE, _, Some(shape)) => scene.add_trace(shape),
+        (KEIKAN_MARCH, None, _) | (KEIKAN_TRACE, _, None) => return KEIKAN_WRONG_KIND,
+        _ => return KEIKAN_BAD_ARGUMENT,
+    }
+
+    return KEIKAN_OK;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn keikan_scene_add_sphere(
+    scene: *mut Scene, kind: c_int, center: KeikanVec3, radius: f64, material: KeikanMaterial,
+) -> c_int {
+    let shape = Sphere::new(center.into(), radius as Float, material.into());
+    return add(scene, kind, Some(shape.into()), Some(shape.into()));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn keikan_scene_add_plane(
+    scene: *mut Scene, kind: c_int, position: KeikanVec3, normal: KeikanVec3, material: KeikanMaterial,
+) -> c_int {
+    let shape = Plane::new(position.into(), normal.into(), material.into());
+    return add(scene, kind, Some(shape.into()), Some(shape.into()));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn keikan_scene_add_cuboid(
+    scene: *mut Scene, kind: c_int, center: KeikanVec3, size: KeikanVec3, radius: f64, material: KeikanMaterial,
+) -> c_int {
+    let shape = Cuboid::rounded(center.into(), size.into(), radius as Float, material.into());
+    return add(scene, kind, Some(shape.into()), Some(shape.into()));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn keikan_scene_add_torus(
+    scene: *mut Scene, kind: c_int, center: KeikanVec3, major: f64, minor: f64, material: KeikanMaterial,
+) -> c_int {
+    let shape = Torus::new(center.into(), major as Float, minor as Float, material.into());
+    return add(scene, kind, Some(shape.into()), None);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn keikan_scene_add_mandelbulb(
+    scene: *mut Scene, kind: c_int, center: KeikanVec3, power: f64, iterations: u32, material: KeikanMaterial,
+) -> c_int {
+    let shape = Mandelbulb::new(center.into(), power as Float, iterations as usize, material.into());
+    return add(scene, kind, Some(shape.into()), None);
+}
+
+// `vertices` holds x, y, z for each vertex and `indices` three per triangle.
+// meshes are always traced.
+#[no_mangle]
+pub unsafe extern "C" fn keikan_scene_add_mesh(
+    scene: *mut Scene,
+    vertices: *const f64, vertex_count: usize,
+    indices: *const u32, index_count: usize,
+    material: KeikanMaterial,
+) -> c_int {
+    let scene = match scene.as_mut() { Some(scene) => scene, None => return KEIKAN_NULL };
+    if vertices.is_null() || indices.is_null() { return KEIKAN_NULL; }
+    if !index_count.is_multiple_of(3) { return KEIKAN_BAD_ARGUMENT; }
+
+    let coordinates = match vertex_count.checked_mul(3) { Some(coordinates) => coordinates, None => return KEIKAN_BAD_ARGUMENT };
+    let vertices: Vec<Vec3> = slice::from_raw_parts(vertices, coordinates)
+        .chunks_exact(3)
+        .map(|v| Vec3::new(v[0] as Float, v[1] as Float, v[2] as Float))
+        .collect();
+
+    let indices = slice::from_raw_parts(indices, index_count);
+    if indices.iter().any(|i| *i as usize >= vertices.len()) { return KEIKAN_BAD_ARGUMENT; }
+
+    let triangles = indices.chunks_exact(3).map(|t| [t[0] as usize, t[1] as usize, t[2] as usize]).collect();
+    scene.add_trace(Mesh::new(vertices, triangles, material.into()));
+    return KEIKAN_OK;
+}
+
+// a glowing ball the camera sees and bidirectional paths start from
+#[no_mangle]
+pub unsafe extern "C" fn keikan_scene_add_light(
+    scene: *mut Scene, position: KeikanVec3, radius: f64, color: KeikanVec3, strength: f64,
+) -> c_int {
+    let scene = match scene.as_mut() { Some(scene) => scene, None => return KEIKAN_NULL };
+
+    let mut glow = Material::blank();
+    glow.color = color.into();
+    glow.emission = strength as Float;
+
+    scene.add_trace(Sphere::new(position.into(), radius as Float, glow));
+    scene.emitters.push(Emitter::sphere(position.into(), radius as Float, glow.color * glow.emission));
+    return KEIKAN_OK;
+}
+
+// how many numbers an rgb image that size takes, none if it's empty or
+// more than fit in a usize
+fn channels(width: usize, height: usize) -> Option<usize> {
+    if width == 0 || height == 0 { return None; }
+    return width.checked_mul(height)?.checked_mul(3);
+}
+
+// rgb bytes, rows top to bottom, tone mapped like saved pngs.
+// `buffer` holds at least `width * height * 3` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn keikan_render_rgb8(
+    scene: *const Scene, width: usize, height: usize, buffer: *mut u8, length: usize,
+) -> c_int {
+    let scene = match scene.as_ref() { Some(scene) => scene, None => return KEIKAN_NULL };
+    if buffer.is_null() { return KEIKAN_NULL; }
+    let needed = match channels(width, height) { Some(needed) => needed, None => return KEIKAN_BAD_ARGUMENT };
+    if length < needed { return KEIKAN_TOO_SMALL; }
+
+    let (image, _) = match try_render_image(scene, [width, height]) {
+        Ok(rendered) => rendered,
+        Err(_) => return KEIKAN_BAD_SCENE,
+    };
+    let out = slice::from_raw_parts_mut(buffer, length);
+    for (pixel, rgb) in image.iter().flatten().zip(out.chunks_exact_mut(3)) {
+        rgb.copy_from_slice(&pixel.colorize());
+    }
+
+    return KEIKAN_OK;
+}
+
+// linear rgb floats, rows top to bottom. `buffer` holds at least
+// `width * height * 3` of them.
+#[no_mangle]
+#[allow(clippy::unnecessary_cast)] // Float may already be f32
+pub unsafe extern "C" fn keikan_render_rgbf(
+    scene: *const Scene, width: usize, height: usize, buffer: *mut f32, length: usize,
+) -> c_int {
+    let scene = match scene.as_ref() { Some(scene) => scene, None => return KEIKAN_NULL };
+    if buffer.is_null() { return KEIKAN_NULL; }
+    let needed = match channels(width, height) { Some(needed) => needed, None => return KEIKAN_BAD_ARGUMENT };
+    if length < needed { return KEIKAN_TOO_SMALL; }
+
+    let (image, _) = match try_render_image(scene, [width, height]) {
+        Ok(rendered) => rendered,
+        Err(_) => return KEIKAN_BAD_SCENE,
+    };
+    let out = slice::from_raw_parts_mut(buffer, length);
+    for (pixel, rgb) in image.iter().flatten().zip(out.chunks_exact_mut(3)) {
+        rgb.copy_from_slice(&[pixel.x as f32, pixel.y as f32, pixel.z as f32]);
+    }
+
+    return KEIKAN_OK;
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::{
+        KeikanVec3, KeikanMaterial, KEIKAN_OK, KEIKAN_NULL, KEIKAN_TOO_SMALL, KEIKAN_WRONG_KIND, KEIKAN_BAD_ARGUMENT, KEIKAN_BAD_SCENE,
+        KEIKAN_MARCH, KEIKAN_TRACE, keikan_scene_new, keikan_scene_load, keikan_scene_free, keikan_scene_set_samples,
+        keikan_scene_add_sphere, keikan_scene_add_torus, keikan_scene_add_mesh, keikan_render_rgb8, keikan_render_rgbf,
+    };
+
+    fn vec3(x: f64, y: f64, z: f64) -> KeikanVec3 {
+        KeikanVec3 { x: x, y: y, z: z }
+    }
+
+    #[test]
+    fn test_ffi() {
+        let red = KeikanMaterial {
+            color: vec3(1.0, 0.0, 0.0),
+            emission: 0.0, metallic: 0.0, specular: 0.0, roughness: 0.5, transmission: 0.0, ior: 1.5,
+        };
+
+        unsafe {
+            let scene = keikan_scene_new(vec3(0.0, 0.0, 0.0), vec3(0.0, 0.0, -1.0), vec3(0.0, 1.0, 0.0), 0.0);
+            assert_eq!(keikan_scene_set_samples(scene, 1), KEIKAN_OK);
+            assert_eq!(keikan_scene_add_sphere(scene, KEIKAN_MARCH, vec3(0.0, 0.0, -5.0), 1.0, red), KEIKAN_OK);
+            assert_eq!(keikan_scene_add_torus(scene, KEIKAN_TRACE, vec3(0.0, 0.0, -5.0), 1.0, 0.1, red), KEIKAN_WRONG_KIND);
+
+            let vertices = [-1.0, -1.0, -3.0, 1.0, -1.0, -3.0, 0.0, -2.0, -3.0];
+            assert_eq!(keikan_scene_add_mesh(scene, vertices.as_ptr(), 3, [0, 1, 2].as_ptr(), 3, red), KEIKAN_OK);
+            assert_eq!(keikan_scene_add_mesh(scene, vertices.as_ptr(), 3, [0, 1, 3].as_ptr(), 3, red), KEIKAN_BAD_ARGUMENT);
+
+            assert_eq!(keikan_scene_add_mesh(scene, vertices.as_ptr(), usize::MAX, [0, 1, 2].as_ptr(), 3, red), KEIKAN_BAD_ARGUMENT);
+
+            let mut small = [0u8; 10];
+            assert_eq!(keikan_render_rgb8(scene, 8, 4, small.as_mut_ptr(), small.len()), KEIKAN_TOO_SMALL);
+            assert_eq!(keikan_render_rgb8(scene, usize::MAX, 2, small.as_mut_ptr(), small.len()), KEIKAN_BAD_ARGUMENT);
+
+            // the sphere is in the middle, red against the sky
+            let mut pixels = [0.0f32; 8 * 4 * 3];
+            assert_eq!(keikan_render_rgbf(scene, 8, 4, pixels.as_mut_ptr(), pixels.len()), KEIKAN_OK);
+            let middle = (2 * 8 + 4) * 3;
+            assert!(pixels[middle] > pixels[middle + 2]);
+            assert!(pixels[2] > pixels[0]);
+
+            keikan_scene_free(scene);
+
+            // a camera looking along its own up has no picture to give
+            let broken = keikan_scene_new(vec3(0.0, 0.0, 0.0), vec3(0.0, 0.0, -1.0), vec3(0.0, 0.0, -1.0), 0.0);
+            assert_eq!(keikan_render_rgbf(broken, 8, 4, pixels.as_mut_ptr(), pixels.len()), KEIKAN_BAD_SCENE);
+            keikan_scene_free(broken);
+
+            assert_eq!(keikan_scene_set_samples(std::ptr::null_mut(), 1), KEIKAN_NULL);
+            assert!(keikan_scene_load(std::ptr::null()).is_null());
+        }
+    }
+}
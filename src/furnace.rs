@@ -0,0 +1,88 @@
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::camera::Camera;
+use crate::structures::material::Material;
+use crate::structures::scene::Scene;
+use crate::render::render_image;
+use crate::objects::sphere::Sphere;
+
+// the white furnace: a sphere on its own under a uniformly white sky. all
+// the light it sends back comes from the sky, so a material that absorbs
+// nothing disappears into it, and one that shows up brighter than the sky
+// is making light up. check materials against it after touching the
+// shading, rather than squinting at renders.
+
+pub const RESOLUTION: [usize; 2] = [16, 16];
+const SAMPLES: u32 = 4;
+
+// framed so the sphere fills every pixel
+pub fn scene(material: Material) -> Scene {
+    let mut camera = Camera::new(Vec3::new(0.0, 0.0, 3.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+    camera.fov = 30.0;
+
+    return Scene::builder()
+        .camera(camera)
+        .environment(Vec3::new(1.0, 1.0, 1.0))
+        .trace(Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0, material))
+        .samples(SAMPLES)
+        .build();
+}
+
+// how much of the furnace's light the material sends back, per channel,
+// averaged over the whole sphere
+pub fn albedo(material: Material) -> Vec3 {
+    let (image, _) = render_image(&scene(material), RESOLUTION);
+    let pixels = (RESOLUTION[0] * RESOLUTION[1]) as Float;
+
+    return image.iter().flatten().fold(Vec3::new(0.0, 0.0, 0.0), |sum, pixel| sum + *pixel) / pixels;
+}
+
+// the albedo, or what's wrong with it if the material gains energy by
+// more than `tolerance` on any channel. glowing is making light on
+// purpose, so emission is turned off first.
+pub fn check(material: Material, tolerance: Float) -> Result<Vec3, String> {
+    let albedo = albedo(Material { emission: 0.0, ..material });
+
+    if !albedo.x.is_finite() || !albedo.y.is_finite() || !albedo.z.is_finite() {
+        return Err(format!("{:?} comes out of the furnace as {:?}", material, albedo));
+    }
+
+    if albedo.x.max(albedo.y).max(albedo.z) > 1.0 + tolerance {
+        return Err(format!("{:?} reflects {:?} of the light it gets", material, albedo));
+    }
+
+    return Ok(albedo);
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::{ albedo, check };
+    use crate::structures::vec3::Vec3;
+    use crate::structures::material::Material;
+
+    #[test]
+    fn test_furnace() {
+        let white = Material { color: Vec3::new(1.0, 1.0, 1.0), emission: 0.0, ..Material::blank() };
+        let grey = Material { color: Vec3::new(0.5, 0.5, 0.5), ..white };
+
+        // nothing absorbed, nothing made up
+        assert!((albedo(white) - Vec3::new(1.0, 1.0, 1.0)).length() < 0.001);
+        assert!((albedo(grey) - Vec3::new(0.5, 0.5, 0.5)).length() < 0.001);
+
+        let materials = [
+            white,
+            Material { metallic: 1.0, ..white },
+            Material { metallic: 1.0, roughness: 0.5, ..white },
+            Material { specular: 0.5, roughness: 0.8, ..grey },
+            Material { metallic: 0.5, ..grey },
+            Material { specular: 0.5, ..white },
+            Material { specular: 1.0, metallic: 0.3, ..white },
+            Material { transmission: 1.0, ior: 1.5, ..white },
+            Material { emission: 0.5, ..white },
+        ];
+
+        for material in materials.iter() {
+            if let Err(message) = check(*material, 0.001) { panic!("{}", message); }
+        }
+    }
+}
@@ -0,0 +1,418 @@
+#[cfg(feature = "gpu")]
+use std::time::Instant;
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::scene::Scene;
+use crate::objects::visible::RayKind;
+#[cfg(feature = "gpu")]
+use crate::structures::stats::RenderStats;
+#[cfg(feature = "gpu")]
+use crate::render;
+
+// compiles a scene into a wgsl compute kernel for quick previews: camera
+// rays only, shaded with a light at the eye, one invocation per pixel.
+// the kernel writes rgba rows top to bottom into the storage buffer at
+// group 0 binding 0, in workgroups of 8 by 8.
+//
+// with the gpu feature render_image runs it through wgpu. scenes with
+// anything the kernel can't do, like fractals, transformed shapes, shadow
+// catchers or participating media, don't compile at all and render on
+// the cpu instead. objects the camera can't see are left out, and light
+// groups don't change the picture, so they're ignored.
+
+const WORKGROUP: usize = 8;
+
+pub fn workgroups(resolution: [usize; 2]) -> [usize; 2] {
+    [
+        resolution[0].div_ceil(WORKGROUP),
+        resolution[1].div_ceil(WORKGROUP),
+    ]
+}
+
+// literals the shapes splice into their expressions, none for what an f32
+// can't hold, infinities and nans included, which has no wgsl
+pub fn float(value: Float) -> Option<String> {
+    if !value.is_finite() || value.abs() > f32::MAX as Float { return None; }
+    return Some(format!("{:?}", value)); // always has a point or an exponent
+}
+
+pub fn vec3(v: Vec3) -> Option<String> {
+    return Some(format!("vec3<f32>({}, {}, {})", float(v.x)?, float(v.y)?, float(v.z)?));
+}
+
+// helpers the shapes' expressions call into
+const PRELUDE: &str = "
+struct Hit {
+    t: f32,
+    normal: vec3<f32>,
+    index: i32,
+}
+
+fn miss() -> Hit {
+    return Hit(-1.0, vec3<f32>(0.0, 1.0, 0.0), -1);
+}
+
+fn trace_sphere(o: vec3<f32>, d: vec3<f32>, c: vec3<f32>, r: f32) -> Hit {
+    let oc = o - c;
+    let a = dot(d, d);
+    let b = dot(oc, d);
+    let disc = b * b - a * (dot(oc, oc) - r * r);
+    if (disc <= 0.0) { return miss(); }
+
+    var t = (-b - sqrt(disc)) / a;
+    if (t <= EPSILON) { t = (-b + sqrt(disc)) / a; }
+    return Hit(t, normalize(o + d * t - c), 0);
+}
+
+fn trace_plane(o: vec3<f32>, d: vec3<f32>, p: vec3<f32>, n: vec3<f32>) -> Hit {
+    let denom = dot(n, d);
+    if (denom == 0.0) { return miss(); }
+    return Hit(dot(p - o, n) / denom, n, 0);
+}
+
+fn sd_cuboid(p: vec3<f32>, c: vec3<f32>, size: vec3<f32>, radius: f32) -> f32 {
+    let q = abs(p - c) - size + radius;
+    return length(max(q, vec3<f32>(0.0))) + min(max(q.x, max(q.y, q.z)), 0.0) - radius;
+}
+
+fn sd_torus(p: vec3<f32>, c: vec3<f32>, major: f32, minor: f32) -> f32 {
+    let q = p - c;
+    let ring = length(q.xz) - major;
+    return length(vec2<f32>(ring, q.y)) - minor;
+}
+";
+
+// everything after the scene specific parts
+const KERNEL: &str = "
+fn march(o: vec3<f32>, d: vec3<f32>) -> Hit {
+    var t = 0.0;
+    for (var i = 0; i < MAX_STEPS; i++) {
+        let closest = sdf(o + d * t);
+        if (closest.x <= EPSILON) {
+            let p = o + d * t;
+            let h = vec2<f32>(1.0, -1.0) * NORMAL_EPSILON;
+            let normal = normalize(
+                h.xyy * sdf(p + h.xyy).x + h.yyx * sdf(p + h.yyx).x +
+                h.yxy * sdf(p + h.yxy).x + h.xxx * sdf(p + h.xxx).x
+            );
+            return Hit(t, normal, i32(closest.y));
+        }
+        t += closest.x;
+        if (t >= MAX_DISTANCE) { break; }
+    }
+    return miss();
+}
+
+fn cast_ray(o: vec3<f32>, d: vec3<f32>) -> Hit {
+    var best = march(o, d);
+    let traced = trace(o, d);
+    if (traced.index >= 0 && (best.index < 0 || traced.t < best.t)) { best = traced; }
+    return best;
+}
+
+@group(0) @binding(0) var<storage, read_write> image: array<vec4<f32>>;
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= WIDTH || id.y >= HEIGHT) { return; }
+
+    let height = f32(HEIGHT);
+    let uv = vec2<f32>(
+        (f32(id.x) + 0.5) / height - f32(WIDTH) / height * 0.5,
+        0.5 - (f32(id.y) + 0.5) / height,
+    );
+    let d = normalize(RIGHT * uv.x + UP * uv.y + FORWARD * ZOOM);
+
+    let hit = cast_ray(ORIGIN, d);
+    var color = SKY;
+    if (hit.index >= 0) {
+        color = COLORS[hit.index] * (0.2 + 0.8 * abs(dot(hit.normal, d)));
+    }
+
+    image[id.y * WIDTH + id.x] = vec4<f32>(color, 1.0);
+}
+";
+
+pub fn compile(scene: &Scene, resolution: [usize; 2]) -> Option<String> {
+    if scene.medium.is_some() || !scene.volumes.is_empty() || scene.environment_map.is_some() { return None; }
+    // the kernel fills every pixel, it can't leave the ones outside a region black
+    if scene.region.is_some() { return None; }
+    if scene.march.iter().any(|object| object.shadow_catcher()) || scene.trace.iter().any(|object| object.shadow_catcher()) { return None; }
+
+    // only camera rays are cast, so what they can't see isn't there
+    let march: Vec<_> = scene.march.iter().filter(|object| object.visibility().sees(RayKind::Camera)).collect();
+    let trace: Vec<_> = scene.trace.iter().filter(|object| object.visibility().sees(RayKind::Camera)).collect();
+
+    let marched = march.iter().map(|object| object.wgsl()).collect::<Option<Vec<String>>>()?;
+    let traced = trace.iter().map(|object| object.wgsl()).collect::<Option<Vec<String>>>()?;
+
+    // marched objects are numbered first, then traced ones
+    let mut colors: Vec<String> = march.iter().map(|object| vec3(object.material().color))
+        .chain(trace.iter().map(|object| vec3(object.material().color)))
+        .collect::<Option<Vec<String>>>()?;
+    if colors.is_empty() { colors.push(vec3(Vec3::new(0.0, 0.0, 0.0))?); }
+
+    let camera = scene.camera;
+    let forward = camera.ray.direction;
+    let right = forward.cross(&camera.up).unit();
+    let up = right.cross(&forward);
+
+    let mut source = String::new();
+    source += &format!("const WIDTH: u32 = {}u;\n", resolution[0]);
+    source += &format!("const HEIGHT: u32 = {}u;\n", resolution[1]);
+    source += "const MAX_STEPS: i32 = 128;\n";
+    source += "const MAX_DISTANCE: f32 = 100.0;\n";
+    source += "const EPSILON: f32 = 0.002;\n";
+    source += "const NORMAL_EPSILON: f32 = 0.001;\n";
+    source += &format!("const ORIGIN = {};\n", vec3(camera.ray.origin)?);
+    source += &format!("const FORWARD = {};\n", vec3(forward)?);
+    source += &format!("const RIGHT = {};\n", vec3(right)?);
+    source += &format!("const UP = {};\n", vec3(up)?);
+    // the kernel's picture is one unit high, see Camera::half_size
+    let zoom = 1.0 / (camera.fov.to_radians() / 2.0).tan() / (2.0 * camera.half_size(resolution)[1]);
+    source += &format!("const ZOOM: f32 = {};\n", float(zoom)?);
+    source += &format!("const SKY = {};\n", vec3(scene.environment)?);
+    source += &format!("var<private> COLORS: array<vec3<f32>, {}> = array<vec3<f32>, {}>(\n", colors.len(), colors.len());
+    for color in &colors { source += &format!("    {},\n", color); }
+    source += ");\n";
+    source += PRELUDE;
+
+    // distance to the closest marched object, and which one it is
+    source += "\nfn sdf(p: vec3<f32>) -> vec2<f32> {\n    var closest = vec2<f32>(MAX_DISTANCE, -1.0);\n";
+    for (index, expression) in marched.iter().enumerate() {
+        source += &format!("    {{ let d = {}; if (d < closest.x) {{ closest = vec2<f32>(d, {}.0); }} }}\n", expression, index);
+    }
+    source += "    return closest;\n}\n";
+
+    source += "\nfn trace(o: vec3<f32>, d: vec3<f32>) -> Hit {\n    var best = miss();\n";
+    for (index, expression) in traced.iter().enumerate() {
+        source += &format!(
+            "    {{\n        var hit = {};\n        hit.index = {};\n        if (hit.t > EPSILON && (best.index < 0 || hit.t < best.t)) {{ best = hit; }}\n    }}\n",
+            expression, marched.len() + index,
+        );
+    }
+    source += "    return best;\n}\n";
+
+    source += KERNEL;
+    return Some(source);
+}
+
+// render::render_image's picture as the kernel sees it, on whatever gpu
+// wgpu finds first. scenes that don't compile, and machines without a gpu
+// to run them on, get render::render_image instead.
+#[cfg(feature = "gpu")]
+pub fn render_image(scene: &Scene, resolution: [usize; 2]) -> (Vec<Vec<Vec3>>, RenderStats) {
+    let (image, _, stats) = render_sample_counts(scene, resolution);
+    return (image, stats);
+}
+
+// render_image, and how many samples each pixel took: the kernel's one
+// through the middle of each, or render::render_sample_counts' on the cpu
+#[cfg(feature = "gpu")]
+pub fn render_sample_counts(scene: &Scene, resolution: [usize; 2]) -> (Vec<Vec<Vec3>>, Vec<Vec<u32>>, RenderStats) {
+    let start = Instant::now();
+    let image = compile(scene, resolution).and_then(|source| pollster::block_on(run(&source, resolution)));
+
+    return match image {
+        Some(image) => (image, vec![vec![1; resolution[0]]; resolution[1]], RenderStats { total: start.elapsed(), ..RenderStats::default() }),
+        None => render::render_sample_counts(scene, resolution),
+    };
+}
+
+// one dispatch of a compiled kernel and its picture read back, none if
+// there's no gpu or the picture doesn't fit in one buffer on it
+#[cfg(feature = "gpu")]
+async fn run(source: &str, resolution: [usize; 2]) -> Option<Vec<Vec<Vec3>>> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::new_without_display_handle());
+    let adapter = instance.request_adapter(&wgpu::RequestAdapterOptions::default()).await.ok()?;
+    let (device, queue) = adapter.request_device(&wgpu::DeviceDescriptor::default()).await.ok()?;
+
+    let size = (resolution[0] * resolution[1] * 16) as u64; // a vec4<f32> a pixel
+    if size == 0 || size > device.limits().max_storage_buffer_binding_size { return None; }
+
+    let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("keikan preview"),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("keikan preview"),
+        layout: None,
+        module: &module,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    let image = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("image"),
+        size: size,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+    let readback = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("readback"),
+        size: size,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let bindings = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[wgpu::BindGroupEntry { binding: 0, resource: image.as_entire_binding() }],
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(0, &bindings, &[]);
+        let [x, y] = workgroups(resolution);
+        pass.dispatch_workgroups(x as u32, y as u32, 1);
+    }
+    encoder.copy_buffer_to_buffer(&image, 0, &readback, 0, size);
+    queue.submit([encoder.finish()]);
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+    readback.slice(..).map_async(wgpu::MapMode::Read, move |mapped| { let _ = sender.send(mapped); });
+    device.poll(wgpu::PollType::wait_indefinitely()).ok()?;
+    receiver.recv().ok()?.ok()?;
+
+    let bytes = readback.slice(..).get_mapped_range().ok()?;
+    let channels: Vec<Float> = bytes.chunks_exact(4)
+        .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as Float)
+        .collect();
+    return Some(channels.chunks_exact(4 * resolution[0]).map(|row| {
+        row.chunks_exact(4).map(|pixel| Vec3::new(pixel[0], pixel[1], pixel[2])).collect()
+    }).collect());
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::{ compile, float, workgroups };
+    use crate::structures::float::Float;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::camera::Camera;
+    use crate::structures::scene::Scene;
+    use crate::structures::material::Material;
+    use crate::objects::sphere::Sphere;
+    use crate::objects::plane::Plane;
+    use crate::objects::torus::Torus;
+    use crate::objects::cuboid::Cuboid;
+    use crate::objects::mandelbulb::Mandelbulb;
+    use crate::objects::visible::{ Visible, Visibility };
+    use crate::objects::shadow_catcher::ShadowCatcher;
+    use crate::objects::clipped::ClipPlane;
+    use crate::structures::tile::Tile;
+
+    // what a gpu would make of it, or why it wouldn't
+    fn validate(source: &str) {
+        let module = naga::front::wgsl::parse_str(source).unwrap_or_else(|error| panic!("{}", error.emit_to_string(source)));
+        naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::all())
+            .validate(&module)
+            .unwrap_or_else(|error| panic!("{}", error.emit_to_string(source)));
+    }
+
+    #[test]
+    fn test_compile() {
+        let camera = Camera::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0));
+        let mut scene = Scene::new(camera);
+        scene.add_march(Sphere::new(Vec3::new(0.0, 0.0, -5.0), 1.0, Material::blank()));
+        scene.add_trace(Plane::new(Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Material::blank()));
+
+        let source = compile(&scene, [64, 48]).unwrap();
+        assert!(source.contains("length(p - vec3<f32>(0.0, 0.0, -5.0)) - 1.0"));
+        assert!(source.contains("trace_plane(o, d,"));
+        assert!(source.contains("array<vec3<f32>, 2>"));
+        validate(&source);
+
+        // a fractal has no wgsl, so the whole scene stays on the cpu
+        scene.add_march(Mandelbulb::new(Vec3::new(0.0, 0.0, 0.0), 8.0, 8, Material::blank()));
+        assert!(compile(&scene, [64, 48]).is_none());
+
+        assert_eq!(float(3.0), Some("3.0".to_string()));
+        assert_eq!(workgroups([64, 50]), [8, 7]);
+    }
+
+    #[test]
+    fn test_every_shape() {
+        let camera = Camera::new(Vec3::new(0.0, 1.0, 6.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let material = Material::blank();
+
+        // everything with wgsl, marched and traced
+        let scene = Scene::builder()
+            .camera(camera)
+            .march(Torus::new(Vec3::new(-2.0, 0.0, 0.0), 1.0, 0.25, material))
+            .march(Cuboid::rounded(Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.5, 0.5, 0.5), 0.1, material))
+            .march(Sphere::new(Vec3::new(0.0, 2.0, 0.0), 0.5, material))
+            .march(Plane::new(Vec3::new(0.0, -2.0, 0.0), Vec3::new(0.0, 1.0, 0.0), material))
+            .trace(Sphere::new(Vec3::new(0.0, 0.0, -2.0), 1.0, material))
+            .trace(Plane::new(Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 1.0, 0.0), material))
+            .build();
+        validate(&compile(&scene, [32, 32]).unwrap());
+
+        // cut in half, which only marched objects can be on the gpu
+        let clipped = Scene::builder()
+            .camera(camera)
+            .march(Torus::new(Vec3::new(0.0, 0.0, 0.0), 1.0, 0.25, material))
+            .clip(ClipPlane::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0)))
+            .build();
+        validate(&compile(&clipped, [32, 32]).unwrap());
+        validate(&compile(&Scene::builder().camera(camera).build(), [8, 8]).unwrap());
+    }
+
+    #[test]
+    fn test_refused() {
+        let camera = Camera::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0));
+        let sphere = Sphere::new(Vec3::new(0.0, 0.0, -5.0), 1.0, Material::blank());
+
+        // numbers with no wgsl keep the scene on the cpu
+        assert_eq!((float(Float::INFINITY), float(Float::NAN)), (None, None));
+        let huge = Scene::builder().camera(camera).trace(Sphere::new(Vec3::new(0.0, 0.0, -5.0), Float::INFINITY, Material::blank())).build();
+        assert!(compile(&huge, [8, 8]).is_none());
+
+        // what the camera doesn't see is left out
+        let hidden = Scene::builder().camera(camera)
+            .trace(sphere)
+            .trace(Visible::new(Sphere::new(Vec3::new(0.0, 0.0, -9.0), 2.0, Material::blank()), Visibility::CAMERA_INVISIBLE))
+            .build();
+        let source = compile(&hidden, [8, 8]).unwrap();
+        assert!(!source.contains("-9.0") && source.contains("array<vec3<f32>, 1>"));
+        validate(&source);
+
+        // and the kernel has no shadows to catch
+        let catcher = Scene::builder().camera(camera).trace(ShadowCatcher::new(sphere)).build();
+        assert!(compile(&catcher, [8, 8]).is_none());
+
+        // nor a region to keep to
+        let mut region = Scene::builder().camera(camera).trace(sphere).build();
+        region.region = Some(Tile { x: 0, y: 0, width: 4, height: 4 });
+        assert!(compile(&region, [8, 8]).is_none());
+    }
+
+    #[cfg(feature = "gpu")]
+    #[test]
+    fn test_render_image() {
+        let camera = Camera::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0));
+
+        // on the gpu if there is one, else on the cpu, the same size either way
+        let simple = Scene::builder().camera(camera).samples(1).trace(Sphere::new(Vec3::new(0.0, 0.0, -5.0), 1.0, Material::blank())).build();
+        let (image, _) = super::render_image(&simple, [20, 10]);
+        assert_eq!((image.len(), image[0].len()), (10, 20));
+
+        // where there is one, the sphere's in the middle in its own color, lit
+        // from the eye, and the sky's around it
+        let red = Material { color: Vec3::new(1.0, 0.0, 0.0), emission: 0.0, ..Material::blank() };
+        let scene = Scene::builder().camera(camera).environment(Vec3::new(0.0, 0.0, 1.0)).trace(Sphere::new(Vec3::new(0.0, 0.0, -5.0), 1.0, red)).build();
+        if let Some(image) = pollster::block_on(super::run(&compile(&scene, [20, 10]).unwrap(), [20, 10])) {
+            assert!((image[5][10] - Vec3::new(1.0, 0.0, 0.0)).length() < 0.05);
+            assert_eq!(image[0][0], Vec3::new(0.0, 0.0, 1.0));
+        }
+
+        // a fractal never compiles, so it's always the cpu
+        let fractal = Scene::builder().camera(camera).samples(1).march(Mandelbulb::new(Vec3::new(0.0, 0.0, -4.0), 8.0, 4, Material::blank())).build();
+        let (image, stats) = super::render_image(&fractal, [20, 10]);
+        assert_eq!((image.len(), image[0].len()), (10, 20));
+        assert!(stats.rays > 0);
+    }
+}
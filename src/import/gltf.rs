@@ -0,0 +1,201 @@
+use std::path::Path;
+
+use crate::error::{ Error, Result };
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::camera::Camera;
+use crate::structures::material::Material;
+use crate::structures::scene::Scene;
+use crate::structures::transform::Transform;
+use crate::objects::mesh::Mesh;
+
+// gltf matrices are column major
+fn transform(node: &::gltf::Node) -> Result<Transform> {
+    let m = node.transform().matrix();
+    let mut matrix = [[0.0; 4]; 4];
+
+    for (col, column) in m.iter().enumerate() {
+        for (row, value) in column.iter().enumerate() {
+            matrix[row][col] = *value as Float;
+        }
+    }
+
+    return Transform::try_new(matrix).ok_or_else(|| Error::Invalid(format!("gltf: node {} has a transform with no inverse", node.index())));
+}
+
+// maps the metallic/roughness model onto keikan's principled material
+pub fn material(material: &::gltf::Material) -> Material {
+    let pbr = material.pbr_metallic_roughness();
+    let [r, g, b, _] = pbr.base_color_factor();
+    let [er, eg, eb] = material.emissive_factor();
+
+    let strength = material.emissive_strength().unwrap_or(1.0) as Float;
+    let brightest = er.max(eg).max(eb) as Float;
+
+    let transmission = material.transmission()
+        .map(|t| t.transmission_factor() as Float)
+        .unwrap_or(0.0);
+
+    let mut mapped = Material {
+        color: Vec3::new(r as Float, g as Float, b as Float),
+        emission: 0.0,
+
+        metallic: pbr.metallic_factor() as Float,
+        specular: 0.04, // gltf dielectrics reflect about 4% head on
+        roughness: pbr.roughness_factor() as Float,
+
+        transmission: transmission,
+        ior: material.ior().unwrap_or(1.5) as Float,
+
+        two_sided: material.double_sided(),
+        ..Material::blank()
+    };
+
+    // emissive color replaces the base color, keikan only has the one
+    if brightest > 0.0 {
+        mapped.color = Vec3::new(er as Float, eg as Float, eb as Float) / brightest;
+        mapped.emission = brightest * strength;
+    }
+
+    return mapped;
+}
+
+// indices are checked, a file pointing past its vertices is an error
+fn mesh(mesh: &::gltf::Mesh, buffers: &[::gltf::buffer::Data], transform: &Transform) -> Result<Vec<Mesh>> {
+    let mut meshes = vec![];
+
+    for primitive in mesh.primitives() {
+        if primitive.mode() != ::gltf::mesh::Mode::Triangles { continue; }
+
+        let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+
+        let vertices: Vec<Vec3> = match reader.read_positions() {
+            Some(positions) => positions.map(|[x, y, z]| Vec3::new(x as Float, y as Float, z as Float)).collect(),
+            None => continue,
+        };
+
+        let normals: Vec<Vec3> = match reader.read_normals() {
+            Some(normals) => normals.map(|[x, y, z]| Vec3::new(x as Float, y as Float, z as Float)).collect(),
+            None => vec![],
+        };
+
+        // unindexed primitives are a plain triangle list
+        let indices: Vec<usize> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().map(|i| i as usize).collect(),
+            None => (0..vertices.len()).collect(),
+        };
+
+        let triangles = indices.chunks_exact(3).map(|t| [t[0], t[1], t[2]]).collect();
+
+        meshes.push(
+            Mesh::try_smooth(vertices, normals, triangles, material(&primitive.material()))?
+                .transform(transform)
+        );
+    }
+
+    return Ok(meshes);
+}
+
+fn camera(camera: &::gltf::Camera, transform: &Transform) -> Camera {
+    let from = transform.point(Vec3::new(0.0, 0.0, 0.0));
+    let forward = transform.vector(Vec3::new(0.0, 0.0, -1.0));
+    let up = transform.vector(Vec3::new(0.0, 1.0, 0.0)).unit();
+
+    let mut mapped = Camera::new(from, from + forward, up);
+
+    // keikan's fov spans twice the image height, gltf's spans it exactly
+    if let ::gltf::camera::Projection::Perspective(perspective) = camera.projection() {
+        let half = (perspective.yfov() as Float / 2.0).tan();
+        mapped.fov = (2.0 * (2.0 * half).atan()).to_degrees();
+    }
+
+    return mapped;
+}
+
+fn walk(
+    node: ::gltf::Node,
+    parent: &Transform,
+    buffers: &[::gltf::buffer::Data],
+    meshes: &mut Vec<Mesh>,
+    cameras: &mut Vec<Camera>,
+) -> Result<()> {
+    let world = *parent * transform(&node)?;
+    if !world.is_finite() { return Err(Error::Invalid(format!("gltf: node {} is moved out of sight", node.index()))); }
+
+    if let Some(m) = node.mesh() { meshes.extend(mesh(&m, buffers, &world)?); }
+    if let Some(c) = node.camera() { cameras.push(camera(&c, &world)); }
+
+    for child in node.children() {
+        walk(child, &world, buffers, meshes, cameras)?;
+    }
+
+    return Ok(());
+}
+
+// loads the default scene of a .gltf or .glb file.
+// the first camera found is used, otherwise one looking down -z at the origin.
+pub fn load(path: impl AsRef<Path>) -> Result<Scene> {
+    let path = path.as_ref();
+    let (document, buffers, _) = ::gltf::import(path).map_err(|error| match error {
+        ::gltf::Error::Io(error) => Error::reading(path, error),
+        error => Error::from(error),
+    })?;
+
+    let mut meshes = vec![];
+    let mut cameras = vec![];
+
+    if let Some(scene) = document.default_scene().or_else(|| document.scenes().next()) {
+        for node in scene.nodes() {
+            walk(node, &Transform::identity(), &buffers, &mut meshes, &mut cameras)?;
+        }
+    }
+
+    let camera = cameras.into_iter().next().unwrap_or_else(|| Camera::new(
+        Vec3::new(0.0, 0.0, 5.0),
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+    ));
+
+    let mut scene = Scene::new(camera);
+    for mesh in meshes {
+        scene.add_trace(mesh);
+    }
+
+    return Ok(scene);
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::mesh;
+    use crate::structures::transform::Transform;
+
+    // a triangle, then the same one with a corner pointing past the three
+    // vertices there are
+    const TRIANGLES: &str = r#"{
+        "asset": { "version": "2.0" },
+        "buffers": [{ "byteLength": 52, "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAAAAABAAIAAAAAAAEABQAAAA==" }],
+        "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+            { "buffer": 0, "byteOffset": 36, "byteLength": 6 },
+            { "buffer": 0, "byteOffset": 44, "byteLength": 6 }
+        ],
+        "accessors": [
+            { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0, 0, 0], "max": [1, 1, 0] },
+            { "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" },
+            { "bufferView": 2, "componentType": 5123, "count": 3, "type": "SCALAR" }
+        ],
+        "meshes": [
+            { "primitives": [{ "attributes": { "POSITION": 0 }, "indices": 1 }] },
+            { "primitives": [{ "attributes": { "POSITION": 0 }, "indices": 2 }] }
+        ]
+    }"#;
+
+    #[test]
+    fn test_indices() {
+        let (document, buffers, _) = ::gltf::import_slice(TRIANGLES.as_bytes()).unwrap();
+        let meshes: Vec<_> = document.meshes().collect();
+
+        assert_eq!(mesh(&meshes[0], &buffers, &Transform::identity()).unwrap()[0].triangles, vec![[0, 1, 2]]);
+        assert!(mesh(&meshes[1], &buffers, &Transform::identity()).err().unwrap().to_string().contains("past the 3 vertices"));
+    }
+}
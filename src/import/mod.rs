@@ -0,0 +1,10 @@
+pub mod stl;
+pub mod ply;
+pub mod vox;
+pub mod xyz;
+pub mod ttf;
+pub mod pbrt;
+pub mod scene_file;
+
+#[cfg(feature = "gltf")]
+pub mod gltf;
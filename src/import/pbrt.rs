@@ -0,0 +1,651 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{ Path, PathBuf };
+use std::sync::Arc;
+
+use crate::error::{ Error, Result };
+use crate::structures::float::Float;
+use crate::structures::float::consts::PI;
+use crate::structures::vec3::Vec3;
+use crate::structures::camera::Camera;
+use crate::structures::material::Material;
+use crate::structures::photon_map::Emitter;
+use crate::structures::scene::Scene;
+use crate::structures::transform::Transform;
+use crate::objects::sphere::Sphere;
+use crate::objects::disk::Disk;
+use crate::objects::mesh::Mesh;
+use crate::objects::instance::Instance;
+use crate::objects::transformed::Transformed;
+use crate::objects::traits::Trace;
+use crate::import::ply;
+
+// the parts of pbrt v3 scenes that map onto keikan: transforms, the
+// perspective camera, film resolution, spheres, disks, triangle and ply
+// meshes, object instancing, the common materials, area lights and point
+// lights. anything else is skipped, so most scenes load, just with less in them.
+//
+// pbrt is left handed, so the whole scene is mirrored along x to come out
+// the same way round as pbrt draws it.
+
+const POINT_RADIUS: Float = 0.05; // keikan can only see lights it can hit, so points get a size
+
+fn invalid(message: &str) -> Error {
+    Error::Invalid(format!("pbrt: {}", message))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String), // directives, numbers and bools
+    Text(String), // anything in quotes
+    Open,
+    Close,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '#' => while chars.peek().is_some_and(|c| *c != '\n') { chars.next(); },
+            '[' => tokens.push(Token::Open),
+            ']' => tokens.push(Token::Close),
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => text.push(c),
+                        None => return Err(invalid("unterminated string")),
+                    }
+                }
+                tokens.push(Token::Text(text));
+            },
+            c if c.is_whitespace() => (),
+            c => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.peek() {
+                    if c.is_whitespace() || "[]\"#".contains(*c) { break; }
+                    word.push(*c);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            },
+        }
+    }
+
+    return Ok(tokens);
+}
+
+fn number(token: &Token) -> Option<Float> {
+    match token {
+        Token::Word(word) => word.parse().ok(),
+        _ => None,
+    }
+}
+
+// a parameter list, like "float radius" [2] "rgb Kd" [0.5 0.5 0.8]
+#[derive(Debug, Default)]
+struct Params {
+    list: Vec<(String, String, Vec<Token>)>, // type, name, values
+}
+
+impl Params {
+    fn get(&self, name: &str) -> Option<(&str, &[Token])> {
+        self.list.iter()
+            .find(|(_, n, _)| n == name)
+            .map(|(kind, _, values)| (kind.as_str(), values.as_slice()))
+    }
+
+    fn floats(&self, name: &str) -> Vec<Float> {
+        self.get(name).map(|(_, values)| values.iter().filter_map(number).collect()).unwrap_or_default()
+    }
+
+    fn float(&self, name: &str, default: Float) -> Float {
+        self.floats(name).first().copied().unwrap_or(default)
+    }
+
+    fn string(&self, name: &str) -> Option<&str> {
+        match self.get(name)?.1.first()? {
+            Token::Text(text) => Some(text.as_str()),
+            _ => None,
+        }
+    }
+
+    fn points(&self, name: &str) -> Vec<Vec3> {
+        self.floats(name).chunks_exact(3).map(|p| Vec3::new(p[0], p[1], p[2])).collect()
+    }
+
+    // rgb and single floats are taken as they are, blackbody as white.
+    // sampled spectra and textures fall back to the default.
+    fn color(&self, name: &str, default: Vec3) -> Vec3 {
+        let values = self.floats(name);
+        match self.get(name).map(|(kind, _)| kind) {
+            Some("rgb") | Some("color") if values.len() == 3 => Vec3::new(values[0], values[1], values[2]),
+            Some("float") if values.len() == 1 => Vec3::new(values[0], values[0], values[0]),
+            Some("blackbody") if values.len() >= 2 => Vec3::new(values[1], values[1], values[1]),
+            _ => default,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Attributes {
+    transform: Transform,
+    material: Material,
+    glow: Option<Vec3>, // radiance of the current area light
+}
+
+fn grey(shade: Float) -> Material {
+    Material {
+        color: Vec3::new(shade, shade, shade),
+        emission: 0.0,
+
+        metallic: 0.0,
+        specular: 0.0,
+        roughness: 1.0,
+
+        transmission: 0.0,
+        ior: 1.5,
+
+        ..Material::blank()
+    }
+}
+
+// maps pbrt's materials onto keikan's principled one, as closely as it goes
+fn material(kind: &str, params: &Params) -> Material {
+    let mut mapped = grey(0.5);
+    let brightest = |c: Vec3| c.x.max(c.y).max(c.z);
+
+    match kind {
+        "matte" => {
+            mapped.color = params.color("Kd", Vec3::new(0.5, 0.5, 0.5));
+        },
+        "plastic" | "uber" | "substrate" => {
+            mapped.color = params.color("Kd", Vec3::new(0.25, 0.25, 0.25));
+            mapped.specular = brightest(params.color("Ks", Vec3::new(0.25, 0.25, 0.25)));
+            mapped.roughness = params.float("roughness", 0.1);
+            mapped.transmission = brightest(params.color("Kt", Vec3::new(0.0, 0.0, 0.0)));
+            mapped.ior = params.float("index", params.float("eta", 1.5));
+        },
+        "metal" => {
+            mapped.color = Vec3::new(0.95, 0.64, 0.54); // pbrt's default is copper
+            mapped.metallic = 1.0;
+            mapped.roughness = params.float("roughness", 0.01);
+        },
+        "mirror" => {
+            mapped.color = params.color("Kr", Vec3::new(0.9, 0.9, 0.9));
+            mapped.metallic = 1.0;
+            mapped.roughness = 0.0;
+        },
+        "glass" => {
+            mapped.color = params.color("Kt", Vec3::new(1.0, 1.0, 1.0));
+            mapped.transmission = 1.0;
+            mapped.roughness = params.float("uroughness", 0.0);
+            mapped.ior = params.float("index", params.float("eta", 1.5));
+        },
+        "disney" => {
+            mapped.color = params.color("color", Vec3::new(0.5, 0.5, 0.5));
+            mapped.metallic = params.float("metallic", 0.0);
+            mapped.roughness = params.float("roughness", 0.5);
+            mapped.transmission = params.float("spectrans", 0.0);
+            mapped.ior = params.float("eta", 1.5);
+            mapped.specular = ((mapped.ior - 1.0) / (mapped.ior + 1.0)).powi(2); // reflected head on
+        },
+        _ => (),
+    }
+
+    return mapped;
+}
+
+// pbrt's LookAt, the world as seen from the eye. looking at the eye itself
+// or straight along up has no answer.
+fn look_at(from: Vec3, to: Vec3, up: Vec3) -> Result<Transform> {
+    let direction = (to - from).unit();
+    let right = up.unit().cross(&direction).unit();
+    let up = direction.cross(&right);
+
+    let camera_to_world = Transform::try_new([
+        [right.x, up.x, direction.x, from.x],
+        [right.y, up.y, direction.y, from.y],
+        [right.z, up.z, direction.z, from.z],
+        [0.0, 0.0, 0.0, 1.0],
+    ]).ok_or_else(|| invalid("LookAt doesn't look anywhere"))?;
+
+    return Ok(camera_to_world.inverted());
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    at: usize,
+    directory: PathBuf,
+
+    attributes: Attributes,
+    saved: Vec<Attributes>,
+    transforms: Vec<Transform>,
+    systems: HashMap<String, Transform>,
+    materials: HashMap<String, Material>,
+
+    camera: Option<(Transform, Float, Float, Float)>, // camera to world, fov, lens radius and focal distance
+    resolution: [usize; 2],
+
+    objects: HashMap<String, Vec<Arc<dyn Trace>>>,
+    defining: Option<(String, Vec<Arc<dyn Trace>>)>,
+    shapes: Vec<Arc<dyn Trace>>,
+    emitters: Vec<Emitter>,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.at).cloned();
+        self.at += 1;
+        return token;
+    }
+
+    fn text(&mut self) -> Result<String> {
+        match self.next() {
+            Some(Token::Text(text)) => Ok(text),
+            _ => Err(invalid("expected a string")),
+        }
+    }
+
+    // n numbers, bracketed or not
+    fn numbers(&mut self, n: usize) -> Result<Vec<Float>> {
+        let bracketed = self.tokens.get(self.at) == Some(&Token::Open);
+        if bracketed { self.at += 1; }
+
+        let mut values = vec![];
+        for _ in 0..n {
+            let value = self.next().as_ref().and_then(number).ok_or_else(|| invalid("expected a number"))?;
+            values.push(value);
+        }
+
+        if bracketed && self.next() != Some(Token::Close) { return Err(invalid("expected ]")); }
+        return Ok(values);
+    }
+
+    fn vector(&mut self) -> Result<Vec3> {
+        let v = self.numbers(3)?;
+        return Ok(Vec3::new(v[0], v[1], v[2]));
+    }
+
+    fn matrix(&mut self) -> Result<Transform> {
+        let m = self.numbers(16)?;
+
+        // pbrt writes its matrices a column at a time
+        let mut matrix = [[0.0; 4]; 4];
+        for (row, values) in matrix.iter_mut().enumerate() {
+            for (col, value) in values.iter_mut().enumerate() {
+                *value = m[col * 4 + row];
+            }
+        }
+
+        return Transform::try_new(matrix).ok_or_else(|| invalid("a matrix with no inverse"));
+    }
+
+    // parameter declarations always have a space in them, "type name"
+    fn params(&mut self) -> Result<Params> {
+        let mut params = Params::default();
+
+        while let Some(Token::Text(declaration)) = self.tokens.get(self.at).cloned() {
+            let mut words = declaration.split_whitespace();
+            let (kind, name) = match (words.next(), words.next()) {
+                (Some(kind), Some(name)) => (kind.to_string(), name.to_string()),
+                _ => break,
+            };
+            self.at += 1;
+
+            let values = match self.next() {
+                Some(Token::Open) => {
+                    let mut values = vec![];
+                    loop {
+                        match self.next() {
+                            Some(Token::Close) => break,
+                            Some(token) => values.push(token),
+                            None => return Err(invalid("expected ]")),
+                        }
+                    }
+                    values
+                },
+                Some(token) => vec![token],
+                None => return Err(invalid("parameter without a value")),
+            };
+
+            params.list.push((kind, name, values));
+        }
+
+        return Ok(params);
+    }
+
+    // a nan or a zero scale anywhere would lose whatever it moves
+    fn transform(&mut self, by: Transform) -> Result<()> {
+        let transform = self.attributes.transform * by;
+        if !by.is_finite() || !transform.is_finite() { return Err(invalid("a transform with no inverse")); }
+        self.attributes.transform = transform;
+        return Ok(());
+    }
+
+    fn directive(&mut self, name: &str) -> Result<()> {
+        match name {
+            "Identity" => self.attributes.transform = Transform::identity(),
+            "Translate" => { let v = self.vector()?; self.transform(Transform::translate(v))?; },
+            "Scale" => { let v = self.vector()?; self.transform(Transform::scale(v))?; },
+            "Rotate" => {
+                let v = self.numbers(4)?;
+                self.transform(Transform::rotate(Vec3::new(v[1], v[2], v[3]), v[0]))?;
+            },
+            "LookAt" => {
+                let (from, to, up) = (self.vector()?, self.vector()?, self.vector()?);
+                let view = look_at(from, to, up)?;
+                self.transform(view)?;
+            },
+            "Transform" => self.attributes.transform = self.matrix()?,
+            "ConcatTransform" => { let m = self.matrix()?; self.transform(m)?; },
+            "CoordinateSystem" => { let name = self.text()?; self.systems.insert(name, self.attributes.transform); },
+            "CoordSysTransform" => {
+                let name = self.text()?;
+                if let Some(transform) = self.systems.get(&name) { self.attributes.transform = *transform; }
+            },
+
+            "Camera" => {
+                self.text()?;
+                let params = self.params()?;
+                let camera_to_world = self.attributes.transform.inverted();
+                self.camera = Some((camera_to_world, params.float("fov", 90.0), params.float("lensradius", 0.0), params.float("focaldistance", 1e6)));
+                self.systems.insert("camera".to_string(), camera_to_world);
+            },
+            "Film" => {
+                self.text()?;
+                let params = self.params()?;
+                self.resolution = [
+                    params.float("xresolution", 1280.0) as usize,
+                    params.float("yresolution", 720.0) as usize,
+                ];
+            },
+            "WorldBegin" => {
+                self.attributes.transform = Transform::identity();
+                self.systems.insert("world".to_string(), Transform::identity());
+            },
+            "WorldEnd" => (),
+
+            "AttributeBegin" => self.saved.push(self.attributes),
+            "AttributeEnd" => self.attributes = self.saved.pop().ok_or_else(|| invalid("AttributeEnd without AttributeBegin"))?,
+            "TransformBegin" => self.transforms.push(self.attributes.transform),
+            "TransformEnd" => {
+                self.attributes.transform = self.transforms.pop().ok_or_else(|| invalid("TransformEnd without TransformBegin"))?;
+            },
+
+            "Material" => {
+                let kind = self.text()?;
+                let params = self.params()?;
+                self.attributes.material = material(&kind, &params);
+            },
+            "MakeNamedMaterial" => {
+                let name = self.text()?;
+                let params = self.params()?;
+                let made = material(params.string("type").unwrap_or("matte"), &params);
+                self.materials.insert(name, made);
+            },
+            "NamedMaterial" => {
+                let name = self.text()?;
+                self.attributes.material = *self.materials.get(&name).ok_or_else(|| invalid(&format!("no material called {}", name)))?;
+            },
+
+            "AreaLightSource" => {
+                self.text()?;
+                let params = self.params()?;
+                self.attributes.glow = Some(params.color("L", Vec3::new(1.0, 1.0, 1.0)) * params.color("scale", Vec3::new(1.0, 1.0, 1.0)));
+            },
+            "LightSource" => {
+                let kind = self.text()?;
+                let params = self.params()?;
+                self.light(&kind, &params);
+            },
+            "Shape" => {
+                let kind = self.text()?;
+                let params = self.params()?;
+                self.shape(&kind, &params)?;
+            },
+
+            "ObjectBegin" => {
+                let name = self.text()?;
+                self.saved.push(self.attributes);
+                self.defining = Some((name, vec![]));
+            },
+            "ObjectEnd" => {
+                if let Some((name, shapes)) = self.defining.take() { self.objects.insert(name, shapes); }
+                self.attributes = self.saved.pop().ok_or_else(|| invalid("ObjectEnd without ObjectBegin"))?;
+            },
+            "ObjectInstance" => {
+                let name = self.text()?;
+                let placed = mirror() * self.attributes.transform;
+                for shape in self.objects.get(&name).ok_or_else(|| invalid(&format!("no object called {}", name)))? {
+                    self.shapes.push(Arc::new(Instance::new(Arc::clone(shape), placed)));
+                }
+            },
+
+            "Include" | "Import" => {
+                let file = self.text()?;
+                let path = self.directory.join(file);
+                let included = tokenize(&fs::read_to_string(&path).map_err(|error| Error::reading(&path, error))?)?;
+                self.tokens.splice(self.at..self.at, included);
+            },
+
+            // the rest is for pbrt itself, or has nothing in keikan to map
+            // onto, so only its arguments need getting past
+            _ => {
+                if let Some(Token::Text(_)) = self.tokens.get(self.at) { self.at += 1; }
+                self.params()?;
+            },
+        }
+
+        return Ok(());
+    }
+
+    fn light(&mut self, kind: &str, params: &Params) {
+        // spot lights lose their cone, distant and infinite lights have no
+        // equivalent since keikan's sky is fixed
+        if kind != "point" && kind != "spot" { return; }
+
+        let intensity = params.color("I", Vec3::new(1.0, 1.0, 1.0)) * params.color("scale", Vec3::new(1.0, 1.0, 1.0));
+        let position = (mirror() * self.attributes.transform).point(params.points("from").first().copied().unwrap_or(Vec3::new(0.0, 0.0, 0.0)));
+
+        // a ball that gives off the same intensity as the point did
+        let radiance = intensity / (PI * POINT_RADIUS * POINT_RADIUS);
+        self.shapes.push(Arc::new(Sphere::new(position, POINT_RADIUS, glowing(grey(0.0), radiance))));
+        self.emitters.push(Emitter::sphere(position, POINT_RADIUS, radiance));
+    }
+
+    fn shape(&mut self, kind: &str, params: &Params) -> Result<()> {
+        let mut material = self.attributes.material;
+        if let Some(glow) = self.attributes.glow { material = glowing(material, glow); }
+
+        // shapes inside an object are placed later, by each instance of it
+        let transform = match self.defining {
+            Some(_) => self.attributes.transform,
+            None => mirror() * self.attributes.transform,
+        };
+
+        let shape: Arc<dyn Trace> = match kind {
+            "sphere" => {
+                let radius = params.float("radius", 1.0);
+                if let (Some(glow), None) = (self.attributes.glow, &self.defining) {
+                    let scale = transform.vector(Vec3::new(1.0, 0.0, 0.0)).length();
+                    self.emitters.push(Emitter::sphere(transform.point(Vec3::new(0.0, 0.0, 0.0)), radius * scale, glow));
+                }
+                Arc::new(Transformed::new(Sphere::new(Vec3::new(0.0, 0.0, 0.0), radius, material), transform))
+            },
+            "disk" => {
+                let center = Vec3::new(0.0, 0.0, params.float("height", 0.0));
+                let disk = Disk::new(center, Vec3::new(0.0, 0.0, 1.0), params.float("radius", 1.0), material);
+                Arc::new(Transformed::new(disk, transform))
+            },
+            "trianglemesh" => {
+                let vertices = params.points("P");
+                let normals = params.points("N");
+                let mut indices: Vec<usize> = params.floats("indices").iter().map(|i| *i as usize).collect();
+                if indices.is_empty() && vertices.len() == 3 { indices = vec![0, 1, 2]; }
+
+                if indices.iter().any(|i| *i >= vertices.len()) { return Err(invalid("triangle index out of range")); }
+                let normals = if normals.len() == vertices.len() { normals } else { vec![] };
+
+                let triangles = indices.chunks_exact(3).map(|t| [t[0], t[1], t[2]]).collect();
+                Arc::new(Mesh::smooth(vertices, normals, triangles, material).transform(&transform))
+            },
+            "plymesh" => {
+                let file = params.string("filename").ok_or_else(|| invalid("plymesh without a filename"))?;
+                let path = self.directory.join(file);
+                Arc::new(ply::load(&path, material)?.transform(&transform))
+            },
+            _ => return Ok(()),
+        };
+
+        match &mut self.defining {
+            Some((_, shapes)) => shapes.push(shape),
+            None => self.shapes.push(shape),
+        }
+
+        return Ok(());
+    }
+}
+
+fn mirror() -> Transform {
+    Transform::scale(Vec3::new(-1.0, 1.0, 1.0))
+}
+
+// a material giving off `radiance`, keeping the rest of it
+fn glowing(mut material: Material, radiance: Vec3) -> Material {
+    let brightest = radiance.x.max(radiance.y).max(radiance.z);
+    if brightest > 0.0 {
+        material.color = radiance / brightest;
+        material.emission = brightest;
+    }
+    return material;
+}
+
+// `directory` is where included files and meshes are looked for.
+// returns the scene and the film's resolution.
+pub fn parse(text: &str, directory: &Path) -> Result<(Scene, [usize; 2])> {
+    let mut parser = Parser {
+        tokens: tokenize(text)?,
+        at: 0,
+        directory: directory.to_path_buf(),
+
+        attributes: Attributes { transform: Transform::identity(), material: grey(0.5), glow: None },
+        saved: vec![],
+        transforms: vec![],
+        systems: HashMap::new(),
+        materials: HashMap::new(),
+
+        camera: None,
+        resolution: [1280, 720],
+
+        objects: HashMap::new(),
+        defining: None,
+        shapes: vec![],
+        emitters: vec![],
+    };
+
+    while let Some(token) = parser.next() {
+        match token {
+            Token::Word(name) => parser.directive(&name)?,
+            _ => return Err(invalid("expected a directive")),
+        }
+    }
+
+    let [width, height] = parser.resolution;
+    if width == 0 || height == 0 { return Err(invalid("empty film")); }
+
+    // without a Camera, pbrt looks down +z from the origin
+    let (camera_to_world, fov, lens_radius, focal_distance) = parser.camera.unwrap_or((Transform::identity(), 90.0, 0.0, 1e6));
+    let to_world = mirror() * camera_to_world;
+    let from = to_world.point(Vec3::new(0.0, 0.0, 0.0));
+    let forward = to_world.vector(Vec3::new(0.0, 0.0, 1.0));
+    let up = to_world.vector(Vec3::new(0.0, 1.0, 0.0)).unit();
+
+    let mut camera = Camera::new(from, from + forward, up);
+
+    // pbrt's fov spans the shorter side of the image, keikan's twice its height
+    let half = (fov.to_radians() / 2.0).tan() * (height as Float / width as Float).max(1.0);
+    camera.fov = (2.0 * (2.0 * half).atan()).to_degrees();
+
+    camera.aperture = 2.0 * lens_radius;
+    camera.focus = focal_distance;
+
+    let mut scene = Scene::new(camera);
+    scene.trace = parser.shapes;
+    scene.emitters = parser.emitters;
+
+    return Ok((scene, parser.resolution));
+}
+
+pub fn load(path: impl AsRef<Path>) -> Result<(Scene, [usize; 2])> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path).map_err(|error| Error::reading(path, error))?;
+    return parse(&text, path.parent().unwrap_or_else(|| Path::new(".")));
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::path::Path;
+
+    use super::parse;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::ray::Ray;
+
+    #[test]
+    fn test_pbrt() {
+        let text = r#"
+            # a red ball on a floor, under an area light
+            LookAt 0 0 5  0 0 0  0 1 0
+            Camera "perspective" "float fov" [45]
+            Film "image" "integer xresolution" [200] "integer yresolution" 100
+            Sampler "halton" "integer pixelsamples" 128
+
+            WorldBegin
+            AttributeBegin
+                Material "matte" "rgb Kd" [0.8 0.1 0.1]
+                Translate 1 0 0
+                Shape "sphere" "float radius" 0.5
+            AttributeEnd
+
+            MakeNamedMaterial "floor" "string type" "plastic" "rgb Kd" [0.2 0.2 0.2]
+            NamedMaterial "floor"
+            Shape "trianglemesh" "integer indices" [0 1 2 0 2 3]
+                "point P" [-5 -1 -5  5 -1 -5  5 -1 5  -5 -1 5]
+
+            AttributeBegin
+                AreaLightSource "diffuse" "rgb L" [4 4 4]
+                Translate 0 3 0
+                Shape "sphere" "float radius" 1
+            AttributeEnd
+            WorldEnd
+        "#;
+
+        let (scene, resolution) = parse(text, Path::new(".")).unwrap();
+        assert_eq!(resolution, [200, 100]);
+        assert_eq!((scene.trace.len(), scene.emitters.len()), (3, 1));
+
+        // the camera looks the way pbrt's does
+        assert!((scene.camera.ray.origin - Vec3::new(0.0, 0.0, 5.0)).length() < 1e-9);
+        assert!((scene.camera.ray.direction - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-9);
+
+        // pbrt puts +x on the left of this view, so the ball is mirrored across
+        let ball = &scene.trace[0];
+        let (hit, distance, _) = ball.trace(Ray::new(Vec3::new(-1.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0)));
+        assert!(hit && (distance - 4.5).abs() < 1e-9);
+        assert!(ball.material().color.x > ball.material().color.y);
+
+        assert!(scene.trace[1].material().specular > 0.0);
+        assert_eq!(scene.trace[2].material().emission, 4.0);
+        assert!((scene.emitters[0].position - Vec3::new(0.0, 3.0, 0.0)).length() < 1e-9);
+
+        assert!(parse("NamedMaterial \"nothing\"", Path::new(".")).is_err());
+
+        // transforms that lose whatever they move are errors, not panics or nans
+        for broken in ["Scale nan 1 1", "Scale 0 1 1", "Rotate 90 0 0 0", "LookAt 0 0 0  0 0 0  0 1 0", "ConcatTransform [0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 1]"].iter() {
+            assert!(parse(broken, Path::new(".")).is_err(), "{}", broken);
+        }
+    }
+}
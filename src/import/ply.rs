@@ -0,0 +1,403 @@
+use std::convert::TryInto;
+use std::path::Path;
+
+use crate::error::{ self, Error, Result };
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::material::Material;
+use crate::objects::mesh::Mesh;
+use crate::objects::point_cloud::{ PointCloud, Point };
+
+fn invalid(message: &str) -> Error {
+    Error::Invalid(format!("ply: {}", message))
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Format {
+    Ascii,
+    LittleEndian,
+    BigEndian,
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Kind {
+    I8, U8, I16, U16, I32, U32, F32, F64,
+}
+
+impl Kind {
+    fn parse(name: &str) -> Result<Kind> {
+        match name {
+            "char"   | "int8"    => Ok(Kind::I8),
+            "uchar"  | "uint8"   => Ok(Kind::U8),
+            "short"  | "int16"   => Ok(Kind::I16),
+            "ushort" | "uint16"  => Ok(Kind::U16),
+            "int"    | "int32"   => Ok(Kind::I32),
+            "uint"   | "uint32"  => Ok(Kind::U32),
+            "float"  | "float32" => Ok(Kind::F32),
+            "double" | "float64" => Ok(Kind::F64),
+            _ => Err(invalid("unknown property type")),
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            Kind::I8  | Kind::U8  => 1,
+            Kind::I16 | Kind::U16 => 2,
+            Kind::I32 | Kind::U32 | Kind::F32 => 4,
+            Kind::F64 => 8,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Property {
+    Scalar(String, Kind),
+    List(String, Kind, Kind),
+}
+
+#[derive(Debug, Clone)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    // the fewest bytes one of them can take up: lists can be empty, and
+    // ascii values are at least a character each
+    fn least(&self, format: Format) -> usize {
+        let size = |kind: &Kind| if format == Format::Ascii { 1 } else { kind.size() };
+
+        return self.properties.iter().map(|property| match property {
+            Property::Scalar(_, kind) => size(kind),
+            Property::List(_, count, _) => size(count),
+        }).sum::<usize>().max(1);
+    }
+}
+
+// walks the body one value at a time, whatever the encoding
+struct Reader<'a> {
+    format: Format,
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Reader<'a> {
+    // how many values of `kind` could still be left, so counts read from
+    // the file can't ask for more memory than the file could fill
+    fn room(&self, kind: Kind) -> usize {
+        let size = if self.format == Format::Ascii { 1 } else { kind.size() };
+        return self.bytes.len().saturating_sub(self.at) / size;
+    }
+
+    fn read(&mut self, kind: Kind) -> Result<Float> {
+        if self.format == Format::Ascii {
+            while self.at < self.bytes.len() && self.bytes[self.at].is_ascii_whitespace() { self.at += 1; }
+            let start = self.at;
+            while self.at < self.bytes.len() && !self.bytes[self.at].is_ascii_whitespace() { self.at += 1; }
+
+            return std::str::from_utf8(&self.bytes[start..self.at]).ok()
+                .and_then(|t| t.parse::<Float>().ok())
+                .ok_or_else(|| invalid("bad value"));
+        }
+
+        let size = kind.size();
+        if self.at + size > self.bytes.len() { return Err(invalid("truncated file")); }
+
+        let mut raw = [0u8; 8];
+        raw[..size].copy_from_slice(&self.bytes[self.at..self.at + size]);
+        if self.format == Format::BigEndian { raw[..size].reverse(); }
+        self.at += size;
+
+        let value = match kind {
+            Kind::I8  => raw[0] as i8 as Float,
+            Kind::U8  => raw[0] as Float,
+            Kind::I16 => i16::from_le_bytes(raw[..2].try_into().unwrap()) as Float,
+            Kind::U16 => u16::from_le_bytes(raw[..2].try_into().unwrap()) as Float,
+            Kind::I32 => i32::from_le_bytes(raw[..4].try_into().unwrap()) as Float,
+            Kind::U32 => u32::from_le_bytes(raw[..4].try_into().unwrap()) as Float,
+            Kind::F32 => f32::from_le_bytes(raw[..4].try_into().unwrap()) as Float,
+            Kind::F64 => f64::from_le_bytes(raw) as Float,
+        };
+
+        return Ok(value);
+    }
+}
+
+fn header(bytes: &[u8]) -> Result<(Format, Vec<Element>, usize)> {
+    let end = bytes.windows(10).position(|w| w == b"end_header")
+        .ok_or_else(|| invalid("no end_header"))?;
+
+    // the body starts after the newline following end_header
+    let mut body = end + 10;
+    while body < bytes.len() && bytes[body] != b'\n' { body += 1; }
+    body += 1;
+
+    let text = std::str::from_utf8(&bytes[..end]).map_err(|_| invalid("header isn't utf-8"))?;
+    let mut lines = text.lines();
+
+    if lines.next().map(|l| l.trim()) != Some("ply") { return Err(invalid("missing magic")); }
+
+    let mut format = None;
+    let mut elements: Vec<Element> = vec![];
+
+    for line in lines {
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        match words.as_slice() {
+            ["format", "ascii", _] => format = Some(Format::Ascii),
+            ["format", "binary_little_endian", _] => format = Some(Format::LittleEndian),
+            ["format", "binary_big_endian", _] => format = Some(Format::BigEndian),
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse().map_err(|_| invalid("bad element count"))?,
+                properties: vec![],
+            }),
+            ["property", "list", count, item, name] => elements.last_mut()
+                .ok_or_else(|| invalid("property outside element"))?
+                .properties.push(Property::List(name.to_string(), Kind::parse(count)?, Kind::parse(item)?)),
+            ["property", kind, name] => elements.last_mut()
+                .ok_or_else(|| invalid("property outside element"))?
+                .properties.push(Property::Scalar(name.to_string(), Kind::parse(kind)?)),
+            _ => (), // comments, obj_info, blank lines
+        }
+    }
+
+    // counts come straight from the header, so a broken or hostile one
+    // could ask for anything. it can't be more than the body has room for.
+    let format = format.ok_or_else(|| invalid("missing format"))?;
+    let least = elements.iter().fold(0usize, |sum, element| sum.saturating_add(element.count.saturating_mul(element.least(format))));
+    if least > bytes.len().saturating_sub(body) { return Err(invalid("more elements than the file has room for")); }
+
+    return Ok((format, elements, body));
+}
+
+pub fn parse(bytes: &[u8], material: Material) -> Result<Mesh> {
+    let (format, elements, body) = header(bytes)?;
+    let mut reader = Reader { format: format, bytes: bytes, at: body };
+
+    let mut vertices = vec![];
+    let mut normals = vec![];
+    let mut triangles = vec![];
+
+    for element in elements.iter() {
+        for _ in 0..element.count {
+            let mut position = [0.0; 3];
+            let mut normal = [0.0; 3];
+            let mut has_normal = false;
+
+            for property in element.properties.iter() {
+                match property {
+                    Property::Scalar(name, kind) => {
+                        let value = reader.read(*kind)?;
+                        match name.as_str() {
+                            "x" => position[0] = value,
+                            "y" => position[1] = value,
+                            "z" => position[2] = value,
+                            "nx" => { normal[0] = value; has_normal = true; },
+                            "ny" => normal[1] = value,
+                            "nz" => normal[2] = value,
+                            _ => (),
+                        }
+                    },
+                    Property::List(name, count, item) => {
+                        let count = reader.read(*count)? as usize;
+                        let mut indices = Vec::with_capacity(count.min(reader.room(*item)));
+                        for _ in 0..count { indices.push(reader.read(*item)? as usize); }
+
+                        // fan out polygons into triangles
+                        if element.name == "face" && (name == "vertex_indices" || name == "vertex_index") {
+                            for i in 1..count.saturating_sub(1) {
+                                triangles.push([indices[0], indices[i], indices[i + 1]]);
+                            }
+                        }
+                    },
+                }
+            }
+
+            if element.name == "vertex" {
+                vertices.push(Vec3::new(position[0], position[1], position[2]));
+                if has_normal { normals.push(Vec3::new(normal[0], normal[1], normal[2])); }
+            }
+        }
+    }
+
+    if triangles.iter().flatten().any(|i| *i >= vertices.len()) {
+        return Err(invalid("face index out of range"));
+    }
+
+    // only smooth shade when every vertex came with a normal
+    if normals.len() != vertices.len() { normals = vec![]; }
+
+    return Ok(Mesh::smooth(vertices, normals, triangles, material));
+}
+
+// loads an ascii or binary ply file, only vertex positions, normals,
+// and faces are read; everything else is skipped over
+pub fn load(path: impl AsRef<Path>, material: Material) -> Result<Mesh> {
+    let path = path.as_ref();
+    return parse(&error::read(path)?, material).map_err(|error| error.in_file(path));
+}
+
+// just the vertices, as a point cloud. each gets its "red", "green" and
+// "blue" if it has them, bytes or shorts scaled to 1 and anything else
+// taken as it is, and its "radius" if it has one, `radius` if not.
+pub fn parse_points(bytes: &[u8], radius: Float, material: Material) -> Result<PointCloud> {
+    let (format, elements, body) = header(bytes)?;
+    let mut reader = Reader { format: format, bytes: bytes, at: body };
+    let mut points = vec![];
+
+    for element in elements.iter() {
+        for _ in 0..element.count {
+            let mut point = Point::new(Vec3::new(0.0, 0.0, 0.0), radius, material.color);
+
+            for property in element.properties.iter() {
+                match property {
+                    Property::Scalar(name, kind) => {
+                        let value = reader.read(*kind)?;
+                        let color = match kind {
+                            Kind::U8 => value / 255.0,
+                            Kind::U16 => value / 65535.0,
+                            _ => value,
+                        };
+
+                        match name.as_str() {
+                            "x" => point.position.x = value,
+                            "y" => point.position.y = value,
+                            "z" => point.position.z = value,
+                            "red" => point.color.x = color,
+                            "green" => point.color.y = color,
+                            "blue" => point.color.z = color,
+                            "radius" => point.radius = value,
+                            _ => (),
+                        }
+                    },
+                    Property::List(_, count, item) => {
+                        let count = reader.read(*count)? as usize;
+                        for _ in 0..count { reader.read(*item)?; }
+                    },
+                }
+            }
+
+            if element.name == "vertex" { points.push(point); }
+        }
+    }
+
+    return Ok(PointCloud::new(points, material));
+}
+
+pub fn load_points(path: impl AsRef<Path>, radius: Float, material: Material) -> Result<PointCloud> {
+    let path = path.as_ref();
+    return parse_points(&error::read(path)?, radius, material).map_err(|error| error.in_file(path));
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::{ parse, parse_points };
+    use crate::structures::vec3::Vec3;
+    use crate::structures::material::Material;
+
+    #[test]
+    fn test_ascii() {
+        let ply = "ply
+format ascii 1.0
+comment a unit quad
+element vertex 4
+property float x
+property float y
+property float z
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0
+1 0 0
+1 1 0
+0 1 0
+4 0 1 2 3
+";
+
+        let mesh = parse(ply.as_bytes(), Material::blank()).unwrap();
+
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.triangles, vec![[0, 1, 2], [0, 2, 3]]);
+    }
+
+    #[test]
+    fn test_binary() {
+        let mut ply = b"ply
+format binary_big_endian 1.0
+element vertex 3
+property double x
+property double y
+property double z
+element face 1
+property list uchar ushort vertex_indices
+end_header
+".to_vec();
+
+        for value in &[0.0f64, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+            ply.extend(&value.to_be_bytes());
+        }
+        ply.push(3);
+        for index in &[0u16, 1, 2] {
+            ply.extend(&index.to_be_bytes());
+        }
+
+        let mesh = parse(&ply, Material::blank()).unwrap();
+
+        assert_eq!(mesh.vertices[1].x, 1.0);
+        assert_eq!(mesh.triangles, vec![[0, 1, 2]]);
+    }
+
+    #[test]
+    fn test_points() {
+        let ply = "ply
+format ascii 1.0
+element vertex 2
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 0
+property list uchar int vertex_indices
+end_header
+0 0 0 255 0 0
+1 2 3 0 0 255
+";
+
+        let cloud = parse_points(ply.as_bytes(), 0.1, Material::blank()).unwrap();
+
+        assert_eq!(cloud.points.len(), 2);
+        assert_eq!(cloud.points[1].position, Vec3::new(1.0, 2.0, 3.0));
+        assert_eq!(cloud.points[0].color, Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(cloud.points[1].radius, 0.1);
+    }
+
+    #[test]
+    fn test_oversized() {
+        // a header asking for far more than is there is an error, not an abort
+        let ply = "ply
+format binary_little_endian 1.0
+element vertex 4000000000000000000
+property float x
+property float y
+property float z
+end_header
+";
+        assert!(parse(ply.as_bytes(), Material::blank()).err().unwrap().to_string().contains("room"));
+        assert!(parse_points(ply.as_bytes(), 0.1, Material::blank()).is_err());
+
+        // and so is a face with more corners than the file has left
+        let mut faces = b"ply
+format binary_little_endian 1.0
+element face 1
+property list uint uint vertex_indices
+end_header
+".to_vec();
+        faces.extend(&u32::MAX.to_le_bytes());
+        faces.extend(&0u32.to_le_bytes());
+        assert!(parse(&faces, Material::blank()).err().unwrap().to_string().contains("truncated"));
+    }
+}
@@ -0,0 +1,599 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use serde::{ Deserialize, Deserializer };
+use serde::de::Error as _;
+use serde_json::{ Map, Value };
+
+use crate::error::{ Error, Result };
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::camera::{ Camera, Framing };
+use crate::structures::material::Material;
+use crate::structures::medium::Medium;
+use crate::structures::photon_map::Emitter;
+use crate::structures::scene::Scene;
+use crate::structures::transform::Transform;
+use crate::structures::aabb::Aabb;
+use crate::structures::node::NodeObject;
+use crate::structures::post::Post;
+use crate::structures::noise::Fractal;
+use crate::render::{ Integrator, Bounces, NanGuard };
+use crate::objects::sphere::Sphere;
+use crate::objects::plane::Plane;
+use crate::objects::cuboid::Cuboid;
+use crate::objects::torus::Torus;
+use crate::objects::cylinder::Cylinder;
+use crate::objects::capsule::Capsule;
+use crate::objects::cone::Cone;
+use crate::objects::hex_prism::HexPrism;
+use crate::objects::disk::Disk;
+use crate::objects::quad::Quad;
+use crate::objects::triangle::Triangle;
+use crate::objects::mandelbulb::Mandelbulb;
+use crate::objects::julia::Julia;
+use crate::objects::menger::Menger;
+use crate::objects::text::Text;
+use crate::objects::curves::{ Curves, Curve, CurveShape };
+use crate::objects::csg::{ Union, Intersection, Difference, SmoothUnion };
+use crate::objects::modifiers::{ Rounded, Shell, Onion, Displaced };
+use crate::objects::domain::{ Repeat, RepeatLimited, Mirror, Polar };
+use crate::objects::transformed::Transformed;
+use crate::objects::baked::Baked;
+use crate::objects::visible::{ Visible, Visibility };
+use crate::objects::shadow_catcher::ShadowCatcher;
+use crate::objects::light_group::LightGroup;
+use crate::objects::clipped::ClipPlane;
+use crate::objects::traits::{ March, Trace };
+use crate::import::{ stl, ply, xyz, ttf };
+
+// scenes written by hand in json. a file looks like
+//
+//     {
+//         "camera": { "from": [-2, 1, 4], "to": [0, 0, 0] },
+//         "materials": { "gold": { "color": [0.9, 0.9, 0.7], "metallic": 1 } },
+//         "lights": [{ "position": [4, 4, 4], "radius": 2, "color": [1, 1, 1], "strength": 1 }],
+//         "march": [
+//             { "type": "SmoothUnion", "k": 0.3, "objects": [
+//                 { "type": "Sphere", "position": [0, 0, 0], "radius": 1, "material": "gold" },
+//                 { "type": "Torus", "position": [0, -0.5, 0], "major": 1.5, "minor": 0.2 }
+//             ]}
+//         ],
+//         "trace": [{ "type": "Plane", "position": [0, -1, 0], "normal": [0, 1, 0] }]
+//     }
+//
+// "camera" is where it's seen from. it can take an "up", a "fov", an
+// "aperture" sharp at "focus" or at whatever's under "autofocus": [0.5, 0.5],
+// a lens "distortion" and chromatic "aberration", and a "framing" like
+// { "Fit": 1.78 } or "Fill", see Camera and Framing.
+//
+// "cameras" names more of them to render the same scene from too, see
+// render::render_cameras.
+//
+// "materials" are named ones for objects to use. objects can write theirs
+// out in place instead. any field left out is a plain grey diffuse's, see
+// Material for the rest, like "importance", "two_sided_emission", "hair"
+// and "priority" (which only sppm refracts through).
+//
+// "lights" are glowing spheres, which can be put in a "group", see
+// LightGroup.
+//
+// "march" and "trace" are the objects, by "type", see Object. marched ones
+// can be combined and warped, traced ones are the exact primitives and
+// meshes. top level ones can have a "name", see Scene::add_named, a
+// "visibility" like { "camera": false }, a "light_group" and be made a
+// "shadow_catcher", see ShadowCatcher.
+//
+// "clip" is a list of planes like { "point": [0, 0, 0], "normal": [0, 0, 1],
+// "cap": "red" } slicing through everything but the lights, see ClipPlane.
+//
+// "environment" is the color of misses, the blue sky otherwise, and
+// "medium" fills the whole scene, see Medium.
+//
+// "integrator", "samples", "blue_noise": true, "nan_guard" and "bounces"
+// like { "diffuse": 3, "regularize": 1 } set how it's rendered, see
+// Integrator, structures::blue_noise, NanGuard and Bounces.
+//
+// "post" is a list of effects for the finished frame, like
+// { "Vignette": { "strength": 0.3 } }, see Post.
+
+fn invalid(message: String) -> Error {
+    Error::Invalid(format!("scene file: {}", message))
+}
+
+#[derive(Deserialize)]
+struct File {
+    camera: CameraFile,
+    #[serde(default)]
+    cameras: HashMap<String, CameraFile>,
+    #[serde(default)]
+    materials: HashMap<String, Surface>,
+    #[serde(default)]
+    lights: Vec<Light>,
+    #[serde(default)]
+    march: Vec<Entry>,
+    #[serde(default)]
+    trace: Vec<Entry>,
+    #[serde(default)]
+    medium: Option<Medium>,
+    #[serde(default)]
+    integrator: Integrator,
+    #[serde(default)]
+    samples: Option<u32>,
+    #[serde(default)]
+    blue_noise: bool,
+    #[serde(default)]
+    environment: Option<Vec3>,
+    #[serde(default)]
+    clip: Vec<Clip>,
+    #[serde(default)]
+    nan_guard: NanGuard,
+    #[serde(default)]
+    bounces: Bounces,
+    #[serde(default)]
+    post: Vec<Post>,
+}
+
+// a clipping plane, in the camera's space if "camera" is set
+#[derive(Deserialize)]
+struct Clip {
+    point: Vec3,
+    normal: Vec3,
+    #[serde(default)]
+    camera: bool,
+    #[serde(default)]
+    cap: Option<MaterialRef>,
+}
+
+#[derive(Deserialize)]
+struct CameraFile {
+    from: Vec3,
+    to: Vec3,
+    #[serde(default = "up")]
+    up: Vec3,
+    #[serde(default = "fov")]
+    fov: Float,
+    #[serde(default)]
+    aperture: Float,
+    #[serde(default = "focus")]
+    focus: Float,
+    #[serde(default)]
+    autofocus: Option<[Float; 2]>,
+    #[serde(default)]
+    distortion: Float,
+    #[serde(default)]
+    aberration: Float,
+    #[serde(default)]
+    framing: Framing,
+}
+
+impl CameraFile {
+    fn camera(&self) -> Camera {
+        let mut camera = Camera::new(self.from, self.to, self.up);
+        camera.fov = self.fov;
+        camera.aperture = self.aperture;
+        camera.focus = self.focus;
+        camera.autofocus = self.autofocus;
+        camera.distortion = self.distortion;
+        camera.aberration = self.aberration;
+        camera.framing = self.framing;
+        return camera;
+    }
+}
+
+fn up() -> Vec3 { Vec3::new(0.0, 1.0, 0.0) }
+fn fov() -> Float { 60.0 }
+fn focus() -> Float { 1.0 }
+
+// a material as scene files write it, any of Material's fields over a
+// plain grey. it goes through json values so whatever Material gains
+// can be written here too.
+#[derive(Clone, Copy)]
+struct Surface(Material);
+
+impl Default for Surface {
+    fn default() -> Surface {
+        Surface(Material {
+            color: Vec3::new(0.8, 0.8, 0.8),
+            emission: 0.0,
+            roughness: 0.5,
+            ior: 1.5,
+            ..Material::blank()
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Surface {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Surface, D::Error> {
+        let mut fields = match serde_json::to_value(Surface::default().0) {
+            Ok(Value::Object(fields)) => fields,
+            _ => return Err(D::Error::custom("the default material isn't an object")),
+        };
+        fields.extend(Map::deserialize(deserializer)?);
+        return Material::deserialize(Value::Object(fields)).map(Surface).map_err(D::Error::custom);
+    }
+}
+
+impl Surface {
+    fn material(&self) -> Material {
+        return self.0;
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MaterialRef {
+    Named(String),
+    Inline(Surface),
+}
+
+impl Default for MaterialRef {
+    fn default() -> MaterialRef { MaterialRef::Inline(Surface::default()) }
+}
+
+// a glowing sphere, seen by the camera and by the bidirectional integrator
+#[derive(Deserialize)]
+struct Light {
+    position: Vec3,
+    radius: Float,
+    #[serde(default = "white")]
+    color: Vec3,
+    #[serde(default = "one")]
+    strength: Float,
+    #[serde(default)]
+    group: Option<String>,
+}
+
+fn white() -> Vec3 { Vec3::new(1.0, 1.0, 1.0) }
+fn one() -> Float { 1.0 }
+
+#[derive(Deserialize)]
+struct Rotation {
+    axis: Vec3,
+    degrees: Float,
+}
+
+// a top level object, which can be given a name to find it by later, see
+// Scene::add_named, and hidden from some rays, see Visible
+#[derive(Deserialize)]
+struct Entry {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    visibility: Visibility,
+    #[serde(default)]
+    shadow_catcher: bool,
+    #[serde(default)]
+    light_group: Option<String>,
+    #[serde(flatten)]
+    object: Object,
+}
+
+// every kind of object a file can hold, by its "type"
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+enum Object {
+    Sphere { position: Vec3, radius: Float, #[serde(default)] material: MaterialRef },
+    Plane { position: Vec3, normal: Vec3, #[serde(default)] material: MaterialRef },
+    Cuboid { position: Vec3, size: Vec3, #[serde(default)] radius: Float, #[serde(default)] material: MaterialRef },
+    Torus { position: Vec3, major: Float, minor: Float, #[serde(default)] material: MaterialRef },
+    Cylinder { position: Vec3, radius: Float, height: Float, #[serde(default)] material: MaterialRef },
+    Capsule { start: Vec3, end: Vec3, radius: Float, #[serde(default)] material: MaterialRef },
+    Cone { position: Vec3, radius: Float, height: Float, #[serde(default)] material: MaterialRef },
+    HexPrism { position: Vec3, radius: Float, height: Float, #[serde(default)] material: MaterialRef },
+    Mandelbulb { position: Vec3, power: Float, iterations: usize, #[serde(default)] material: MaterialRef },
+    Julia { position: Vec3, c: [Float; 4], iterations: usize, #[serde(default)] material: MaterialRef },
+    Menger { position: Vec3, size: Float, iterations: usize, #[serde(default)] material: MaterialRef },
+    // "text" in a .ttf "font", see objects::text
+    Text { font: String, text: String, position: Vec3, size: Float, depth: Float, #[serde(default)] material: MaterialRef },
+
+    // traced only
+    Disk { position: Vec3, normal: Vec3, radius: Float, #[serde(default)] material: MaterialRef },
+    Quad { corner: Vec3, u: Vec3, v: Vec3, #[serde(default)] material: MaterialRef },
+    Triangle { a: Vec3, b: Vec3, c: Vec3, #[serde(default)] material: MaterialRef },
+    Mesh { path: String, #[serde(default)] material: MaterialRef },
+    // four "points" and root and tip "widths" each, "Flat" or "Cylinder", see objects::curves
+    Curves { curves: Vec<Curve>, #[serde(default)] shape: CurveShape, #[serde(default)] material: MaterialRef },
+    // a ball for each point of an .xyz or .ply cloud
+    Points { path: String, radius: Float, #[serde(default)] material: MaterialRef },
+
+    // marched only
+    Union { objects: Vec<Object> },
+    Intersection { objects: Vec<Object> },
+    Difference { from: Box<Object>, subtract: Box<Object> },
+    SmoothUnion { objects: Vec<Object>, k: Float },
+    Rounded { object: Box<Object>, radius: Float },
+    Shell { object: Box<Object>, thickness: Float },
+    Onion { object: Box<Object>, thickness: Float, layers: usize },
+    // "noise" like { "basis": "Simplex", "octaves": 5 }, see noise::Fractal
+    Displaced { object: Box<Object>, amplitude: Float, #[serde(default)] noise: Fractal },
+    Repeat { object: Box<Object>, period: Vec3 },
+    RepeatLimited { object: Box<Object>, period: Vec3, limit: Vec3 },
+    Mirror { object: Box<Object>, axes: [bool; 3] },
+    Polar { object: Box<Object>, count: usize },
+    // sampled once, "resolution" cells along the longest side, see objects::baked
+    Baked { object: Box<Object>, min: Vec3, max: Vec3, resolution: usize },
+
+    // scaled, then rotated, then moved
+    Transform {
+        object: Box<Object>,
+        #[serde(default)] scale: Option<Vec3>,
+        #[serde(default)] rotate: Option<Rotation>,
+        #[serde(default)] translate: Option<Vec3>,
+    },
+}
+
+// what the objects in a file need from the rest of it
+struct Context<'a> {
+    materials: &'a HashMap<String, Surface>,
+    directory: &'a Path,
+}
+
+impl<'a> Context<'a> {
+    fn material(&self, reference: &MaterialRef) -> Result<Material> {
+        match reference {
+            MaterialRef::Inline(surface) => Ok(surface.material()),
+            MaterialRef::Named(name) => self.materials.get(name)
+                .map(|surface| surface.material())
+                .ok_or_else(|| invalid(format!("no material called {}", name))),
+        }
+    }
+
+    fn march(&self, object: &Object) -> Result<Arc<dyn March>> {
+        let shape: Arc<dyn March> = match object {
+            Object::Sphere { position, radius, material } => Arc::new(Sphere::new(*position, *radius, self.material(material)?)),
+            Object::Plane { position, normal, material } => Arc::new(Plane::new(*position, *normal, self.material(material)?)),
+            Object::Cuboid { position, size, radius, material } => Arc::new(Cuboid::rounded(*position, *size, *radius, self.material(material)?)),
+            Object::Torus { position, major, minor, material } => Arc::new(Torus::new(*position, *major, *minor, self.material(material)?)),
+            Object::Cylinder { position, radius, height, material } => Arc::new(Cylinder::new(*position, *radius, *height, self.material(material)?)),
+            Object::Capsule { start, end, radius, material } => Arc::new(Capsule::new(*start, *end, *radius, self.material(material)?)),
+            Object::Cone { position, radius, height, material } => Arc::new(Cone::new(*position, *radius, *height, self.material(material)?)),
+            Object::HexPrism { position, radius, height, material } => Arc::new(HexPrism::new(*position, *radius, *height, self.material(material)?)),
+            Object::Mandelbulb { position, power, iterations, material } => Arc::new(Mandelbulb::new(*position, *power, *iterations, self.material(material)?)),
+            Object::Julia { position, c, iterations, material } => Arc::new(Julia::new(*position, *c, *iterations, self.material(material)?)),
+            Object::Menger { position, size, iterations, material } => Arc::new(Menger::new(*position, *size, *iterations, self.material(material)?)),
+            Object::Text { font, text, position, size, depth, material } => {
+                let path = self.directory.join(font);
+                let font = ttf::load(&path)?;
+                Arc::new(Text::new(&font, text, *position, *size, *depth, self.material(material)?))
+            },
+
+            Object::Union { objects } => self.fold(objects, |a, b| Arc::new(Union::new(a, b)))?,
+            Object::Intersection { objects } => self.fold(objects, |a, b| Arc::new(Intersection::new(a, b)))?,
+            Object::SmoothUnion { objects, k } => self.fold(objects, |a, b| Arc::new(SmoothUnion::new(a, b, *k)))?,
+            Object::Difference { from, subtract } => Arc::new(Difference::new(self.march(from)?, self.march(subtract)?)),
+            Object::Rounded { object, radius } => Arc::new(Rounded::new(self.march(object)?, *radius)),
+            Object::Shell { object, thickness } => Arc::new(Shell::new(self.march(object)?, *thickness)),
+            Object::Onion { object, thickness, layers } => Arc::new(Onion::new(self.march(object)?, *thickness, *layers)),
+            Object::Displaced { object, amplitude, noise } => Arc::new(Displaced::new(self.march(object)?, *amplitude, *noise)),
+            Object::Repeat { object, period } => Arc::new(Repeat::new(self.march(object)?, *period)),
+            Object::RepeatLimited { object, period, limit } => Arc::new(RepeatLimited::new(self.march(object)?, *period, *limit)),
+            Object::Mirror { object, axes } => Arc::new(Mirror::new(self.march(object)?, *axes)),
+            Object::Polar { object, count } => Arc::new(Polar::new(self.march(object)?, *count)),
+            Object::Baked { object, min, max, resolution } => Arc::new(Baked::new(self.march(object)?, Aabb::new(*min, *max), *resolution)),
+
+            Object::Transform { object, scale, rotate, translate } => {
+                Arc::new(Transformed::new(self.march(object)?, transform(scale, rotate, translate)?))
+            },
+
+            Object::Disk { .. } | Object::Quad { .. } | Object::Triangle { .. } | Object::Mesh { .. } | Object::Curves { .. } | Object::Points { .. } => {
+                return Err(invalid(format!("{} can only be traced", name(object))));
+            },
+        };
+
+        return Ok(shape);
+    }
+
+    fn trace(&self, object: &Object) -> Result<Arc<dyn Trace>> {
+        let shape: Arc<dyn Trace> = match object {
+            Object::Sphere { position, radius, material } => Arc::new(Sphere::new(*position, *radius, self.material(material)?)),
+            Object::Plane { position, normal, material } => Arc::new(Plane::new(*position, *normal, self.material(material)?)),
+            Object::Cuboid { position, size, material, .. } => Arc::new(Cuboid::new(*position, *size, self.material(material)?)),
+            Object::Disk { position, normal, radius, material } => Arc::new(Disk::new(*position, *normal, *radius, self.material(material)?)),
+            Object::Quad { corner, u, v, material } => Arc::new(Quad::new(*corner, *u, *v, self.material(material)?)),
+            Object::Triangle { a, b, c, material } => Arc::new(Triangle::new(*a, *b, *c, self.material(material)?)),
+
+            Object::Mesh { path, material } => {
+                let path = self.directory.join(path);
+                let material = self.material(material)?;
+
+                match path.extension().and_then(|extension| extension.to_str()) {
+                    Some("stl") => Arc::new(stl::load(&path, material)?),
+                    Some("ply") => Arc::new(ply::load(&path, material)?),
+                    _ => return Err(invalid(format!("can't load meshes like {}", path.display()))),
+                }
+            },
+
+            Object::Curves { curves, shape, material } => Arc::new(Curves::new(curves.clone(), *shape, self.material(material)?)),
+
+            Object::Points { path, radius, material } => {
+                let path = self.directory.join(path);
+                let material = self.material(material)?;
+
+                match path.extension().and_then(|extension| extension.to_str()) {
+                    Some("xyz") => Arc::new(xyz::load(&path, *radius, material)?),
+                    Some("ply") => Arc::new(ply::load_points(&path, *radius, material)?),
+                    _ => return Err(invalid(format!("can't load point clouds like {}", path.display()))),
+                }
+            },
+
+            Object::Transform { object, scale, rotate, translate } => {
+                Arc::new(Transformed::new(self.trace(object)?, transform(scale, rotate, translate)?))
+            },
+
+            _ => return Err(invalid(format!("{} can only be marched", name(object)))),
+        };
+
+        return Ok(shape);
+    }
+
+    // a list of objects combined pairwise, left to right
+    fn fold(
+        &self,
+        objects: &[Object],
+        combine: impl Fn(Arc<dyn March>, Arc<dyn March>) -> Arc<dyn March>,
+    ) -> Result<Arc<dyn March>> {
+        let mut shapes = objects.iter().map(|object| self.march(object));
+        let first = shapes.next().ok_or_else(|| invalid("combining nothing".to_string()))??;
+        return shapes.try_fold(first, |a, b| Ok(combine(a, b?)));
+    }
+}
+
+// a zero scale or a rotation about nothing would lose the object
+fn transform(scale: &Option<Vec3>, rotate: &Option<Rotation>, translate: &Option<Vec3>) -> Result<Transform> {
+    let mut transform = Transform::identity();
+    if let Some(factor) = scale { transform = Transform::scale(*factor) * transform; }
+    if let Some(rotation) = rotate { transform = Transform::rotate(rotation.axis, rotation.degrees) * transform; }
+    if let Some(offset) = translate { transform = Transform::translate(*offset) * transform; }
+    if !transform.is_finite() { return Err(invalid("a transform with no inverse".to_string())); }
+    return Ok(transform);
+}
+
+fn name(object: &Object) -> &'static str {
+    match object {
+        Object::Disk { .. } => "a disk",
+        Object::Quad { .. } => "a quad",
+        Object::Triangle { .. } => "a triangle",
+        Object::Mesh { .. } => "a mesh",
+        Object::Curves { .. } => "curves",
+        Object::Points { .. } => "a point cloud",
+        Object::Mandelbulb { .. } | Object::Julia { .. } | Object::Menger { .. } => "a fractal",
+        Object::Text { .. } => "text",
+        Object::Union { .. } | Object::Intersection { .. } | Object::Difference { .. } | Object::SmoothUnion { .. } => "a combination",
+        Object::Baked { .. } => "a baked field",
+        _ => "this",
+    }
+}
+
+// `directory` is where meshes are looked for
+pub fn parse(text: &str, directory: &Path) -> Result<Scene> {
+    let file: File = serde_json::from_str(text).map_err(|error| invalid(error.to_string()))?;
+    let context = Context { materials: &file.materials, directory: directory };
+
+    let camera = file.camera.camera();
+    let mut scene = Scene::new(camera);
+    for (name, other) in &file.cameras { scene.add_camera(name, other.camera()); }
+    scene.medium = file.medium;
+    scene.integrator = file.integrator;
+    scene.nan_guard = file.nan_guard;
+    scene.bounces = file.bounces;
+    scene.post = file.post.clone();
+    if let Some(samples) = file.samples { scene.samples = samples; }
+    scene.blue_noise = file.blue_noise;
+    if let Some(environment) = file.environment { scene.environment = environment; }
+
+    for entry in &file.march {
+        let mut object = context.march(&entry.object)?;
+        if entry.visibility != Visibility::ALL { object = Arc::new(Visible::new(object, entry.visibility)); }
+        if entry.shadow_catcher { object = Arc::new(ShadowCatcher::new(object)); }
+        if let Some(group) = &entry.light_group { object = Arc::new(LightGroup::new(object, group)); }
+
+        let object = NodeObject::March(object);
+        match &entry.name { Some(name) => scene.add_named(name, object), None => scene.add(object) }
+    }
+    for entry in &file.trace {
+        let mut object = context.trace(&entry.object)?;
+        if entry.visibility != Visibility::ALL { object = Arc::new(Visible::new(object, entry.visibility)); }
+        if entry.shadow_catcher { object = Arc::new(ShadowCatcher::new(object)); }
+        if let Some(group) = &entry.light_group { object = Arc::new(LightGroup::new(object, group)); }
+
+        let object = NodeObject::Trace(object);
+        match &entry.name { Some(name) => scene.add_named(name, object), None => scene.add(object) }
+    }
+
+    for clip in &file.clip {
+        let mut plane = match clip.camera {
+            true => ClipPlane::camera(&camera, clip.point, clip.normal),
+            false => ClipPlane::new(clip.point, clip.normal),
+        };
+        if let Some(cap) = &clip.cap { plane = plane.with_cap(context.material(cap)?); }
+        scene.clip(plane);
+    }
+
+    for light in &file.lights {
+        let mut glow = Material::blank();
+        glow.color = light.color;
+        glow.emission = light.strength;
+
+        let sphere = Sphere::new(light.position, light.radius, glow);
+        match &light.group {
+            Some(group) => scene.add_trace(LightGroup::new(sphere, group)),
+            None => scene.add_trace(sphere),
+        }
+        scene.emitters.push(Emitter::sphere(light.position, light.radius, light.color * light.strength));
+    }
+
+    return Ok(scene);
+}
+
+pub fn load(path: impl AsRef<Path>) -> Result<Scene> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path).map_err(|error| Error::reading(path, error))?;
+    return parse(&text, path.parent().unwrap_or_else(|| Path::new(".")));
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::path::Path;
+
+    use super::parse;
+    use crate::error::Error;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::camera::Framing;
+
+    #[test]
+    fn test_scene_file() {
+        let text = r#"{
+            "camera": { "from": [0, 0, 5], "to": [0, 0, 0], "fov": 45 },
+            "cameras": { "side": { "from": [5, 0, 0], "to": [0, 0, 0], "framing": { "Fill": 1.5 } } },
+            "materials": { "gold": { "color": [0.9, 0.9, 0.7], "metallic": 1, "priority": 2 } },
+            "lights": [{ "position": [4, 4, 4], "radius": 1 }],
+            "march": [{
+                "type": "Transform", "translate": [0, 1, 0],
+                "object": { "type": "Difference",
+                    "from": { "type": "Cuboid", "position": [0, 0, 0], "size": [1, 1, 1], "material": "gold" },
+                    "subtract": { "type": "Sphere", "position": [0, 0, 0], "radius": 1.2 }
+                }
+            }],
+            "trace": [{ "type": "Plane", "position": [0, -1, 0], "normal": [0, 1, 0] }]
+        }"#;
+
+        let scene = parse(text, Path::new(".")).unwrap();
+        assert_eq!(scene.camera.fov, 45.0);
+        assert_eq!(scene.cameras["side"].ray.origin, Vec3::new(5.0, 0.0, 0.0));
+        assert_eq!(scene.cameras["side"].framing, Framing::Fill(1.5));
+        assert_eq!((scene.march.len(), scene.trace.len(), scene.emitters.len()), (1, 2, 1));
+
+        // the middle of the box is carved out, its corners aren't
+        assert!(scene.march[0].march(Vec3::new(0.0, 1.0, 0.0)) > 0.0);
+        assert!(scene.march[0].march(Vec3::new(0.95, 1.95, 0.95)) < 0.0);
+        assert_eq!(scene.march[0].material().metallic, 1.0);
+
+        // and whatever a material leaves out is the plain grey's
+        let gold = scene.march[0].material();
+        assert_eq!((gold.priority, gold.roughness, gold.ior, gold.emission), (2, 0.5, 1.5, 0.0));
+        assert_eq!(scene.trace[0].material().color, Vec3::new(0.8, 0.8, 0.8));
+
+        // mistakes say what's wrong
+        let wrong = r#"{ "camera": { "from": [0, 0, 5], "to": [0, 0, 0] }, "march": [{ "type": "Disk",
+            "position": [0, 0, 0], "normal": [0, 1, 0], "radius": 1 }] }"#;
+        assert!(parse(wrong, Path::new(".")).err().unwrap().to_string().contains("traced"));
+
+        let missing = r#"{ "camera": { "from": [0, 0, 5], "to": [0, 0, 0] }, "trace": [{ "type": "Sphere",
+            "position": [0, 0, 0], "radius": 1, "material": "chrome" }] }"#;
+        assert!(parse(missing, Path::new(".")).err().unwrap().to_string().contains("chrome"));
+
+        let flat = r#"{ "camera": { "from": [0, 0, 5], "to": [0, 0, 0] }, "trace": [{ "type": "Transform",
+            "scale": [0, 1, 1], "object": { "type": "Sphere", "position": [0, 0, 0], "radius": 1 } }] }"#;
+        assert!(parse(flat, Path::new(".")).err().unwrap().to_string().contains("inverse"));
+
+        // and files that aren't there say which
+        let mesh = r#"{ "camera": { "from": [0, 0, 5], "to": [0, 0, 0] }, "trace": [{ "type": "Mesh",
+            "path": "nowhere/teapot.stl" }] }"#;
+        match parse(mesh, Path::new("models")) {
+            Err(Error::NotFound(path)) => assert_eq!(path, Path::new("models/nowhere/teapot.stl")),
+            other => panic!("expected the mesh not to be found, not {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::path::Path;
+
+use crate::error::{ self, Error, Result };
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::material::Material;
+use crate::objects::mesh::Mesh;
+
+fn invalid(message: &str) -> Error {
+    Error::Invalid(format!("stl: {}", message))
+}
+
+// stl stores every triangle with its own three corners,
+// so identical corners are welded back together.
+fn weld(corners: Vec<Vec3>, material: Material) -> Mesh {
+    let mut seen = HashMap::new();
+    let mut vertices = vec![];
+
+    let indices: Vec<usize> = corners.iter().map(|c| {
+        *seen.entry([c.x.to_bits(), c.y.to_bits(), c.z.to_bits()]).or_insert_with(|| {
+            vertices.push(*c);
+            vertices.len() - 1
+        })
+    }).collect();
+
+    let triangles = indices.chunks_exact(3).map(|t| [t[0], t[1], t[2]]).collect();
+    return Mesh::new(vertices, triangles, material);
+}
+
+fn binary(bytes: &[u8]) -> Result<Vec<Vec3>> {
+    let count = u32::from_le_bytes(bytes[80..84].try_into().unwrap()) as usize;
+
+    if bytes.len() < 84 + count * 50 { return Err(invalid("truncated file")); }
+
+    let float = |at: usize| f32::from_le_bytes(bytes[at..at + 4].try_into().unwrap()) as Float;
+    let mut corners = Vec::with_capacity(count * 3);
+
+    for triangle in 0..count {
+        // skip the 12 byte facet normal, it's recomputed from the winding
+        let start = 84 + triangle * 50 + 12;
+        for corner in 0..3 {
+            let at = start + corner * 12;
+            corners.push(Vec3::new(float(at), float(at + 4), float(at + 8)));
+        }
+    }
+
+    return Ok(corners);
+}
+
+fn ascii(text: &str) -> Result<Vec<Vec3>> {
+    let mut corners = vec![];
+    let mut tokens = text.split_whitespace();
+
+    while let Some(token) = tokens.next() {
+        if token != "vertex" { continue; }
+
+        let mut xyz = [0.0; 3];
+        for axis in xyz.iter_mut() {
+            *axis = tokens.next()
+                .and_then(|t| t.parse::<Float>().ok())
+                .ok_or_else(|| invalid("bad vertex"))?;
+        }
+
+        corners.push(Vec3::new(xyz[0], xyz[1], xyz[2]));
+    }
+
+    if corners.len() % 3 != 0 { return Err(invalid("vertex count isn't a multiple of 3")); }
+
+    return Ok(corners);
+}
+
+pub fn parse(bytes: &[u8], material: Material) -> Result<Mesh> {
+    if bytes.len() < 84 && !bytes.starts_with(b"solid") {
+        return Err(invalid("too short"));
+    }
+
+    // binary files may start with "solid" too, so trust the size when it adds up
+    let is_binary = bytes.len() >= 84 && {
+        let count = u32::from_le_bytes(bytes[80..84].try_into().unwrap()) as usize;
+        bytes.len() == 84 + count * 50 || !bytes.starts_with(b"solid")
+    };
+
+    let corners = if is_binary {
+        binary(bytes)?
+    } else {
+        ascii(std::str::from_utf8(bytes).map_err(|_| invalid("not utf-8"))?)?
+    };
+
+    return Ok(weld(corners, material));
+}
+
+// loads a binary or ascii stl file
+pub fn load(path: impl AsRef<Path>, material: Material) -> Result<Mesh> {
+    let path = path.as_ref();
+    return parse(&error::read(path)?, material).map_err(|error| error.in_file(path));
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::parse;
+    use crate::structures::material::Material;
+
+    #[test]
+    fn test_ascii() {
+        let stl = "solid quad
+            facet normal 0 0 1
+              outer loop
+                vertex 0 0 0
+                vertex 1 0 0
+                vertex 1 1 0
+              endloop
+            endfacet
+            facet normal 0 0 1
+              outer loop
+                vertex 0 0 0
+                vertex 1 1 0
+                vertex 0 1 0
+              endloop
+            endfacet
+        endsolid quad";
+
+        let mesh = parse(stl.as_bytes(), Material::blank()).unwrap();
+
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.triangles, vec![[0, 1, 2], [0, 2, 3]]);
+    }
+
+    #[test]
+    fn test_binary() {
+        let mut stl = vec![0u8; 80];
+        stl.extend(&1u32.to_le_bytes());
+        for value in &[0.0f32, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+            stl.extend(&value.to_le_bytes());
+        }
+        stl.extend(&[0, 0]);
+
+        let mesh = parse(&stl, Material::blank()).unwrap();
+
+        assert_eq!(mesh.vertices.len(), 3);
+        assert_eq!(mesh.triangles, vec![[0, 1, 2]]);
+    }
+}
@@ -0,0 +1,410 @@
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::path::Path;
+
+use crate::error::{ self, Error, Result };
+use crate::structures::float::Float;
+
+// straight pieces each curve of an outline is cut into
+const STEPS: usize = 8;
+// how deep composite glyphs can nest before the font's assumed broken
+const DEPTH: usize = 8;
+
+fn invalid(message: &str) -> Error {
+    Error::Invalid(format!("ttf: {}", message))
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> Result<u16> {
+    bytes.get(at..at + 2)
+        .map(|b| u16::from_be_bytes(b.try_into().unwrap()))
+        .ok_or_else(|| invalid("truncated file"))
+}
+
+fn i16_at(bytes: &[u8], at: usize) -> Result<i16> {
+    u16_at(bytes, at).map(|value| value as i16)
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> Result<u32> {
+    bytes.get(at..at + 4)
+        .map(|b| u32::from_be_bytes(b.try_into().unwrap()))
+        .ok_or_else(|| invalid("truncated file"))
+}
+
+fn byte_at(bytes: &[u8], at: usize) -> Result<u8> {
+    bytes.get(at).copied().ok_or_else(|| invalid("truncated file"))
+}
+
+// the outline of one character, as closed polylines, and how far along
+// the next one starts. everything's in ems, so 1 is the font's size.
+#[derive(Debug, Clone, Default)]
+pub struct Glyph {
+    pub contours: Vec<Vec<[Float; 2]>>,
+    pub advance: Float,
+}
+
+// the outlines out of a truetype font, with quadratic curves only, so
+// .ttf and not the postscript flavoured .otf. hinting, kerning and
+// everything else that isn't the shape of a glyph is left out.
+#[derive(Debug, Clone)]
+pub struct Font {
+    pub line_height: Float,
+    glyphs: HashMap<char, Glyph>,
+}
+
+impl Font {
+    pub fn glyph(&self, character: char) -> Option<&Glyph> {
+        self.glyphs.get(&character)
+    }
+}
+
+// an outline point, and whether it's on the curve or the control point
+// between two that are
+type Point = ([Float; 2], bool);
+
+struct Tables<'a> {
+    glyf: &'a [u8],
+    loca: Vec<usize>,
+}
+
+impl<'a> Tables<'a> {
+    fn contours(&self, index: usize, depth: usize) -> Result<Vec<Vec<Point>>> {
+        if depth > DEPTH { return Err(invalid("composite glyphs nested too deep")); }
+
+        let (start, end) = match (self.loca.get(index), self.loca.get(index + 1)) {
+            (Some(start), Some(end)) if start < end => (*start, *end),
+            // a space, or anything else with nothing to draw
+            (Some(_), Some(_)) => return Ok(vec![]),
+            _ => return Err(invalid("glyph out of range")),
+        };
+
+        let glyph = self.glyf.get(start..end).ok_or_else(|| invalid("truncated glyph"))?;
+        let count = i16_at(glyph, 0)?;
+        return if count >= 0 { simple(glyph, count as usize) } else { self.composite(glyph, depth) };
+    }
+
+    // other glyphs put together, each moved and maybe scaled
+    fn composite(&self, glyph: &[u8], depth: usize) -> Result<Vec<Vec<Point>>> {
+        let mut contours = vec![];
+        let mut at = 10;
+
+        loop {
+            let flags = u16_at(glyph, at)?;
+            let index = u16_at(glyph, at + 2)? as usize;
+            at += 4;
+
+            let (dx, dy) = if flags & 0x0001 != 0 {
+                at += 4;
+                (i16_at(glyph, at - 4)? as Float, i16_at(glyph, at - 2)? as Float)
+            } else {
+                at += 2;
+                (byte_at(glyph, at - 2)? as i8 as Float, byte_at(glyph, at - 1)? as i8 as Float)
+            };
+
+            // anchored by point numbers instead, which is rare enough to ignore
+            let (dx, dy) = if flags & 0x0002 != 0 { (dx, dy) } else { (0.0, 0.0) };
+
+            let f2dot14 = |at: usize| i16_at(glyph, at).map(|value| value as Float / 16384.0);
+            let [a, b, c, d] = if flags & 0x0008 != 0 {
+                at += 2;
+                let scale = f2dot14(at - 2)?;
+                [scale, 0.0, 0.0, scale]
+            } else if flags & 0x0040 != 0 {
+                at += 4;
+                [f2dot14(at - 4)?, 0.0, 0.0, f2dot14(at - 2)?]
+            } else if flags & 0x0080 != 0 {
+                at += 8;
+                [f2dot14(at - 8)?, f2dot14(at - 6)?, f2dot14(at - 4)?, f2dot14(at - 2)?]
+            } else {
+                [1.0, 0.0, 0.0, 1.0]
+            };
+
+            for contour in self.contours(index, depth + 1)? {
+                contours.push(contour.into_iter().map(|([x, y], on)| ([a * x + c * y + dx, b * x + d * y + dy], on)).collect());
+            }
+
+            if flags & 0x0020 == 0 { break; }
+        }
+
+        return Ok(contours);
+    }
+}
+
+// a glyph drawn out point by point
+fn simple(glyph: &[u8], count: usize) -> Result<Vec<Vec<Point>>> {
+    let ends = (0..count).map(|i| u16_at(glyph, 10 + i * 2).map(|end| end as usize)).collect::<Result<Vec<usize>>>()?;
+    let total = ends.last().map_or(0, |end| end + 1);
+
+    let instructions = u16_at(glyph, 10 + count * 2)? as usize;
+    let mut at = 12 + count * 2 + instructions;
+
+    let mut flags = Vec::with_capacity(total);
+    while flags.len() < total {
+        let flag = byte_at(glyph, at)?;
+        at += 1;
+        flags.push(flag);
+
+        if flag & 0x08 != 0 {
+            let repeat = byte_at(glyph, at)?;
+            at += 1;
+            for _ in 0..repeat { flags.push(flag); }
+        }
+    }
+    flags.truncate(total);
+
+    // coordinates are deltas, bytes with a sign bit or shorts or repeats
+    let mut coordinates = |short: u8, same: u8| -> Result<Vec<Float>> {
+        let mut value = 0.0;
+        let mut values = Vec::with_capacity(total);
+
+        for flag in flags.iter() {
+            if flag & short != 0 {
+                let delta = byte_at(glyph, at)? as Float;
+                at += 1;
+                value += if flag & same != 0 { delta } else { -delta };
+            } else if flag & same == 0 {
+                value += i16_at(glyph, at)? as Float;
+                at += 2;
+            }
+            values.push(value);
+        }
+
+        return Ok(values);
+    };
+
+    let xs = coordinates(0x02, 0x10)?;
+    let ys = coordinates(0x04, 0x20)?;
+
+    let mut contours = vec![];
+    let mut start = 0;
+    for end in ends {
+        if end < start || end >= total { return Err(invalid("bad contour")); }
+        contours.push((start..=end).map(|i| ([xs[i], ys[i]], flags[i] & 0x01 != 0)).collect());
+        start = end + 1;
+    }
+
+    return Ok(contours);
+}
+
+// the curves of a contour cut into straight pieces. two control points in
+// a row have an on curve point halfway between them that's left out.
+fn flatten(contour: &[Point], scale: Float) -> Vec<[Float; 2]> {
+    let n = contour.len();
+    let midpoint = |a: [Float; 2], b: [Float; 2]| [(a[0] + b[0]) * 0.5, (a[1] + b[1]) * 0.5];
+
+    // start from a point that's on it, making one up if none are
+    let first = match contour.iter().position(|(_, on)| *on) {
+        Some(first) => first,
+        None => {
+            let start = midpoint(contour[n - 1].0, contour[0].0);
+            let mut shifted = vec![(start, true)];
+            shifted.extend_from_slice(contour);
+            return flatten(&shifted, scale);
+        },
+    };
+
+    let mut polyline = vec![];
+    let mut current = contour[first].0;
+    let mut control: Option<[Float; 2]> = None;
+
+    for i in 1..=n {
+        let (point, on) = contour[(first + i) % n];
+
+        let (next, via) = match (on, control) {
+            (true, via) => (point, via),
+            (false, None) => { control = Some(point); continue; },
+            (false, Some(via)) => { control = Some(point); (midpoint(via, point), Some(via)) },
+        };
+        if on { control = None; }
+
+        match via {
+            None => polyline.push(current),
+            Some(via) => for step in 0..STEPS {
+                let t = step as Float / STEPS as Float;
+                let s = 1.0 - t;
+                polyline.push([
+                    s * s * current[0] + 2.0 * s * t * via[0] + t * t * next[0],
+                    s * s * current[1] + 2.0 * s * t * via[1] + t * t * next[1],
+                ]);
+            },
+        }
+        current = next;
+    }
+
+    return polyline.into_iter().map(|[x, y]| [x * scale, y * scale]).collect();
+}
+
+// which glyph every character is drawn with, from a cmap subtable in
+// format 4 (the basic multilingual plane) or 12 (all of unicode)
+fn characters(cmap: &[u8]) -> Result<Vec<(char, usize)>> {
+    let count = u16_at(cmap, 2)? as usize;
+    let mut best = None;
+
+    for i in 0..count {
+        let (platform, encoding) = (u16_at(cmap, 4 + i * 8)?, u16_at(cmap, 6 + i * 8)?);
+        let offset = u32_at(cmap, 8 + i * 8)? as usize;
+        let format = u16_at(cmap, offset)?;
+
+        let unicode = platform == 0 || (platform == 3 && (encoding == 1 || encoding == 10));
+        if unicode && (format == 12 || (format == 4 && best.is_none())) { best = Some((offset, format)); }
+    }
+
+    let (at, format) = best.ok_or_else(|| invalid("no unicode cmap"))?;
+    let mut map = vec![];
+
+    if format == 12 {
+        for group in 0..u32_at(cmap, at + 12)? as usize {
+            let g = at + 16 + group * 12;
+            let (first, last, glyph) = (u32_at(cmap, g)?, u32_at(cmap, g + 4)?, u32_at(cmap, g + 8)?);
+            for code in first..=last.min(first.saturating_add(0xFFFF)) {
+                if let Some(character) = char::from_u32(code) { map.push((character, (glyph + code - first) as usize)); }
+            }
+        }
+        return Ok(map);
+    }
+
+    let segments = u16_at(cmap, at + 6)? as usize / 2;
+    let (ends, starts) = (at + 14, at + 16 + segments * 2);
+    let (deltas, ranges) = (starts + segments * 2, starts + segments * 4);
+
+    for segment in 0..segments {
+        let (start, end) = (u16_at(cmap, starts + segment * 2)?, u16_at(cmap, ends + segment * 2)?);
+        let delta = u16_at(cmap, deltas + segment * 2)?;
+        let range = u16_at(cmap, ranges + segment * 2)? as usize;
+        if start == 0xFFFF { continue; }
+
+        for code in start..=end {
+            let glyph = if range == 0 {
+                code.wrapping_add(delta)
+            } else {
+                let glyph = u16_at(cmap, ranges + segment * 2 + range + (code - start) as usize * 2)?;
+                if glyph == 0 { 0 } else { glyph.wrapping_add(delta) }
+            };
+            if let Some(character) = char::from_u32(code as u32) { map.push((character, glyph as usize)); }
+        }
+    }
+
+    return Ok(map);
+}
+
+pub fn parse(bytes: &[u8]) -> Result<Font> {
+    let version = u32_at(bytes, 0)?;
+    if version != 0x00010000 && &bytes[0..4] != b"true" { return Err(invalid("not a truetype font")); }
+
+    let mut tables = HashMap::new();
+    for i in 0..u16_at(bytes, 4)? as usize {
+        let record = 12 + i * 16;
+        let tag = bytes.get(record..record + 4).ok_or_else(|| invalid("truncated file"))?;
+        let (offset, length) = (u32_at(bytes, record + 8)? as usize, u32_at(bytes, record + 12)? as usize);
+        tables.insert(tag.to_vec(), bytes.get(offset..offset + length).ok_or_else(|| invalid("truncated table"))?);
+    }
+    let table = |tag: &[u8]| tables.get(tag).copied().ok_or_else(|| invalid(&format!("no {} table", String::from_utf8_lossy(tag))));
+
+    let (head, maxp, hhea, hmtx) = (table(b"head")?, table(b"maxp")?, table(b"hhea")?, table(b"hmtx")?);
+    let scale = 1.0 / u16_at(head, 18)?.max(1) as Float;
+    let long = i16_at(head, 50)? != 0;
+    let count = u16_at(maxp, 4)? as usize;
+
+    let loca = table(b"loca")?;
+    let loca = (0..=count).map(|i| {
+        if long { u32_at(loca, i * 4).map(|at| at as usize) } else { u16_at(loca, i * 2).map(|at| at as usize * 2) }
+    }).collect::<Result<Vec<usize>>>()?;
+
+    // glyphs past the last metric keep its advance
+    let metrics = u16_at(hhea, 34)?.max(1) as usize;
+    let advance = |glyph: usize| u16_at(hmtx, glyph.min(metrics - 1) * 4).map(|advance| advance as Float * scale);
+
+    let outlines = Tables { glyf: table(b"glyf")?, loca: loca };
+    let mut glyphs = HashMap::new();
+    for (character, index) in characters(table(b"cmap")?)? {
+        if index == 0 || index >= count { continue; }
+
+        let contours = outlines.contours(index, 0)?.iter()
+            .filter(|contour| contour.len() > 1)
+            .map(|contour| flatten(contour, scale))
+            .collect();
+
+        glyphs.insert(character, Glyph { contours: contours, advance: advance(index)? });
+    }
+
+    let (ascender, descender, gap) = (i16_at(hhea, 4)? as Float, i16_at(hhea, 6)? as Float, i16_at(hhea, 8)? as Float);
+    return Ok(Font { line_height: (ascender - descender + gap) * scale, glyphs: glyphs });
+}
+
+pub fn load(path: impl AsRef<Path>) -> Result<Font> {
+    let path = path.as_ref();
+    return parse(&error::read(path)?).map_err(|error| error.in_file(path));
+}
+
+// a font with one glyph, a square on "a", and nothing for " " but an
+// advance. enough for the tests of whatever draws text with one.
+#[cfg(test)]
+pub fn square() -> Vec<u8> {
+    let be16 = |values: &[i32]| values.iter().flat_map(|v| (*v as u16).to_be_bytes()).collect::<Vec<u8>>();
+
+    // a 1000 unit em, short loca, three glyphs: missing, square, space
+    let mut head = vec![0; 54];
+    head[18..20].copy_from_slice(&1000u16.to_be_bytes());
+    let mut maxp = vec![0; 6];
+    maxp[4..6].copy_from_slice(&3u16.to_be_bytes());
+    let mut hhea = vec![0; 36];
+    hhea[4..10].copy_from_slice(&be16(&[800, -200, 0]));
+    hhea[34..36].copy_from_slice(&3u16.to_be_bytes());
+    let hmtx = be16(&[500, 0, 600, 0, 250, 0]);
+
+    // a square from 100 to 500 on both axes, wound clockwise
+    let mut glyf = be16(&[1, 100, 100, 500, 500, 3, 0]);
+    glyf.extend_from_slice(&[0x01; 4]);
+    glyf.extend(be16(&[100, 0, 400, 0]));
+    glyf.extend(be16(&[100, 400, 0, -400]));
+    let loca = be16(&[0, 0, glyf.len() as i32 / 2, glyf.len() as i32 / 2]);
+
+    // format 4, "a" to glyph 1 and " " to 2
+    let mut cmap = be16(&[0, 1, 3, 1, 0, 12]);
+    cmap.extend(be16(&[4, 40, 0, 6, 0, 0, 0]));
+    cmap.extend(be16(&[0x20, 0x61, 0xFFFF, 0, 0x20, 0x61, 0xFFFF]));
+    cmap.extend(be16(&[2 - 0x20, 1 - 0x61, 1, 0, 0, 0]));
+
+    let tables: Vec<(&[u8], Vec<u8>)> = vec![
+        (b"cmap", cmap), (b"glyf", glyf), (b"head", head), (b"hhea", hhea), (b"hmtx", hmtx), (b"loca", loca), (b"maxp", maxp),
+    ];
+
+    let mut font = vec![0, 1, 0, 0];
+    font.extend(be16(&[tables.len() as i32, 0, 0, 0]));
+    let mut offset = 12 + tables.len() * 16;
+    let mut body = vec![];
+    for (tag, data) in tables.iter() {
+        font.extend_from_slice(tag);
+        font.extend_from_slice(&[0; 4]);
+        font.extend_from_slice(&(offset as u32).to_be_bytes());
+        font.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        body.extend_from_slice(data);
+        while body.len() % 4 != 0 { body.push(0); }
+        offset = 12 + tables.len() * 16 + body.len();
+    }
+    font.extend(body);
+    return font;
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::{ parse, square };
+
+    #[test]
+    fn test_ttf() {
+        let font = parse(&square()).unwrap();
+        assert!((font.line_height - 1.0).abs() < 0.0001);
+
+        let glyph = font.glyph('a').unwrap();
+        assert!((glyph.advance - 0.6).abs() < 0.0001);
+        assert_eq!(glyph.contours.len(), 1);
+        let corners = [[0.1, 0.1], [0.1, 0.5], [0.5, 0.5], [0.5, 0.1]];
+        assert_eq!(glyph.contours[0].len(), 4);
+        assert!(glyph.contours[0].iter().zip(corners.iter()).all(|(a, b)| (a[0] - b[0]).abs() + (a[1] - b[1]).abs() < 0.0001));
+
+        let space = font.glyph(' ').unwrap();
+        assert!(space.contours.is_empty() && (space.advance - 0.25).abs() < 0.0001);
+        assert!(font.glyph('b').is_none());
+
+        assert!(parse(b"not a font").is_err());
+    }
+}
@@ -0,0 +1,171 @@
+use std::convert::TryInto;
+use std::path::Path;
+
+use crate::error::{ self, Error, Result };
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::material::Material;
+use crate::objects::voxels::Voxels;
+
+// magicavoxel's models are at most this many voxels along each side
+const LARGEST: usize = 256;
+
+fn invalid(message: &str) -> Error {
+    Error::Invalid(format!("vox: {}", message))
+}
+
+fn int(bytes: &[u8], at: usize) -> Result<u32> {
+    bytes.get(at..at + 4)
+        .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+        .ok_or_else(|| invalid("truncated file"))
+}
+
+// reads the first model out of a magicavoxel file. `material` is the template
+// for every palette entry, only its color gets replaced. the grid sits with its
+// minimum corner at `position`, and magicavoxel's z up becomes y up.
+pub fn parse(bytes: &[u8], position: Vec3, size: Float, material: Material) -> Result<Voxels> {
+    if !bytes.starts_with(b"VOX ") { return Err(invalid("missing magic")); }
+
+    let mut dimensions = None;
+    let mut voxels: Vec<[u8; 4]> = vec![];
+    let mut colors: Option<Vec<Vec3>> = None;
+
+    // chunks are flat inside MAIN, so walk them in order and skip what we don't need
+    let mut at = 8;
+    while at + 12 <= bytes.len() {
+        let id = &bytes[at..at + 4];
+        let content = int(bytes, at + 4)? as usize;
+        let body = at + 12;
+
+        if id == b"MAIN" { at = body; continue; }
+
+        let chunk = bytes.get(body..body + content).ok_or_else(|| invalid("truncated chunk"))?;
+
+        match id {
+            b"SIZE" if dimensions.is_none() => {
+                let size = [int(chunk, 0)? as usize, int(chunk, 4)? as usize, int(chunk, 8)? as usize];
+                if size.iter().any(|side| *side > LARGEST) { return Err(invalid("model bigger than 256 voxels a side")); }
+                dimensions = Some(size);
+            },
+            b"XYZI" if voxels.is_empty() => {
+                let count = int(chunk, 0)? as usize;
+                let end = count.checked_mul(4).and_then(|bytes| bytes.checked_add(4)).ok_or_else(|| invalid("too many voxels"))?;
+                let data = chunk.get(4..end).ok_or_else(|| invalid("truncated voxels"))?;
+                voxels = data.chunks_exact(4).map(|v| [v[0], v[1], v[2], v[3]]).collect();
+            },
+            b"RGBA" => {
+                colors = Some(chunk.chunks_exact(4)
+                    .map(|c| Vec3::new(c[0] as Float, c[1] as Float, c[2] as Float) / 255.0)
+                    .collect());
+            },
+            _ => (),
+        }
+
+        at = body + content + int(bytes, at + 8)? as usize;
+    }
+
+    let [x, y, z] = dimensions.ok_or_else(|| invalid("no SIZE chunk"))?;
+    x.checked_mul(y).and_then(|cells| cells.checked_mul(z)).ok_or_else(|| invalid("too many voxels"))?;
+
+    // index i uses color i - 1. files without a palette fall back to plain grey
+    // rather than magicavoxel's built in default palette.
+    let mut palette = vec![material; 256];
+    for (index, entry) in palette.iter_mut().enumerate().skip(1) {
+        entry.color = match &colors {
+            Some(colors) => colors.get(index - 1).copied().unwrap_or(material.color),
+            None => Vec3::new(0.6, 0.6, 0.6),
+        };
+    }
+
+    let mut grid = Voxels::dense(position, size, [x, z, y], palette);
+    for [vx, vy, vz, color] in voxels {
+        // flip the depth axis so the model isn't mirrored by the axis swap
+        let depth = y.saturating_sub(1).saturating_sub(vy as usize);
+        grid.set([vx as usize, vz as usize, depth], color);
+    }
+
+    return Ok(grid);
+}
+
+pub fn load(path: impl AsRef<Path>, position: Vec3, size: Float, material: Material) -> Result<Voxels> {
+    let path = path.as_ref();
+    return parse(&error::read(path)?, position, size, material).map_err(|error| error.in_file(path));
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::parse;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::ray::Ray;
+    use crate::structures::material::Material;
+    use crate::objects::traits::Trace;
+
+    fn chunk(id: &[u8], content: &[u8]) -> Vec<u8> {
+        let mut bytes = id.to_vec();
+        bytes.extend(&(content.len() as u32).to_le_bytes());
+        bytes.extend(&0u32.to_le_bytes());
+        bytes.extend(content);
+        return bytes;
+    }
+
+    #[test]
+    fn test_parse() {
+        let mut size = vec![];
+        for d in &[2u32, 2, 2] { size.extend(&d.to_le_bytes()); }
+
+        let mut xyzi = 1u32.to_le_bytes().to_vec();
+        xyzi.extend(&[0, 1, 0, 3]); // x, y, z, color
+
+        let mut rgba = vec![0u8; 1024];
+        rgba[8..12].copy_from_slice(&[255, 0, 0, 255]);
+
+        let mut children = chunk(b"SIZE", &size);
+        children.extend(chunk(b"XYZI", &xyzi));
+        children.extend(chunk(b"RGBA", &rgba));
+
+        let mut vox = b"VOX ".to_vec();
+        vox.extend(&150u32.to_le_bytes());
+        vox.extend(b"MAIN");
+        vox.extend(&0u32.to_le_bytes());
+        vox.extend(&(children.len() as u32).to_le_bytes());
+        vox.extend(children);
+
+        let grid = parse(&vox, Vec3::new(0.0, 0.0, 0.0), 1.0, Material::blank()).unwrap();
+
+        // y = 1 of 2 flips to depth 0
+        assert_eq!(grid.get([0, 0, 0]), 3);
+
+        let ray = Ray::new(Vec3::new(0.5, 5.0, 0.5), Vec3::new(0.0, -1.0, 0.0));
+        let (hit, distance, normal) = grid.trace(ray);
+
+        assert!(hit);
+        assert_eq!(distance, 4.0);
+        assert_eq!(normal, Vec3::new(0.0, 1.0, 0.0));
+
+        let material = grid.material_at(ray.point_at(&distance), normal);
+        assert_eq!(material.color, Vec3::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_too_big() {
+        let file = |children: Vec<u8>| {
+            let mut vox = b"VOX ".to_vec();
+            vox.extend(&150u32.to_le_bytes());
+            vox.extend(b"MAIN");
+            vox.extend(&0u32.to_le_bytes());
+            vox.extend(&(children.len() as u32).to_le_bytes());
+            vox.extend(children);
+            vox
+        };
+
+        // a hundred thousand a side is an error, not a petabyte
+        let mut size = vec![];
+        for d in &[100000u32, 100000, 100000] { size.extend(&d.to_le_bytes()); }
+        let error = parse(&file(chunk(b"SIZE", &size)), Vec3::new(0.0, 0.0, 0.0), 1.0, Material::blank()).err().unwrap();
+        assert!(error.to_string().contains("256"));
+
+        // and so is a count of voxels past the end of the chunk
+        let xyzi = u32::MAX.to_le_bytes().to_vec();
+        assert!(parse(&file(chunk(b"XYZI", &xyzi)), Vec3::new(0.0, 0.0, 0.0), 1.0, Material::blank()).is_err());
+    }
+}
@@ -0,0 +1,74 @@
+use std::fs;
+use std::path::Path;
+
+use crate::error::{ Error, Result };
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::material::Material;
+use crate::objects::point_cloud::{ PointCloud, Point };
+
+fn invalid(message: &str) -> Error {
+    Error::Invalid(format!("xyz: {}", message))
+}
+
+// reads an xyz point cloud, one "x y z" per line and maybe "r g b" after
+// it, blank lines and # comments skipped. colors can be 0 to 1 or 0 to
+// 255, whichever the file looks like. every point is `radius` big and
+// takes its color from `material` when the file doesn't give one.
+pub fn parse(text: &str, radius: Float, material: Material) -> Result<PointCloud> {
+    let mut points = vec![];
+
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') { continue; }
+
+        let values = line.split_whitespace()
+            .map(|value| value.parse::<Float>())
+            .collect::<std::result::Result<Vec<Float>, _>>()
+            .map_err(|_| invalid(&format!("bad number on line {}", number + 1)))?;
+
+        let color = match values.len() {
+            3 => material.color,
+            n if n >= 6 => Vec3::new(values[3], values[4], values[5]),
+            _ => return Err(invalid(&format!("expected x y z on line {}", number + 1))),
+        };
+
+        points.push(Point::new(Vec3::new(values[0], values[1], values[2]), radius, color));
+    }
+
+    // from a file of bytes, not fractions
+    if points.iter().any(|point| point.color.x > 1.0 || point.color.y > 1.0 || point.color.z > 1.0) {
+        for point in points.iter_mut() { point.color = point.color / 255.0; }
+    }
+
+    return Ok(PointCloud::new(points, material));
+}
+
+pub fn load(path: impl AsRef<Path>, radius: Float, material: Material) -> Result<PointCloud> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path).map_err(|error| Error::reading(path, error))?;
+    return parse(&text, radius, material).map_err(|error| error.in_file(path));
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::parse;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::material::Material;
+
+    #[test]
+    fn test_xyz() {
+        let cloud = parse("# a scan\n0 0 0 255 0 0\n\n1 2 3 0 0 255\n", 0.1, Material::blank()).unwrap();
+
+        assert_eq!(cloud.points.len(), 2);
+        assert_eq!(cloud.points[1].position, Vec3::new(1.0, 2.0, 3.0));
+        assert_eq!(cloud.points[0].color, Vec3::new(1.0, 0.0, 0.0));
+
+        // without colors, the material's
+        let plain = parse("0.5 0.5 0.5\n", 0.1, Material::blank()).unwrap();
+        assert_eq!(plain.points[0].color, Material::blank().color);
+
+        assert!(parse("1 2\n", 0.1, Material::blank()).is_err());
+        assert!(parse("1 2 x\n", 0.1, Material::blank()).is_err());
+    }
+}
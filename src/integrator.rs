@@ -0,0 +1,170 @@
+use rand::{ Rng, RngCore };
+
+use crate::structures::float::Float;
+use crate::structures::float::consts::PI;
+use crate::structures::vec3::Vec3;
+use crate::structures::ray::Ray;
+use crate::structures::frame::Frame;
+use crate::structures::scene::Scene;
+use crate::structures::cast_result::CastResult;
+use crate::objects::visible::RayKind;
+use crate::render::{ self, cast_ray, offset, SAMPLES };
+use crate::bidirectional;
+
+// how the light coming back along a camera ray is worked out. the built in
+// ones are picked by scene.integrator, see render::Integrator::tracer, and
+// anything else goes in scene.custom_integrator, which render then asks for
+// every camera ray instead. it's just for the rays: sppm, which gathers over
+// the whole picture at once, and the wavefront backend, which splits the
+// path tracer into stages, only run with the built in ones.
+pub trait Integrator: Send + Sync {
+    // the light along `ray`, one sample of it
+    fn li(&self, scene: &Scene, ray: Ray, sampler: &mut dyn RngCore) -> Vec3;
+
+    // the same, with what the ray hits already cast, see samples
+    fn li_hit(&self, scene: &Scene, ray: Ray, _first: CastResult, sampler: &mut dyn RngCore) -> Vec3 {
+        self.li(scene, ray, sampler)
+    }
+
+    // how many samples each camera ray takes, averaged. with more than one,
+    // what the ray hits is cast once up front, a packet of rays at a time if
+    // the scene wants, and handed to li_hit.
+    fn samples(&self) -> u32 { 1 }
+}
+
+// from the camera only, see render::path
+#[derive(Debug, Copy, Clone, Default)]
+pub struct PathTracer;
+
+impl Integrator for PathTracer {
+    fn li(&self, scene: &Scene, ray: Ray, mut sampler: &mut dyn RngCore) -> Vec3 {
+        render::color(scene, ray, None, scene.bounces, &mut sampler)
+    }
+
+    fn li_hit(&self, scene: &Scene, ray: Ray, first: CastResult, mut sampler: &mut dyn RngCore) -> Vec3 {
+        render::color(scene, ray, Some(first), scene.bounces, &mut sampler)
+    }
+
+    fn samples(&self) -> u32 { SAMPLES }
+}
+
+// from both ends, see bidirectional
+#[derive(Debug, Copy, Clone, Default)]
+pub struct BidirectionalTracer;
+
+impl Integrator for BidirectionalTracer {
+    fn li(&self, scene: &Scene, ray: Ray, mut sampler: &mut dyn RngCore) -> Vec3 {
+        bidirectional::radiance(scene, ray, &mut sampler)
+    }
+}
+
+// how open the sky is over what the camera sees: white where nothing's
+// within `distance` of it, darker the more of it's shut in. misses are
+// white too. no lights or materials come into it, it's for looking at the
+// shapes of things.
+#[derive(Debug, Copy, Clone)]
+pub struct AmbientOcclusion {
+    pub distance: Float,
+}
+
+impl AmbientOcclusion {
+    pub fn new(distance: Float) -> AmbientOcclusion {
+        AmbientOcclusion { distance: distance }
+    }
+}
+
+impl Integrator for AmbientOcclusion {
+    fn li(&self, scene: &Scene, ray: Ray, sampler: &mut dyn RngCore) -> Vec3 {
+        self.li_hit(scene, ray, cast_ray(scene, ray, RayKind::Camera), sampler)
+    }
+
+    // one ray off the surface, cosine weighted, so the average is the
+    // share of the light from a white sky that would get there
+    fn li_hit(&self, scene: &Scene, ray: Ray, first: CastResult, sampler: &mut dyn RngCore) -> Vec3 {
+        if !first.hit { return Vec3::new(1.0, 1.0, 1.0); }
+
+        let [a, b] = sampler.gen::<[Float; 2]>();
+        let (radius, angle) = (a.sqrt(), 2.0 * PI * b);
+        let local = Vec3::new(radius * angle.cos(), radius * angle.sin(), (1.0 - a).max(0.0).sqrt());
+        let direction = Frame::new(first.normal).to_world(local);
+
+        let position = ray.point_at(&first.distance);
+        let shadow = Ray::new(offset(position, first.normal, direction), direction).with_max(self.distance);
+        return if scene.occluded(shadow) { Vec3::new(0.0, 0.0, 0.0) } else { Vec3::new(1.0, 1.0, 1.0) };
+    }
+
+    fn samples(&self) -> u32 { SAMPLES }
+}
+
+// what the camera sees as colors, the normal's x, y and z from -1..1 to
+// 0..1 as red, green and blue, black where it sees nothing. for checking
+// normals point the way they should.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Normals;
+
+impl Integrator for Normals {
+    fn li(&self, scene: &Scene, ray: Ray, sampler: &mut dyn RngCore) -> Vec3 {
+        self.li_hit(scene, ray, cast_ray(scene, ray, RayKind::Camera), sampler)
+    }
+
+    fn li_hit(&self, _scene: &Scene, _ray: Ray, first: CastResult, _sampler: &mut dyn RngCore) -> Vec3 {
+        if !first.hit { return Vec3::new(0.0, 0.0, 0.0); }
+        return (first.normal + Vec3::new(1.0, 1.0, 1.0)) * 0.5;
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use rand::RngCore;
+
+    use super::{ Integrator, AmbientOcclusion, Normals };
+    use crate::structures::float::Float;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::ray::Ray;
+    use crate::structures::camera::Camera;
+    use crate::structures::material::Material;
+    use crate::structures::scene::Scene;
+    use crate::objects::plane::Plane;
+    use crate::render::render_image;
+
+    // the same color whatever the ray, so it's easy to tell it was used
+    struct Flat(Vec3);
+
+    impl Integrator for Flat {
+        fn li(&self, _scene: &Scene, _ray: Ray, _sampler: &mut dyn RngCore) -> Vec3 { self.0 }
+    }
+
+    #[test]
+    fn test_integrator() {
+        let camera = Camera::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, -4.0), Vec3::new(0.0, 1.0, 0.0));
+        let floor = Plane::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Material { emission: 0.0, ..Material::blank() });
+        let mut rng = rand::thread_rng();
+
+        // one of your own takes over every pixel
+        let scene = Scene::builder()
+            .camera(camera)
+            .add(floor)
+            .samples(2)
+            .custom_integrator(Flat(Vec3::new(0.25, 0.5, 0.75)))
+            .build();
+        let (image, _) = render_image(&scene, [8, 6]);
+        assert!(image.iter().flatten().all(|pixel| (*pixel - Vec3::new(0.25, 0.5, 0.75)).length() < 1e-6));
+
+        // an open floor isn't shut in anywhere, and under a low ceiling it
+        // mostly is
+        let open = AmbientOcclusion::new(0.5);
+        let down = Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let clear = (0..400).map(|_| open.li(&scene, down, &mut rng).x).sum::<Float>() / 400.0;
+        assert_eq!(clear, 1.0);
+
+        let ceiling = Plane::new(Vec3::new(0.0, 0.1, 0.0), Vec3::new(0.0, -1.0, 0.0), Material { emission: 0.0, ..Material::blank() });
+        let low = Scene::builder().camera(camera).add(floor).add(ceiling).build();
+        let below = Ray::new(Vec3::new(0.0, 0.05, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let shut = (0..400).map(|_| open.li(&low, below, &mut rng).x).sum::<Float>() / 400.0;
+        assert!(shut < 0.25);
+
+        // the floor faces straight up
+        assert!((Normals.li(&scene, down, &mut rng) - Vec3::new(0.5, 1.0, 0.5)).length() < 1e-3);
+        assert_eq!(Normals.li(&scene, Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 0.0)), &mut rng), Vec3::new(0.0, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,23 @@
+// explicit returns and field names are the house style
+#![allow(clippy::needless_return, clippy::redundant_field_names)]
+
+pub mod error;
+pub mod structures;
+pub mod objects;
+pub mod write;
+pub mod render;
+pub mod integrator;
+pub mod bidirectional;
+pub mod sppm;
+pub mod wavefront;
+pub mod temporal;
+pub mod furnace;
+pub mod contact_sheet;
+pub mod scenes;
+pub mod gpu;
+#[cfg(not(target_arch = "wasm32"))] // sockets and threads
+pub mod distributed;
+pub mod import;
+pub mod ffi;
+
+pub use error::Error;
@@ -1,37 +1,406 @@
-mod structures;
-mod objects;
-mod write;
-mod render;
+#![allow(clippy::needless_return, clippy::redundant_field_names)]
+
 mod make_scene;
 
-use render::render;
+use std::env;
+use std::path::Path;
+use std::process;
+use std::sync::Arc;
+
+use keikan::render::{ render_image, render_sample_counts, render_alpha, render_mattes, render_light_groups, Integrator, NanGuard };
+use keikan::structures::float::Float;
+use keikan::structures::scene::Scene;
+use keikan::structures::camera::Framing;
+use keikan::structures::resolution::Resolution;
+use keikan::structures::film::Film;
+use keikan::structures::validate::Severity;
+use keikan::structures::top_level::TopLevel;
+use keikan::structures::light_tree::LightTree;
+use keikan::integrator::{ self, AmbientOcclusion, Normals };
+use keikan::import::pbrt;
+use keikan::scenes;
+use keikan::wavefront;
+use keikan::temporal;
+use keikan::write;
+use keikan::error;
 use make_scene::make_scene;
-use structures::vec3::Vec3;
 
 const RESOLUTION: [usize; 2] = [200, 100];
-const RENDER_OUT: &str = "/Users/isaac/Desktop/render.png"; // make this your own path
+const RENDER_OUT: &str = "render.png";
+const OCCLUSION: Float = 1.0; // how far ambient occlusion looks for things in the way
 
-fn main() {
-    let mut image: Vec<Vec<Vec3>> = vec![];
-    let scene = make_scene();
+const USAGE: &str = "usage: keikan [scene] [options]
+
+renders a .json scene file or a .pbrt scene, or one of the standard
+scenes by name: cornell_box, material_spheres, fractal or bunny. the
+built in demo scene is rendered when none is given. saved as a png.
 
-    for y in 0..RESOLUTION[1] {
-        let mut row = vec![];
+options:
+    -r, --resolution WxH     image size, or 720p, 1080p, 1440p, 4k, square, instagram, instagram-landscape,
+                             instagram-story or cinema. defaults to the pbrt film or 200x100
+        --framing MODE       fit or fill the shot as it is at that default size, to keep it at any other
+    -s, --samples N          jittered camera rays per pixel
+        --blue-noise         jitter them so the noise left is fine grained
+        --noise-threshold X  stop a pixel early once its error is under X of its brightness, like 0.02
+    -i, --integrator NAME    path, bidirectional or sppm, or ao or normals to look at the shapes
+    -b, --bounces N          bounces every path gets
+        --specular-bounces N more on top of those, off mirrors only
+        --regularize N       roughen mirrors after N bounces
+    -o, --output PATH        where the png goes, defaults to render.png
+    -n, --nan-guard MODE     off, discard, mark or log NaN and infinite samples
+    -a, --alpha              give the png an alpha channel, with shadow catchers' shadows in it
+    -m, --mattes             also save object and material id mattes next to the png
+    -l, --light-groups       also save each light group as an exr next to the png
+        --sample-counts      also save how many samples each pixel took as a heatmap, for tuning that
+        --cull               skip traced objects the camera can't see for camera rays
+    -t, --top-level          put every object with bounds in one tree, for scenes with lots of them
+    -w, --wavefront          path trace a wave of pixels at a time, stage by stage
+        --gpu                a quick preview on the gpu where the scene allows, built with the gpu feature
+    -c, --camera NAME        render through one of the scene's named cameras instead
+        --all-cameras        also render every named camera, each next to the png
+    -f, --frames N           render an animation instead, render.0000.png and on
+        --to NAME            moving the camera over it to one of the scene's named cameras
+        --temporal           blend each frame into the last where they line up, for fewer samples
+    -e, --exposures STOPS    also save the render at each of these exposures, like -2,0,2
+        --exr                also save the render untouched as an exr next to the png
+        --report             say what's in the scene and what it'll cost to render, without rendering it
+    -h, --help               this";
 
-        println!("\rrow {} / {} ", y + 1, RESOLUTION[1]);
+struct Options {
+    scene: Option<String>,
+    resolution: Option<[usize; 2]>,
+    framing: Option<fn(&Resolution) -> Framing>,
+    samples: Option<u32>,
+    noise_threshold: Option<Float>,
+    blue_noise: bool,
+    integrator: Option<Integrator>,
+    custom_integrator: Option<Arc<dyn integrator::Integrator>>,
+    nan_guard: Option<NanGuard>,
+    bounces: Option<u32>,
+    specular_bounces: Option<u32>,
+    regularize: Option<u32>,
+    output: String,
+    alpha: bool,
+    mattes: bool,
+    light_groups: bool,
+    sample_counts: bool,
+    cull: bool,
+    top_level: bool,
+    wavefront: bool,
+    gpu: bool,
+    camera: Option<String>,
+    all_cameras: bool,
+    frames: Option<u32>,
+    to: Option<String>,
+    temporal: bool,
+    exposures: Vec<Float>,
+    exr: bool,
+    report: bool,
+}
+
+fn parse(arguments: &[String]) -> Result<Options, String> {
+    let mut options = Options { scene: None, resolution: None, framing: None, samples: None, noise_threshold: None, blue_noise: false, integrator: None, custom_integrator: None, nan_guard: None, bounces: None, specular_bounces: None, regularize: None, output: RENDER_OUT.to_string(), alpha: false, mattes: false, light_groups: false, sample_counts: false, cull: false, top_level: false, wavefront: false, gpu: false, camera: None, all_cameras: false, frames: None, to: None, temporal: false, exposures: vec![], exr: false, report: false };
+    let mut arguments = arguments.iter();
 
-        for x in 0..RESOLUTION[0] {
-            row.push(render(
-                &scene,
-                [x as f64, (RESOLUTION[1] - y) as f64],
-                RESOLUTION,
-            ));
+    while let Some(argument) = arguments.next() {
+        let mut value = || arguments.next().cloned().ok_or(format!("{} needs a value", argument));
+
+        match argument.as_str() {
+            "-r" | "--resolution" => {
+                let text = value()?;
+                let resolution = Resolution::parse(&text).ok_or(format!("resolution should look like 640x480 or 1080p, not {}", text))?;
+                options.resolution = Some(resolution.into());
+            },
+            "--framing" => {
+                options.framing = Some(match value()?.as_str() {
+                    "fit" => Resolution::fit,
+                    "fill" => Resolution::fill,
+                    other => return Err(format!("framing is fit or fill, not {}", other)),
+                });
+            },
+            "-s" | "--samples" => {
+                let text = value()?;
+                options.samples = Some(text.parse().ok().filter(|n| *n > 0).ok_or(format!("bad sample count {}", text))?);
+            },
+            "--noise-threshold" => {
+                let text = value()?;
+                options.noise_threshold = Some(text.parse().ok().filter(|x: &Float| x.is_finite() && *x > 0.0).ok_or(format!("bad noise threshold {}", text))?);
+            },
+            "-i" | "--integrator" => match value()?.as_str() {
+                "path" => options.integrator = Some(Integrator::Path),
+                "bidirectional" => options.integrator = Some(Integrator::Bidirectional),
+                "sppm" => options.integrator = Some(Integrator::Sppm),
+                "ao" => options.custom_integrator = Some(Arc::new(AmbientOcclusion::new(OCCLUSION))),
+                "normals" => options.custom_integrator = Some(Arc::new(Normals)),
+                other => return Err(format!("no integrator called {}", other)),
+            },
+            "-b" | "--bounces" => {
+                let text = value()?;
+                options.bounces = Some(text.parse().map_err(|_| format!("bad bounce count {}", text))?);
+            },
+            "--specular-bounces" => {
+                let text = value()?;
+                options.specular_bounces = Some(text.parse().map_err(|_| format!("bad bounce count {}", text))?);
+            },
+            "--regularize" => {
+                let text = value()?;
+                options.regularize = Some(text.parse().map_err(|_| format!("bad bounce count {}", text))?);
+            },
+            "-n" | "--nan-guard" => {
+                options.nan_guard = Some(match value()?.as_str() {
+                    "off" => NanGuard::Off,
+                    "discard" => NanGuard::Discard,
+                    "mark" => NanGuard::Mark,
+                    "log" => NanGuard::Log,
+                    other => return Err(format!("no nan guard called {}", other)),
+                });
+            },
+            "--blue-noise" => options.blue_noise = true,
+            "-o" | "--output" => options.output = value()?,
+            "-a" | "--alpha" => options.alpha = true,
+            "-m" | "--mattes" => options.mattes = true,
+            "-l" | "--light-groups" => options.light_groups = true,
+            "--sample-counts" => options.sample_counts = true,
+            "--cull" => options.cull = true,
+            "-t" | "--top-level" => options.top_level = true,
+            "-w" | "--wavefront" => options.wavefront = true,
+            "--gpu" => options.gpu = true,
+            "-c" | "--camera" => options.camera = Some(value()?),
+            "--all-cameras" => options.all_cameras = true,
+            "-f" | "--frames" => {
+                let text = value()?;
+                options.frames = Some(text.parse().ok().filter(|n| *n > 0).ok_or(format!("bad frame count {}", text))?);
+            },
+            "--to" => options.to = Some(value()?),
+            "--temporal" => options.temporal = true,
+            "-e" | "--exposures" => {
+                let text = value()?;
+                options.exposures = text.split(',').map(|stop| stop.trim().parse().map_err(|_| format!("bad exposure {}", stop))).collect::<Result<_, _>>()?;
+            },
+            "--exr" => options.exr = true,
+            "--report" => options.report = true,
+            flag if flag.starts_with('-') => return Err(format!("unknown option {}\n\n{}", flag, USAGE)),
+            path if options.scene.is_none() => options.scene = Some(path.to_string()),
+            extra => return Err(format!("only one scene at a time, {} is one too many", extra)),
         }
+    }
 
-        image.push(row);
+    return Ok(options);
+}
+
+// the scene, and the resolution it asks for if it does
+fn load(path: &str) -> error::Result<(Scene, Option<[usize; 2]>)> {
+    if let Some(scene) = scenes::named(path) { return Ok((scene, None)); }
+
+    match Path::new(path).extension().and_then(|extension| extension.to_str()) {
+        Some("pbrt") => pbrt::load(path).map(|(scene, resolution)| (scene, Some(resolution))),
+        _ => Scene::from_file(path).map(|scene| (scene, None)),
     }
+}
+
+// stops at the first file that can't be written, there's no point
+// rendering the rest
+fn save(result: error::Result<()>) {
+    if let Err(error) = result {
+        eprintln!("couldn't save the render: {}", error);
+        process::exit(1);
+    }
+}
 
-    write::png(image, RENDER_OUT.to_string());
+// the errors a render would come out black or broken from stop it
+// before it starts, see Scene::check
+fn checked(scene: &Scene, resolution: [usize; 2]) {
+    if let Err(error) = scene.check(resolution) {
+        eprintln!("can't render it: {}", error);
+        process::exit(1);
+    }
 }
 
-// TODO: write tests
+fn main() {
+    let arguments: Vec<String> = env::args().skip(1).collect();
+    if arguments.iter().any(|argument| argument == "-h" || argument == "--help") {
+        println!("{}", USAGE);
+        return;
+    }
+
+    let options = parse(&arguments).unwrap_or_else(|message| {
+        eprintln!("{}", message);
+        process::exit(1);
+    });
+
+    let (mut scene, wanted) = match &options.scene {
+        Some(path) => load(path).unwrap_or_else(|error| {
+            eprintln!("couldn't load {}: {}", path, error);
+            process::exit(1);
+        }),
+        None => (make_scene(), None),
+    };
+
+    if let Some(samples) = options.samples { scene.samples = samples; }
+    if let Some(threshold) = options.noise_threshold { scene.noise_threshold = Some(threshold); }
+    if options.blue_noise { scene.blue_noise = true; }
+    if let Some(integrator) = options.integrator { scene.integrator = integrator; }
+    if let Some(integrator) = &options.custom_integrator { scene.custom_integrator = Some(integrator.clone()); }
+    if let Some(nan_guard) = options.nan_guard { scene.nan_guard = nan_guard; }
+    if let Some(bounces) = options.bounces { scene.bounces.diffuse = bounces; }
+    if let Some(bounces) = options.specular_bounces { scene.bounces.specular = bounces; }
+    if let Some(after) = options.regularize { scene.bounces.regularize = Some(after); }
+    let resolution = options.resolution.or(wanted).unwrap_or(RESOLUTION);
+
+    // framed around the size the scene was set up at, not the one it's
+    // rendered at
+    if let Some(framing) = options.framing {
+        let framing = framing(&Resolution::from(wanted.unwrap_or(RESOLUTION)));
+        scene.camera.framing = framing;
+        for camera in scene.cameras.values_mut() { camera.framing = framing; }
+    }
+
+    if let Some(name) = &options.camera {
+        scene = scene.with_camera(name).unwrap_or_else(|| {
+            eprintln!("the scene has no camera called {}", name);
+            process::exit(1);
+        });
+    }
+
+    for diagnostic in scene.validate().iter().filter(|diagnostic| diagnostic.severity == Severity::Warning) { eprintln!("{}", diagnostic); }
+    checked(&scene, resolution);
+
+    // culling after only hides objects, it doesn't move them, so the tree
+    // still fits, and the named cameras' views share it
+    if options.top_level {
+        let top = TopLevel::new(&scene);
+        let (boxed, aside) = top.counts();
+        println!("{} objects in the top level, {} without bounds", boxed, aside);
+        scene.top_level = Some(top);
+    }
+
+    // picking from lots of lights evenly is hopeless, see LightTree. sppm
+    // sends photons out of every one of them anyway.
+    if scene.integrator != Integrator::Sppm && scene.emitters.len() > 1 {
+        scene.light_tree = LightTree::new(&scene.emitters);
+    }
+
+    if options.report {
+        println!("{}", scene.report());
+        return;
+    }
+
+    let part = if options.wavefront { "wave" } else { "tile" };
+    scene.progress = Some(Arc::new(move |done, total| println!("\r{} {} / {} ", part, done, total)));
+    scene.bad_samples = Some(Arc::new(|pixel, sample, ray| {
+        eprintln!("pixel {}, {}: {:?} along the camera ray {:?}", pixel[0], pixel[1], sample, ray);
+    }));
+
+    let render = if options.wavefront { wavefront::render_image } else { render_image };
+    let counted = if options.wavefront { wavefront::render_sample_counts } else { render_sample_counts };
+    #[cfg(feature = "gpu")]
+    let render = if options.gpu { keikan::gpu::render_image } else { render };
+    #[cfg(feature = "gpu")]
+    let counted = if options.gpu { keikan::gpu::render_sample_counts } else { counted };
+    #[cfg(not(feature = "gpu"))]
+    if options.gpu { eprintln!("built without the gpu feature, rendering on the cpu"); }
+
+    // render.png gets render.0000.png, render.0001.png and so on, the
+    // camera going from where it is to the --to camera. each frame's culled
+    // for itself.
+    if let Some(frames) = options.frames {
+        let start = scene.camera;
+        let end = match &options.to {
+            Some(name) => *scene.cameras.get(name).unwrap_or_else(|| {
+                eprintln!("the scene has no camera called {}", name);
+                process::exit(1);
+            }),
+            None => start,
+        };
+
+        let mut history = None;
+        for frame in 0..frames {
+            let mut view = scene.clone();
+            view.camera = start.lerp(&end, if frames > 1 { frame as Float / (frames - 1) as Float } else { 0.0 });
+            view.frame = frame;
+            checked(&view, resolution);
+            if options.cull { view.cull(resolution); }
+
+            println!("rendering frame {} / {}", frame + 1, frames);
+            let (image, stats) = if options.temporal {
+                let (image, next, stats) = temporal::render_frame(&view, resolution, history.as_ref(), render);
+                history = Some(next);
+                (image, stats)
+            } else {
+                render(&view, resolution)
+            };
+            stats.print();
+
+            let mut film = Film::from(image);
+            film.post(&scene.post);
+            save(write::png(film.rows(), Path::new(&options.output).with_extension(format!("{:04}.png", frame)).display().to_string()));
+        }
+        return;
+    }
+
+    // render.png gets render.top.png for a camera called top. each gets
+    // culled for itself, before the main one is
+    if options.all_cameras {
+        for name in scene.cameras.keys() {
+            let mut view = scene.with_camera(name).unwrap();
+            checked(&view, resolution);
+            if options.cull { view.cull(resolution); }
+
+            println!("rendering camera {}", name);
+            let (image, stats) = render(&view, resolution);
+            stats.print();
+
+            let mut film = Film::from(image);
+            film.post(&scene.post);
+            save(write::png(film.rows(), Path::new(&options.output).with_extension(format!("{}.png", name)).display().to_string()));
+        }
+    }
+
+    if options.cull { println!("culled {} objects outside the camera's view", scene.cull(resolution)); }
+
+    println!("rendering {}x{} at {} samples per pixel", resolution[0], resolution[1], scene.samples);
+    // render.png gets render.samples.png
+    let (mut image, stats) = if options.sample_counts {
+        let (image, counts, stats) = counted(&scene, resolution);
+        save(write::heatmap(counts, Path::new(&options.output).with_extension("samples.png").display().to_string()));
+        (image, stats)
+    } else {
+        render(&scene, resolution)
+    };
+
+    stats.print();
+
+    if !scene.post.is_empty() {
+        let mut film = Film::from(image);
+        film.post(&scene.post);
+        image = film.rows();
+    }
+
+    // render.png gets render.exr, and render.ev+1.png and so on, see write::bracket
+    if options.exr { save(write::exr(image.clone(), Path::new(&options.output).with_extension("exr").display().to_string())); }
+    if !options.exposures.is_empty() { save(write::bracket(&Film::from(image.clone()), &options.exposures, options.output.clone())); }
+
+    // render.png gets render.objects.png and render.materials.png
+    if options.mattes {
+        let (objects, materials, _) = render_mattes(&scene, resolution);
+        let output = Path::new(&options.output);
+        save(write::matte(&objects, output.with_extension("objects.png").display().to_string()));
+        save(write::matte(&materials, output.with_extension("materials.png").display().to_string()));
+    }
+
+    // and render.environment.exr, render.default.exr, and one per group
+    if options.light_groups {
+        let (groups, _) = render_light_groups(&scene, resolution);
+        let output = Path::new(&options.output);
+        for (group, image) in groups {
+            save(write::exr(image, output.with_extension(format!("{}.exr", group)).display().to_string()));
+        }
+    }
+
+    if options.alpha {
+        let (alpha, _) = render_alpha(&scene, resolution);
+        save(write::png_alpha(image, alpha, options.output));
+    } else {
+        save(write::png(image, options.output));
+    }
+}
@@ -1,9 +1,10 @@
-use crate::structures::material::Material;
-use crate::structures::camera::Camera;
-use crate::structures::scene::Scene;
-use crate::structures::vec3::Vec3;
-use crate::objects::sphere::Sphere;
-use crate::objects::mandelbulb::Mandelbulb;
+use keikan::structures::material::Material;
+use keikan::structures::camera::Camera;
+use keikan::structures::scene::Scene;
+use keikan::structures::vec3::Vec3;
+use keikan::objects::sphere::Sphere;
+use keikan::objects::plane::Plane;
+use keikan::objects::mandelbulb::Mandelbulb;
 
 pub fn make_scene() -> Scene {
     let camera = Camera::new(
@@ -26,6 +27,9 @@ pub fn make_scene() -> Scene {
         // see-through
         transmission: 0.0,
         ior: 0.0,
+
+        // both sides, and the rest as usual
+        ..Material::blank()
     };
 
     let light = |color: Vec3| {
@@ -41,6 +45,9 @@ pub fn make_scene() -> Scene {
             // not transparent
             transmission: 0.0,
             ior: 0.0,
+
+            // both sides, and the rest as usual
+            ..Material::blank()
         }
     };
 
@@ -56,13 +63,16 @@ pub fn make_scene() -> Scene {
         // not transparent
         transmission: 0.0,
         ior: 0.0,
+
+        // both sides, and the rest as usual
+        ..Material::blank()
     };
 
     scene.add_trace(Sphere::new(Vec3::new(4.0, 4.0, 4.0), 2.0, light(Vec3::new(1.0, 0.0, 0.0))));
     scene.add_trace(Sphere::new(Vec3::new(4.0, 0.0, 4.0), 2.0, light(Vec3::new(0.0, 1.0, 0.0))));
     scene.add_trace(Sphere::new(Vec3::new(4.0, 4.0, 0.0), 2.0, light(Vec3::new(0.0, 0.0, 1.0))));
     scene.add_march(Mandelbulb::new(Vec3::new(0.0, 0.0, 0.0), 8.0, 10, metal));
-    scene.add_trace(Sphere::new(Vec3::new(0.0, -101.0, 0.0), 100.0, plastic));
+    scene.add_trace(Plane::new(Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 1.0, 0.0), plastic));
 
     return scene;
 }
@@ -0,0 +1,93 @@
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::material::Material;
+use crate::structures::aabb::Aabb;
+use crate::structures::report::Footprint;
+use crate::objects::sdf_grid::SdfGrid;
+use crate::objects::traits::March;
+use crate::objects::visible::Visibility;
+
+// an expensive distance field, like a fractal or a deep csg tree, sampled
+// into an SdfGrid once and marched by looking it up after that. it's as
+// sharp as the cells are small, `resolution` of them along the longest
+// side of `bounds`, which should fit around all of it. the object's kept
+// for its materials. baking takes as long as marching that many points.
+#[derive(Debug, Clone)]
+pub struct Baked<T> {
+    pub object: T,
+    pub bounds: Aabb,
+    pub grid: SdfGrid, // a little bigger than bounds, see SdfGrid::cubic
+}
+
+impl<T: March> Baked<T> {
+    pub fn new(object: T, bounds: Aabb, resolution: usize) -> Baked<T> {
+        let grid = SdfGrid::cubic(bounds, resolution, object.material(), |point| object.march(point));
+        Baked { object: object, bounds: bounds, grid: grid }
+    }
+}
+
+impl<T: March> March for Baked<T> {
+    fn material(&self) -> Material { self.object.material() }
+
+    // outside the grid all that's known is the surface is inside bounds,
+    // which is at least the padding away still
+    fn march(&self, point: Vec3) -> Float {
+        if self.grid.bounds.distance(&point) > 0.0 { return self.bounds.distance(&point); }
+        return self.grid.sample(point);
+    }
+
+    fn bounds(&self) -> Option<Aabb> { Some(self.bounds) }
+
+    // the grid's what gets marched, but the object's kept too
+    fn footprint(&self) -> Footprint {
+        Footprint { kind: "baked", ..self.grid.footprint() }.with_bytes(self.object.footprint().bytes)
+    }
+
+    fn material_at(&self, point: Vec3) -> Material { self.object.material_at(point) }
+    fn visibility(&self) -> Visibility { self.object.visibility() }
+    fn shadow_catcher(&self) -> bool { self.object.shadow_catcher() }
+    fn light_group(&self) -> Option<&str> { self.object.light_group() }
+    fn tangent(&self, point: Vec3, normal: Vec3) -> Vec3 { self.object.tangent(point, normal) }
+    fn uv(&self, point: Vec3) -> [Float; 2] { self.object.uv(point) }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::Baked;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::material::Material;
+    use crate::structures::aabb::Aabb;
+    use crate::objects::sphere::Sphere;
+    use crate::objects::mandelbulb::Mandelbulb;
+    use crate::objects::visible::{ Visible, Visibility };
+    use crate::objects::shadow_catcher::ShadowCatcher;
+    use crate::objects::light_group::LightGroup;
+    use crate::objects::traits::March;
+
+    #[test]
+    fn test_baked() {
+        let sphere = Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0, Material::blank());
+        let baked = Baked::new(sphere, Aabb::new(Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, 1.0, 1.0)), 32);
+
+        // close to the real thing inside the grid, within about a cell
+        for point in [Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.5, 0.3, -0.2), Vec3::new(1.05, 0.0, 0.0)].iter() {
+            assert!((baked.march(*point) - sphere.march(*point)).abs() < 0.05);
+        }
+
+        // and outside it, never further than it really is
+        let far = Vec3::new(6.0, 2.0, 0.0);
+        assert!(baked.march(far) <= sphere.march(far) + 0.05);
+
+        // a fractal comes out the same, but for the detail finer than a cell
+        let bulb = Mandelbulb::new(Vec3::new(0.0, 0.0, 0.0), 8.0, 6, Material::blank());
+        let baked = Baked::new(bulb, Aabb::new(Vec3::new(-1.2, -1.2, -1.2), Vec3::new(1.2, 1.2, 1.2)), 24);
+        assert!(baked.march(Vec3::new(0.0, 0.0, 0.0)).abs() < 0.01);
+        assert!((baked.march(Vec3::new(0.0, 0.0, 1.5)) - bulb.march(Vec3::new(0.0, 0.0, 1.5))).abs() < 0.1);
+
+        // what the object was flagged as it still is once baked
+        let bounds = Aabb::new(Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, 1.0, 1.0));
+        assert_eq!(Baked::new(Visible::new(sphere, Visibility::CAMERA_INVISIBLE), bounds, 8).visibility(), Visibility::CAMERA_INVISIBLE);
+        assert!(Baked::new(ShadowCatcher::new(sphere), bounds, 8).shadow_catcher());
+        assert_eq!(Baked::new(LightGroup::new(sphere, "key"), bounds, 8).light_group(), Some("key"));
+    }
+}
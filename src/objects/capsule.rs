@@ -0,0 +1,71 @@
+use serde::{ Serialize, Deserialize };
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::aabb::Aabb;
+use crate::structures::material::Material;
+use crate::objects::primitive::MarchPrimitive;
+use crate::objects::traits::March;
+
+// a line segment with a radius, like a pill
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Capsule {
+    pub start: Vec3,
+    pub end: Vec3,
+    pub radius: Float,
+    pub material: Material,
+}
+
+impl Capsule {
+    pub fn new(start: Vec3, end: Vec3, radius: Float, material: Material) -> Capsule {
+        Capsule {
+            start: start,
+            end: end,
+            radius: radius,
+            material: material,
+        }
+    }
+}
+
+impl March for Capsule {
+    fn material(&self) -> Material { self.material }
+
+    fn march(&self, point: Vec3) -> Float {
+        let pa = point - self.start;
+        let ba = self.end - self.start;
+
+        // closest point on the segment, which is its start when it has
+        // no length and the capsule is just a sphere
+        let length = ba.dot(&ba);
+        let h = if length > 0.0 { (pa.dot(&ba) / length).clamp(0.0, 1.0) } else { 0.0 };
+
+        return (pa - ba * h).length() - self.radius;
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let radius = Vec3::new(self.radius, self.radius, self.radius);
+        return Some(Aabb::new(self.start.min_by(&self.end) - radius, self.start.max_by(&self.end) + radius));
+    }
+
+    fn primitive(&self) -> Option<MarchPrimitive> { Some((*self).into()) }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::Capsule;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::material::Material;
+    use crate::objects::traits::March;
+
+    #[test]
+    fn test_capsule() {
+        let capsule = Capsule::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 2.0, 0.0), 0.5, Material::blank());
+        assert!((capsule.march(Vec3::new(1.0, 1.0, 0.0)) - 0.5).abs() < 1e-9);
+        assert!((capsule.march(Vec3::new(0.0, 3.0, 0.0)) - 0.5).abs() < 1e-9);
+
+        // with both ends in one place it's a sphere, not nan
+        let sphere = Capsule::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0), 0.5, Material::blank());
+        assert!((sphere.march(Vec3::new(0.0, 0.0, 2.0)) - 1.5).abs() < 1e-9);
+        assert_eq!(sphere.march(Vec3::new(0.0, 0.0, 0.0)), -0.5);
+    }
+}
@@ -0,0 +1,196 @@
+use serde::{ Serialize, Deserialize };
+
+use crate::gpu;
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::ray::Ray;
+use crate::structures::aabb::Aabb;
+use crate::structures::report::Footprint;
+use crate::structures::camera::Camera;
+use crate::structures::material::Material;
+use crate::structures::frame::Frame;
+use crate::objects::traits::{ March, Trace };
+use crate::objects::visible::Visibility;
+
+const EPSILON: Float = 0.0001;
+
+// how many times a traced ray carries on past a hit that was cut away
+const MAX_PASSES: usize = 16;
+
+// cuts away everything on the side the normal points to. on marched objects
+// the cut face is filled in with `cap`, or left open to show the inside
+// when there's none. traced objects are always left open, and only show
+// their inside if they can be hit from within, like meshes.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct ClipPlane {
+    pub point: Vec3,
+    pub normal: Vec3,
+    #[serde(default)]
+    pub cap: Option<Material>,
+}
+
+impl ClipPlane {
+    pub fn new(point: Vec3, normal: Vec3) -> ClipPlane {
+        ClipPlane { point: point, normal: normal.unit(), cap: None }
+    }
+
+    // a plane relative to the camera: x to the right, y up, and looking
+    // down -z, so (0, 0, -d) with normal (0, 0, 1) cuts off everything
+    // nearer than d
+    pub fn camera(camera: &Camera, point: Vec3, normal: Vec3) -> ClipPlane {
+        let f = camera.ray.direction;
+        let s = (f.cross(&camera.up)).unit();
+        let u = s.cross(&f);
+        let world = |v: Vec3| s * v.x + u * v.y - f * v.z;
+
+        ClipPlane::new(camera.ray.origin + world(point), world(normal))
+    }
+
+    pub fn with_cap(self, cap: Material) -> ClipPlane {
+        ClipPlane { cap: Some(cap), ..self }
+    }
+
+    // how far into the cut away side, negative on the side that's kept
+    pub fn distance(&self, point: Vec3) -> Float {
+        (point - self.point).dot(&self.normal)
+    }
+}
+
+// anything with a clipping plane through it, see Scene::clip
+#[derive(Debug, Copy, Clone)]
+pub struct Clipped<T> {
+    pub object: T,
+    pub plane: ClipPlane,
+}
+
+impl<T> Clipped<T> {
+    pub fn new(object: T, plane: ClipPlane) -> Clipped<T> {
+        Clipped { object: object, plane: plane }
+    }
+}
+
+impl<T: March> Clipped<T> {
+    // whether the surface at `point` is the cut rather than the object's own
+    fn on_cut(&self, point: Vec3) -> bool {
+        self.plane.distance(point) >= self.object.march(point)
+    }
+}
+
+impl<T: March> March for Clipped<T> {
+    fn material(&self) -> Material { self.object.material() }
+    fn bounds(&self) -> Option<Aabb> { self.object.bounds() }
+
+    // capped it's the intersection with the half space that's kept, open
+    // it's the object's shell, so rays through the cut find the inside wall
+    fn march(&self, point: Vec3) -> Float {
+        let distance = self.object.march(point);
+
+        match self.plane.cap {
+            Some(_) => distance.max(self.plane.distance(point)),
+            None => distance.abs().max(self.plane.distance(point)),
+        }
+    }
+
+    fn material_at(&self, point: Vec3) -> Material {
+        match self.plane.cap {
+            Some(cap) if self.on_cut(point) => cap,
+            _ => self.object.material_at(point),
+        }
+    }
+
+    fn normal(&self, point: Vec3) -> Vec3 {
+        if self.plane.cap.is_some() && self.on_cut(point) { self.plane.normal } else { self.object.normal(point) }
+    }
+
+    fn tangent(&self, point: Vec3, normal: Vec3) -> Vec3 {
+        if self.plane.cap.is_some() && self.on_cut(point) { Frame::new(normal).tangent } else { self.object.tangent(point, normal) }
+    }
+
+    fn uv(&self, point: Vec3) -> [Float; 2] { self.object.uv(point) }
+
+    fn wgsl(&self) -> Option<String> {
+        let inner = self.object.wgsl()?;
+        let inner = if self.plane.cap.is_some() { inner } else { format!("abs({})", inner) };
+        Some(format!("max({}, dot(p - {}, {}))", inner, gpu::vec3(self.plane.point)?, gpu::vec3(self.plane.normal)?))
+    }
+
+    fn visibility(&self) -> Visibility { self.object.visibility() }
+    fn shadow_catcher(&self) -> bool { self.object.shadow_catcher() }
+    fn light_group(&self) -> Option<&str> { self.object.light_group() }
+    fn footprint(&self) -> Footprint { self.object.footprint() }
+}
+
+impl<T: Trace> Trace for Clipped<T> {
+    fn material(&self) -> Material { self.object.material() }
+
+    // hits on the cut away side don't count, the ray goes on past them
+    fn trace(&self, ray: Ray) -> (bool, Float, Vec3) {
+        let mut travelled = 0.0;
+
+        for _ in 0..MAX_PASSES {
+            let (hit, distance, normal) = self.object.trace(Ray::new(ray.point_at(&travelled), ray.direction));
+            if !hit || distance < 0.0 { break; }
+
+            travelled += distance;
+            if self.plane.distance(ray.point_at(&travelled)) <= 0.0 { return (true, travelled, normal); }
+            travelled += EPSILON;
+        }
+
+        return (false, 0.0, Vec3::new(0.0, 0.0, 0.0));
+    }
+
+    // what's cut away is still inside, it only makes the box loose
+    fn bounds(&self) -> Option<Aabb> { self.object.bounds() }
+
+    fn material_at(&self, point: Vec3, normal: Vec3) -> Material { self.object.material_at(point, normal) }
+    fn tangent(&self, point: Vec3, normal: Vec3) -> Vec3 { self.object.tangent(point, normal) }
+    fn uv(&self, point: Vec3, normal: Vec3) -> [Float; 2] { self.object.uv(point, normal) }
+    fn visibility(&self) -> Visibility { self.object.visibility() }
+    fn shadow_catcher(&self) -> bool { self.object.shadow_catcher() }
+    fn light_group(&self) -> Option<&str> { self.object.light_group() }
+    fn footprint(&self) -> Footprint { self.object.footprint() }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::{ ClipPlane, Clipped };
+    use crate::structures::vec3::Vec3;
+    use crate::structures::ray::Ray;
+    use crate::structures::camera::Camera;
+    use crate::structures::material::Material;
+    use crate::objects::traits::{ March, Trace };
+    use crate::objects::sphere::Sphere;
+    use crate::objects::mesh::Mesh;
+
+    #[test]
+    fn test_clipped() {
+        let red = Material { color: Vec3::new(1.0, 0.0, 0.0), ..Material::blank() };
+        let sphere = Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0, Material::blank());
+        let front = ClipPlane::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+
+        // traced, the front wall is gone so the ray finds the back one
+        let walls = Mesh::new(vec![
+            Vec3::new(-1.0, -1.0, 1.0), Vec3::new(1.0, -1.0, 1.0), Vec3::new(0.0, 1.0, 1.0),
+            Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, -1.0, -1.0), Vec3::new(0.0, 1.0, -1.0),
+        ], vec![[0, 1, 2], [3, 4, 5]], Material::blank());
+        let (hit, distance, _) = Trace::trace(&Clipped::new(walls, front), ray);
+        assert!(hit);
+        assert!((distance - 6.0).abs() < 0.001);
+
+        // marched with a cap, the cut face is solid and takes the cap
+        let capped = Clipped::new(sphere, front.with_cap(red));
+        assert!((capped.march(Vec3::new(0.0, 0.0, 2.0)) - 2.0).abs() < 0.001);
+        assert_eq!(March::material_at(&capped, Vec3::new(0.0, 0.0, 0.0)).color, red.color);
+        assert_eq!(March::normal(&capped, Vec3::new(0.0, 0.0, 0.0)), Vec3::new(0.0, 0.0, 1.0));
+
+        // and open, the middle of it is empty
+        assert!(Clipped::new(sphere, front).march(Vec3::new(0.0, 0.0, -0.5)) > 0.0);
+
+        // five ahead of the camera is the origin, and back toward it is +z
+        let camera = Camera::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let near = ClipPlane::camera(&camera, Vec3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!((near.point - Vec3::new(0.0, 0.0, 0.0)).length() < 0.001);
+        assert!((near.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 0.001);
+    }
+}
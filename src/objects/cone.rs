@@ -0,0 +1,77 @@
+use serde::{ Serialize, Deserialize };
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::aabb::Aabb;
+use crate::structures::material::Material;
+use crate::objects::primitive::MarchPrimitive;
+use crate::objects::traits::March;
+
+// a solid cone, standing on its base at `position` with the tip pointing up
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Cone {
+    pub position: Vec3,
+    pub radius: Float,
+    pub height: Float,
+    pub material: Material,
+}
+
+impl Cone {
+    pub fn new(position: Vec3, radius: Float, height: Float, material: Material) -> Cone {
+        Cone {
+            position: position,
+            radius: radius,
+            height: height,
+            material: material,
+        }
+    }
+}
+
+impl March for Cone {
+    fn material(&self) -> Material { self.material }
+
+    // exact, worked out in the 2d slice through the axis, with the tip at the origin
+    fn march(&self, point: Vec3) -> Float {
+        let p = point - self.position - Vec3::new(0.0, self.height, 0.0);
+
+        let (qx, qy) = (self.radius, -self.height);
+        let (wx, wy) = ((p.x * p.x + p.z * p.z).sqrt(), p.y);
+
+        // closest point on the slanted side, then on the base
+        let t = ((wx * qx + wy * qy) / (qx * qx + qy * qy)).clamp(0.0, 1.0);
+        let (ax, ay) = (wx - qx * t, wy - qy * t);
+
+        let s = (wx / qx).clamp(0.0, 1.0);
+        let (bx, by) = (wx - qx * s, wy - qy);
+
+        let distance = (ax * ax + ay * ay).min(bx * bx + by * by).sqrt();
+        let side = (-(wx * qy - wy * qx)).max(-(wy - qy));
+
+        return if side > 0.0 { distance } else { -distance };
+    }
+
+    // from the middle of the base up to the tip
+    fn bounds(&self) -> Option<Aabb> {
+        let base = Vec3::new(self.radius, 0.0, self.radius);
+        return Some(Aabb::new(self.position - base, self.position + base + Vec3::new(0.0, self.height, 0.0)));
+    }
+
+    fn primitive(&self) -> Option<MarchPrimitive> { Some((*self).into()) }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::Cone;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::material::Material;
+    use crate::objects::traits::March;
+
+    #[test]
+    fn test_march() {
+        let cone = Cone::new(Vec3::new(0.0, 0.0, 0.0), 1.0, 2.0, Material::blank());
+
+        assert_eq!(cone.march(Vec3::new(0.0, 3.0, 0.0)), 1.0);
+        assert_eq!(cone.march(Vec3::new(0.0, -1.0, 0.0)), 1.0);
+        assert!(cone.march(Vec3::new(0.0, 0.5, 0.0)) < 0.0);
+    }
+}
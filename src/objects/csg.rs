@@ -0,0 +1,190 @@
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::aabb::Aabb;
+use crate::structures::material::Material;
+use crate::structures::report::Footprint;
+use crate::objects::traits::March;
+
+// boolean combinators over two distance fields. they nest, so any
+// tree of shapes can be built out of pairs.
+
+pub struct Union<A, B> {
+    pub a: A,
+    pub b: B,
+}
+
+pub struct Intersection<A, B> {
+    pub a: A,
+    pub b: B,
+}
+
+// carves b out of a
+pub struct Difference<A, B> {
+    pub a: A,
+    pub b: B,
+}
+
+// union with the seam rounded off over a distance of `k`
+pub struct SmoothUnion<A, B> {
+    pub a: A,
+    pub b: B,
+    pub k: Float,
+}
+
+impl<A, B> Union<A, B> {
+    pub fn new(a: A, b: B) -> Union<A, B> { Union { a: a, b: b } }
+}
+
+impl<A, B> Intersection<A, B> {
+    pub fn new(a: A, b: B) -> Intersection<A, B> { Intersection { a: a, b: b } }
+}
+
+impl<A, B> Difference<A, B> {
+    pub fn new(a: A, b: B) -> Difference<A, B> { Difference { a: a, b: b } }
+}
+
+impl<A, B> SmoothUnion<A, B> {
+    pub fn new(a: A, b: B, k: Float) -> SmoothUnion<A, B> { SmoothUnion { a: a, b: b, k: k } }
+
+    // how much of a is showing: 1 is all a, 0 all b. with no blend at all
+    // it's whichever's nearer, like Union, instead of 0 / 0 on the seam.
+    fn blend(&self, da: Float, db: Float) -> Float {
+        if self.k <= 0.0 { return if da <= db { 1.0 } else { 0.0 }; }
+        return (0.5 + 0.5 * (db - da) / self.k).clamp(0.0, 1.0);
+    }
+}
+
+impl<A: March, B: March> March for Union<A, B> {
+    fn material(&self) -> Material { self.a.material() }
+
+    fn march(&self, point: Vec3) -> Float {
+        self.a.march(point).min(self.b.march(point))
+    }
+
+    fn bounds(&self) -> Option<Aabb> { Some(self.a.bounds()?.union(&self.b.bounds()?)) }
+
+    fn footprint(&self) -> Footprint { Footprint { kind: "union", ..self.a.footprint().and(self.b.footprint()) } }
+
+    fn material_at(&self, point: Vec3) -> Material {
+        if self.a.march(point) <= self.b.march(point) {
+            self.a.material_at(point)
+        } else {
+            self.b.material_at(point)
+        }
+    }
+}
+
+impl<A: March, B: March> March for Intersection<A, B> {
+    fn material(&self) -> Material { self.a.material() }
+
+    fn march(&self, point: Vec3) -> Float {
+        self.a.march(point).max(self.b.march(point))
+    }
+
+    // could be cut to where they overlap, but either one will do
+    fn bounds(&self) -> Option<Aabb> { self.a.bounds().or_else(|| self.b.bounds()) }
+
+    fn footprint(&self) -> Footprint { Footprint { kind: "intersection", ..self.a.footprint().and(self.b.footprint()) } }
+
+    // the surface belongs to whichever side is further out
+    fn material_at(&self, point: Vec3) -> Material {
+        if self.a.march(point) >= self.b.march(point) {
+            self.a.material_at(point)
+        } else {
+            self.b.material_at(point)
+        }
+    }
+}
+
+impl<A: March, B: March> March for Difference<A, B> {
+    fn material(&self) -> Material { self.a.material() }
+
+    fn march(&self, point: Vec3) -> Float {
+        self.a.march(point).max(-self.b.march(point))
+    }
+
+    fn bounds(&self) -> Option<Aabb> { self.a.bounds() }
+
+    fn footprint(&self) -> Footprint { Footprint { kind: "difference", ..self.a.footprint().and(self.b.footprint()) } }
+
+    // the carved out walls take on b's material
+    fn material_at(&self, point: Vec3) -> Material {
+        if self.a.march(point) >= -self.b.march(point) {
+            self.a.material_at(point)
+        } else {
+            self.b.material_at(point)
+        }
+    }
+}
+
+impl<A: March, B: March> March for SmoothUnion<A, B> {
+    fn material(&self) -> Material { self.a.material() }
+
+    // polynomial smooth minimum
+    fn march(&self, point: Vec3) -> Float {
+        let (da, db) = (self.a.march(point), self.b.march(point));
+        let h = self.blend(da, db);
+
+        return db + (da - db) * h - self.k * h * (1.0 - h);
+    }
+
+    // the blend fills out the middle by at most a quarter of k
+    fn bounds(&self) -> Option<Aabb> { Some(self.a.bounds()?.union(&self.b.bounds()?).padded(self.k * 0.25)) }
+
+    fn footprint(&self) -> Footprint { Footprint { kind: "smooth union", ..self.a.footprint().and(self.b.footprint()) } }
+
+    fn material_at(&self, point: Vec3) -> Material {
+        let h = self.blend(self.a.march(point), self.b.march(point));
+        return self.b.material_at(point).lerp(&self.a.material_at(point), h);
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::{ Union, Intersection, Difference, SmoothUnion };
+    use crate::structures::vec3::Vec3;
+    use crate::structures::material::Material;
+    use crate::objects::sphere::Sphere;
+    use crate::objects::traits::March;
+
+    fn spheres() -> (Sphere, Sphere) {
+        let mut red = Material::blank();
+        red.color = Vec3::new(1.0, 0.0, 0.0);
+
+        (
+            Sphere::new(Vec3::new(-1.0, 0.0, 0.0), 1.5, red),
+            Sphere::new(Vec3::new( 1.0, 0.0, 0.0), 1.5, Material::blank()),
+        )
+    }
+
+    #[test]
+    fn test_booleans() {
+        let (a, b) = spheres();
+        let origin = Vec3::new(0.0, 0.0, 0.0);
+        let left = Vec3::new(-3.0, 0.0, 0.0);
+
+        assert_eq!(Union::new(a, b).march(left), a.march(left));
+        assert_eq!(Intersection::new(a, b).march(left), b.march(left));
+        assert_eq!(Difference::new(a, b).march(origin), 0.5);
+    }
+
+    #[test]
+    fn test_smooth_union() {
+        let (a, b) = spheres();
+        let smooth = SmoothUnion::new(a, b, 0.5);
+        let origin = Vec3::new(0.0, 0.0, 0.0);
+
+        // the seam bulges out past the hard union
+        assert!(smooth.march(origin) < Union::new(a, b).march(origin));
+
+        // and is an even mix of both materials
+        assert_eq!(smooth.material_at(origin).color.x, 0.625);
+
+        // without any blend it's the hard union, even right on the seam
+        let hard = SmoothUnion::new(a, b, 0.0);
+        assert_eq!(hard.march(origin), Union::new(a, b).march(origin));
+        let off = Vec3::new(0.4, 0.3, 0.0);
+        assert_eq!(hard.march(off), Union::new(a, b).march(off));
+        assert_eq!(hard.material_at(origin).color.x, 1.0);
+    }
+}
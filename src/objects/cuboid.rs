@@ -0,0 +1,179 @@
+use serde::{ Serialize, Deserialize };
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::ray::Ray;
+use crate::structures::aabb::Aabb;
+use crate::structures::material::Material;
+use crate::objects::primitive::{ MarchPrimitive, TracePrimitive };
+use crate::objects::traits::{ March, Trace };
+use crate::gpu;
+
+// a box, named so it doesn't fight std's Box.
+// `size` is the half extent along each axis, `radius` rounds the edges off
+// without growing the box.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Cuboid {
+    pub position: Vec3,
+    pub size: Vec3,
+    pub radius: Float,
+    pub material: Material,
+}
+
+impl Cuboid {
+    pub fn new(position: Vec3, size: Vec3, material: Material) -> Cuboid {
+        Cuboid::rounded(position, size, 0.0, material)
+    }
+
+    pub fn rounded(position: Vec3, size: Vec3, radius: Float, material: Material) -> Cuboid {
+        Cuboid {
+            position: position,
+            size: size,
+            radius: radius,
+            material: material,
+        }
+    }
+}
+
+impl March for Cuboid {
+    fn material(&self) -> Material { self.material }
+
+    fn march(&self, point: Vec3) -> Float {
+        let q = (point - self.position).abs() - self.size + self.radius;
+        let outside = q.max_by(&Vec3::new(0.0, 0.0, 0.0)).length();
+        let inside = q.x.max(q.y).max(q.z).min(0.0);
+
+        return outside + inside - self.radius;
+    }
+
+    fn bounds(&self) -> Option<Aabb> { Trace::bounds(self) }
+
+    // outside, away from the closest point of the inner box. inside, out
+    // through whichever face is nearest.
+    fn normal(&self, point: Vec3) -> Vec3 {
+        let d = point - self.position;
+        let q = d.abs() - self.size + self.radius;
+        let sign = Vec3::new(d.x.signum(), d.y.signum(), d.z.signum());
+
+        let outside = q.max_by(&Vec3::new(0.0, 0.0, 0.0));
+        if outside.length_squared() > 0.0 { return (outside.unit() * sign).unit(); }
+
+        let axis = if q.x >= q.y && q.x >= q.z { 0 } else if q.y >= q.z { 1 } else { 2 };
+        return match axis {
+            0 => Vec3::new(sign.x, 0.0, 0.0),
+            1 => Vec3::new(0.0, sign.y, 0.0),
+            _ => Vec3::new(0.0, 0.0, sign.z),
+        };
+    }
+
+    fn wgsl(&self) -> Option<String> {
+        Some(format!(
+            "sd_cuboid(p, {}, {}, {})",
+            gpu::vec3(self.position)?, gpu::vec3(self.size)?, gpu::float(self.radius)?,
+        ))
+    }
+
+    fn primitive(&self) -> Option<MarchPrimitive> { Some((*self).into()) }
+}
+
+// traced boxes are always sharp, rounding only applies when marched
+impl Trace for Cuboid {
+    fn material(&self) -> Material { self.material }
+
+    fn bounds(&self) -> Option<Aabb> { Some(Aabb::new(self.position - self.size, self.position + self.size)) }
+
+    fn trace(&self, ray: Ray) -> (bool, Float, Vec3) {
+        let mut near = Float::MIN;
+        let mut far = Float::MAX;
+
+        for axis in 0..3 {
+            let inverse = 1.0 / ray.direction.axis(axis);
+            let center = self.position.axis(axis) - ray.origin.axis(axis);
+            let t0 = (center - self.size.axis(axis)) * inverse;
+            let t1 = (center + self.size.axis(axis)) * inverse;
+
+            near = near.max(t0.min(t1));
+            far = far.min(t0.max(t1));
+        }
+
+        if far < near || far < 0.0 {
+            return (false, Float::MAX, Vec3::new(0.0, 1.0, 0.0));
+        }
+
+        // from inside, the ray leaves through the far side
+        let distance = if near > 0.0 { near } else { far };
+
+        // the face hit is the axis the point sticks out furthest along
+        let local = (ray.point_at(&distance) - self.position) / self.size;
+        let axis = (0..3)
+            .max_by(|a, b| local.axis(*a).abs().partial_cmp(&local.axis(*b).abs()).unwrap())
+            .unwrap();
+
+        let mut normal = Vec3::new(0.0, 0.0, 0.0);
+        let sign = local.axis(axis).signum();
+        match axis {
+            0 => normal.x = sign,
+            1 => normal.y = sign,
+            _ => normal.z = sign,
+        }
+
+        return (true, distance, normal);
+    }
+
+    fn primitive(&self) -> Option<TracePrimitive> { Some((*self).into()) }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::Cuboid;
+    use crate::structures::float::Float;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::material::Material;
+    use crate::structures::ray::Ray;
+    use crate::objects::traits::{ March, Trace };
+
+    #[test]
+    fn test_march() {
+        let cube = Cuboid::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 1.0), Material::blank());
+
+        assert_eq!(cube.march(Vec3::new(3.0, 0.0, 0.0)), 2.0);
+        assert_eq!(cube.march(Vec3::new(0.5, 0.0, 0.0)), -0.5);
+        assert_eq!(cube.march(Vec3::new(2.0, 2.0, 1.0)), Float::sqrt(2.0));
+    }
+
+    #[test]
+    fn test_rounded() {
+        let cube = Cuboid::rounded(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 1.0), 0.5, Material::blank());
+
+        // faces stay put, corners pull in
+        assert_eq!(cube.march(Vec3::new(3.0, 0.0, 0.0)), 2.0);
+        assert!(cube.march(Vec3::new(1.0, 1.0, 1.0)) > 0.0);
+    }
+
+    #[test]
+    fn test_trace() {
+        let cube = Cuboid::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 1.0), Material::blank());
+
+        let outside = Ray::new(Vec3::new(0.5, 4.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert_eq!(cube.trace(outside), (true, 3.0, Vec3::new(0.0, 1.0, 0.0)));
+
+        let inside = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(cube.trace(inside), (true, 1.0, Vec3::new(1.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn test_normal() {
+        let cube = Cuboid::rounded(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 1.0), 0.25, Material::blank());
+        assert_eq!(cube.normal(Vec3::new(1.0, 0.2, 0.0)), Vec3::new(1.0, 0.0, 0.0));
+
+        // the exact normal agrees with estimating it from the field
+        let edge = Vec3::new(0.95, -0.95, 0.3);
+        let h = 0.0001;
+        let numeric = Vec3::new(
+            cube.march(edge + Vec3::new(h, 0.0, 0.0)) - cube.march(edge - Vec3::new(h, 0.0, 0.0)),
+            cube.march(edge + Vec3::new(0.0, h, 0.0)) - cube.march(edge - Vec3::new(0.0, h, 0.0)),
+            cube.march(edge + Vec3::new(0.0, 0.0, h)) - cube.march(edge - Vec3::new(0.0, 0.0, h)),
+        ).unit();
+        assert!((cube.normal(edge) - numeric).length() < 1e-6);
+    }
+}
@@ -0,0 +1,260 @@
+use std::mem::size_of_val;
+use rand::Rng;
+use serde::{ Serialize, Deserialize };
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::ray::Ray;
+use crate::structures::material::Material;
+use crate::structures::aabb::Aabb;
+use crate::structures::report::Footprint;
+use crate::structures::bvh::Bvh;
+use crate::structures::alias::AliasTable;
+use crate::objects::mesh::Mesh;
+use crate::objects::traits::Trace;
+
+// straight pieces each curve is cut into for hitting it
+const SEGMENTS: usize = 8;
+const EPSILON: Float = 0.0001;
+
+// how a curve is hit. flat ones are ribbons always turned to face the
+// ray, cheap and fine for hair too thin to see round. cylinders are round
+// tubes, for anything thick enough to show it.
+#[derive(Debug, Copy, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub enum CurveShape {
+    #[default]
+    Flat,
+    Cylinder,
+}
+
+// a cubic bézier through four control points, as wide as `widths` says at
+// either end and in between along the way
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Curve {
+    pub points: [Vec3; 4],
+    pub widths: [Float; 2],
+}
+
+impl Curve {
+    pub fn new(points: [Vec3; 4], widths: [Float; 2]) -> Curve {
+        Curve { points: points, widths: widths }
+    }
+
+    pub fn point(&self, t: Float) -> Vec3 {
+        let [a, b, c, d] = self.points;
+        let s = 1.0 - t;
+        return a * (s * s * s) + b * (3.0 * s * s * t) + c * (3.0 * s * t * t) + d * (t * t * t);
+    }
+
+    pub fn width(&self, t: Float) -> Float {
+        self.widths[0] + (self.widths[1] - self.widths[0]) * t
+    }
+}
+
+// one straight piece of a curve, `range` being where along it the piece is
+#[derive(Debug, Copy, Clone)]
+struct Segment {
+    start: Vec3,
+    end: Vec3,
+    radii: [Float; 2],
+    range: [Float; 2],
+}
+
+impl Segment {
+    // where along the ray it hits, where along the segment, and the normal
+    fn hit(&self, ray: &Ray, shape: CurveShape) -> Option<(Float, Float, Vec3)> {
+        let length = ray.direction.length();
+        let d = ray.direction / length;
+        let a = self.end - self.start;
+        let w = ray.origin - self.start;
+
+        // closest points between the ray and the axis
+        let (b, c, e, f) = (d.dot(&a), a.dot(&a), d.dot(&w), a.dot(&w));
+        let denom = c - b * b;
+        if denom <= 1e-12 * c { return None; }
+
+        let s = ((f - b * e) / denom).clamp(0.0, 1.0);
+        let axis = self.start + a * s;
+        let t = (axis - ray.origin).dot(&d);
+
+        let radius = self.radii[0] + (self.radii[1] - self.radii[0]) * s;
+        let gap = (ray.origin + d * t - axis).length_squared();
+        if gap > radius * radius { return None; }
+
+        let along = a / c.sqrt();
+        let (t, normal) = match shape {
+            CurveShape::Flat => {
+                let facing = along * d.dot(&along) - d;
+                (t, if facing.length_squared() > 0.0 { facing.unit() } else { d * -1.0 })
+            },
+            CurveShape::Cylinder => {
+                let sine = d.cross(&along).length();
+                let t = t - (radius * radius - gap).sqrt() / sine;
+                let point = ray.origin + d * t;
+                let s = ((point - self.start).dot(&a) / c).clamp(0.0, 1.0);
+                (t, (point - (self.start + a * s)).unit())
+            },
+        };
+
+        // anything within the fibre it's leaving from is itself
+        if t <= EPSILON.max(2.0 * radius) { return None; }
+        return Some((t / length, s, normal));
+    }
+
+    fn distance(&self, point: Vec3) -> Float {
+        let (pa, ba) = (point - self.start, self.end - self.start);
+        let h = (pa.dot(&ba) / ba.dot(&ba)).clamp(0.0, 1.0);
+        return (pa - ba * h).length() - (self.radii[0] + (self.radii[1] - self.radii[0]) * h);
+    }
+}
+
+// lots of curves under one material, like fur or a patch of grass. give
+// them a hair material (Material::hair) to shade them along their length.
+// traced only, use `fur` to grow them out of a mesh, or out of an sdf
+// turned into one with Scene::extract_mesh.
+#[derive(Debug, Clone)]
+pub struct Curves {
+    pub curves: Vec<Curve>,
+    pub shape: CurveShape,
+    pub material: Material,
+    segments: Vec<Segment>,
+    bvh: Bvh,
+}
+
+impl Curves {
+    pub fn new(curves: Vec<Curve>, shape: CurveShape, material: Material) -> Curves {
+        let segments: Vec<Segment> = curves.iter().flat_map(|curve| (0..SEGMENTS).map(move |i| {
+            let range = [i as Float / SEGMENTS as Float, (i + 1) as Float / SEGMENTS as Float];
+            Segment {
+                start: curve.point(range[0]),
+                end: curve.point(range[1]),
+                radii: [curve.width(range[0]) * 0.5, curve.width(range[1]) * 0.5],
+                range: range,
+            }
+        })).collect();
+
+        let bounds: Vec<Aabb> = segments.iter().map(|segment| {
+            let pad = Vec3::new(1.0, 1.0, 1.0) * segment.radii[0].max(segment.radii[1]);
+            let around = Aabb::around(&[segment.start, segment.end]);
+            Aabb::new(around.min - pad, around.max + pad)
+        }).collect();
+
+        Curves { bvh: Bvh::new(&bounds), curves: curves, shape: shape, material: material, segments: segments }
+    }
+
+    // `count` hairs `length` long, scattered evenly over the mesh and
+    // standing out along it, drooping a little under their own weight.
+    // they're `width` wide at the root and taper to nearly nothing.
+    pub fn fur(mesh: &Mesh, count: usize, length: Float, width: Float, material: Material, rng: &mut impl Rng) -> Curves {
+        let corners = |triangle: &[usize; 3]| (mesh.vertices[triangle[0]], mesh.vertices[triangle[1]], mesh.vertices[triangle[2]]);
+        let areas: Vec<Float> = mesh.triangles.iter().map(|triangle| {
+            let (a, b, c) = corners(triangle);
+            (b - a).cross(&(c - a)).length()
+        }).collect();
+
+        let table = match AliasTable::new(&areas) {
+            Some(table) => table,
+            None => return Curves::new(vec![], CurveShape::Flat, material),
+        };
+
+        let down = Vec3::new(0.0, -1.0, 0.0) * (length * 0.25);
+        let curves = (0..count).map(|_| {
+            let (index, _) = table.sample(rng.gen());
+            let (a, b, c) = corners(&mesh.triangles[index]);
+
+            // evenly over the triangle
+            let (mut u, mut v): (Float, Float) = (rng.gen(), rng.gen());
+            if u + v > 1.0 { u = 1.0 - u; v = 1.0 - v; }
+            let root = a + (b - a) * u + (c - a) * v;
+            let normal = (b - a).cross(&(c - a)).unit() * length;
+
+            Curve::new([root, root + normal / 3.0, root + normal * (2.0 / 3.0) + down * 0.5, root + normal + down], [width, width * 0.1])
+        }).collect();
+
+        return Curves::new(curves, CurveShape::Flat, material);
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        self.bvh.bounds()
+    }
+
+    fn nearest(&self, point: Vec3) -> Option<&Segment> {
+        self.bvh.nearest(&point, |index| self.segments[index].distance(point)).map(|(index, _)| &self.segments[index])
+    }
+}
+
+impl Trace for Curves {
+    fn material(&self) -> Material { self.material }
+    fn bounds(&self) -> Option<Aabb> { Some(self.bvh.bounds()) }
+
+    fn footprint(&self) -> Footprint {
+        let bytes = size_of_val(&self.curves[..]) + size_of_val(&self.segments[..]);
+        return Footprint::new("curves").with_bytes(bytes).with_bvh(&self.bvh);
+    }
+
+    fn trace(&self, ray: Ray) -> (bool, Float, Vec3) {
+        let hit = self.bvh.traverse(&ray, |index| self.segments[index].hit(&ray, self.shape).map(|(t, _, _)| t));
+
+        if let Some((index, _)) = hit {
+            if let Some((t, _, normal)) = self.segments[index].hit(&ray, self.shape) {
+                return (true, t, normal);
+            }
+        }
+
+        return (false, Float::MAX, Vec3::new(0.0, 1.0, 0.0));
+    }
+
+    // the way the curve runs, root to tip
+    fn tangent(&self, point: Vec3, _normal: Vec3) -> Vec3 {
+        self.nearest(point).map_or(Vec3::new(0.0, 1.0, 0.0), |segment| (segment.end - segment.start).unit())
+    }
+
+    // how far along its curve it is, and 0
+    fn uv(&self, point: Vec3, _normal: Vec3) -> [Float; 2] {
+        self.nearest(point).map_or([0.0, 0.0], |segment| {
+            let (pa, ba) = (point - segment.start, segment.end - segment.start);
+            let h = (pa.dot(&ba) / ba.dot(&ba)).clamp(0.0, 1.0);
+            [segment.range[0] + (segment.range[1] - segment.range[0]) * h, 0.0]
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::{ Curves, Curve, CurveShape };
+    use crate::structures::float::Float;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::ray::Ray;
+    use crate::structures::material::Material;
+    use crate::objects::mesh::Mesh;
+    use crate::objects::traits::Trace;
+
+    #[test]
+    fn test_curves() {
+        // a straight strand up the y axis, 0.2 wide
+        let up = |y: Float| Vec3::new(0.0, y, 0.0);
+        let strand = Curve::new([up(0.0), up(1.0 / 3.0), up(2.0 / 3.0), up(1.0)], [0.2, 0.2]);
+        let ray = Ray::new(Vec3::new(0.05, 0.5, 5.0), Vec3::new(0.0, 0.0, -1.0));
+
+        // a ribbon's hit on its middle, facing the ray; a tube's on its side
+        let (hit, distance, normal) = Curves::new(vec![strand], CurveShape::Flat, Material::blank()).trace(ray);
+        assert!(hit && (distance - 5.0).abs() < 0.0001);
+        assert!((normal - Vec3::new(0.0, 0.0, 1.0)).length() < 0.0001);
+
+        let tube = Curves::new(vec![strand], CurveShape::Cylinder, Material::blank());
+        let (hit, distance, normal) = tube.trace(ray);
+        assert!(hit && (distance - (5.0 - (0.01 as Float - 0.0025).sqrt())).abs() < 0.0001);
+        assert!(normal.z > 0.8 && normal.x > 0.0);
+        assert!((tube.tangent(ray.point_at(&distance), normal) - up(1.0)).length() < 0.0001);
+
+        // and past either side or the end, nothing
+        assert!(!tube.trace(Ray::new(Vec3::new(0.2, 0.5, 5.0), Vec3::new(0.0, 0.0, -1.0))).0);
+        assert!(!tube.trace(Ray::new(Vec3::new(0.0, 1.5, 5.0), Vec3::new(0.0, 0.0, -1.0))).0);
+
+        // fur stands out of the mesh from all over it
+        let floor = Mesh::new(vec![up(0.0), Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0)], vec![[0, 2, 1]], Material::blank());
+        let fur = Curves::fur(&floor, 50, 0.5, 0.01, Material::blank(), &mut rand::thread_rng());
+        assert_eq!(fur.curves.len(), 50);
+        assert!(fur.curves.iter().all(|curve| curve.points[0].y.abs() < 0.0001 && curve.points[3].y > 0.3));
+    }
+}
@@ -0,0 +1,47 @@
+use serde::{ Serialize, Deserialize };
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::aabb::Aabb;
+use crate::structures::material::Material;
+use crate::objects::primitive::MarchPrimitive;
+use crate::objects::traits::March;
+
+// a capped cylinder standing along y, `height` is half the full height
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Cylinder {
+    pub position: Vec3,
+    pub radius: Float,
+    pub height: Float,
+    pub material: Material,
+}
+
+impl Cylinder {
+    pub fn new(position: Vec3, radius: Float, height: Float, material: Material) -> Cylinder {
+        Cylinder {
+            position: position,
+            radius: radius,
+            height: height,
+            material: material,
+        }
+    }
+}
+
+impl March for Cylinder {
+    fn material(&self) -> Material { self.material }
+
+    fn march(&self, point: Vec3) -> Float {
+        let p = point - self.position;
+        let dx = (p.x * p.x + p.z * p.z).sqrt() - self.radius;
+        let dy = p.y.abs() - self.height;
+
+        let inside = dx.max(dy).min(0.0);
+        let outside = (dx.max(0.0).powi(2) + dy.max(0.0).powi(2)).sqrt();
+
+        return inside + outside;
+    }
+
+    fn bounds(&self) -> Option<Aabb> { Some(Aabb::new(self.position - Vec3::new(self.radius, self.height, self.radius), self.position + Vec3::new(self.radius, self.height, self.radius))) }
+
+    fn primitive(&self) -> Option<MarchPrimitive> { Some((*self).into()) }
+}
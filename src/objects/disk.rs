@@ -0,0 +1,54 @@
+use serde::{ Serialize, Deserialize };
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::ray::Ray;
+use crate::structures::aabb::Aabb;
+use crate::structures::material::Material;
+use crate::objects::primitive::TracePrimitive;
+use crate::objects::traits::Trace;
+use crate::objects::plane::Plane;
+
+// a round, flat patch of a plane
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Disk {
+    pub position: Vec3,
+    pub normal: Vec3,
+    pub radius: Float,
+    pub material: Material,
+}
+
+impl Disk {
+    pub fn new(position: Vec3, normal: Vec3, radius: Float, material: Material) -> Disk {
+        Disk {
+            position: position,
+            normal: normal.unit(),
+            radius: radius,
+            material: material,
+        }
+    }
+}
+
+impl Trace for Disk {
+    fn material(&self) -> Material { self.material }
+
+    // as far out along each axis as the rim goes
+    fn bounds(&self) -> Option<Aabb> {
+        let n = self.normal.unit();
+        let reach = Vec3::new((1.0 - n.x * n.x).max(0.0).sqrt(), (1.0 - n.y * n.y).max(0.0).sqrt(), (1.0 - n.z * n.z).max(0.0).sqrt()) * self.radius;
+        Some(Aabb::new(self.position - reach, self.position + reach))
+    }
+
+    fn trace(&self, ray: Ray) -> (bool, Float, Vec3) {
+        let plane = Plane::new(self.position, self.normal, self.material);
+        let (hit, distance, normal) = plane.trace(ray);
+
+        if hit && (ray.point_at(&distance) - self.position).length_squared() <= self.radius * self.radius {
+            return (true, distance, normal);
+        }
+
+        return (false, Float::MAX, self.normal);
+    }
+
+    fn primitive(&self) -> Option<TracePrimitive> { Some((*self).into()) }
+}
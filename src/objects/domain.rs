@@ -0,0 +1,157 @@
+use crate::structures::float::Float;
+use crate::structures::float::consts::PI;
+
+use crate::structures::vec3::Vec3;
+use crate::structures::material::Material;
+use crate::structures::report::Footprint;
+use crate::objects::traits::March;
+
+// space warps: each folds the point back into a single cell before asking
+// the wrapped object, so one primitive shows up many times for free.
+// distances stay exact as long as the object fits inside its cell.
+
+// tiles space forever, a period of 0 leaves that axis alone
+pub struct Repeat<T> {
+    pub object: T,
+    pub period: Vec3,
+}
+
+// like repeat, but only `limit` copies out on each side of the original
+pub struct RepeatLimited<T> {
+    pub object: T,
+    pub period: Vec3,
+    pub limit: Vec3,
+}
+
+// reflects the negative half of each flagged axis onto the positive one
+pub struct Mirror<T> {
+    pub object: T,
+    pub axes: [bool; 3],
+}
+
+// `count` copies spun evenly around the y axis
+pub struct Polar<T> {
+    pub object: T,
+    pub count: usize,
+}
+
+fn fold(value: Float, period: Float, limit: Float) -> Float {
+    if period == 0.0 { return value; }
+    let cell = (value / period).round().clamp(-limit, limit);
+    return value - period * cell;
+}
+
+impl<T> Repeat<T> {
+    pub fn new(object: T, period: Vec3) -> Repeat<T> {
+        Repeat { object: object, period: period }
+    }
+
+    fn local(&self, p: Vec3) -> Vec3 {
+        Vec3::new(
+            fold(p.x, self.period.x, Float::INFINITY),
+            fold(p.y, self.period.y, Float::INFINITY),
+            fold(p.z, self.period.z, Float::INFINITY),
+        )
+    }
+}
+
+impl<T> RepeatLimited<T> {
+    pub fn new(object: T, period: Vec3, limit: Vec3) -> RepeatLimited<T> {
+        RepeatLimited { object: object, period: period, limit: limit }
+    }
+
+    fn local(&self, p: Vec3) -> Vec3 {
+        Vec3::new(
+            fold(p.x, self.period.x, self.limit.x),
+            fold(p.y, self.period.y, self.limit.y),
+            fold(p.z, self.period.z, self.limit.z),
+        )
+    }
+}
+
+impl<T> Mirror<T> {
+    pub fn new(object: T, axes: [bool; 3]) -> Mirror<T> {
+        Mirror { object: object, axes: axes }
+    }
+
+    fn local(&self, p: Vec3) -> Vec3 {
+        Vec3::new(
+            if self.axes[0] { p.x.abs() } else { p.x },
+            if self.axes[1] { p.y.abs() } else { p.y },
+            if self.axes[2] { p.z.abs() } else { p.z },
+        )
+    }
+}
+
+impl<T> Polar<T> {
+    pub fn new(object: T, count: usize) -> Polar<T> {
+        Polar { object: object, count: count.max(1) }
+    }
+
+    // rotates the point into the sector centered on +x
+    fn local(&self, p: Vec3) -> Vec3 {
+        let sector = 2.0 * PI / (self.count as Float);
+        let angle = p.z.atan2(p.x);
+        let folded = angle - sector * (angle / sector).round();
+        let radius = (p.x * p.x + p.z * p.z).sqrt();
+
+        return Vec3::new(radius * folded.cos(), p.y, radius * folded.sin());
+    }
+}
+
+// they all look the same from the outside
+macro_rules! warp {
+    ($name:ident) => {
+        impl<T: March> March for $name<T> {
+            fn material(&self) -> Material { self.object.material() }
+
+            fn march(&self, point: Vec3) -> Float {
+                self.object.march(self.local(point))
+            }
+
+            fn material_at(&self, point: Vec3) -> Material {
+                self.object.material_at(self.local(point))
+            }
+
+            fn footprint(&self) -> Footprint { self.object.footprint() }
+        }
+    };
+}
+
+warp!(Repeat);
+warp!(RepeatLimited);
+warp!(Mirror);
+warp!(Polar);
+
+#[cfg(test)]
+pub mod test {
+    use super::{ Repeat, RepeatLimited, Polar };
+    use crate::structures::vec3::Vec3;
+    use crate::structures::material::Material;
+    use crate::objects::sphere::Sphere;
+    use crate::objects::traits::March;
+
+    fn unit() -> Sphere {
+        Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0, Material::blank())
+    }
+
+    #[test]
+    fn test_repeat() {
+        let grid = Repeat::new(unit(), Vec3::new(4.0, 0.0, 4.0));
+        assert_eq!(grid.march(Vec3::new(40.0, 0.0, -8.0)), -1.0);
+        assert_eq!(grid.march(Vec3::new(40.0, 3.0, -8.0)), 2.0);
+    }
+
+    #[test]
+    fn test_repeat_limited() {
+        let row = RepeatLimited::new(unit(), Vec3::new(4.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0));
+        assert_eq!(row.march(Vec3::new(8.0, 0.0, 0.0)), -1.0);
+        assert_eq!(row.march(Vec3::new(12.0, 0.0, 0.0)), 3.0);
+    }
+
+    #[test]
+    fn test_polar() {
+        let ring = Polar::new(Sphere::new(Vec3::new(3.0, 0.0, 0.0), 1.0, Material::blank()), 4);
+        assert!((ring.march(Vec3::new(0.0, 0.0, -3.0)) + 1.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,63 @@
+use serde::{ Serialize, Deserialize };
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::aabb::Aabb;
+use crate::structures::material::Material;
+use crate::objects::primitive::MarchPrimitive;
+use crate::objects::traits::March;
+
+// a hexagonal column standing along y. `radius` is center to the middle of
+// a side, `height` is half the full height.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct HexPrism {
+    pub position: Vec3,
+    pub radius: Float,
+    pub height: Float,
+    pub material: Material,
+}
+
+impl HexPrism {
+    pub fn new(position: Vec3, radius: Float, height: Float, material: Material) -> HexPrism {
+        HexPrism {
+            position: position,
+            radius: radius,
+            height: height,
+            material: material,
+        }
+    }
+}
+
+impl March for HexPrism {
+    fn material(&self) -> Material { self.material }
+
+    fn march(&self, point: Vec3) -> Float {
+        // cos 30, sin 30, tan 30, rounded off when building with f32
+        #[allow(clippy::excessive_precision)]
+        let (kx, ky, kz) = (-0.866_025_403_784_438_6, 0.5, 0.577_350_269_189_625_8);
+
+        let p = (point - self.position).abs();
+        let (mut x, mut z) = (p.x, p.z);
+
+        // fold the hexagon onto a single side
+        let fold = 2.0 * (kx * x + ky * z).min(0.0);
+        x -= fold * kx;
+        z -= fold * ky;
+
+        let edge = x.clamp(-kz * self.radius, kz * self.radius);
+        let side = ((x - edge).powi(2) + (z - self.radius).powi(2)).sqrt();
+        let dx = if z > self.radius { side } else { -side };
+        let dy = p.y - self.height;
+
+        return dx.max(dy).min(0.0) + (dx.max(0.0).powi(2) + dy.max(0.0).powi(2)).sqrt();
+    }
+
+    // out to the corners, a little further than the flat sides
+    fn bounds(&self) -> Option<Aabb> {
+        let corner = self.radius * 2.0 / (3.0 as Float).sqrt();
+        let reach = Vec3::new(corner, self.height, corner);
+        return Some(Aabb::new(self.position - reach, self.position + reach));
+    }
+
+    fn primitive(&self) -> Option<MarchPrimitive> { Some((*self).into()) }
+}
@@ -0,0 +1,125 @@
+use std::sync::Arc;
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::ray::Ray;
+use crate::structures::aabb::Aabb;
+use crate::structures::report::Footprint;
+use crate::structures::material::Material;
+use crate::structures::transform::Transform;
+use crate::objects::traits::{ March, Trace };
+use crate::objects::visible::Visibility;
+use crate::objects::transformed::stretch;
+
+// a placed copy of shared geometry. thousands of instances of one mesh
+// share its vertices and bvh, each only pays for a transform.
+#[derive(Debug)]
+pub struct Instance<T: ?Sized> {
+    pub object: Arc<T>,
+    pub transform: Transform,
+    // replaces the shared object's material when set
+    pub material: Option<Material>,
+    stretch: Float,
+}
+
+impl<T: ?Sized> Instance<T> {
+    pub fn new(object: Arc<T>, transform: Transform) -> Instance<T> {
+        Instance {
+            object: object,
+            stretch: stretch(&transform),
+            transform: transform,
+            material: None,
+        }
+    }
+
+    pub fn with_material(object: Arc<T>, transform: Transform, material: Material) -> Instance<T> {
+        let mut instance = Instance::new(object, transform);
+        instance.material = Some(material);
+        return instance;
+    }
+}
+
+impl<T: ?Sized> Clone for Instance<T> {
+    fn clone(&self) -> Instance<T> {
+        Instance {
+            object: Arc::clone(&self.object),
+            transform: self.transform,
+            material: self.material,
+            stretch: self.stretch,
+        }
+    }
+}
+
+impl<T: Trace + ?Sized> Trace for Instance<T> {
+    fn material(&self) -> Material {
+        self.material.unwrap_or_else(|| self.object.material())
+    }
+
+    fn trace(&self, ray: Ray) -> (bool, Float, Vec3) {
+        let (hit, distance, normal) = self.object.trace(self.transform.inverted().ray(ray));
+        return (hit, distance, self.transform.normal(normal));
+    }
+
+    fn bounds(&self) -> Option<Aabb> { self.object.bounds().map(|bounds| self.transform.aabb(&bounds)) }
+
+    fn material_at(&self, point: Vec3, normal: Vec3) -> Material {
+        let local = self.transform.inverted();
+
+        match self.material {
+            Some(material) => material,
+            None => self.object.material_at(local.point(point), local.normal(normal)),
+        }
+    }
+
+    fn tangent(&self, point: Vec3, normal: Vec3) -> Vec3 {
+        let local = self.transform.inverted();
+        self.transform.vector(self.object.tangent(local.point(point), local.normal(normal))).unit()
+    }
+
+    fn uv(&self, point: Vec3, normal: Vec3) -> [Float; 2] {
+        let local = self.transform.inverted();
+        self.object.uv(local.point(point), local.normal(normal))
+    }
+
+    fn visibility(&self) -> Visibility { self.object.visibility() }
+    fn shadow_catcher(&self) -> bool { self.object.shadow_catcher() }
+    fn light_group(&self) -> Option<&str> { self.object.light_group() }
+    fn footprint(&self) -> Footprint { self.object.footprint() }
+}
+
+impl<T: March + ?Sized> March for Instance<T> {
+    fn material(&self) -> Material {
+        self.material.unwrap_or_else(|| self.object.material())
+    }
+
+    fn march(&self, point: Vec3) -> Float {
+        self.object.march(self.transform.inverted().point(point)) * self.stretch
+    }
+
+    fn bounds(&self) -> Option<Aabb> { self.object.bounds().map(|bounds| self.transform.aabb(&bounds)) }
+
+    fn material_at(&self, point: Vec3) -> Material {
+        match self.material {
+            Some(material) => material,
+            None => self.object.material_at(self.transform.inverted().point(point)),
+        }
+    }
+
+    fn normal(&self, point: Vec3) -> Vec3 {
+        self.transform.normal(self.object.normal(self.transform.inverted().point(point)))
+    }
+
+    fn tangent(&self, point: Vec3, normal: Vec3) -> Vec3 {
+        let local = self.transform.inverted();
+        self.transform.vector(self.object.tangent(local.point(point), local.normal(normal))).unit()
+    }
+
+    fn uv(&self, point: Vec3) -> [Float; 2] {
+        self.object.uv(self.transform.inverted().point(point))
+    }
+
+    fn visibility(&self) -> Visibility { self.object.visibility() }
+    fn shadow_catcher(&self) -> bool { self.object.shadow_catcher() }
+    fn light_group(&self) -> Option<&str> { self.object.light_group() }
+    fn footprint(&self) -> Footprint { self.object.footprint() }
+}
@@ -0,0 +1,76 @@
+use serde::{ Serialize, Deserialize };
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::material::Material;
+use crate::objects::primitive::MarchPrimitive;
+use crate::objects::traits::March;
+use crate::objects::orbit_trap::OrbitTrap;
+
+// a 3d slice through a quaternion julia set, z -> z^2 + c
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Julia {
+    pub position: Vec3,
+    pub c: [Float; 4],
+    pub iterations: usize,
+    pub material: Material,
+    pub trap: Option<OrbitTrap>,
+}
+
+impl Julia {
+    pub fn new(position: Vec3, c: [Float; 4], iterations: usize, material: Material) -> Julia {
+        Julia {
+            position: position,
+            c: c,
+            iterations: iterations,
+            material: material,
+            trap: None,
+        }
+    }
+
+    // distance estimate, and how close the orbit got to the origin
+    fn orbit(&self, point: Vec3) -> (Float, Float) {
+        let p = point - self.position;
+        let mut z = [p.x, p.y, p.z, 0.0];
+        let mut z2 = p.length_squared();
+        let mut dz2 = 1.0; // squared length of the derivative
+        let mut trap = Float::MAX;
+
+        for _ in 0..self.iterations {
+            dz2 *= 4.0 * z2;
+
+            // quaternion square
+            let [a, b, c, d] = z;
+            z = [
+                a * a - b * b - c * c - d * d + self.c[0],
+                2.0 * a * b + self.c[1],
+                2.0 * a * c + self.c[2],
+                2.0 * a * d + self.c[3],
+            ];
+
+            z2 = z.iter().map(|v| v * v).sum();
+            trap = trap.min(z2);
+
+            if z2 > 256.0 { break; }
+        }
+
+        return (0.25 * z2.ln() * (z2 / dz2).sqrt(), trap.sqrt());
+    }
+}
+
+impl March for Julia {
+    fn material(&self) -> Material { self.material }
+
+    fn march(&self, point: Vec3) -> Float {
+        self.orbit(point).0.max(0.0)
+    }
+
+    fn material_at(&self, point: Vec3) -> Material {
+        match self.trap {
+            Some(trap) => trap.apply(self.material, self.orbit(point).1),
+            None => self.material,
+        }
+    }
+
+    fn primitive(&self) -> Option<MarchPrimitive> { Some((*self).into()) }
+}
@@ -0,0 +1,89 @@
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::ray::Ray;
+use crate::structures::aabb::Aabb;
+use crate::structures::report::Footprint;
+use crate::structures::material::Material;
+use crate::structures::packet::{ WideVec3, RayPacket, Lanes, LANES };
+use crate::objects::traits::{ March, Trace };
+use crate::objects::visible::Visibility;
+
+// wraps a light, or anything glowing, so what it lights gets rendered into
+// its own buffer, see render::render_light_groups. lights sharing a group
+// name end up in the same buffer.
+#[derive(Debug, Clone)]
+pub struct LightGroup<T> {
+    pub object: T,
+    pub group: String,
+}
+
+impl<T> LightGroup<T> {
+    pub fn new(object: T, group: &str) -> LightGroup<T> {
+        LightGroup { object: object, group: group.to_string() }
+    }
+}
+
+impl<T: March> March for LightGroup<T> {
+    fn material(&self) -> Material { self.object.material() }
+    fn march(&self, point: Vec3) -> Float { self.object.march(point) }
+    fn bounds(&self) -> Option<Aabb> { self.object.bounds() }
+    fn material_at(&self, point: Vec3) -> Material { self.object.material_at(point) }
+    fn normal(&self, point: Vec3) -> Vec3 { self.object.normal(point) }
+    fn tangent(&self, point: Vec3, normal: Vec3) -> Vec3 { self.object.tangent(point, normal) }
+    fn uv(&self, point: Vec3) -> [Float; 2] { self.object.uv(point) }
+    fn march_packet(&self, points: &WideVec3) -> Lanes { self.object.march_packet(points) }
+    fn wgsl(&self) -> Option<String> { self.object.wgsl() }
+    fn visibility(&self) -> Visibility { self.object.visibility() }
+    fn shadow_catcher(&self) -> bool { self.object.shadow_catcher() }
+    fn light_group(&self) -> Option<&str> { Some(&self.group) }
+    fn footprint(&self) -> Footprint { self.object.footprint() }
+}
+
+impl<T: Trace> Trace for LightGroup<T> {
+    fn material(&self) -> Material { self.object.material() }
+    fn trace(&self, ray: Ray) -> (bool, Float, Vec3) { self.object.trace(ray) }
+    fn bounds(&self) -> Option<Aabb> { self.object.bounds() }
+    fn material_at(&self, point: Vec3, normal: Vec3) -> Material { self.object.material_at(point, normal) }
+    fn tangent(&self, point: Vec3, normal: Vec3) -> Vec3 { self.object.tangent(point, normal) }
+    fn uv(&self, point: Vec3, normal: Vec3) -> [Float; 2] { self.object.uv(point, normal) }
+    fn trace_packet(&self, packet: &RayPacket) -> [(bool, Float, Vec3); LANES] { self.object.trace_packet(packet) }
+    fn wgsl(&self) -> Option<String> { self.object.wgsl() }
+    fn visibility(&self) -> Visibility { self.object.visibility() }
+    fn shadow_catcher(&self) -> bool { self.object.shadow_catcher() }
+    fn light_group(&self) -> Option<&str> { Some(&self.group) }
+    fn footprint(&self) -> Footprint { self.object.footprint() }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::LightGroup;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::camera::Camera;
+    use crate::structures::material::Material;
+    use crate::structures::scene::Scene;
+    use crate::render::render_light_groups;
+    use crate::objects::sphere::Sphere;
+
+    #[test]
+    fn test_light_groups() {
+        let warm = Material { color: Vec3::new(1.0, 0.5, 0.0), ..Material::blank() };
+        let mut camera = Camera::new(Vec3::new(0.0, 0.0, 4.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        camera.fov = 1.0;
+
+        let scene = Scene::builder()
+            .camera(camera)
+            .environment(Vec3::new(0.0, 0.0, 0.0))
+            .trace(LightGroup::new(Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0, warm), "key"))
+            .trace(LightGroup::new(Sphere::new(Vec3::new(5.0, 0.0, 0.0), 1.0, warm), "fill"))
+            .samples(1)
+            .build();
+
+        let (groups, _) = render_light_groups(&scene, [1, 1]);
+        let names: Vec<&str> = groups.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["environment", "default", "key", "fill"]);
+
+        // looking straight at the key light, and nothing else
+        assert_eq!(groups[2].1[0][0], warm.color);
+        assert_eq!(groups[3].1[0][0], Vec3::new(0.0, 0.0, 0.0));
+    }
+}
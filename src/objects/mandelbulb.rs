@@ -1,59 +1,82 @@
-use std::f64;
+use serde::{ Serialize, Deserialize };
 
+use crate::structures::float::Float;
 use crate::structures::vec3::Vec3;
 use crate::structures::material::Material;
+use crate::objects::primitive::MarchPrimitive;
 use crate::objects::traits::March;
+use crate::objects::orbit_trap::OrbitTrap;
 
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct Mandelbulb {
     pub position: Vec3,
-    pub power: f64,
+    pub power: Float,
     pub iterations: usize,
     pub material: Material,
+    pub trap: Option<OrbitTrap>,
 }
 
-fn length(x: f64, y: f64) -> f64 {
+fn length(x: Float, y: Float) -> Float {
     (x * x + y * y).sqrt()
 }
 
 impl Mandelbulb {
-    pub fn new(position: Vec3, power: f64, iterations: usize, material: Material) -> Mandelbulb {
+    pub fn new(position: Vec3, power: Float, iterations: usize, material: Material) -> Mandelbulb {
         Mandelbulb {
             position: position,
             power: power,
             iterations: iterations,
             material: material,
+            trap: None,
         }
     }
-}
 
-impl March for Mandelbulb {
-    fn material(&self) -> Material { self.material }
-
-    fn march(&self, point: Vec3) -> f64 {
-        let mut zn = point.clone() - self.position; // added self.position
-        let mut rad = 0.0;
-        let mut hit = 0.0;
+    // distance estimate, and how close the orbit got to the origin
+    fn orbit(&self, point: Vec3) -> (Float, Float) {
+        let c = point - self.position; // added self.position
+        let mut zn = c;
+        let mut rad = zn.length();
         let mut d = 1.0;
+        let mut trap = Float::MAX;
 
-        for i in 0..self.iterations {
+        for _ in 0..self.iterations {
             rad = zn.length();
+            trap = trap.min(rad);
+
+            if rad > 2.0 { break; }
 
-            if rad > 2.0 {
-                hit = 0.5 * rad.ln() * rad / d;
-            } else {
-                let th = length(zn.x, zn.y).atan2(zn.z);
-                let phi = zn.y.atan2(zn.x);
-                let rado = rad.powi(8);
-                d = rad.powi(7) * 7.0 * d + 1.0;
-
-                let sint = (th * self.power).sin();
-                zn.x = rado * sint * (phi * self.power).cos();
-                zn.y = rado * sint * (phi * self.power).sin();
-                zn.z = rado * (th * self.power).cos();
-                zn = zn + (point - self.position); // added self.position
-            }
+            let th = length(zn.x, zn.y).atan2(zn.z);
+            let phi = zn.y.atan2(zn.x);
+            let rado = rad.powf(self.power);
+            d = rad.powf(self.power - 1.0) * self.power * d + 1.0;
+
+            let sint = (th * self.power).sin();
+            zn.x = rado * sint * (phi * self.power).cos();
+            zn.y = rado * sint * (phi * self.power).sin();
+            zn.z = rado * (th * self.power).cos();
+            zn = zn + c;
         }
 
-        return hit;
+        // never escaped, so it's inside
+        if rad <= 2.0 { return (0.0, trap); }
+
+        return (0.5 * rad.ln() * rad / d, trap);
     }
 }
+
+impl March for Mandelbulb {
+    fn material(&self) -> Material { self.material }
+
+    fn march(&self, point: Vec3) -> Float {
+        self.orbit(point).0
+    }
+
+    fn material_at(&self, point: Vec3) -> Material {
+        match self.trap {
+            Some(trap) => trap.apply(self.material, self.orbit(point).1),
+            None => self.material,
+        }
+    }
+
+    fn primitive(&self) -> Option<MarchPrimitive> { Some((*self).into()) }
+}
@@ -0,0 +1,101 @@
+use serde::{ Serialize, Deserialize };
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::aabb::Aabb;
+use crate::structures::material::Material;
+use crate::objects::primitive::MarchPrimitive;
+use crate::objects::traits::March;
+use crate::objects::orbit_trap::OrbitTrap;
+
+// a cube with crosses punched through it, then through every smaller cube
+// left over, `iterations` times. `size` is the half extent.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Menger {
+    pub position: Vec3,
+    pub size: Float,
+    pub iterations: usize,
+    pub material: Material,
+    pub trap: Option<OrbitTrap>,
+}
+
+impl Menger {
+    pub fn new(position: Vec3, size: Float, iterations: usize, material: Material) -> Menger {
+        Menger {
+            position: position,
+            size: size,
+            iterations: iterations,
+            material: material,
+            trap: None,
+        }
+    }
+
+    // distance, and the closest a fold got to the center of its cell
+    fn orbit(&self, point: Vec3) -> (Float, Float) {
+        let p = (point - self.position) / self.size;
+
+        let q = p.abs() - 1.0;
+        let mut distance = q.max_by(&Vec3::new(0.0, 0.0, 0.0)).length() + q.x.max(q.y).max(q.z).min(0.0);
+        let mut scale = 1.0;
+        let mut trap = Float::MAX;
+
+        for _ in 0..self.iterations {
+            let a = Vec3::new(
+                (p.x * scale).rem_euclid(2.0) - 1.0,
+                (p.y * scale).rem_euclid(2.0) - 1.0,
+                (p.z * scale).rem_euclid(2.0) - 1.0,
+            );
+            scale *= 3.0;
+            trap = trap.min(a.length());
+
+            let r = (1.0 - 3.0 * a.abs()).abs();
+            let cross = (r.x.max(r.y).min(r.y.max(r.z)).min(r.z.max(r.x)) - 1.0) / scale;
+
+            distance = distance.max(cross);
+        }
+
+        return (distance * self.size, trap);
+    }
+}
+
+impl March for Menger {
+    fn material(&self) -> Material { self.material }
+
+    fn march(&self, point: Vec3) -> Float {
+        self.orbit(point).0
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let size = Vec3::new(self.size, self.size, self.size);
+        return Some(Aabb::new(self.position - size, self.position + size));
+    }
+
+    fn material_at(&self, point: Vec3) -> Material {
+        match self.trap {
+            Some(trap) => trap.apply(self.material, self.orbit(point).1),
+            None => self.material,
+        }
+    }
+
+    fn primitive(&self) -> Option<MarchPrimitive> { Some((*self).into()) }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::Menger;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::material::Material;
+    use crate::objects::traits::March;
+
+    #[test]
+    fn test_march() {
+        let solid = Menger::new(Vec3::new(0.0, 0.0, 0.0), 1.0, 0, Material::blank());
+        let sponge = Menger::new(Vec3::new(0.0, 0.0, 0.0), 1.0, 3, Material::blank());
+        let center = Vec3::new(0.0, 0.0, 0.0);
+
+        // the first cross hollows out the middle
+        assert_eq!(solid.march(center), -1.0);
+        assert!(sponge.march(center) > 0.0);
+        assert_eq!(sponge.march(Vec3::new(3.0, 0.0, 0.0)), 2.0);
+    }
+}
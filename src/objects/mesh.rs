@@ -0,0 +1,314 @@
+use std::mem::size_of_val;
+
+use crate::error::{ Error, Result };
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::ray::Ray;
+use crate::structures::material::Material;
+use crate::structures::aabb::Aabb;
+use crate::structures::report::Footprint;
+use crate::structures::bvh::Bvh;
+use crate::structures::transform::Transform;
+use crate::objects::traits::Trace;
+
+// hits closer than this are the ray leaving the triangle it started on
+const EPSILON: Float = 0.0000001;
+
+// an indexed triangle mesh, with an optional normal per vertex
+#[derive(Debug, Clone)]
+pub struct Mesh {
+    pub vertices: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub triangles: Vec<[usize; 3]>,
+    pub material: Material,
+    bvh: Bvh,
+}
+
+// watertight, from woop et al. 2013, returns the distance and barycentric
+// coordinates of b and c. everything's moved so the ray starts at the
+// origin and points down z, which makes the edge tests the same sums
+// whichever triangle they're worked out for, so a ray through an edge two
+// triangles share hits at least one of them instead of slipping between.
+#[allow(clippy::unnecessary_cast)] // Float may already be f64
+pub fn intersect_triangle(ray: &Ray, a: Vec3, b: Vec3, c: Vec3) -> Option<(Float, Float, Float)> {
+    let d = ray.direction;
+
+    // z is the direction's longest axis, keeping the winding if it's negative
+    let kz = (0..3).fold(0, |longest, axis| if d.axis(axis).abs() > d.axis(longest).abs() { axis } else { longest });
+    let (mut kx, mut ky) = ((kz + 1) % 3, (kz + 2) % 3);
+    if d.axis(kz) < 0.0 { std::mem::swap(&mut kx, &mut ky); }
+
+    let shear = [d.axis(kx) / d.axis(kz), d.axis(ky) / d.axis(kz), 1.0 / d.axis(kz)];
+    let [a, b, c] = [a - ray.origin, b - ray.origin, c - ray.origin];
+    let sheared = |v: Vec3| [v.axis(kx) - shear[0] * v.axis(kz), v.axis(ky) - shear[1] * v.axis(kz)];
+    let ([ax, ay], [bx, by], [cx, cy]) = (sheared(a), sheared(b), sheared(c));
+
+    // twice the signed areas of the triangles the ray makes with each
+    // edge. exactly zero is on the edge itself, and gets done again in
+    // f64 in case Float is f32.
+    let mut edges = [cx * by - cy * bx, ax * cy - ay * cx, bx * ay - by * ax];
+    if edges.contains(&0.0) {
+        let cross = |px: Float, py: Float, qx: Float, qy: Float| (px as f64 * qy as f64 - py as f64 * qx as f64) as Float;
+        edges = [cross(cx, cy, bx, by), cross(ax, ay, cx, cy), cross(bx, by, ax, ay)];
+    }
+    let [u, v, w] = edges;
+
+    // outside unless they all agree
+    if (u < 0.0 || v < 0.0 || w < 0.0) && (u > 0.0 || v > 0.0 || w > 0.0) { return None; }
+
+    // parallel to the triangle
+    let det = u + v + w;
+    if det == 0.0 { return None; }
+
+    let t = (u * shear[2] * a.axis(kz) + v * shear[2] * b.axis(kz) + w * shear[2] * c.axis(kz)) / det;
+    if t <= EPSILON { return None; }
+
+    return Some((t, v / det, w / det));
+}
+
+// closest point on a triangle to p, from real-time collision detection
+pub fn closest_on_triangle(p: Vec3, a: Vec3, b: Vec3, c: Vec3) -> Vec3 {
+    let ab = b - a;
+    let ac = c - a;
+    let ap = p - a;
+
+    let d1 = ab.dot(&ap);
+    let d2 = ac.dot(&ap);
+    if d1 <= 0.0 && d2 <= 0.0 { return a; }
+
+    let bp = p - b;
+    let d3 = ab.dot(&bp);
+    let d4 = ac.dot(&bp);
+    if d3 >= 0.0 && d4 <= d3 { return b; }
+
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 { return a + ab * (d1 / (d1 - d3)); }
+
+    let cp = p - c;
+    let d5 = ab.dot(&cp);
+    let d6 = ac.dot(&cp);
+    if d6 >= 0.0 && d5 <= d6 { return c; }
+
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 { return a + ac * (d2 / (d2 - d6)); }
+
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+
+    let denom = 1.0 / (va + vb + vc);
+    return a + ab * (vb * denom) + ac * (vc * denom);
+}
+
+impl Mesh {
+    pub fn new(vertices: Vec<Vec3>, triangles: Vec<[usize; 3]>, material: Material) -> Mesh {
+        Mesh::smooth(vertices, vec![], triangles, material)
+    }
+
+    // `normals` is either empty (flat shading) or one per vertex
+    pub fn smooth(vertices: Vec<Vec3>, normals: Vec<Vec3>, triangles: Vec<[usize; 3]>, material: Material) -> Mesh {
+        let bounds: Vec<Aabb> = triangles.iter()
+            .map(|t| Aabb::around(&[vertices[t[0]], vertices[t[1]], vertices[t[2]]]))
+            .collect();
+
+        Mesh {
+            bvh: Bvh::new(&bounds),
+            vertices: vertices,
+            normals: normals,
+            triangles: triangles,
+            material: material,
+        }
+    }
+
+    // the same, for triangles and normals out of a file, which might not
+    // line up with the vertices they go with
+    pub fn try_smooth(vertices: Vec<Vec3>, normals: Vec<Vec3>, triangles: Vec<[usize; 3]>, material: Material) -> Result<Mesh> {
+        if let Some(index) = triangles.iter().flatten().find(|index| **index >= vertices.len()) {
+            return Err(Error::Invalid(format!("triangle corner {} is past the {} vertices", index, vertices.len())));
+        }
+        if !normals.is_empty() && normals.len() != vertices.len() {
+            return Err(Error::Invalid(format!("{} normals for {} vertices", normals.len(), vertices.len())));
+        }
+
+        return Ok(Mesh::smooth(vertices, normals, triangles, material));
+    }
+
+    // bakes a transform into the vertices
+    pub fn transform(self, transform: &Transform) -> Mesh {
+        Mesh::smooth(
+            self.vertices.iter().map(|v| transform.point(*v)).collect(),
+            self.normals.iter().map(|n| transform.normal(*n)).collect(),
+            self.triangles,
+            self.material,
+        )
+    }
+
+    // glues another mesh onto this one, keeping this material
+    pub fn merge(self, other: Mesh) -> Mesh {
+        let offset = self.vertices.len();
+        let smooth = !self.normals.is_empty() && !other.normals.is_empty();
+
+        let mut vertices = self.vertices;
+        let mut normals = if smooth { self.normals } else { vec![] };
+        let mut triangles = self.triangles;
+
+        vertices.extend(other.vertices);
+        if smooth { normals.extend(other.normals); }
+        triangles.extend(other.triangles.iter().map(|t| [t[0] + offset, t[1] + offset, t[2] + offset]));
+
+        Mesh::smooth(vertices, normals, triangles, self.material)
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        self.bvh.bounds()
+    }
+
+    pub fn bvh(&self) -> &Bvh {
+        &self.bvh
+    }
+
+    fn corners(&self, index: usize) -> (Vec3, Vec3, Vec3) {
+        let [a, b, c] = self.triangles[index];
+        (self.vertices[a], self.vertices[b], self.vertices[c])
+    }
+
+    // whether a point is enclosed, by counting crossings along a few rays.
+    // a majority vote keeps small holes and grazing hits from flipping it.
+    pub fn inside(&self, point: Vec3) -> bool {
+        let directions = [
+            Vec3::new(1.0, 0.000_13, 0.000_37),
+            Vec3::new(0.000_29, 1.0, 0.000_11),
+            Vec3::new(0.000_17, 0.000_31, 1.0),
+        ];
+
+        let votes = directions.iter().filter(|direction| {
+            let ray = Ray::new(point, direction.unit());
+            let mut crossings = 0;
+
+            self.bvh.each(&ray, |index| {
+                let (a, b, c) = self.corners(index);
+                if intersect_triangle(&ray, a, b, c).is_some() { crossings += 1; }
+            });
+
+            crossings % 2 == 1
+        }).count();
+
+        return votes >= 2;
+    }
+
+    // signed distance to the surface, negative inside. only meaningful for
+    // closed meshes, open ones are treated as if they had no inside.
+    pub fn distance(&self, point: Vec3) -> Float {
+        let nearest = self.bvh.nearest(&point, |index| {
+            let (a, b, c) = self.corners(index);
+            (closest_on_triangle(point, a, b, c) - point).length()
+        });
+
+        let distance = nearest.map(|(_, d)| d).unwrap_or(Float::MAX);
+        return if self.inside(point) { -distance } else { distance };
+    }
+
+    fn normal(&self, triangle: [usize; 3], u: Float, v: Float) -> Vec3 {
+        let [a, b, c] = triangle;
+
+        if self.normals.is_empty() {
+            return (self.vertices[b] - self.vertices[a])
+                .cross(&(self.vertices[c] - self.vertices[a]))
+                .unit();
+        }
+
+        return (
+            self.normals[a] * (1.0 - u - v)
+          + self.normals[b] * u
+          + self.normals[c] * v
+        ).unit();
+    }
+}
+
+impl Trace for Mesh {
+    fn material(&self) -> Material { self.material }
+    fn bounds(&self) -> Option<Aabb> { Some(self.bvh.bounds()) }
+
+    fn footprint(&self) -> Footprint {
+        let bytes = size_of_val(&self.vertices[..]) + size_of_val(&self.normals[..]) + size_of_val(&self.triangles[..]);
+        return Footprint::new("mesh").with_bytes(bytes).with_bvh(&self.bvh);
+    }
+
+    fn trace(&self, ray: Ray) -> (bool, Float, Vec3) {
+        let hit = self.bvh.traverse(&ray, |index| {
+            let (a, b, c) = self.corners(index);
+            intersect_triangle(&ray, a, b, c).map(|(t, _, _)| t)
+        });
+
+        if let Some((index, _)) = hit {
+            let (a, b, c) = self.corners(index);
+            if let Some((t, u, v)) = intersect_triangle(&ray, a, b, c) {
+                return (true, t, self.normal(self.triangles[index], u, v));
+            }
+        }
+
+        return (false, Float::MAX, Vec3::new(0.0, 1.0, 0.0));
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::Mesh;
+    use crate::structures::float::Float;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::ray::Ray;
+    use crate::structures::material::Material;
+    use crate::objects::traits::Trace;
+
+    fn quad() -> Mesh {
+        Mesh::new(
+            vec![
+                Vec3::new(-1.0, 0.0, -1.0),
+                Vec3::new( 1.0, 0.0, -1.0),
+                Vec3::new( 1.0, 0.0,  1.0),
+                Vec3::new(-1.0, 0.0,  1.0),
+            ],
+            vec![[0, 2, 1], [0, 3, 2]],
+            Material::blank(),
+        )
+    }
+
+    #[test]
+    fn test_hit() {
+        let ray = Ray::new(Vec3::new(0.2, 2.0, 0.3), Vec3::new(0.0, -1.0, 0.0));
+        let (hit, distance, normal) = quad().trace(ray);
+
+        assert!(hit);
+        assert_eq!(distance, 2.0);
+        assert_eq!(normal, Vec3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_watertight() {
+        // straight down the diagonal the two triangles share
+        for i in 1..100 {
+            let along = i as Float / 50.0 - 1.0;
+            let ray = Ray::new(Vec3::new(along, 2.0, along), Vec3::new(0.0001, -1.0, 0.0001).unit());
+            assert!(quad().trace(ray).0, "slipped through at {}", along);
+        }
+    }
+
+    #[test]
+    fn test_try_smooth() {
+        let corners = vec![Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0)];
+        assert!(Mesh::try_smooth(corners.clone(), vec![], vec![[0, 2, 1]], Material::blank()).is_ok());
+
+        let past = Mesh::try_smooth(corners.clone(), vec![], vec![[0, 2, 3]], Material::blank());
+        assert!(past.err().unwrap().to_string().contains("corner 3"));
+        assert!(Mesh::try_smooth(corners, vec![Vec3::new(0.0, 1.0, 0.0)], vec![[0, 2, 1]], Material::blank()).is_err());
+    }
+
+    #[test]
+    fn test_miss() {
+        let ray = Ray::new(Vec3::new(2.0, 2.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let (hit, _, _) = quad().trace(ray);
+
+        assert!(!hit);
+    }
+}
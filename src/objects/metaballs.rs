@@ -0,0 +1,97 @@
+use std::mem::size_of_val;
+use serde::{ Serialize, Deserialize };
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::material::Material;
+use crate::structures::report::Footprint;
+use crate::objects::traits::March;
+
+// the steepest the falloff (1 - s^2)^3 gets, at s = 1 / sqrt(5)
+const STEEPEST: Float = 1.717_262_3;
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Ball {
+    pub position: Vec3,
+    pub radius: Float, // how far its influence reaches
+    pub weight: Float,
+}
+
+impl Ball {
+    pub fn new(position: Vec3, radius: Float, weight: Float) -> Ball {
+        Ball { position: position, radius: radius, weight: weight }
+    }
+}
+
+// blobs that melt into each other. each ball adds a smooth bump of potential
+// and the surface is where the total crosses `threshold`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Metaballs {
+    pub balls: Vec<Ball>,
+    pub threshold: Float,
+    pub material: Material,
+}
+
+impl Metaballs {
+    pub fn new(balls: Vec<Ball>, threshold: Float, material: Material) -> Metaballs {
+        Metaballs { balls: balls, threshold: threshold, material: material }
+    }
+
+    pub fn potential(&self, point: Vec3) -> Float {
+        self.balls.iter().map(|ball| {
+            let s2 = (point - ball.position).length_squared() / (ball.radius * ball.radius);
+            if s2 >= 1.0 { 0.0 } else { ball.weight * (1.0 - s2).powi(3) }
+        }).sum()
+    }
+
+    // upper bound on how fast the potential can change
+    fn lipschitz(&self) -> Float {
+        self.balls.iter().map(|ball| ball.weight.abs() * STEEPEST / ball.radius).sum()
+    }
+}
+
+impl March for Metaballs {
+    fn material(&self) -> Material { self.material }
+
+    // the potential isn't a distance, but it can't change faster than its
+    // lipschitz bound, so dividing by that gives a step that never overshoots.
+    // out past every ball's reach the surface is at least that far away too.
+    fn march(&self, point: Vec3) -> Float {
+        let bounded = (self.threshold - self.potential(point)) / self.lipschitz();
+
+        let reach = self.balls.iter()
+            .map(|ball| (point - ball.position).length() - ball.radius)
+            .fold(Float::MAX, Float::min);
+
+        return bounded.max(reach);
+    }
+
+    // every ball, twice over, for the potential and how far off they are
+    fn footprint(&self) -> Footprint {
+        Footprint::new("metaballs").with_bytes(size_of_val(&self.balls[..])).with_cost(2.0 * self.balls.len() as Float)
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::{ Ball, Metaballs };
+    use crate::structures::vec3::Vec3;
+    use crate::structures::material::Material;
+    use crate::objects::traits::March;
+
+    #[test]
+    fn test_march() {
+        let blobs = Metaballs::new(
+            vec![
+                Ball::new(Vec3::new(-0.5, 0.0, 0.0), 1.0, 1.0),
+                Ball::new(Vec3::new( 0.5, 0.0, 0.0), 1.0, 1.0),
+            ],
+            0.5,
+            Material::blank(),
+        );
+
+        // the middle is inside, where both overlap
+        assert!(blobs.march(Vec3::new(0.0, 0.0, 0.0)) < 0.0);
+        assert_eq!(blobs.march(Vec3::new(4.5, 0.0, 0.0)), 3.0);
+    }
+}
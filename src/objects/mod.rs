@@ -1,4 +1,38 @@
 pub mod sphere;
 pub mod plane;
+pub mod disk;
+pub mod quad;
+pub mod triangle;
+pub mod cuboid;
+pub mod torus;
+pub mod cylinder;
+pub mod capsule;
+pub mod cone;
+pub mod hex_prism;
+pub mod mesh;
+pub mod curves;
+pub mod point_cloud;
+pub mod text;
+pub mod sdf_grid;
+pub mod baked;
+pub mod transformed;
+pub mod instance;
+pub mod visible;
+pub mod shadow_catcher;
+pub mod light_group;
+pub mod clipped;
+pub mod shared;
+pub mod primitive;
+pub mod csg;
+pub mod domain;
+pub mod modifiers;
+pub mod sdf_fn;
+pub mod metaballs;
+pub mod volume;
+pub mod voxels;
+pub mod voxel_octree;
 pub mod mandelbulb;
+pub mod menger;
+pub mod julia;
+pub mod orbit_trap;
 pub mod traits;
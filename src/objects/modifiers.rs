@@ -0,0 +1,152 @@
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::aabb::Aabb;
+use crate::structures::material::Material;
+use crate::structures::noise::Fractal;
+use crate::structures::report::Footprint;
+use crate::objects::traits::March;
+
+// modifiers that reshape a distance field after the fact
+
+// grows the surface outwards by `radius`, filleting every edge
+pub struct Rounded<T> {
+    pub object: T,
+    pub radius: Float,
+}
+
+// hollows the object out, leaving a wall `thickness` thick either side of the surface
+pub struct Shell<T> {
+    pub object: T,
+    pub thickness: Float,
+}
+
+// shells the shell, each layer doubles the number of walls
+pub struct Onion<T> {
+    pub object: T,
+    pub thickness: Float,
+    pub layers: usize,
+}
+
+// bumps the surface in and out by up to `amplitude`, following the noise,
+// for rock and bark and the like. noise isn't a distance, so the marcher
+// has to go slower near it by how steep the bumps can get.
+pub struct Displaced<T> {
+    pub object: T,
+    pub amplitude: Float,
+    pub noise: Fractal,
+}
+
+impl<T> Rounded<T> {
+    pub fn new(object: T, radius: Float) -> Rounded<T> {
+        Rounded { object: object, radius: radius }
+    }
+}
+
+impl<T> Shell<T> {
+    pub fn new(object: T, thickness: Float) -> Shell<T> {
+        Shell { object: object, thickness: thickness }
+    }
+}
+
+impl<T> Onion<T> {
+    pub fn new(object: T, thickness: Float, layers: usize) -> Onion<T> {
+        Onion { object: object, thickness: thickness, layers: layers }
+    }
+}
+
+impl<T> Displaced<T> {
+    pub fn new(object: T, amplitude: Float, noise: Fractal) -> Displaced<T> {
+        Displaced { object: object, amplitude: amplitude, noise: noise }
+    }
+}
+
+impl<T: March> March for Rounded<T> {
+    fn material(&self) -> Material { self.object.material() }
+
+    fn march(&self, point: Vec3) -> Float {
+        self.object.march(point) - self.radius
+    }
+
+    fn bounds(&self) -> Option<Aabb> { Some(self.object.bounds()?.padded(self.radius)) }
+    fn footprint(&self) -> Footprint { self.object.footprint() }
+
+    fn material_at(&self, point: Vec3) -> Material { self.object.material_at(point) }
+}
+
+impl<T: March> March for Shell<T> {
+    fn material(&self) -> Material { self.object.material() }
+
+    fn march(&self, point: Vec3) -> Float {
+        self.object.march(point).abs() - self.thickness
+    }
+
+    fn bounds(&self) -> Option<Aabb> { Some(self.object.bounds()?.padded(self.thickness)) }
+    fn footprint(&self) -> Footprint { self.object.footprint() }
+
+    fn material_at(&self, point: Vec3) -> Material { self.object.material_at(point) }
+}
+
+impl<T: March> March for Onion<T> {
+    fn material(&self) -> Material { self.object.material() }
+
+    fn march(&self, point: Vec3) -> Float {
+        let mut distance = self.object.march(point);
+
+        for _ in 0..self.layers {
+            distance = distance.abs() - self.thickness;
+        }
+
+        return distance;
+    }
+
+    // each layer goes out by the thickness again
+    fn bounds(&self) -> Option<Aabb> { Some(self.object.bounds()?.padded(self.thickness * self.layers as Float)) }
+    fn footprint(&self) -> Footprint { self.object.footprint() }
+
+    fn material_at(&self, point: Vec3) -> Material { self.object.material_at(point) }
+}
+
+impl<T: March> March for Displaced<T> {
+    fn material(&self) -> Material { self.object.material() }
+
+    fn march(&self, point: Vec3) -> Float {
+        let distance = self.object.march(point) - self.amplitude * self.noise.sample(point);
+        return distance / (1.0 + self.amplitude.abs() * self.noise.steepness());
+    }
+
+    fn bounds(&self) -> Option<Aabb> { Some(self.object.bounds()?.padded(self.amplitude.abs())) }
+
+    // and a few hashes for each octave of the noise
+    fn footprint(&self) -> Footprint {
+        let footprint = self.object.footprint();
+        return footprint.with_cost(footprint.cost + 4.0 * self.noise.octaves as Float);
+    }
+
+    fn material_at(&self, point: Vec3) -> Material { self.object.material_at(point) }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::{ Rounded, Shell, Onion, Displaced };
+    use crate::structures::vec3::Vec3;
+    use crate::structures::material::Material;
+    use crate::structures::noise::Fractal;
+    use crate::objects::sphere::Sphere;
+    use crate::objects::traits::March;
+
+    #[test]
+    fn test_modifiers() {
+        let sphere = Sphere::new(Vec3::new(0.0, 0.0, 0.0), 2.0, Material::blank());
+        let center = Vec3::new(0.0, 0.0, 0.0);
+
+        assert_eq!(Rounded::new(sphere, 0.5).march(center), -2.5);
+        assert_eq!(Shell::new(sphere, 0.5).march(center), 1.5);
+        assert_eq!(Onion::new(sphere, 0.5, 2).march(center), 1.0);
+
+        // bumped, the surface is somewhere within the amplitude of where it
+        // was, and the distance never overshoots it
+        let rock = Displaced::new(sphere, 0.2, Fractal { frequency: 3.0, ..Fractal::default() });
+        assert!(rock.march(Vec3::new(0.0, 0.0, 1.7)) < 0.0 && rock.march(Vec3::new(0.0, 0.0, 2.3)) > 0.0);
+        assert!(rock.march(Vec3::new(0.0, 0.0, 2.3)) <= Rounded::new(sphere, 0.2).march(Vec3::new(0.0, 0.0, 2.3)) + 1e-6);
+    }
+}
@@ -0,0 +1,28 @@
+use serde::{ Serialize, Deserialize };
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::material::Material;
+
+// colors a fractal by how close the orbit of a point came to the origin.
+// points whose orbit got close take on `color`, the rest keep the material's.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct OrbitTrap {
+    pub color: Vec3,
+    pub falloff: Float, // higher is a tighter band of color
+}
+
+impl OrbitTrap {
+    pub fn new(color: Vec3, falloff: Float) -> OrbitTrap {
+        OrbitTrap { color: color, falloff: falloff }
+    }
+
+    // `trap` is the closest the orbit came
+    pub fn apply(&self, material: Material, trap: Float) -> Material {
+        let t = (-trap * self.falloff).exp();
+
+        let mut trapped = material;
+        trapped.color = material.color + (self.color - material.color) * t;
+        return trapped;
+    }
+}
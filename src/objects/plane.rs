@@ -1,9 +1,16 @@
+use serde::{ Serialize, Deserialize };
+
+use crate::structures::float::Float;
 use crate::structures::vec3::Vec3;
 use crate::structures::ray::Ray;
 use crate::structures::material::Material;
+use crate::structures::frame::Frame;
+use crate::structures::packet::{ WideVec3, RayPacket, Lanes, LANES };
+use crate::objects::primitive::{ MarchPrimitive, TracePrimitive };
 use crate::objects::traits::{ March, Trace };
+use crate::gpu;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct Plane {
     pub position: Vec3,
     pub normal: Vec3,
@@ -13,34 +20,75 @@ pub struct Plane {
 impl Plane {
     pub fn new(position: Vec3, normal: Vec3, material: Material) -> Plane {
         Plane {
-	    position: position,
-            normal: normal,
+            position: position,
+            normal: normal.unit(),
             material: material,
         }
     }
+
+    // how far along the tangent and bitangent from the position
+    fn surface_uv(&self, point: Vec3) -> [Float; 2] {
+        let local = Frame::new(self.normal).to_local(point - self.position);
+        [local.x, local.y]
+    }
 }
 
 impl Trace for Plane {
     fn material(&self) -> Material { self.material }
 
-    fn trace(&self, ray: Ray) -> (bool, f64, Vec3) {
-        let denom = self.normal.dot(ray.direction);
-        if (denom.abs > 0.0) {
-            let t = (self.position - ray.origin).dot(self.normal) / denom;
-	    
-            if (t >= 0.0) {
+    fn trace(&self, ray: Ray) -> (bool, Float, Vec3) {
+        let denom = self.normal.dot(&ray.direction);
+        if denom.abs() > 0.0 {
+            let t = (self.position - ray.origin).dot(&self.normal) / denom;
+
+            if t >= 0.0 {
                  return (true, t, self.normal);
             }
         }
 
-        return (false, f64::MAX, self.normal);
+        return (false, Float::MAX, self.normal);
+    }
+
+    fn trace_packet(&self, packet: &RayPacket) -> [(bool, Float, Vec3); LANES] {
+        let normal = WideVec3::splat(self.normal);
+        let denom = normal.dot(&packet.direction);
+        let along = (WideVec3::splat(self.position) - packet.origin).dot(&normal);
+        let (denom, t) = (denom.to_array(), (along / denom).to_array());
+
+        std::array::from_fn(|i| {
+            if denom[i].abs() > 0.0 && t[i] >= 0.0 { (true, t[i], self.normal) } else { (false, Float::MAX, self.normal) }
+        })
     }
+
+    fn wgsl(&self) -> Option<String> {
+        Some(format!("trace_plane(o, d, {}, {})", gpu::vec3(self.position)?, gpu::vec3(self.normal)?))
+    }
+
+    fn uv(&self, point: Vec3, _normal: Vec3) -> [Float; 2] { self.surface_uv(point) }
+
+    fn primitive(&self) -> Option<TracePrimitive> { Some((*self).into()) }
 }
 
-impl March for Sphere {
+impl March for Plane {
     fn material(&self) -> Material { self.material }
 
-    fn march(&self, point: Vec3) -> f64 {
-        (point - self.position).length() - self.radius // TODO modulo with 6 for infinite rep.
+    fn march(&self, point: Vec3) -> Float {
+        (point - self.position).dot(&self.normal)
+    }
+
+    fn normal(&self, _point: Vec3) -> Vec3 {
+        self.normal
+    }
+
+    fn march_packet(&self, points: &WideVec3) -> Lanes {
+        (*points - WideVec3::splat(self.position)).dot(&WideVec3::splat(self.normal))
     }
+
+    fn wgsl(&self) -> Option<String> {
+        Some(format!("dot(p - {}, {})", gpu::vec3(self.position)?, gpu::vec3(self.normal)?))
+    }
+
+    fn uv(&self, point: Vec3) -> [Float; 2] { self.surface_uv(point) }
+
+    fn primitive(&self) -> Option<MarchPrimitive> { Some((*self).into()) }
 }
@@ -0,0 +1,120 @@
+use std::mem::size_of_val;
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::ray::Ray;
+use crate::structures::material::Material;
+use crate::structures::aabb::Aabb;
+use crate::structures::report::Footprint;
+use crate::structures::bvh::Bvh;
+use crate::objects::traits::Trace;
+
+// one sample of a scan, drawn as a little ball of its own color
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Point {
+    pub position: Vec3,
+    pub radius: Float,
+    pub color: Vec3,
+}
+
+impl Point {
+    pub fn new(position: Vec3, radius: Float, color: Vec3) -> Point {
+        Point { position: position, radius: radius, color: color }
+    }
+
+    fn hit(&self, ray: &Ray) -> Option<Float> {
+        let oc = ray.origin - self.position;
+        let a = ray.direction.dot(&ray.direction);
+        let b = oc.dot(&ray.direction);
+        let c = oc.dot(&oc) - self.radius * self.radius;
+        let disc = b * b - a * c;
+        if disc <= 0.0 { return None; }
+
+        // the near side, or the far one from inside
+        let root = disc.sqrt();
+        let near = (-b - root) / a;
+        let t = if near > 0.0 { near } else { (-b + root) / a };
+        return if t > 0.0 { Some(t) } else { None };
+    }
+}
+
+// lots of points straight out of a scanner, see import::ply::load_points and
+// import::xyz. everything but the color comes from `material`.
+#[derive(Debug, Clone)]
+pub struct PointCloud {
+    pub points: Vec<Point>,
+    pub material: Material,
+    bvh: Bvh,
+}
+
+impl PointCloud {
+    pub fn new(points: Vec<Point>, material: Material) -> PointCloud {
+        let bounds: Vec<Aabb> = points.iter().map(|point| {
+            let extent = Vec3::new(1.0, 1.0, 1.0) * point.radius;
+            Aabb::new(point.position - extent, point.position + extent)
+        }).collect();
+
+        PointCloud { bvh: Bvh::new(&bounds), points: points, material: material }
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        self.bvh.bounds()
+    }
+
+    fn nearest(&self, point: Vec3) -> Option<&Point> {
+        self.bvh.nearest(&point, |index| (self.points[index].position - point).length() - self.points[index].radius)
+            .map(|(index, _)| &self.points[index])
+    }
+}
+
+impl Trace for PointCloud {
+    fn material(&self) -> Material { self.material }
+    fn bounds(&self) -> Option<Aabb> { Some(self.bvh.bounds()) }
+
+    fn footprint(&self) -> Footprint {
+        Footprint::new("point cloud").with_bytes(size_of_val(&self.points[..])).with_bvh(&self.bvh)
+    }
+
+    fn trace(&self, ray: Ray) -> (bool, Float, Vec3) {
+        match self.bvh.traverse(&ray, |index| self.points[index].hit(&ray)) {
+            Some((index, t)) => (true, t, (ray.point_at(&t) - self.points[index].position).unit()),
+            None => (false, Float::MAX, Vec3::new(0.0, 1.0, 0.0)),
+        }
+    }
+
+    fn material_at(&self, point: Vec3, _normal: Vec3) -> Material {
+        match self.nearest(point) {
+            Some(nearest) => Material { color: nearest.color, ..self.material },
+            None => self.material,
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::{ PointCloud, Point };
+    use crate::structures::float::Float;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::ray::Ray;
+    use crate::structures::material::Material;
+    use crate::objects::traits::Trace;
+
+    #[test]
+    fn test_point_cloud() {
+        let (red, blue) = (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        let cloud = PointCloud::new((0..10).map(|i| {
+            Point::new(Vec3::new(i as Float, 0.0, 0.0), 0.25, if i < 5 { red } else { blue })
+        }).collect(), Material::blank());
+
+        // the nearest one along the ray, in its own color
+        let ray = Ray::new(Vec3::new(7.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let (hit, distance, normal) = cloud.trace(ray);
+        assert!(hit && (distance - 4.75).abs() < 0.0001);
+        assert_eq!(normal, Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(cloud.material_at(ray.point_at(&distance), normal).color, blue);
+        assert_eq!(cloud.material_at(Vec3::new(1.0, 0.25, 0.0), normal).color, red);
+
+        // and straight between two of them, nothing
+        assert!(!cloud.trace(Ray::new(Vec3::new(7.5, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0))).0);
+    }
+}
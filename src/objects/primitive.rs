@@ -0,0 +1,329 @@
+use serde::{ Serialize, Deserialize };
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::ray::Ray;
+use crate::structures::aabb::Aabb;
+use crate::structures::material::Material;
+use crate::structures::frame::Frame;
+use crate::structures::report::Footprint;
+use crate::objects::sphere::Sphere;
+use crate::objects::plane::Plane;
+use crate::objects::disk::Disk;
+use crate::objects::quad::Quad;
+use crate::objects::triangle::Triangle;
+use crate::objects::cuboid::Cuboid;
+use crate::objects::torus::Torus;
+use crate::objects::cylinder::Cylinder;
+use crate::objects::capsule::Capsule;
+use crate::objects::cone::Cone;
+use crate::objects::hex_prism::HexPrism;
+use crate::objects::mandelbulb::Mandelbulb;
+use crate::objects::julia::Julia;
+use crate::objects::menger::Menger;
+use crate::objects::traits::{ March, Trace };
+
+// hits closer than this are the ray leaving the surface it started on,
+// same as the renderer's
+const EPSILON: Float = 0.002;
+
+// closed sets of the built in shapes, stored by value and dispatched with a
+// match instead of through a pointer. put lots of them in a `Primitives`
+// list and add that to the scene as a single object: the loop over them
+// then has no virtual calls or pointer chasing in it at all.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum MarchPrimitive {
+    Sphere(Sphere),
+    Plane(Plane),
+    Cuboid(Cuboid),
+    Torus(Torus),
+    Cylinder(Cylinder),
+    Capsule(Capsule),
+    Cone(Cone),
+    HexPrism(HexPrism),
+    Mandelbulb(Mandelbulb),
+    Julia(Julia),
+    Menger(Menger),
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum TracePrimitive {
+    Sphere(Sphere),
+    Plane(Plane),
+    Cuboid(Cuboid),
+    Disk(Disk),
+    Quad(Quad),
+    Triangle(Triangle),
+}
+
+// forwards a call to whichever shape is inside
+macro_rules! dispatch {
+    ($enum:ident, $self:ident, $shape:ident => $call:expr, $($variant:ident),*) => {
+        match $self { $($enum::$variant($shape) => $call,)* }
+    };
+}
+
+macro_rules! march {
+    ($self:ident, $shape:ident => $call:expr) => {
+        dispatch!(MarchPrimitive, $self, $shape => $call, Sphere, Plane, Cuboid, Torus, Cylinder, Capsule, Cone, HexPrism, Mandelbulb, Julia, Menger)
+    };
+}
+
+macro_rules! trace {
+    ($self:ident, $shape:ident => $call:expr) => {
+        dispatch!(TracePrimitive, $self, $shape => $call, Sphere, Plane, Cuboid, Disk, Quad, Triangle)
+    };
+}
+
+impl March for MarchPrimitive {
+    fn material(&self) -> Material { march!(self, shape => March::material(shape)) }
+    fn march(&self, point: Vec3) -> Float { march!(self, shape => shape.march(point)) }
+    fn material_at(&self, point: Vec3) -> Material { march!(self, shape => March::material_at(shape, point)) }
+    fn normal(&self, point: Vec3) -> Vec3 { march!(self, shape => shape.normal(point)) }
+    fn tangent(&self, point: Vec3, normal: Vec3) -> Vec3 { march!(self, shape => March::tangent(shape, point, normal)) }
+    fn uv(&self, point: Vec3) -> [Float; 2] { march!(self, shape => March::uv(shape, point)) }
+    fn wgsl(&self) -> Option<String> { march!(self, shape => March::wgsl(shape)) }
+    fn primitive(&self) -> Option<MarchPrimitive> { Some(*self) }
+    fn bounds(&self) -> Option<Aabb> { march!(self, shape => March::bounds(shape)) }
+
+    // fractals go round their formula once an iteration
+    fn footprint(&self) -> Footprint {
+        match self {
+            MarchPrimitive::Sphere(_) => Footprint::new("sphere"),
+            MarchPrimitive::Plane(_) => Footprint::new("plane"),
+            MarchPrimitive::Cuboid(_) => Footprint::new("cuboid"),
+            MarchPrimitive::Torus(_) => Footprint::new("torus"),
+            MarchPrimitive::Cylinder(_) => Footprint::new("cylinder"),
+            MarchPrimitive::Capsule(_) => Footprint::new("capsule"),
+            MarchPrimitive::Cone(_) => Footprint::new("cone"),
+            MarchPrimitive::HexPrism(_) => Footprint::new("hex prism"),
+            MarchPrimitive::Mandelbulb(s) => Footprint::new("mandelbulb").with_cost(s.iterations as Float),
+            MarchPrimitive::Julia(s) => Footprint::new("julia").with_cost(s.iterations as Float),
+            MarchPrimitive::Menger(s) => Footprint::new("menger").with_cost(s.iterations as Float),
+        }
+    }
+}
+
+impl Trace for TracePrimitive {
+    fn material(&self) -> Material { trace!(self, shape => Trace::material(shape)) }
+    fn trace(&self, ray: Ray) -> (bool, Float, Vec3) { trace!(self, shape => shape.trace(ray)) }
+    fn bounds(&self) -> Option<Aabb> { trace!(self, shape => Trace::bounds(shape)) }
+    fn material_at(&self, point: Vec3, normal: Vec3) -> Material {
+        trace!(self, shape => Trace::material_at(shape, point, normal))
+    }
+    fn tangent(&self, point: Vec3, normal: Vec3) -> Vec3 { trace!(self, shape => Trace::tangent(shape, point, normal)) }
+    fn uv(&self, point: Vec3, normal: Vec3) -> [Float; 2] { trace!(self, shape => Trace::uv(shape, point, normal)) }
+    fn wgsl(&self) -> Option<String> { trace!(self, shape => Trace::wgsl(shape)) }
+    fn primitive(&self) -> Option<TracePrimitive> { Some(*self) }
+
+    fn footprint(&self) -> Footprint {
+        Footprint::new(match self {
+            TracePrimitive::Sphere(_) => "sphere",
+            TracePrimitive::Plane(_) => "plane",
+            TracePrimitive::Cuboid(_) => "cuboid",
+            TracePrimitive::Disk(_) => "disk",
+            TracePrimitive::Quad(_) => "quad",
+            TracePrimitive::Triangle(_) => "triangle",
+        })
+    }
+}
+
+macro_rules! wrap {
+    ($enum:ident, $($variant:ident),*) => {
+        $(impl From<$variant> for $enum {
+            fn from(shape: $variant) -> $enum { $enum::$variant(shape) }
+        })*
+    };
+}
+
+wrap!(MarchPrimitive, Sphere, Plane, Cuboid, Torus, Cylinder, Capsule, Cone, HexPrism, Mandelbulb, Julia, Menger);
+wrap!(TracePrimitive, Sphere, Plane, Cuboid, Disk, Quad, Triangle);
+
+// `material` is taken by the traits, so restyling a shape in a chain of
+// calls goes through this instead
+macro_rules! with_material {
+    ($($shape:ident),*) => {
+        $(impl $shape {
+            pub fn with_material(mut self, material: Material) -> $shape {
+                self.material = material;
+                return self;
+            }
+        })*
+    };
+}
+
+with_material!(Sphere, Plane, Cuboid, Torus, Cylinder, Capsule, Cone, HexPrism, Mandelbulb, Julia, Menger, Disk, Quad, Triangle);
+
+impl MarchPrimitive {
+    pub fn with_material(self, material: Material) -> MarchPrimitive {
+        march!(self, shape => shape.with_material(material).into())
+    }
+}
+
+impl TracePrimitive {
+    pub fn with_material(self, material: Material) -> TracePrimitive {
+        trace!(self, shape => shape.with_material(material).into())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Primitives<T> {
+    pub items: Vec<T>,
+}
+
+impl<T> Primitives<T> {
+    pub fn new() -> Primitives<T> {
+        Primitives { items: vec![] }
+    }
+
+    pub fn add(&mut self, shape: impl Into<T>) {
+        self.items.push(shape.into());
+    }
+}
+
+impl<T> Default for Primitives<T> {
+    fn default() -> Primitives<T> { Primitives::new() }
+}
+
+impl Primitives<MarchPrimitive> {
+    fn closest(&self, point: Vec3) -> Option<&MarchPrimitive> {
+        self.items.iter().min_by(|a, b| {
+            a.march(point).partial_cmp(&b.march(point)).unwrap_or(std::cmp::Ordering::Equal)
+        })
+    }
+}
+
+impl Primitives<TracePrimitive> {
+    fn closest(&self, ray: Ray) -> Option<(&TracePrimitive, Float, Vec3)> {
+        let mut best = None;
+        let mut closest = Float::MAX;
+
+        for item in &self.items {
+            let (hit, distance, normal) = item.trace(ray);
+            if hit && distance > EPSILON && ray.contains(distance) && distance < closest {
+                closest = distance;
+                best = Some((item, distance, normal));
+            }
+        }
+
+        return best;
+    }
+}
+
+impl March for Primitives<MarchPrimitive> {
+    fn material(&self) -> Material {
+        self.items.first().map(|item| item.material()).unwrap_or_else(Material::blank)
+    }
+
+    fn march(&self, point: Vec3) -> Float {
+        self.items.iter().fold(Float::MAX, |min, item| min.min(item.march(point)))
+    }
+
+    // endless if any of them is
+    fn bounds(&self) -> Option<Aabb> {
+        self.items.iter().try_fold(Aabb::empty(), |bounds, item| item.bounds().map(|other| bounds.union(&other)))
+    }
+
+    fn material_at(&self, point: Vec3) -> Material {
+        self.closest(point).map(|item| item.material_at(point)).unwrap_or_else(|| self.material())
+    }
+
+    fn normal(&self, point: Vec3) -> Vec3 {
+        self.closest(point).map(|item| item.normal(point)).unwrap_or_else(|| Vec3::new(0.0, 1.0, 0.0))
+    }
+
+    fn tangent(&self, point: Vec3, normal: Vec3) -> Vec3 {
+        self.closest(point).map_or_else(|| Frame::new(normal).tangent, |item| item.tangent(point, normal))
+    }
+
+    fn uv(&self, point: Vec3) -> [Float; 2] {
+        self.closest(point).map_or([0.0, 0.0], |item| item.uv(point))
+    }
+
+    // every one of them is looked at
+    fn footprint(&self) -> Footprint {
+        let cost = self.items.iter().map(|item| item.footprint().cost).sum();
+        Footprint::new("primitives").with_bytes(std::mem::size_of_val(&self.items[..])).with_cost(cost)
+    }
+}
+
+impl Trace for Primitives<TracePrimitive> {
+    fn material(&self) -> Material {
+        self.items.first().map(|item| item.material()).unwrap_or_else(Material::blank)
+    }
+
+    // endless if any of them is
+    fn bounds(&self) -> Option<Aabb> {
+        self.items.iter().try_fold(Aabb::empty(), |bounds, item| item.bounds().map(|other| bounds.union(&other)))
+    }
+
+    fn trace(&self, ray: Ray) -> (bool, Float, Vec3) {
+        match self.closest(ray) {
+            Some((_, distance, normal)) => (true, distance, normal),
+            None => (false, Float::MAX, Vec3::new(0.0, 1.0, 0.0)),
+        }
+    }
+
+    // which one was hit isn't kept, so look again from just off the surface
+    fn material_at(&self, point: Vec3, normal: Vec3) -> Material {
+        let back = Ray::new(point + normal * (EPSILON * 2.0), normal * -1.0);
+
+        match self.closest(back) {
+            Some((item, _, _)) => item.material_at(point, normal),
+            None => self.material(),
+        }
+    }
+
+    // same again
+    fn tangent(&self, point: Vec3, normal: Vec3) -> Vec3 {
+        let back = Ray::new(point + normal * (EPSILON * 2.0), normal * -1.0);
+        self.closest(back).map_or_else(|| Frame::new(normal).tangent, |(item, _, _)| item.tangent(point, normal))
+    }
+
+    fn uv(&self, point: Vec3, normal: Vec3) -> [Float; 2] {
+        let back = Ray::new(point + normal * (EPSILON * 2.0), normal * -1.0);
+        self.closest(back).map_or([0.0, 0.0], |(item, _, _)| item.uv(point, normal))
+    }
+
+    fn footprint(&self) -> Footprint {
+        let cost = self.items.iter().map(|item| item.footprint().cost).sum();
+        Footprint::new("primitives").with_bytes(std::mem::size_of_val(&self.items[..])).with_cost(cost)
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::{ Primitives, MarchPrimitive, TracePrimitive };
+    use crate::structures::vec3::Vec3;
+    use crate::structures::ray::Ray;
+    use crate::structures::material::Material;
+    use crate::objects::sphere::Sphere;
+    use crate::objects::cuboid::Cuboid;
+    use crate::objects::quad::Quad;
+    use crate::objects::traits::{ March, Trace };
+
+    #[test]
+    fn test_primitives() {
+        let mut red = Material::blank();
+        red.color = Vec3::new(1.0, 0.0, 0.0);
+
+        let mut marched: Primitives<MarchPrimitive> = Primitives::new();
+        marched.add(Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0, Material::blank()));
+        marched.add(Cuboid::new(Vec3::new(4.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 1.0), red));
+
+        assert_eq!(marched.march(Vec3::new(2.0, 0.0, 0.0)), 1.0);
+        assert_eq!(marched.material_at(Vec3::new(3.0, 0.0, 0.0)).color, red.color);
+        assert_eq!(marched.normal(Vec3::new(0.0, 1.0, 0.0)), Vec3::new(0.0, 1.0, 0.0));
+
+        let mut traced: Primitives<TracePrimitive> = Primitives::new();
+        traced.add(Sphere::new(Vec3::new(0.0, 0.0, -5.0), 1.0, Material::blank()));
+        traced.add(Quad::new(Vec3::new(-1.0, -1.0, -2.0), Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 2.0, 0.0), red));
+
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let (hit, distance, normal) = traced.trace(ray);
+        assert!(hit && (distance - 2.0).abs() < 1e-9);
+        assert_eq!(traced.material_at(ray.point_at(&distance), normal).color, red.color);
+    }
+}
@@ -0,0 +1,71 @@
+use serde::{ Serialize, Deserialize };
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::ray::Ray;
+use crate::structures::aabb::Aabb;
+use crate::structures::material::Material;
+use crate::objects::primitive::TracePrimitive;
+use crate::objects::traits::Trace;
+
+// a parallelogram spanned by two edges out of one corner.
+// the normal follows the right hand rule, u cross v.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Quad {
+    pub corner: Vec3,
+    pub u: Vec3,
+    pub v: Vec3,
+    pub material: Material,
+}
+
+impl Quad {
+    pub fn new(corner: Vec3, u: Vec3, v: Vec3, material: Material) -> Quad {
+        Quad {
+            corner: corner,
+            u: u,
+            v: v,
+            material: material,
+        }
+    }
+}
+
+impl Trace for Quad {
+    fn material(&self) -> Material { self.material }
+
+    fn bounds(&self) -> Option<Aabb> {
+        Some(Aabb::around(&[self.corner, self.corner + self.u, self.corner + self.v, self.corner + self.u + self.v]))
+    }
+
+    fn trace(&self, ray: Ray) -> (bool, Float, Vec3) {
+        let n = self.u.cross(&self.v);
+        let normal = n.unit();
+        let denom = n.dot(&ray.direction);
+
+        if denom.abs() > 0.0 {
+            let t = (self.corner - ray.origin).dot(&n) / denom;
+            let offset = ray.point_at(&t) - self.corner;
+
+            // coordinates along u and v, both in [0, 1] inside the quad
+            let n2 = n.length_squared();
+            let a = offset.cross(&self.v).dot(&n) / n2;
+            let b = self.u.cross(&offset).dot(&n) / n2;
+
+            if t >= 0.0 && (0.0..=1.0).contains(&a) && (0.0..=1.0).contains(&b) {
+                return (true, t, normal);
+            }
+        }
+
+        return (false, Float::MAX, normal);
+    }
+
+    fn tangent(&self, _point: Vec3, _normal: Vec3) -> Vec3 { self.u.unit() }
+
+    // how far along u and v, like in trace
+    fn uv(&self, point: Vec3, _normal: Vec3) -> [Float; 2] {
+        let n = self.u.cross(&self.v);
+        let offset = point - self.corner;
+        [offset.cross(&self.v).dot(&n) / n.length_squared(), self.u.cross(&offset).dot(&n) / n.length_squared()]
+    }
+
+    fn primitive(&self) -> Option<TracePrimitive> { Some((*self).into()) }
+}
@@ -0,0 +1,25 @@
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::material::Material;
+use crate::objects::traits::March;
+
+// a distance field straight from a closure, for quick experiments:
+// SdfFn::new(|p: Vec3| p.length() - 1.0, material)
+pub struct SdfFn<F> {
+    pub sdf: F,
+    pub material: Material,
+}
+
+impl<F: Fn(Vec3) -> Float + Send + Sync> SdfFn<F> {
+    pub fn new(sdf: F, material: Material) -> SdfFn<F> {
+        SdfFn { sdf: sdf, material: material }
+    }
+}
+
+impl<F: Fn(Vec3) -> Float + Send + Sync> March for SdfFn<F> {
+    fn material(&self) -> Material { self.material }
+
+    fn march(&self, point: Vec3) -> Float {
+        (self.sdf)(point)
+    }
+}
@@ -0,0 +1,180 @@
+use std::mem::size_of_val;
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::material::Material;
+use crate::structures::aabb::Aabb;
+use crate::structures::report::Footprint;
+use crate::objects::mesh::Mesh;
+use crate::objects::traits::March;
+
+// a distance field sampled on a regular grid and read back trilinearly.
+// turns anything with a distance, like a mesh, into something that can
+// be marched, blended and warped like the analytic shapes.
+#[derive(Debug, Clone)]
+pub struct SdfGrid {
+    pub bounds: Aabb,
+    pub dimensions: [usize; 3], // samples along each axis, at least 2
+    pub values: Vec<Float>,
+    pub material: Material,
+}
+
+impl SdfGrid {
+    // samples sit on the corners of the cells, so both faces of the box are covered
+    pub fn bake(bounds: Aabb, dimensions: [usize; 3], material: Material, sdf: impl Fn(Vec3) -> Float) -> SdfGrid {
+        let dimensions = [dimensions[0].max(2), dimensions[1].max(2), dimensions[2].max(2)];
+        let [x, y, z] = dimensions;
+
+        let mut grid = SdfGrid {
+            bounds: bounds,
+            dimensions: dimensions,
+            values: Vec::with_capacity(x * y * z),
+            material: material,
+        };
+
+        for k in 0..z {
+            for j in 0..y {
+                for i in 0..x {
+                    let position = grid.position([i, j, k]);
+                    grid.values.push(sdf(position));
+                }
+            }
+        }
+
+        return grid;
+    }
+
+    // `resolution` samples along the longest side of the mesh, see cubic
+    pub fn from_mesh(mesh: &Mesh, resolution: usize) -> SdfGrid {
+        return SdfGrid::cubic(mesh.bounds(), resolution, mesh.material, |point| mesh.distance(point));
+    }
+
+    // `resolution` samples along the longest side of `bounds`, the rest
+    // keep the cells cubic. a couple of cells of padding let the field
+    // settle outside the surface instead of being cut off by the box.
+    pub fn cubic(bounds: Aabb, resolution: usize, material: Material, sdf: impl Fn(Vec3) -> Float) -> SdfGrid {
+        let extent = bounds.extent();
+        let longest = extent.axis(bounds.longest_axis());
+
+        let resolution = resolution.max(4);
+        let cell = longest / (resolution - 4) as Float;
+        let padding = Vec3::new(cell, cell, cell) * 2.0;
+        let padded = Aabb::new(bounds.min - padding, bounds.max + padding);
+
+        let samples = |axis: usize| ((padded.extent().axis(axis) / cell).ceil() as usize + 1).max(2);
+        let dimensions = [samples(0), samples(1), samples(2)];
+        let padded = Aabb::new(padded.min, padded.min + Vec3::new(
+            (dimensions[0] - 1) as Float,
+            (dimensions[1] - 1) as Float,
+            (dimensions[2] - 1) as Float,
+        ) * cell);
+
+        return SdfGrid::bake(padded, dimensions, material, sdf);
+    }
+
+    fn position(&self, sample: [usize; 3]) -> Vec3 {
+        let extent = self.bounds.extent();
+        let at = |axis: usize| {
+            self.bounds.min.axis(axis)
+                + extent.axis(axis) * sample[axis] as Float / (self.dimensions[axis] - 1) as Float
+        };
+        return Vec3::new(at(0), at(1), at(2));
+    }
+
+    fn value(&self, i: usize, j: usize, k: usize) -> Float {
+        self.values[i + self.dimensions[0] * (j + self.dimensions[1] * k)]
+    }
+
+    // trilinear lookup, points outside are clamped onto the box
+    pub fn sample(&self, point: Vec3) -> Float {
+        let extent = self.bounds.extent();
+        let mut cell = [0usize; 3];
+        let mut t = [0.0; 3];
+
+        for axis in 0..3 {
+            let last = (self.dimensions[axis] - 1) as Float;
+            let local = ((point.axis(axis) - self.bounds.min.axis(axis)) / extent.axis(axis) * last).clamp(0.0, last);
+            let index = (local.floor() as usize).min(self.dimensions[axis] - 2);
+
+            cell[axis] = index;
+            t[axis] = local - index as Float;
+        }
+
+        let [i, j, k] = cell;
+        let lerp = |a: Float, b: Float, t: Float| a + (b - a) * t;
+
+        let x00 = lerp(self.value(i, j,     k    ), self.value(i + 1, j,     k    ), t[0]);
+        let x10 = lerp(self.value(i, j + 1, k    ), self.value(i + 1, j + 1, k    ), t[0]);
+        let x01 = lerp(self.value(i, j,     k + 1), self.value(i + 1, j,     k + 1), t[0]);
+        let x11 = lerp(self.value(i, j + 1, k + 1), self.value(i + 1, j + 1, k + 1), t[0]);
+
+        return lerp(lerp(x00, x10, t[1]), lerp(x01, x11, t[1]), t[2]);
+    }
+}
+
+impl March for SdfGrid {
+    fn material(&self) -> Material {
+        self.material
+    }
+
+    // outside the grid, get to the box first. the clamped sample is the
+    // distance left from the face, so adding both never overshoots.
+    fn march(&self, point: Vec3) -> Float {
+        return self.bounds.distance(&point) + self.sample(point);
+    }
+
+    fn bounds(&self) -> Option<Aabb> { Some(self.bounds) }
+
+    // eight samples for every distance
+    fn footprint(&self) -> Footprint {
+        Footprint::new("sdf grid").with_textures(size_of_val(&self.values[..])).with_cost(8.0)
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::SdfGrid;
+    use crate::structures::float::Float;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::material::Material;
+    use crate::objects::mesh::Mesh;
+    use crate::objects::traits::March;
+
+    // a closed cube from -1 to 1, wound outwards
+    fn cube() -> Mesh {
+        let vertices = (0..8).map(|i| Vec3::new(
+            if i & 1 == 0 { -1.0 } else { 1.0 },
+            if i & 2 == 0 { -1.0 } else { 1.0 },
+            if i & 4 == 0 { -1.0 } else { 1.0 },
+        )).collect();
+
+        let triangles = vec![
+            [0, 2, 1], [1, 2, 3], [4, 5, 6], [5, 7, 6],
+            [0, 1, 4], [1, 5, 4], [2, 6, 3], [3, 6, 7],
+            [0, 4, 2], [2, 4, 6], [1, 3, 5], [3, 7, 5],
+        ];
+
+        Mesh::new(vertices, triangles, Material::blank())
+    }
+
+    #[test]
+    fn test_mesh_distance() {
+        let mesh = cube();
+        assert!((mesh.distance(Vec3::new(0.0, 0.0, 0.0)) + 1.0).abs() < 1e-9);
+        assert!((mesh.distance(Vec3::new(3.0, 0.0, 0.0)) - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_from_mesh() {
+        let grid = SdfGrid::from_mesh(&cube(), 16);
+        let exact = (1e-9 as Float).max(Float::EPSILON * 64.0); // rounding, in f32 builds
+
+        // flat faces interpolate exactly, away from the edges
+        assert!((grid.march(Vec3::new(0.0, 0.0, 0.0)) + 1.0).abs() < exact);
+        assert!((grid.march(Vec3::new(1.25, 0.1, 0.2)) - 0.25).abs() < exact);
+
+        // far away it's still roughly right and never overshoots
+        let far = grid.march(Vec3::new(10.0, 0.0, 0.0));
+        assert!(far <= 9.0 + 1e-9 && far > 8.0);
+    }
+}
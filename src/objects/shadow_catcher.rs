@@ -0,0 +1,88 @@
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::ray::Ray;
+use crate::structures::aabb::Aabb;
+use crate::structures::report::Footprint;
+use crate::structures::material::Material;
+use crate::structures::packet::{ WideVec3, RayPacket, Lanes, LANES };
+use crate::objects::traits::{ March, Trace };
+use crate::objects::visible::Visibility;
+
+// wraps any object, usually a ground plane, so the camera sees straight
+// through it to the environment except where it's in shadow or picks up
+// light off the rest of the scene. everything else still bounces off it
+// and is shadowed by it like normal. render_alpha gives the matte to put
+// the shadows over a photograph.
+#[derive(Debug, Copy, Clone)]
+pub struct ShadowCatcher<T> {
+    pub object: T,
+}
+
+impl<T> ShadowCatcher<T> {
+    pub fn new(object: T) -> ShadowCatcher<T> {
+        ShadowCatcher { object: object }
+    }
+}
+
+impl<T: March> March for ShadowCatcher<T> {
+    fn material(&self) -> Material { self.object.material() }
+    fn march(&self, point: Vec3) -> Float { self.object.march(point) }
+    fn bounds(&self) -> Option<Aabb> { self.object.bounds() }
+    fn material_at(&self, point: Vec3) -> Material { self.object.material_at(point) }
+    fn normal(&self, point: Vec3) -> Vec3 { self.object.normal(point) }
+    fn tangent(&self, point: Vec3, normal: Vec3) -> Vec3 { self.object.tangent(point, normal) }
+    fn uv(&self, point: Vec3) -> [Float; 2] { self.object.uv(point) }
+    fn march_packet(&self, points: &WideVec3) -> Lanes { self.object.march_packet(points) }
+    fn wgsl(&self) -> Option<String> { self.object.wgsl() }
+    fn visibility(&self) -> Visibility { self.object.visibility() }
+    fn shadow_catcher(&self) -> bool { true }
+    fn light_group(&self) -> Option<&str> { self.object.light_group() }
+    fn footprint(&self) -> Footprint { self.object.footprint() }
+}
+
+impl<T: Trace> Trace for ShadowCatcher<T> {
+    fn material(&self) -> Material { self.object.material() }
+    fn trace(&self, ray: Ray) -> (bool, Float, Vec3) { self.object.trace(ray) }
+    fn bounds(&self) -> Option<Aabb> { self.object.bounds() }
+    fn material_at(&self, point: Vec3, normal: Vec3) -> Material { self.object.material_at(point, normal) }
+    fn tangent(&self, point: Vec3, normal: Vec3) -> Vec3 { self.object.tangent(point, normal) }
+    fn uv(&self, point: Vec3, normal: Vec3) -> [Float; 2] { self.object.uv(point, normal) }
+    fn trace_packet(&self, packet: &RayPacket) -> [(bool, Float, Vec3); LANES] { self.object.trace_packet(packet) }
+    fn wgsl(&self) -> Option<String> { self.object.wgsl() }
+    fn visibility(&self) -> Visibility { self.object.visibility() }
+    fn shadow_catcher(&self) -> bool { true }
+    fn light_group(&self) -> Option<&str> { self.object.light_group() }
+    fn footprint(&self) -> Footprint { self.object.footprint() }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::ShadowCatcher;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::camera::Camera;
+    use crate::structures::material::Material;
+    use crate::structures::scene::Scene;
+    use crate::render::{ render, alpha };
+    use crate::objects::plane::Plane;
+
+    #[test]
+    fn test_shadow_catcher() {
+        let black = Material { color: Vec3::new(0.0, 0.0, 0.0), emission: 0.0, ..Material::blank() };
+        let mut camera = Camera::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        camera.fov = 1.0;
+
+        let ground = ShadowCatcher::new(Plane::new(Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 1.0, 0.0), black));
+        let open = Scene::builder().camera(camera).environment(Vec3::new(1.0, 1.0, 1.0)).trace(ground).samples(1).build();
+
+        // nothing in the way, so it's as if it weren't there
+        let mut rng = rand::thread_rng();
+        assert_eq!(render(&open, [0.0, 0.0], [1, 1], &mut rng), Vec3::new(1.0, 1.0, 1.0));
+        assert_eq!(alpha(&open, [0.0, 0.0], [1, 1], &mut rng), 0.0);
+
+        // a ceiling shades it completely
+        let ceiling = Plane::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0), black);
+        let shaded = Scene::builder().camera(camera).environment(Vec3::new(1.0, 1.0, 1.0)).trace(ground).trace(ceiling).samples(1).build();
+        assert_eq!(render(&shaded, [0.0, 0.0], [1, 1], &mut rng), Vec3::new(0.0, 0.0, 0.0));
+        assert_eq!(alpha(&shaded, [0.0, 0.0], [1, 1], &mut rng), 1.0);
+    }
+}
@@ -0,0 +1,98 @@
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::ray::Ray;
+use crate::structures::aabb::Aabb;
+use crate::structures::report::Footprint;
+use crate::structures::material::Material;
+use crate::structures::material_registry::{ MaterialRegistry, MaterialId };
+use crate::structures::packet::{ WideVec3, RayPacket, Lanes, LANES };
+use crate::objects::primitive::{ MarchPrimitive, TracePrimitive };
+use crate::objects::traits::{ March, Trace };
+use crate::objects::visible::Visibility;
+
+// wraps any object so it takes its material from a registry instead of its
+// own, all over, see Scene::add_march_shared. whatever material the object
+// has is ignored. saved, it keeps the material it has at the time but
+// not the sharing.
+#[derive(Debug, Clone)]
+pub struct Shared<T> {
+    pub object: T,
+    pub id: MaterialId,
+    pub registry: MaterialRegistry,
+}
+
+impl<T> Shared<T> {
+    pub fn new(object: T, registry: &MaterialRegistry, id: MaterialId) -> Shared<T> {
+        Shared { object: object, id: id, registry: registry.clone() }
+    }
+}
+
+impl<T: March> March for Shared<T> {
+    fn material(&self) -> Material { self.registry.get(self.id) }
+    fn march(&self, point: Vec3) -> Float { self.object.march(point) }
+    fn bounds(&self) -> Option<Aabb> { self.object.bounds() }
+    fn material_at(&self, _point: Vec3) -> Material { self.registry.get(self.id) }
+    fn normal(&self, point: Vec3) -> Vec3 { self.object.normal(point) }
+    fn tangent(&self, point: Vec3, normal: Vec3) -> Vec3 { self.object.tangent(point, normal) }
+    fn uv(&self, point: Vec3) -> [Float; 2] { self.object.uv(point) }
+    fn march_packet(&self, points: &WideVec3) -> Lanes { self.object.march_packet(points) }
+    fn wgsl(&self) -> Option<String> { self.object.wgsl() }
+    fn primitive(&self) -> Option<MarchPrimitive> { self.object.primitive().map(|shape| shape.with_material(self.material())) }
+    fn visibility(&self) -> Visibility { self.object.visibility() }
+    fn shadow_catcher(&self) -> bool { self.object.shadow_catcher() }
+    fn light_group(&self) -> Option<&str> { self.object.light_group() }
+    fn footprint(&self) -> Footprint { self.object.footprint() }
+}
+
+impl<T: Trace> Trace for Shared<T> {
+    fn material(&self) -> Material { self.registry.get(self.id) }
+    fn trace(&self, ray: Ray) -> (bool, Float, Vec3) { self.object.trace(ray) }
+    fn bounds(&self) -> Option<Aabb> { self.object.bounds() }
+    fn material_at(&self, _point: Vec3, _normal: Vec3) -> Material { self.registry.get(self.id) }
+    fn tangent(&self, point: Vec3, normal: Vec3) -> Vec3 { self.object.tangent(point, normal) }
+    fn uv(&self, point: Vec3, normal: Vec3) -> [Float; 2] { self.object.uv(point, normal) }
+    fn trace_packet(&self, packet: &RayPacket) -> [(bool, Float, Vec3); LANES] { self.object.trace_packet(packet) }
+    fn wgsl(&self) -> Option<String> { self.object.wgsl() }
+    fn primitive(&self) -> Option<TracePrimitive> { self.object.primitive().map(|shape| shape.with_material(self.material())) }
+    fn visibility(&self) -> Visibility { self.object.visibility() }
+    fn shadow_catcher(&self) -> bool { self.object.shadow_catcher() }
+    fn light_group(&self) -> Option<&str> { self.object.light_group() }
+    fn footprint(&self) -> Footprint { self.object.footprint() }
+}
+
+#[cfg(test)]
+pub mod test {
+    use crate::structures::vec3::Vec3;
+    use crate::structures::ray::Ray;
+    use crate::structures::camera::Camera;
+    use crate::structures::material::Material;
+    use crate::structures::scene::Scene;
+    use crate::render::cast_ray;
+    use crate::objects::sphere::Sphere;
+    use crate::objects::visible::RayKind;
+
+    #[test]
+    fn test_shared() {
+        let red = Material { color: Vec3::new(1.0, 0.0, 0.0), emission: 0.0, ..Material::blank() };
+        let blue = Material { color: Vec3::new(0.0, 0.0, 1.0), ..red };
+        let ball = |x| Sphere::new(Vec3::new(x, 0.0, 0.0), 1.0, Material::blank());
+
+        let mut scene = Scene::new(Camera::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)));
+        let paint = scene.materials.add_named("paint", red);
+        scene.add_march_shared(ball(0.0), paint);
+        scene.add_trace_shared(ball(5.0), paint);
+
+        let seen = |scene: &Scene| [0.0, 5.0].map(|x| {
+            cast_ray(scene, Ray::new(Vec3::new(x, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0)), RayKind::Camera).material.color
+        });
+        assert_eq!(seen(&scene), [red.color, red.color]);
+
+        // one change and both are blue
+        scene.materials.set(scene.materials.id("paint").unwrap(), blue);
+        assert_eq!(seen(&scene), [blue.color, blue.color]);
+
+        // and saved, they keep it
+        let saved: Scene = serde_json::from_str(&serde_json::to_string(&scene).unwrap()).unwrap();
+        assert_eq!(saved.trace[0].material().color, blue.color);
+    }
+}
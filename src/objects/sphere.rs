@@ -1,29 +1,55 @@
+use serde::{ Serialize, Deserialize };
+
+use crate::structures::float::Float;
+use crate::structures::float::consts::PI;
 use crate::structures::vec3::Vec3;
 use crate::structures::ray::Ray;
+use crate::structures::aabb::Aabb;
 use crate::structures::material::Material;
+use crate::structures::frame::Frame;
+use crate::structures::packet::{ WideVec3, RayPacket, Lanes, LANES };
+use crate::objects::primitive::{ MarchPrimitive, TracePrimitive };
 use crate::objects::traits::{ March, Trace };
+use crate::gpu;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct Sphere {
     pub position: Vec3,
-    pub radius: f64,
+    pub radius: Float,
     pub material: Material,
 }
 
 impl Sphere {
-    pub fn new(position: Vec3, radius: f64, material: Material) -> Sphere {
+    pub fn new(position: Vec3, radius: Float, material: Material) -> Sphere {
         Sphere {
             position: position,
             radius: radius,
             material: material,
         }
     }
+
+    // latitude and longitude, both 0 to 1, with v going down from the top
+    fn surface_uv(&self, point: Vec3) -> [Float; 2] {
+        let d = (point - self.position).unit();
+        [d.z.atan2(d.x) / (2.0 * PI) + 0.5, d.y.clamp(-1.0, 1.0).acos() / PI]
+    }
+
+    // around the y axis, the way u goes
+    fn surface_tangent(&self, point: Vec3, normal: Vec3) -> Vec3 {
+        let d = point - self.position;
+        Frame::with_tangent(normal, Vec3::new(-d.z, 0.0, d.x)).tangent
+    }
 }
 
 impl Trace for Sphere {
     fn material(&self) -> Material { self.material }
 
-    fn trace(&self, ray: Ray) -> (bool, f64, Vec3) {
+    fn bounds(&self) -> Option<Aabb> {
+        let reach = Vec3::new(1.0, 1.0, 1.0) * self.radius;
+        Some(Aabb::new(self.position - reach, self.position + reach))
+    }
+
+    fn trace(&self, ray: Ray) -> (bool, Float, Vec3) {
         let oc = ray.origin - self.position;
 
         let a = ray.direction.dot(&ray.direction);
@@ -31,18 +57,62 @@ impl Trace for Sphere {
         let c = oc.dot(&oc) - self.radius * self.radius;
         let disc = (b * b) - (a * c);
 
-        let hit = if disc > 0.0 { true } else { false };
+        let hit = disc > 0.0;
         let distance = ((0.0 - b - disc.sqrt()) / a).min((0.0 - b + disc.sqrt()) / a);
         let normal = (ray.point_at(&distance) - self.position).unit();
 
         return (hit, distance, normal);
     }
+
+    fn trace_packet(&self, packet: &RayPacket) -> [(bool, Float, Vec3); LANES] {
+        let oc = packet.origin - WideVec3::splat(self.position);
+
+        let a = packet.direction.dot(&packet.direction);
+        let b = oc.dot(&packet.direction);
+        let c = oc.dot(&oc);
+        let disc = (b * b) - (a * (c - self.radius * self.radius));
+
+        let root = disc.sqrt();
+        let distance = ((0.0 - b - root) / a).min((0.0 - b + root) / a);
+        let normal = packet.point_at(&distance) - WideVec3::splat(self.position);
+
+        let (disc, distance) = (disc.to_array(), distance.to_array());
+        std::array::from_fn(|i| (disc[i] > 0.0, distance[i], normal.at(i).unit()))
+    }
+
+    fn wgsl(&self) -> Option<String> {
+        Some(format!("trace_sphere(o, d, {}, {})", gpu::vec3(self.position)?, gpu::float(self.radius)?))
+    }
+
+    fn tangent(&self, point: Vec3, normal: Vec3) -> Vec3 { self.surface_tangent(point, normal) }
+    fn uv(&self, point: Vec3, _normal: Vec3) -> [Float; 2] { self.surface_uv(point) }
+
+    fn primitive(&self) -> Option<TracePrimitive> { Some((*self).into()) }
 }
 
 impl March for Sphere {
     fn material(&self) -> Material { self.material }
 
-    fn march(&self, point: Vec3) -> f64 {
+    fn march(&self, point: Vec3) -> Float {
         (point - self.position).length() - self.radius // TODO modulo with 6 for infinite rep.
     }
+
+    fn bounds(&self) -> Option<Aabb> { Trace::bounds(self) }
+
+    fn normal(&self, point: Vec3) -> Vec3 {
+        (point - self.position).unit()
+    }
+
+    fn march_packet(&self, points: &WideVec3) -> Lanes {
+        (*points - WideVec3::splat(self.position)).length() - self.radius
+    }
+
+    fn wgsl(&self) -> Option<String> {
+        Some(format!("length(p - {}) - {}", gpu::vec3(self.position)?, gpu::float(self.radius)?))
+    }
+
+    fn tangent(&self, point: Vec3, normal: Vec3) -> Vec3 { self.surface_tangent(point, normal) }
+    fn uv(&self, point: Vec3) -> [Float; 2] { self.surface_uv(point) }
+
+    fn primitive(&self) -> Option<MarchPrimitive> { Some((*self).into()) }
 }
@@ -0,0 +1,185 @@
+use std::mem::{ size_of, size_of_val };
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::aabb::Aabb;
+use crate::structures::material::Material;
+use crate::structures::report::Footprint;
+use crate::import::ttf::Font;
+use crate::objects::traits::March;
+
+type Point = [Float; 2];
+
+// one glyph's edges where it sits in the line, and the box around them
+#[derive(Debug, Clone)]
+struct Outline {
+    edges: Vec<[Point; 2]>,
+    min: Point,
+    max: Point,
+}
+
+impl Outline {
+    // how far outside the box a point is, 0 inside it
+    fn gap(&self, x: Float, y: Float) -> Float {
+        let dx = (self.min[0] - x).max(x - self.max[0]).max(0.0);
+        let dy = (self.min[1] - y).max(y - self.max[1]).max(0.0);
+        return (dx * dx + dy * dy).sqrt();
+    }
+}
+
+fn edge_distance([a, b]: &[Point; 2], x: Float, y: Float) -> Float {
+    let (px, py, bx, by) = (x - a[0], y - a[1], b[0] - a[0], b[1] - a[1]);
+    let length = bx * bx + by * by;
+    let h = if length > 0.0 { ((px * bx + py * by) / length).clamp(0.0, 1.0) } else { 0.0 };
+    return ((px - bx * h).powi(2) + (py - by * h).powi(2)).sqrt();
+}
+
+// which way round the edge goes past the point, if it crosses the line
+// going right from it at all, for the winding number
+fn winding([a, b]: &[Point; 2], x: Float, y: Float) -> i32 {
+    let side = (b[0] - a[0]) * (y - a[1]) - (x - a[0]) * (b[1] - a[1]);
+    if a[1] <= y && b[1] > y && side > 0.0 { return 1; }
+    if a[1] > y && b[1] <= y && side < 0.0 { return -1; }
+    return 0;
+}
+
+// a line of text out of a truetype font, see import::ttf, standing on the
+// xy plane and pushed out `depth` either way along z. it starts at
+// `position` on the baseline, each em `size` across, and every "\n"
+// starts a new line under it. characters the font hasn't got are skipped.
+#[derive(Debug, Clone)]
+pub struct Text {
+    pub position: Vec3,
+    pub depth: Float,
+    pub material: Material,
+    outlines: Vec<Outline>,
+    min: Vec3,
+    max: Vec3,
+}
+
+impl Text {
+    pub fn new(font: &Font, text: &str, position: Vec3, size: Float, depth: Float, material: Material) -> Text {
+        let mut outlines = vec![];
+        let (mut x, mut y) = (0.0, 0.0);
+
+        for character in text.chars() {
+            if character == '\n' {
+                x = 0.0;
+                y -= font.line_height * size;
+                continue;
+            }
+
+            let glyph = match font.glyph(character) {
+                Some(glyph) => glyph,
+                None => continue,
+            };
+
+            let mut outline = Outline { edges: vec![], min: [Float::MAX; 2], max: [Float::MIN; 2] };
+            for contour in glyph.contours.iter() {
+                let placed: Vec<Point> = contour.iter().map(|[u, v]| [x + u * size, y + v * size]).collect();
+
+                for (i, a) in placed.iter().enumerate() {
+                    let b = placed[(i + 1) % placed.len()];
+                    outline.edges.push([*a, b]);
+                    outline.min = [outline.min[0].min(a[0]), outline.min[1].min(a[1])];
+                    outline.max = [outline.max[0].max(a[0]), outline.max[1].max(a[1])];
+                }
+            }
+
+            if !outline.edges.is_empty() { outlines.push(outline); }
+            x += glyph.advance * size;
+        }
+
+        let (min, max) = outlines.iter().fold(([Float::MAX; 2], [Float::MIN; 2]), |(min, max), outline| {
+            ([min[0].min(outline.min[0]), min[1].min(outline.min[1])], [max[0].max(outline.max[0]), max[1].max(outline.max[1])])
+        });
+
+        Text {
+            position: position,
+            depth: depth,
+            material: material,
+            outlines: outlines,
+            min: Vec3::new(min[0], min[1], -depth),
+            max: Vec3::new(max[0], max[1], depth),
+        }
+    }
+
+    // how far from the outlines a point on the plane is, less than 0 inside
+    fn flat(&self, x: Float, y: Float) -> Float {
+        let mut nearest = Float::MAX;
+        let mut turns = 0;
+
+        for outline in self.outlines.iter() {
+            let gap = outline.gap(x, y);
+
+            // only what's around the point can go round it
+            if gap > 0.0 && gap >= nearest { continue; }
+
+            for edge in outline.edges.iter() {
+                nearest = nearest.min(edge_distance(edge, x, y));
+                if gap == 0.0 { turns += winding(edge, x, y); }
+            }
+        }
+
+        return if turns != 0 { -nearest } else { nearest };
+    }
+}
+
+impl March for Text {
+    fn material(&self) -> Material { self.material }
+
+    fn march(&self, point: Vec3) -> Float {
+        let p = point - self.position;
+        if self.outlines.is_empty() { return p.length(); }
+
+        // far from all of it, the box is close enough, and much cheaper
+        let outside = (self.min - p).max_by(&(p - self.max)).max_by(&Vec3::new(0.0, 0.0, 0.0));
+        let gap = outside.length();
+        if gap > self.depth.max(0.01) { return gap; }
+
+        let (d, z) = (self.flat(p.x, p.y), p.z.abs() - self.depth);
+        return d.max(z).min(0.0) + (d.max(0.0).powi(2) + z.max(0.0).powi(2)).sqrt();
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        if self.outlines.is_empty() { return Some(Aabb::new(self.position, self.position)); }
+        return Some(Aabb::new(self.position + self.min, self.position + self.max));
+    }
+
+    // near it, every edge of every glyph
+    fn footprint(&self) -> Footprint {
+        let edges: usize = self.outlines.iter().map(|outline| outline.edges.len()).sum();
+        let bytes = size_of_val(&self.outlines[..]) + edges * size_of::<[Point; 2]>();
+        return Footprint::new("text").with_bytes(bytes).with_cost(edges.max(1) as Float);
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::Text;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::material::Material;
+    use crate::import::ttf::{ parse, square };
+    use crate::objects::traits::March;
+
+    #[test]
+    fn test_text() {
+        // squares from 0.1 to 0.5 of each 0.6 wide em, a space 0.25 wide
+        let font = parse(&square()).unwrap();
+        let text = Text::new(&font, "a a\na", Vec3::new(0.0, 0.0, 0.0), 1.0, 0.1, Material::blank());
+
+        // inside the first, and off the face of it
+        assert!((text.march(Vec3::new(0.3, 0.3, 0.0)) + 0.1).abs() < 0.0001);
+        assert!((text.march(Vec3::new(0.3, 0.3, 0.5)) - 0.4).abs() < 0.0001);
+
+        // between the first two, past the space, and on the next line
+        assert!((text.march(Vec3::new(0.7, 0.3, 0.0)) - 0.2).abs() < 0.0001);
+        assert!(text.march(Vec3::new(1.15, 0.3, 0.0)) < 0.0);
+        assert!(text.march(Vec3::new(0.3, -0.7, 0.0)) < 0.0);
+        assert!(text.march(Vec3::new(0.3, 0.7, 0.0)) > 0.0);
+
+        // and very far off, never closer than it really is
+        let far = Vec3::new(40.0, 30.0, 0.0);
+        assert!(text.march(far) <= (far - Vec3::new(1.35, 0.5, 0.0)).length() + 0.0001);
+    }
+}
@@ -0,0 +1,63 @@
+use serde::{ Serialize, Deserialize };
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::aabb::Aabb;
+use crate::structures::material::Material;
+use crate::objects::primitive::MarchPrimitive;
+use crate::objects::traits::March;
+use crate::gpu;
+
+// a ring lying flat in the xz plane
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Torus {
+    pub position: Vec3,
+    pub major: Float, // center of the ring to center of the tube
+    pub minor: Float, // thickness of the tube
+    pub material: Material,
+}
+
+impl Torus {
+    pub fn new(position: Vec3, major: Float, minor: Float, material: Material) -> Torus {
+        Torus {
+            position: position,
+            major: major,
+            minor: minor,
+            material: material,
+        }
+    }
+}
+
+impl March for Torus {
+    fn material(&self) -> Material { self.material }
+
+    fn march(&self, point: Vec3) -> Float {
+        let p = point - self.position;
+        let ring = (p.x * p.x + p.z * p.z).sqrt() - self.major;
+
+        return (ring * ring + p.y * p.y).sqrt() - self.minor;
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        let reach = Vec3::new(self.major + self.minor, self.minor, self.major + self.minor);
+        return Some(Aabb::new(self.position - reach, self.position + reach));
+    }
+
+    // straight out from the closest point on the ring through the middle
+    fn normal(&self, point: Vec3) -> Vec3 {
+        let p = point - self.position;
+        let flat = Vec3::new(p.x, 0.0, p.z);
+
+        if flat.length_squared() == 0.0 { return Vec3::new(0.0, p.y.signum(), 0.0); }
+        return (p - flat.unit() * self.major).unit();
+    }
+
+    fn wgsl(&self) -> Option<String> {
+        Some(format!(
+            "sd_torus(p, {}, {}, {})",
+            gpu::vec3(self.position)?, gpu::float(self.major)?, gpu::float(self.minor)?,
+        ))
+    }
+
+    fn primitive(&self) -> Option<MarchPrimitive> { Some((*self).into()) }
+}
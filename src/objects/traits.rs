@@ -1,15 +1,147 @@
+use std::sync::Arc;
+
+use crate::structures::float::Float;
 use crate::structures::vec3::Vec3;
 use crate::structures::ray::Ray;
+use crate::structures::aabb::Aabb;
 use crate::structures::material::Material;
+use crate::structures::frame::Frame;
+use crate::structures::packet::{ WideVec3, RayPacket, Lanes, LANES, lanes };
+use crate::structures::report::Footprint;
+use crate::objects::primitive::{ MarchPrimitive, TracePrimitive };
+use crate::objects::visible::Visibility;
+
+// how far apart the samples for estimated normals are
+const NORMAL_EPSILON: Float = 0.001;
 
 // I see duplicate code... hmm...
+// both are shared between render threads, hence send + sync
 
-pub trait March {
+pub trait March: Send + Sync {
     fn material(&self) -> Material;
-    fn march(&self, point: Vec3) -> f64;
+    fn march(&self, point: Vec3) -> Float;
+
+    // for objects whose material varies over the surface, like blended sdfs
+    fn material_at(&self, _point: Vec3) -> Material { self.material() }
+
+    // the gradient of the field, estimated from four samples on the corners
+    // of a tetrahedron. shapes that know their normal exactly should say so.
+    fn normal(&self, point: Vec3) -> Vec3 {
+        let h = NORMAL_EPSILON;
+
+        [
+            Vec3::new( 1.0, -1.0, -1.0),
+            Vec3::new(-1.0, -1.0,  1.0),
+            Vec3::new(-1.0,  1.0, -1.0),
+            Vec3::new( 1.0,  1.0,  1.0),
+        ].iter().fold(Vec3::new(0.0, 0.0, 0.0), |sum, k| sum + *k * self.march(point + *k * h)).unit()
+    }
+
+    // LANES points at once. shapes simple enough to do the math on every
+    // lane together should, by default it's one point at a time.
+    fn march_packet(&self, points: &WideVec3) -> Lanes {
+        lanes(|lane| self.march(points.at(lane)))
+    }
+
+    // the distance at `p` as wgsl, for the gpu preview. anything that
+    // can't say keeps the whole scene on the cpu, see gpu::compile.
+    fn wgsl(&self) -> Option<String> { None }
+
+    // the object as plain data, which is how scenes get saved. anything
+    // that isn't one of the built in shapes can't be, yet.
+    fn primitive(&self) -> Option<MarchPrimitive> { None }
+
+    // a box the whole surface is inside, so it can be left out wherever
+    // something else is nearer than the box, see TopLevel. none for
+    // anything endless, like planes and repeats, or that won't say.
+    fn bounds(&self) -> Option<Aabb> { None }
+
+    // which kinds of ray can hit it, see Visible
+    fn visibility(&self) -> Visibility { Visibility::ALL }
+
+    // shows what's behind it, only darker where it's in shadow, see ShadowCatcher
+    fn shadow_catcher(&self) -> bool { false }
+
+    // which light group its glow goes in, see LightGroup
+    fn light_group(&self) -> Option<&str> { None }
+
+    // which way the surface runs at a point on it, and where the point is
+    // on it, for shading in the surface's own space. shapes with no
+    // natural way to lay a surface out get any tangent, and 0, 0.
+    fn tangent(&self, _point: Vec3, normal: Vec3) -> Vec3 { Frame::new(normal).tangent }
+    fn uv(&self, _point: Vec3) -> [Float; 2] { [0.0, 0.0] }
+
+    // what it is and what it keeps, for Scene::report. the built in shapes
+    // say through their primitive, anything else that won't is "custom".
+    fn footprint(&self) -> Footprint {
+        self.primitive().map_or(Footprint::new("custom"), |shape| shape.footprint())
+    }
 }
 
-pub trait Trace {
+pub trait Trace: Send + Sync {
     fn material(&self) -> Material;
-    fn trace(&self, ray: Ray) -> (bool, f64, Vec3);
+    fn trace(&self, ray: Ray) -> (bool, Float, Vec3);
+
+    // the normal says which side of the surface the hit came from
+    fn material_at(&self, _point: Vec3, _normal: Vec3) -> Material { self.material() }
+
+    // same as march_packet, one ray at a time unless the shape knows better
+    fn trace_packet(&self, packet: &RayPacket) -> [(bool, Float, Vec3); LANES] {
+        std::array::from_fn(|lane| self.trace(packet.ray(lane)))
+    }
+
+    // a wgsl Hit for the ray from `o` along `d`, t below zero for a miss
+    fn wgsl(&self) -> Option<String> { None }
+
+    fn primitive(&self) -> Option<TracePrimitive> { None }
+
+    // a box around all of it, for leaving it out where it can't be seen,
+    // see Frustum. none for anything endless, like planes, or that won't say.
+    fn bounds(&self) -> Option<Aabb> { None }
+
+    fn visibility(&self) -> Visibility { Visibility::ALL }
+    fn shadow_catcher(&self) -> bool { false }
+    fn light_group(&self) -> Option<&str> { None }
+
+    fn tangent(&self, _point: Vec3, normal: Vec3) -> Vec3 { Frame::new(normal).tangent }
+    fn uv(&self, _point: Vec3, _normal: Vec3) -> [Float; 2] { [0.0, 0.0] }
+
+    fn footprint(&self) -> Footprint {
+        self.primitive().map_or(Footprint::new("custom"), |shape| shape.footprint())
+    }
+}
+
+// shared objects are objects too, so trees built at runtime, like the
+// ones in scene files, can nest whatever they like
+impl March for Arc<dyn March> {
+    fn material(&self) -> Material { (**self).material() }
+    fn march(&self, point: Vec3) -> Float { (**self).march(point) }
+    fn material_at(&self, point: Vec3) -> Material { (**self).material_at(point) }
+    fn normal(&self, point: Vec3) -> Vec3 { (**self).normal(point) }
+    fn march_packet(&self, points: &WideVec3) -> Lanes { (**self).march_packet(points) }
+    fn wgsl(&self) -> Option<String> { (**self).wgsl() }
+    fn primitive(&self) -> Option<MarchPrimitive> { (**self).primitive() }
+    fn bounds(&self) -> Option<Aabb> { (**self).bounds() }
+    fn visibility(&self) -> Visibility { (**self).visibility() }
+    fn shadow_catcher(&self) -> bool { (**self).shadow_catcher() }
+    fn light_group(&self) -> Option<&str> { (**self).light_group() }
+    fn tangent(&self, point: Vec3, normal: Vec3) -> Vec3 { (**self).tangent(point, normal) }
+    fn uv(&self, point: Vec3) -> [Float; 2] { (**self).uv(point) }
+    fn footprint(&self) -> Footprint { (**self).footprint() }
+}
+
+impl Trace for Arc<dyn Trace> {
+    fn material(&self) -> Material { (**self).material() }
+    fn trace(&self, ray: Ray) -> (bool, Float, Vec3) { (**self).trace(ray) }
+    fn material_at(&self, point: Vec3, normal: Vec3) -> Material { (**self).material_at(point, normal) }
+    fn trace_packet(&self, packet: &RayPacket) -> [(bool, Float, Vec3); LANES] { (**self).trace_packet(packet) }
+    fn wgsl(&self) -> Option<String> { (**self).wgsl() }
+    fn primitive(&self) -> Option<TracePrimitive> { (**self).primitive() }
+    fn bounds(&self) -> Option<Aabb> { (**self).bounds() }
+    fn visibility(&self) -> Visibility { (**self).visibility() }
+    fn shadow_catcher(&self) -> bool { (**self).shadow_catcher() }
+    fn light_group(&self) -> Option<&str> { (**self).light_group() }
+    fn tangent(&self, point: Vec3, normal: Vec3) -> Vec3 { (**self).tangent(point, normal) }
+    fn uv(&self, point: Vec3, normal: Vec3) -> [Float; 2] { (**self).uv(point, normal) }
+    fn footprint(&self) -> Footprint { (**self).footprint() }
 }
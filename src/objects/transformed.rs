@@ -0,0 +1,160 @@
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::ray::Ray;
+use crate::structures::aabb::Aabb;
+use crate::structures::report::Footprint;
+use crate::structures::material::Material;
+use crate::structures::transform::Transform;
+use crate::objects::traits::{ March, Trace };
+use crate::objects::visible::Visibility;
+
+// wraps any object and moves it around, so primitives can be defined
+// around the origin and positioned after the fact
+#[derive(Debug, Copy, Clone)]
+pub struct Transformed<T> {
+    pub object: T,
+    pub transform: Transform,
+    // smallest scale factor, distances shrink by at most this much
+    stretch: Float,
+}
+
+pub(crate) fn stretch(transform: &Transform) -> Float {
+    let m = &transform.matrix;
+
+    (0..3)
+        .map(|col| Vec3::new(m[0][col], m[1][col], m[2][col]).length())
+        .fold(Float::MAX, Float::min)
+}
+
+impl<T> Transformed<T> {
+    pub fn new(object: T, transform: Transform) -> Transformed<T> {
+        Transformed {
+            object: object,
+            stretch: stretch(&transform),
+            transform: transform,
+        }
+    }
+
+    pub fn translate(object: T, offset: Vec3) -> Transformed<T> {
+        Transformed::new(object, Transform::translate(offset))
+    }
+
+    // rotation around an axis, in degrees
+    pub fn rotate(object: T, axis: Vec3, degrees: Float) -> Transformed<T> {
+        Transformed::new(object, Transform::rotate(axis, degrees))
+    }
+
+    pub fn scale(object: T, factor: Vec3) -> Transformed<T> {
+        Transformed::new(object, Transform::scale(factor))
+    }
+
+    // applies another transform on top of this one
+    pub fn then(self, transform: Transform) -> Transformed<T> {
+        let combined = transform * self.transform;
+        Transformed::new(self.object, combined)
+    }
+}
+
+impl<T: Trace> Trace for Transformed<T> {
+    fn material(&self) -> Material { self.object.material() }
+
+    // the local ray isn't renormalized, so distances along it match world space
+    fn trace(&self, ray: Ray) -> (bool, Float, Vec3) {
+        let local = self.transform.inverted().ray(ray);
+        let (hit, distance, normal) = self.object.trace(local);
+
+        return (hit, distance, self.transform.normal(normal));
+    }
+
+    fn bounds(&self) -> Option<Aabb> { self.object.bounds().map(|bounds| self.transform.aabb(&bounds)) }
+
+    fn material_at(&self, point: Vec3, normal: Vec3) -> Material {
+        let local = self.transform.inverted();
+        self.object.material_at(local.point(point), local.normal(normal))
+    }
+
+    fn tangent(&self, point: Vec3, normal: Vec3) -> Vec3 {
+        let local = self.transform.inverted();
+        self.transform.vector(self.object.tangent(local.point(point), local.normal(normal))).unit()
+    }
+
+    fn uv(&self, point: Vec3, normal: Vec3) -> [Float; 2] {
+        let local = self.transform.inverted();
+        self.object.uv(local.point(point), local.normal(normal))
+    }
+
+    fn visibility(&self) -> Visibility { self.object.visibility() }
+    fn shadow_catcher(&self) -> bool { self.object.shadow_catcher() }
+    fn light_group(&self) -> Option<&str> { self.object.light_group() }
+    fn footprint(&self) -> Footprint { self.object.footprint() }
+}
+
+impl<T: March> March for Transformed<T> {
+    fn material(&self) -> Material { self.object.material() }
+
+    // non-uniform scales don't preserve distances, so stay conservative
+    fn march(&self, point: Vec3) -> Float {
+        self.object.march(self.transform.inverted().point(point)) * self.stretch
+    }
+
+    fn bounds(&self) -> Option<Aabb> { self.object.bounds().map(|bounds| self.transform.aabb(&bounds)) }
+
+    fn material_at(&self, point: Vec3) -> Material {
+        self.object.material_at(self.transform.inverted().point(point))
+    }
+
+    fn normal(&self, point: Vec3) -> Vec3 {
+        self.transform.normal(self.object.normal(self.transform.inverted().point(point)))
+    }
+
+    fn tangent(&self, point: Vec3, normal: Vec3) -> Vec3 {
+        let local = self.transform.inverted();
+        self.transform.vector(self.object.tangent(local.point(point), local.normal(normal))).unit()
+    }
+
+    fn uv(&self, point: Vec3) -> [Float; 2] {
+        self.object.uv(self.transform.inverted().point(point))
+    }
+
+    fn visibility(&self) -> Visibility { self.object.visibility() }
+    fn shadow_catcher(&self) -> bool { self.object.shadow_catcher() }
+    fn light_group(&self) -> Option<&str> { self.object.light_group() }
+    fn footprint(&self) -> Footprint { self.object.footprint() }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::Transformed;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::ray::Ray;
+    use crate::structures::material::Material;
+    use crate::objects::sphere::Sphere;
+    use crate::objects::traits::{ March, Trace };
+
+    fn unit() -> Sphere {
+        Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0, Material::blank())
+    }
+
+    #[test]
+    fn test_translate() {
+        let moved = Transformed::translate(unit(), Vec3::new(0.0, 0.0, -5.0));
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+
+        let (hit, distance, normal) = moved.trace(ray);
+        assert!(hit);
+        assert_eq!(distance, 4.0);
+        assert_eq!(normal, Vec3::new(0.0, 0.0, 1.0));
+
+        assert_eq!(moved.march(Vec3::new(0.0, 0.0, 0.0)), 4.0);
+    }
+
+    #[test]
+    fn test_scale() {
+        let scaled = Transformed::scale(unit(), Vec3::new(2.0, 2.0, 2.0));
+        let ray = Ray::new(Vec3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+
+        let (_, distance, _) = scaled.trace(ray);
+        assert_eq!(distance, 3.0);
+        assert_eq!(scaled.march(Vec3::new(0.0, 5.0, 0.0)), 3.0);
+    }
+}
@@ -0,0 +1,56 @@
+use serde::{ Serialize, Deserialize };
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::ray::Ray;
+use crate::structures::aabb::Aabb;
+use crate::structures::material::Material;
+use crate::objects::primitive::TracePrimitive;
+use crate::objects::traits::Trace;
+use crate::objects::mesh::intersect_triangle;
+
+// a single triangle, wound counterclockwise around its normal
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Triangle {
+    pub a: Vec3,
+    pub b: Vec3,
+    pub c: Vec3,
+    pub material: Material,
+}
+
+impl Triangle {
+    pub fn new(a: Vec3, b: Vec3, c: Vec3, material: Material) -> Triangle {
+        Triangle {
+            a: a,
+            b: b,
+            c: c,
+            material: material,
+        }
+    }
+}
+
+impl Trace for Triangle {
+    fn material(&self) -> Material { self.material }
+
+    fn bounds(&self) -> Option<Aabb> { Some(Aabb::around(&[self.a, self.b, self.c])) }
+
+    fn trace(&self, ray: Ray) -> (bool, Float, Vec3) {
+        let normal = (self.b - self.a).cross(&(self.c - self.a)).unit();
+
+        match intersect_triangle(&ray, self.a, self.b, self.c) {
+            Some((t, _, _)) => (true, t, normal),
+            None => (false, Float::MAX, normal),
+        }
+    }
+
+    fn tangent(&self, _point: Vec3, _normal: Vec3) -> Vec3 { (self.b - self.a).unit() }
+
+    // the barycentric coordinates of b and c
+    fn uv(&self, point: Vec3, _normal: Vec3) -> [Float; 2] {
+        let (ab, ac, ap) = (self.b - self.a, self.c - self.a, point - self.a);
+        let n = ab.cross(&ac);
+        [ap.cross(&ac).dot(&n) / n.length_squared(), ab.cross(&ap).dot(&n) / n.length_squared()]
+    }
+
+    fn primitive(&self) -> Option<TracePrimitive> { Some((*self).into()) }
+}
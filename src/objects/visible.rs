@@ -0,0 +1,135 @@
+use serde::{ Serialize, Deserialize };
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::ray::Ray;
+use crate::structures::aabb::Aabb;
+use crate::structures::report::Footprint;
+use crate::structures::material::Material;
+use crate::structures::packet::{ WideVec3, RayPacket, Lanes, LANES };
+use crate::objects::traits::{ March, Trace };
+
+// what a ray is out to find, which decides what it can hit
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RayKind {
+    Camera,   // straight from the lens
+    Indirect, // bounced off something, or a photon
+    Shadow,   // checking if a light can be seen
+}
+
+// which kinds of ray an object shows up for. lighting rigs hide lights
+// from the camera, or block light with things nobody sees.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Visibility {
+    pub camera: bool,
+    pub indirect: bool,
+    pub shadow: bool,
+}
+
+impl Visibility {
+    pub const ALL: Visibility = Visibility { camera: true, indirect: true, shadow: true };
+
+    // lights everything up without being seen
+    pub const CAMERA_INVISIBLE: Visibility = Visibility { camera: false, indirect: true, shadow: true };
+
+    // blocks light, but nothing sees or bounces off it
+    pub const SHADOW_ONLY: Visibility = Visibility { camera: false, indirect: false, shadow: true };
+
+    // seen, and casts shadows, but light doesn't bounce off it onto anything
+    pub const NO_INDIRECT: Visibility = Visibility { camera: true, indirect: false, shadow: true };
+
+    pub fn sees(&self, kind: RayKind) -> bool {
+        match kind {
+            RayKind::Camera => self.camera,
+            RayKind::Indirect => self.indirect,
+            RayKind::Shadow => self.shadow,
+        }
+    }
+}
+
+impl Default for Visibility {
+    fn default() -> Visibility { Visibility::ALL }
+}
+
+// wraps any object and hides it from some kinds of ray
+#[derive(Debug, Copy, Clone)]
+pub struct Visible<T> {
+    pub object: T,
+    pub visibility: Visibility,
+}
+
+impl<T> Visible<T> {
+    pub fn new(object: T, visibility: Visibility) -> Visible<T> {
+        Visible { object: object, visibility: visibility }
+    }
+}
+
+impl<T: March> March for Visible<T> {
+    fn material(&self) -> Material { self.object.material() }
+    fn march(&self, point: Vec3) -> Float { self.object.march(point) }
+    fn bounds(&self) -> Option<Aabb> { self.object.bounds() }
+    fn material_at(&self, point: Vec3) -> Material { self.object.material_at(point) }
+    fn normal(&self, point: Vec3) -> Vec3 { self.object.normal(point) }
+    fn tangent(&self, point: Vec3, normal: Vec3) -> Vec3 { self.object.tangent(point, normal) }
+    fn uv(&self, point: Vec3) -> [Float; 2] { self.object.uv(point) }
+    fn march_packet(&self, points: &WideVec3) -> Lanes { self.object.march_packet(points) }
+    fn visibility(&self) -> Visibility { self.visibility }
+    fn shadow_catcher(&self) -> bool { self.object.shadow_catcher() }
+    fn light_group(&self) -> Option<&str> { self.object.light_group() }
+    fn footprint(&self) -> Footprint { self.object.footprint() }
+
+    // the gpu preview only casts camera rays
+    fn wgsl(&self) -> Option<String> {
+        if self.visibility.camera { self.object.wgsl() } else { None }
+    }
+}
+
+impl<T: Trace> Trace for Visible<T> {
+    fn material(&self) -> Material { self.object.material() }
+    fn trace(&self, ray: Ray) -> (bool, Float, Vec3) { self.object.trace(ray) }
+    fn bounds(&self) -> Option<Aabb> { self.object.bounds() }
+    fn material_at(&self, point: Vec3, normal: Vec3) -> Material { self.object.material_at(point, normal) }
+    fn tangent(&self, point: Vec3, normal: Vec3) -> Vec3 { self.object.tangent(point, normal) }
+    fn uv(&self, point: Vec3, normal: Vec3) -> [Float; 2] { self.object.uv(point, normal) }
+    fn trace_packet(&self, packet: &RayPacket) -> [(bool, Float, Vec3); LANES] { self.object.trace_packet(packet) }
+    fn visibility(&self) -> Visibility { self.visibility }
+    fn shadow_catcher(&self) -> bool { self.object.shadow_catcher() }
+    fn light_group(&self) -> Option<&str> { self.object.light_group() }
+    fn footprint(&self) -> Footprint { self.object.footprint() }
+
+    fn wgsl(&self) -> Option<String> {
+        if self.visibility.camera { self.object.wgsl() } else { None }
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::{ Visible, Visibility, RayKind };
+    use crate::structures::vec3::Vec3;
+    use crate::structures::ray::Ray;
+    use crate::structures::material::Material;
+    use crate::structures::scene::Scene;
+    use crate::render::cast_ray;
+    use crate::objects::sphere::Sphere;
+
+    #[test]
+    fn test_visibility() {
+        let sphere = Sphere::new(Vec3::new(0.0, 0.0, -5.0), 1.0, Material::blank());
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+
+        let hidden = Scene::builder().trace(Visible::new(sphere, Visibility::CAMERA_INVISIBLE)).build();
+        assert!(!cast_ray(&hidden, ray, RayKind::Camera).hit);
+        assert!(cast_ray(&hidden, ray, RayKind::Indirect).hit);
+        assert!(hidden.occluded(ray.with_max(10.0)));
+
+        let blocker = Scene::builder().march(Visible::new(sphere, Visibility::SHADOW_ONLY)).build();
+        assert!(!cast_ray(&blocker, ray, RayKind::Camera).hit);
+        assert!(!cast_ray(&blocker, ray, RayKind::Indirect).hit);
+        assert!(blocker.occluded(ray.with_max(10.0)));
+
+        let seen = Scene::builder().march(Visible::new(sphere, Visibility { shadow: false, ..Visibility::ALL })).build();
+        assert!(cast_ray(&seen, ray, RayKind::Camera).hit);
+        assert!(!seen.occluded(ray.with_max(10.0)));
+    }
+}
@@ -0,0 +1,124 @@
+use std::sync::Arc;
+use rand::Rng;
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::ray::Ray;
+use crate::structures::noise::Fractal;
+use crate::objects::traits::March;
+
+// gives up on rays that wander this long between the pockets of a shape
+const MAX_STEPS: u32 = 512;
+const MAX_DISTANCE: Float = 1000.0;
+const EPSILON: Float = 0.002;
+
+// a cloud or smoke filling the inside of any distance field. density
+// ramps up from nothing at the surface to `density` at `falloff` deep,
+// and can be broken up further by a noise function returning 0 to 1.
+#[derive(Clone)]
+pub struct Volume {
+    pub shape: Arc<dyn March>,
+    pub density: Float, // the most it ever gets, also the majorant for tracking
+    pub falloff: Float,
+    pub color: Vec3,    // single scattering albedo
+    pub g: Float,       // henyey-greenstein asymmetry
+    pub noise: Option<Arc<dyn Fn(Vec3) -> Float + Send + Sync>>,
+}
+
+impl Volume {
+    pub fn new(shape: impl March + 'static, density: Float, falloff: Float, color: Vec3, g: Float) -> Volume {
+        Volume {
+            shape: Arc::new(shape),
+            density: density,
+            falloff: falloff,
+            color: color,
+            g: g.clamp(-0.99, 0.99),
+            noise: None,
+        }
+    }
+
+    pub fn with_noise(mut self, noise: impl Fn(Vec3) -> Float + Send + Sync + 'static) -> Volume {
+        self.noise = Some(Arc::new(noise));
+        return self;
+    }
+
+    // billowing like smoke or cloud, the fractal taken from -1 to 1 up to 0 to 1
+    pub fn with_fractal(self, fractal: Fractal) -> Volume {
+        return self.with_noise(move |point| fractal.sample(point) * 0.5 + 0.5);
+    }
+
+    pub fn density_at(&self, point: Vec3) -> Float {
+        let depth = -self.shape.march(point);
+        if depth <= 0.0 { return 0.0; }
+
+        let ramp = if self.falloff > 0.0 { (depth / self.falloff).min(1.0) } else { 1.0 };
+        let noise = self.noise.as_ref().map(|noise| noise(point).clamp(0.0, 1.0)).unwrap_or(1.0);
+
+        return self.density * ramp * noise;
+    }
+
+    // delta tracking: take free flights as if the whole shape were at full
+    // density, and accept each tentative collision in proportion to how
+    // dense it really is there. returns the first real collision before `max`.
+    // outside the shape it sphere traces to get back in instead.
+    pub fn collide(&self, ray: &Ray, max: Float, rng: &mut impl Rng) -> Option<Float> {
+        if self.density <= 0.0 { return None; }
+
+        let mut t = 0.0;
+
+        for _ in 0..MAX_STEPS {
+            let distance = self.shape.march(ray.point_at(&t));
+
+            if distance > EPSILON {
+                t += distance;
+            } else {
+                t += -(1.0 - rng.gen::<Float>()).ln() / self.density;
+                if t < max && rng.gen::<Float>() * self.density < self.density_at(ray.point_at(&t)) {
+                    return Some(t);
+                }
+            }
+
+            if t >= max || t >= MAX_DISTANCE { return None; }
+        }
+
+        return None;
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::Volume;
+    use crate::structures::float::Float;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::ray::Ray;
+    use crate::structures::material::Material;
+    use crate::structures::noise::Fractal;
+    use crate::objects::sphere::Sphere;
+
+    #[test]
+    fn test_volume() {
+        let ball = Sphere::new(Vec3::new(0.0, 0.0, -5.0), 1.0, Material::blank());
+        let cloud = Volume::new(ball, 50.0, 0.1, Vec3::new(1.0, 1.0, 1.0), 0.0);
+        let mut rng = rand::thread_rng();
+
+        assert_eq!(cloud.density_at(Vec3::new(0.0, 0.0, 0.0)), 0.0);
+        assert_eq!(cloud.density_at(Vec3::new(0.0, 0.0, -5.0)), 50.0);
+
+        // this thick it always stops just inside the surface
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let t = cloud.collide(&ray, Float::MAX, &mut rng).unwrap();
+        assert!(t > 4.0 && t < 6.0);
+
+        // and never through something in front of it
+        assert!(cloud.collide(&ray, 3.0, &mut rng).is_none());
+
+        let miss = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(cloud.collide(&miss, Float::MAX, &mut rng).is_none());
+
+        // broken up, it's thinner in places but never more than it was
+        let patchy = cloud.clone().with_fractal(Fractal { frequency: 4.0, ..Fractal::default() });
+        let inside: Vec<Float> = (0..50).map(|i| patchy.density_at(Vec3::new(0.0, 0.0, -5.5 + i as Float * 0.02))).collect();
+        assert!(inside.iter().all(|density| *density >= 0.0 && *density <= 50.0));
+        assert!(inside.iter().any(|density| *density < 40.0));
+    }
+}
@@ -0,0 +1,384 @@
+use std::mem::size_of_val;
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::ray::Ray;
+use crate::structures::material::Material;
+use crate::structures::aabb::Aabb;
+use crate::structures::report::Footprint;
+use crate::objects::traits::Trace;
+use crate::objects::voxels::{ Voxels, Cells };
+
+// colored cubes like Voxels, for worlds too big to keep a byte per cell
+// or to walk one cell at a time. the grid's a cube 2^depth cells a side,
+// split in eight over and over down to single voxels, and any block that's
+// all empty or all one color stops splitting. open air and solid ground
+// then cost one entry each however big they are, and rays jump straight
+// across empty blocks instead of stepping through every cell in them.
+#[derive(Debug, Clone)]
+pub struct VoxelOctree {
+    pub position: Vec3, // the minimum corner
+    pub size: Float,    // edge length of one voxel
+    pub depth: u32,     // levels of splitting, 2^depth voxels a side
+    pub palette: Vec<Material>,
+    nodes: Vec<[u32; 8]>, // the first is the whole grid, see Entry
+    free: Vec<u32>,       // nodes merged away, to be used again
+}
+
+// what a node's eight children are, by x + 2y + 4z for which half they're
+// in: 0 for empty, SOLID and a palette index for a block all one color, or
+// else another node. the last level down is only ever empty or solid.
+const SOLID: u32 = 1 << 31;
+
+enum Entry {
+    Empty,
+    Solid(u8),
+    Node(u32),
+}
+
+fn entry(value: u32) -> Entry {
+    if value == 0 { return Entry::Empty; }
+    if value & SOLID != 0 { return Entry::Solid((value & !SOLID) as u8); }
+    return Entry::Node(value);
+}
+
+fn solid(value: u8) -> u32 {
+    if value == 0 { 0 } else { SOLID | value as u32 }
+}
+
+impl VoxelOctree {
+    // all empty, at least 2 voxels a side
+    pub fn new(position: Vec3, size: Float, depth: u32, palette: Vec<Material>) -> VoxelOctree {
+        VoxelOctree {
+            position: position,
+            size: size,
+            depth: depth.clamp(1, 30),
+            palette: palette,
+            nodes: vec![[0; 8]],
+            free: vec![],
+        }
+    }
+
+    // the same cubes as a grid, in the smallest octree that holds it
+    pub fn from_voxels(voxels: &Voxels) -> VoxelOctree {
+        let longest = voxels.dimensions.iter().copied().max().unwrap_or(1).max(2);
+        let depth = usize::BITS - (longest - 1).leading_zeros();
+        let mut octree = VoxelOctree::new(voxels.position, voxels.size, depth, voxels.palette.clone());
+
+        match &voxels.cells {
+            Cells::Sparse(cells) => {
+                for (cell, value) in cells { octree.set(*cell, *value); }
+            },
+            Cells::Dense(_) => {
+                let [x, y, z] = voxels.dimensions;
+                for k in 0..z {
+                    for j in 0..y {
+                        for i in 0..x {
+                            let value = voxels.get([i, j, k]);
+                            if value != 0 { octree.set([i, j, k], value); }
+                        }
+                    }
+                }
+            },
+        }
+
+        return octree;
+    }
+
+    // voxels along each side
+    pub fn side(&self) -> usize {
+        1 << self.depth
+    }
+
+    // how many nodes it's split into, which is what it costs to keep
+    pub fn node_count(&self) -> usize {
+        self.nodes.len() - self.free.len()
+    }
+
+    pub fn get(&self, cell: [usize; 3]) -> u8 {
+        if cell.iter().any(|c| *c >= self.side()) { return 0; }
+        let cell = cell.map(|c| c as u32);
+
+        let mut node = 0;
+        for level in (0..self.depth).rev() {
+            match entry(self.nodes[node][child(cell, level)]) {
+                Entry::Empty => return 0,
+                Entry::Solid(value) => return value,
+                Entry::Node(next) => node = next as usize,
+            }
+        }
+        return 0;
+    }
+
+    pub fn set(&mut self, cell: [usize; 3], value: u8) {
+        self.fill(cell, cell.map(|c| c + 1), value);
+    }
+
+    // every voxel from `from` up to but not including `to`, in blocks as
+    // big as fit, so filling in ground or clearing out a room doesn't take
+    // a voxel at a time
+    pub fn fill(&mut self, from: [usize; 3], to: [usize; 3], value: u8) {
+        let side = self.side();
+        let from = from.map(|c| c.min(side) as u32);
+        let to = to.map(|c| c.min(side) as u32);
+        if (0..3).any(|axis| from[axis] >= to[axis]) { return; }
+
+        self.fill_node(0, [0; 3], side as u32, from, to, value);
+    }
+
+    fn fill_node(&mut self, node: usize, min: [u32; 3], size: u32, from: [u32; 3], to: [u32; 3], value: u8) {
+        let half = size / 2;
+
+        for index in 0..8 {
+            let corner: [u32; 3] = std::array::from_fn(|axis| min[axis] + (index >> axis & 1) as u32 * half);
+            let overlaps = (0..3).all(|axis| from[axis] < corner[axis] + half && to[axis] > corner[axis]);
+            if !overlaps { continue; }
+
+            let covered = (0..3).all(|axis| from[axis] <= corner[axis] && to[axis] >= corner[axis] + half);
+            if covered {
+                self.release(self.nodes[node][index]);
+                self.nodes[node][index] = solid(value);
+                continue;
+            }
+
+            // only part of the block changes, so it has to be split up,
+            // all eight the same as it was to start
+            let below = match entry(self.nodes[node][index]) {
+                Entry::Node(below) => below as usize,
+                _ => {
+                    let below = self.allocate([self.nodes[node][index]; 8]);
+                    self.nodes[node][index] = below as u32;
+                    below
+                },
+            };
+            self.fill_node(below, corner, half, from, to, value);
+
+            // and merged back if it's come out all the same
+            let children = self.nodes[below];
+            if children.iter().all(|child| *child == children[0]) && !matches!(entry(children[0]), Entry::Node(_)) {
+                self.nodes[node][index] = children[0];
+                self.free.push(below as u32);
+            }
+        }
+    }
+
+    fn allocate(&mut self, children: [u32; 8]) -> usize {
+        match self.free.pop() {
+            Some(index) => { self.nodes[index as usize] = children; index as usize },
+            None => { self.nodes.push(children); self.nodes.len() - 1 },
+        }
+    }
+
+    // a block being written over, with everything under it
+    fn release(&mut self, value: u32) {
+        if let Entry::Node(node) = entry(value) {
+            for child in self.nodes[node as usize] { self.release(child); }
+            self.free.push(node);
+        }
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        let side = self.side() as Float * self.size;
+        return Aabb::new(self.position, self.position + Vec3::new(side, side, side));
+    }
+
+    // the biggest block around `cell` that's all one thing, as its value,
+    // its minimum corner and how many voxels across it is
+    fn block(&self, cell: [u32; 3]) -> (u8, [u32; 3], u32) {
+        let mut node = 0;
+        for level in (0..self.depth).rev() {
+            let corner = cell.map(|c| c >> level << level);
+            match entry(self.nodes[node][child(cell, level)]) {
+                Entry::Empty => return (0, corner, 1 << level),
+                Entry::Solid(value) => return (value, corner, 1 << level),
+                Entry::Node(next) => node = next as usize,
+            }
+        }
+        return (0, cell, 1);
+    }
+}
+
+// which of a node's children `cell` is in, `level` levels above a voxel
+fn child(cell: [u32; 3], level: u32) -> usize {
+    ((cell[0] >> level & 1) | (cell[1] >> level & 1) << 1 | (cell[2] >> level & 1) << 2) as usize
+}
+
+impl Trace for VoxelOctree {
+    fn material(&self) -> Material {
+        self.palette.get(1).copied().unwrap_or_else(Material::blank)
+    }
+
+    fn bounds(&self) -> Option<Aabb> { Some(VoxelOctree::bounds(self)) }
+
+    // a few blocks on each level down, usually
+    fn footprint(&self) -> Footprint {
+        let bytes = size_of_val(&self.nodes[..]) + size_of_val(&self.free[..]) + size_of_val(&self.palette[..]);
+        return Footprint::new("voxel octree").with_bytes(bytes).with_cost(4.0 * self.depth.max(1) as Float);
+    }
+
+    // amanatides & woo like Voxels, only a block at a time: find the
+    // biggest block the ray's in that's all one thing, and if it's empty
+    // jump to where the ray leaves it. in voxels, so the blocks' edges are
+    // whole numbers, and the axis the ray last crossed is kept exactly on
+    // its edge so it can't land back in the block it left.
+    fn trace(&self, ray: Ray) -> (bool, Float, Vec3) {
+        let miss = (false, Float::MAX, Vec3::new(0.0, 1.0, 0.0));
+        let side = self.side() as Float;
+        let origin = (ray.origin - self.position) / self.size;
+        let direction = ray.direction / self.size;
+
+        // where it's in the grid at all
+        let (mut near, mut far, mut entry) = (0.0 as Float, Float::MAX, None);
+        for axis in 0..3 {
+            let d = direction.axis(axis);
+            if d == 0.0 {
+                if origin.axis(axis) < 0.0 || origin.axis(axis) >= side { return miss; }
+                continue;
+            }
+            let (t0, t1) = ((0.0 - origin.axis(axis)) / d, (side - origin.axis(axis)) / d);
+            let (t0, t1) = (t0.min(t1), t0.max(t1));
+            if t0 > near { near = t0; entry = Some(axis); }
+            far = far.min(t1);
+        }
+        if far < near { return miss; }
+
+        // the axis that was crossed last, and the edge it was crossed at
+        let mut crossed = entry.map(|axis| (axis, if direction.axis(axis) > 0.0 { 0.0 } else { side }));
+        let mut normal = Vec3::new(0.0, 0.0, 0.0);
+        if let Some(axis) = entry { set_axis(&mut normal, axis, -direction.axis(axis).signum()); }
+
+        // rays starting inside a voxel are leaving it, so it doesn't count
+        let mut skip = entry.is_none();
+        let mut t = near;
+
+        while t <= far {
+            let cell: [u32; 3] = std::array::from_fn(|axis| {
+                let d = direction.axis(axis);
+                let x = match crossed {
+                    Some((along, edge)) if along == axis => edge,
+                    _ => origin.axis(axis) + d * t,
+                };
+                // on an edge, the cell the ray's heading into
+                let c = if d < 0.0 && x == x.floor() { x - 1.0 } else { x.floor() };
+                c.clamp(0.0, side - 1.0) as u32
+            });
+
+            let (value, mut corner, mut size) = self.block(cell);
+            if value != 0 && !skip { return (true, t, normal); }
+            if value != 0 { (corner, size) = (cell, 1); }
+            skip = false;
+
+            // out the far side of the block
+            let mut leave = None;
+            for (axis, corner) in corner.iter().enumerate() {
+                let d = direction.axis(axis);
+                if d == 0.0 { continue; }
+                let edge = (corner + if d > 0.0 { size } else { 0 }) as Float;
+                let at = (edge - origin.axis(axis)) / d;
+                if leave.is_none_or(|(_, _, best)| at < best) { leave = Some((axis, edge, at)); }
+            }
+
+            let (axis, edge, at) = match leave {
+                Some(leave) => leave,
+                None => return miss,
+            };
+            if edge <= 0.0 && direction.axis(axis) < 0.0 || edge >= side && direction.axis(axis) > 0.0 { return miss; }
+
+            t = at.max(t);
+            crossed = Some((axis, edge));
+            normal = Vec3::new(0.0, 0.0, 0.0);
+            set_axis(&mut normal, axis, -direction.axis(axis).signum());
+        }
+
+        return miss;
+    }
+
+    // step back against the normal to land inside the voxel that was hit
+    fn material_at(&self, point: Vec3, normal: Vec3) -> Material {
+        let local = (point - normal * (self.size * 0.5) - self.position) / self.size;
+        let cell = [local.x.floor(), local.y.floor(), local.z.floor()];
+        if cell.iter().any(|c| *c < 0.0) { return self.material(); }
+
+        let value = self.get(cell.map(|c| c as usize));
+        if value == 0 { return self.material(); }
+        return self.palette.get(value as usize).copied().unwrap_or_else(|| self.material());
+    }
+}
+
+fn set_axis(v: &mut Vec3, axis: usize, value: Float) {
+    match axis {
+        0 => v.x = value,
+        1 => v.y = value,
+        _ => v.z = value,
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use rand::Rng;
+
+    use super::VoxelOctree;
+    use crate::structures::float::Float;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::ray::Ray;
+    use crate::structures::material::Material;
+    use crate::objects::traits::Trace;
+    use crate::objects::voxels::Voxels;
+
+    #[test]
+    fn test_voxel_octree() {
+        let palette = vec![Material::blank(), Material::blank(), Material { color: Vec3::new(1.0, 0.0, 0.0), ..Material::blank() }];
+
+        // a hill out of a grid, and the same as an octree
+        let mut grid = Voxels::dense(Vec3::new(-3.0, -1.0, -3.0), 0.5, [12, 7, 12], palette.clone());
+        for x in 0..12 {
+            for z in 0..12 {
+                let height = 1 + (x * z) % 5;
+                for y in 0..height { grid.set([x, y, z], if y + 1 == height { 2 } else { 1 }); }
+            }
+        }
+        let octree = VoxelOctree::from_voxels(&grid);
+        assert_eq!(octree.side(), 16);
+        assert_eq!(octree.get([3, 0, 4]), 1);
+        assert_eq!(octree.get([11, 6, 11]), 0);
+
+        // rays from all over hit the same place, the same way, as cell by cell
+        let mut rng = rand::thread_rng();
+        for _ in 0..2000 {
+            let origin = Vec3::new(rng.gen_range(-8.0, 8.0), rng.gen_range(-4.0, 8.0), rng.gen_range(-8.0, 8.0));
+            let toward = Vec3::new(rng.gen_range(-3.0, 3.0), rng.gen_range(-1.0, 2.5), rng.gen_range(-3.0, 3.0));
+            let ray = Ray::new(origin, (toward - origin).unit());
+
+            let (hit, distance, normal) = grid.trace(ray);
+            let (octree_hit, octree_distance, octree_normal) = octree.trace(ray);
+            assert_eq!(hit, octree_hit);
+            if !hit { continue; }
+            assert!((distance - octree_distance).abs() < 1e-3);
+            assert_eq!(normal, octree_normal);
+
+            let point = ray.point_at(&distance);
+            assert_eq!(grid.material_at(point, normal).color, octree.material_at(point, octree_normal).color);
+        }
+
+        // a world a million voxels a side, solid below the ground and clear
+        // above it, is only a handful of nodes, and rays still find it
+        let mut world = VoxelOctree::new(Vec3::new(0.0, 0.0, 0.0), 1.0, 20, palette);
+        let side = world.side();
+        world.fill([0, 0, 0], [side, side / 2, side], 1);
+        world.set([10, side / 2, 10], 2);
+        assert!(world.node_count() < 100);
+
+        let down = Ray::new(Vec3::new(10.5, side as Float - 1.0, 10.5), Vec3::new(0.0, -1.0, 0.0));
+        let (hit, distance, normal) = world.trace(down);
+        assert!(hit && (distance - (side as Float / 2.0 - 2.0)).abs() < 1e-3);
+        assert_eq!(normal, Vec3::new(0.0, 1.0, 0.0));
+        let aside = Ray::new(Vec3::new(20.5, side as Float - 1.0, 10.5), Vec3::new(0.0, -1.0, 0.0));
+        assert!((world.trace(aside).1 - (side as Float / 2.0 - 1.0)).abs() < 1e-3);
+
+        // and clearing the voxel back out merges it all up again
+        let before = world.node_count();
+        world.set([10, side / 2, 10], 0);
+        assert!(world.node_count() < before);
+        assert_eq!(world.get([10, side / 2, 10]), 0);
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+use std::mem::{ size_of, size_of_val };
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::ray::Ray;
+use crate::structures::material::Material;
+use crate::structures::aabb::Aabb;
+use crate::structures::report::Footprint;
+use crate::objects::traits::Trace;
+
+// a cell holds an index into the palette, 0 is empty
+#[derive(Debug, Clone)]
+pub enum Cells {
+    Dense(Vec<u8>),
+    Sparse(HashMap<[usize; 3], u8>),
+}
+
+// an axis aligned grid of colored cubes, traversed cell by cell
+#[derive(Debug, Clone)]
+pub struct Voxels {
+    pub position: Vec3, // the minimum corner
+    pub size: Float,    // edge length of one voxel
+    pub dimensions: [usize; 3],
+    pub cells: Cells,
+    pub palette: Vec<Material>,
+}
+
+impl Voxels {
+    // dense grids are fastest to walk, at a byte per cell
+    pub fn dense(position: Vec3, size: Float, dimensions: [usize; 3], palette: Vec<Material>) -> Voxels {
+        let [x, y, z] = dimensions;
+
+        Voxels {
+            position: position,
+            size: size,
+            dimensions: dimensions,
+            cells: Cells::Dense(vec![0; x * y * z]),
+            palette: palette,
+        }
+    }
+
+    // sparse grids only pay for filled cells
+    pub fn sparse(position: Vec3, size: Float, dimensions: [usize; 3], palette: Vec<Material>) -> Voxels {
+        Voxels {
+            position: position,
+            size: size,
+            dimensions: dimensions,
+            cells: Cells::Sparse(HashMap::new()),
+            palette: palette,
+        }
+    }
+
+    fn index(&self, cell: [usize; 3]) -> usize {
+        cell[0] + self.dimensions[0] * (cell[1] + self.dimensions[1] * cell[2])
+    }
+
+    pub fn get(&self, cell: [usize; 3]) -> u8 {
+        if (0..3).any(|axis| cell[axis] >= self.dimensions[axis]) { return 0; }
+
+        match &self.cells {
+            Cells::Dense(cells) => cells[self.index(cell)],
+            Cells::Sparse(cells) => *cells.get(&cell).unwrap_or(&0),
+        }
+    }
+
+    pub fn set(&mut self, cell: [usize; 3], value: u8) {
+        if (0..3).any(|axis| cell[axis] >= self.dimensions[axis]) { return; }
+
+        let index = self.index(cell);
+        match &mut self.cells {
+            Cells::Dense(cells) => cells[index] = value,
+            Cells::Sparse(cells) if value == 0 => { cells.remove(&cell); },
+            Cells::Sparse(cells) => { cells.insert(cell, value); },
+        }
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        let [x, y, z] = self.dimensions;
+        Aabb::new(self.position, self.position + Vec3::new(x as Float, y as Float, z as Float) * self.size)
+    }
+
+    // the cell a point falls in, which may be outside the grid
+    fn cell(&self, point: Vec3) -> [i64; 3] {
+        let local = (point - self.position) / self.size;
+        [local.x.floor() as i64, local.y.floor() as i64, local.z.floor() as i64]
+    }
+
+    fn filled(&self, cell: [i64; 3]) -> bool {
+        cell.iter().all(|c| *c >= 0) && self.get([cell[0] as usize, cell[1] as usize, cell[2] as usize]) != 0
+    }
+}
+
+impl Trace for Voxels {
+    fn material(&self) -> Material {
+        self.palette.get(1).copied().unwrap_or_else(Material::blank)
+    }
+
+    fn bounds(&self) -> Option<Aabb> { Some(Voxels::bounds(self)) }
+
+    // a ray can go through as many cells as the grid has along all three
+    // sides. sparse cells are counted with their keys, not the table.
+    fn footprint(&self) -> Footprint {
+        let cells = match &self.cells {
+            Cells::Dense(cells) => cells.len(),
+            Cells::Sparse(cells) => cells.len() * (size_of::<[usize; 3]>() + 1),
+        };
+
+        let steps = self.dimensions.iter().sum::<usize>() as Float;
+        return Footprint::new("voxels").with_bytes(cells + size_of_val(&self.palette[..])).with_cost(steps);
+    }
+
+    // amanatides & woo: step to whichever cell boundary is closest, one at a time
+    fn trace(&self, ray: Ray) -> (bool, Float, Vec3) {
+        let miss = (false, Float::MAX, Vec3::new(0.0, 1.0, 0.0));
+
+        let enter = match self.bounds().hit(&ray, Float::MAX) {
+            Some(t) => t,
+            None => return miss,
+        };
+
+        // nudge into the grid so the first cell is the right one
+        let start = ray.point_at(&(enter + 1e-9));
+        let mut cell = self.cell(start);
+        for (axis, c) in cell.iter_mut().enumerate() {
+            *c = (*c).max(0).min(self.dimensions[axis] as i64 - 1);
+        }
+
+        let mut step = [0i64; 3];
+        let mut next = [Float::MAX; 3];
+        let mut delta = [Float::MAX; 3];
+
+        for axis in 0..3 {
+            let d = ray.direction.axis(axis);
+            if d == 0.0 { continue; }
+
+            step[axis] = if d > 0.0 { 1 } else { -1 };
+            delta[axis] = (self.size / d).abs();
+
+            let boundary = self.position.axis(axis)
+                + (cell[axis] + if d > 0.0 { 1 } else { 0 }) as Float * self.size;
+            next[axis] = (boundary - ray.origin.axis(axis)) / d;
+        }
+
+        // rays starting inside a voxel are leaving it, so it doesn't count
+        let mut skip = enter == 0.0;
+        let mut distance = enter;
+        let mut normal = Vec3::new(0.0, 0.0, 0.0);
+
+        // the face the ray came in through is the slab it entered last
+        let bounds = self.bounds();
+        let entry = (0..3).filter(|axis| step[*axis] != 0).max_by(|a, b| {
+            let near = |axis: usize| {
+                let d = ray.direction.axis(axis);
+                let t0 = (bounds.min.axis(axis) - ray.origin.axis(axis)) / d;
+                let t1 = (bounds.max.axis(axis) - ray.origin.axis(axis)) / d;
+                t0.min(t1)
+            };
+            near(*a).partial_cmp(&near(*b)).unwrap_or(std::cmp::Ordering::Equal)
+        }).unwrap_or(1);
+        set_axis(&mut normal, entry, -(step[entry] as Float));
+
+        loop {
+            if !skip && self.filled(cell) {
+                return (true, distance, normal);
+            }
+            skip = false;
+
+            // closest boundary
+            let axis = if next[0] < next[1] {
+                if next[0] < next[2] { 0 } else { 2 }
+            } else if next[1] < next[2] { 1 } else { 2 };
+
+            cell[axis] += step[axis];
+            if cell[axis] < 0 || cell[axis] >= self.dimensions[axis] as i64 { return miss; }
+
+            distance = next[axis];
+            next[axis] += delta[axis];
+
+            normal = Vec3::new(0.0, 0.0, 0.0);
+            set_axis(&mut normal, axis, -(step[axis] as Float));
+        }
+    }
+
+    // step back against the normal to land inside the voxel that was hit
+    fn material_at(&self, point: Vec3, normal: Vec3) -> Material {
+        let cell = self.cell(point - normal * (self.size * 0.5));
+
+        if !self.filled(cell) { return self.material(); }
+
+        let value = self.get([cell[0] as usize, cell[1] as usize, cell[2] as usize]);
+        return self.palette.get(value as usize).copied().unwrap_or_else(|| self.material());
+    }
+}
+
+fn set_axis(v: &mut Vec3, axis: usize, value: Float) {
+    match axis {
+        0 => v.x = value,
+        1 => v.y = value,
+        _ => v.z = value,
+    }
+}
@@ -1,135 +1,499 @@
-use std::f64;
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::sync::atomic::{ AtomicUsize, Ordering };
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread;
 use rand::Rng;
+use rand::rngs::ThreadRng;
+use serde::{ Serialize, Deserialize };
 
+use crate::error;
+use crate::structures::float::Float;
+use crate::structures::float::consts::PI;
 use crate::structures::vec3::Vec3;
 use crate::structures::ray::Ray;
-use crate::structures::camera::Camera;
-use crate::structures::material::Material;
-use crate::structures::scene::Scene;
+use crate::structures::packet::{ RayPacket, Lanes, LANES };
+use crate::structures::camera::{ Camera, CameraSample, halton };
+use crate::structures::scene::{ Scene, Handle };
 use crate::structures::cast_result::CastResult;
+use crate::structures::medium::sample_phase;
+use crate::structures::environment_map::EnvironmentMap;
+use crate::structures::photon_map::Emitter;
+use crate::structures::frame::Frame;
+use crate::structures::ggx;
+use crate::structures::hair;
+use crate::structures::blue_noise;
+use crate::structures::stats::{ RenderStats, Timer, count, Counter };
+use crate::structures::tile::Tile;
+use crate::structures::matte::{ self, Matte };
+use crate::structures::top_level::TopLevel;
 use crate::objects::traits::{ March, Trace };
+use crate::objects::visible::RayKind;
+use crate::integrator::{ self, PathTracer, BidirectionalTracer };
+use crate::sppm;
 
 // constants
 const MAX_STEPS: u32 = 128;
 const MAX_DEPTH: u32 = 10;
 const MAX_BOUNCES: u32 = 3;
-const SAMPLES: u32 = 8;
-const EPSILON: f64 = 0.002;
-const AA: u32 = 16;
+const SPECULAR_BOUNCES: u32 = 4; // more on top, for mirrors, see Bounces
+const REGULARIZED: Float = 0.2; // roughness perfect mirrors get once Bounces::regularize is up
+pub(crate) const SAMPLES: u32 = 8; // paths per jittered camera ray
+const EPSILON: Float = 0.002;
+pub(crate) const AA: u32 = 16; // camera rays per pixel, unless the scene says otherwise
+const RELAXATION: Float = 1.6; // how much further than the safe distance the marcher steps
+const TILE: usize = 16; // pixels along each side of a tile
+const PYRAMID: [usize; 4] = [8, 4, 2, 1]; // pixel steps of the preview levels
+const DARK: Float = 1.0 / 255.0; // one step of an 8 bit png, noise dimmer than that doesn't show
+
+// how light gets from the lights to the camera
+#[derive(Debug, Copy, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub enum Integrator {
+    #[default]
+    Path,          // from the camera only
+    Bidirectional, // from both ends, needs scene.emitters
+    Sppm,          // photons from scene.emitters gathered where the camera sees, see sppm
+}
+
+impl Integrator {
+    // what works out each camera ray's light. sppm needs the whole picture
+    // at once, so a pixel on its own is path traced.
+    pub fn tracer(self) -> &'static dyn integrator::Integrator {
+        match self {
+            Integrator::Path | Integrator::Sppm => &PathTracer,
+            Integrator::Bidirectional => &BidirectionalTracer,
+        }
+    }
+}
+
+// what to do with samples that come back NaN or infinite. averaged in,
+// one of them is enough to ruin the whole pixel.
+#[derive(Debug, Copy, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub enum NanGuard {
+    Off,     // average them in anyway
+    #[default]
+    Discard, // leave them out of the average
+    Mark,    // paint the pixel magenta, to show where they come from
+    Log,     // leave them out, and tell scene.bad_samples the pixel and camera ray they came from
+}
+
+pub(crate) const MARKER: Vec3 = Vec3 { x: 1.0, y: 0.0, z: 1.0 };
+
+// how far paths go. a perfect mirror or glass turns a path without making
+// it any noisier, so those bounces come out of an allowance of their own
+// first, or a room full of mirrors goes black when `diffuse` is kept low
+// for speed. past `roulette` bounces of either kind, paths that carry
+// little light are ended at random, with the ones that survive made up
+// for it, and Material::importance keeps them going off what matters.
+//
+// past `regularize` bounces, if it's set, perfect mirrors are treated as a
+// little rough. a light seen only in a mirror from something diffuse is
+// then found by paths that bounce near enough to it, not just the ones
+// that hit it exactly, at the price of the reflection coming out blurred.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bounces {
+    pub diffuse: u32,  // every path gets these
+    pub specular: u32, // and these too, only off perfectly specular surfaces
+    pub roulette: u32, // bounces before russian roulette starts
+    pub regularize: Option<u32>, // bounces before mirrors are roughened
+}
+
+impl Default for Bounces {
+    fn default() -> Bounces {
+        Bounces { diffuse: MAX_BOUNCES, specular: SPECULAR_BOUNCES, roulette: MAX_BOUNCES, regularize: None }
+    }
+}
 
 // TODO: refactor rendering code into impl for scene, camera, and materials, etc.
-// TODO: results are trapped and rays will self-intersect, especially for metals
-fn hit_march(march: &Vec<Arc<dyn March>>, ray: Ray) -> CastResult {
+fn hit_march(march: &Vec<Arc<dyn March>>, top: Option<&TopLevel>, ray: Ray, kind: RayKind) -> CastResult {
+    // distance to the closest object, and which object that is
     let sdf = |point: Vec3| {
-        let mut min = f64::MAX;
-        let mut mat = Material::blank();
+        if let Some(top) = top {
+            return top.march(&point, |index| {
+                if march[index].visibility().sees(kind) { march[index].march(point) } else { Float::MAX }
+            });
+        }
+
+        let mut min = Float::MAX;
+        let mut closest = None;
 
-        for object in march.iter() {
+        for (index, object) in march.iter().enumerate() {
+            if !object.visibility().sees(kind) { continue; }
             let distance = object.march(point);
 
             if distance <= min {
                 min = distance;
-                mat = object.material();
+                closest = Some(index);
             }
         }
 
-        return (min, mat);
+        return (min, closest);
     };
 
-    let normal = |p: Vec3| {
-        Vec3::new(
-            sdf(Vec3::new(p.x + EPSILON, p.y, p.z)).0 - sdf(Vec3::new(p.x - EPSILON, p.y, p.z)).0,
-            sdf(Vec3::new(p.x, p.y + EPSILON, p.z)).0 - sdf(Vec3::new(p.x, p.y - EPSILON, p.z)).0,
-            sdf(Vec3::new(p.x, p.y, p.z + EPSILON)).0 - sdf(Vec3::new(p.x, p.y, p.z - EPSILON)).0,
-        ).unit()
-    };
+    // nothing before the start of the ray's range is looked at at all
+    let mut depth = ray.t_min;
+    count(Counter::Marches, 1);
+
+    // a ray leaving a surface starts within the hit threshold of it, so
+    // nothing counts until it's got clear
+    let mut escaped = false;
+
+    // one starting inside something marches the field turned inside out,
+    // so it stops at the wall on the way out instead of going through.
+    // the hit's normal still points out, cast_ray turns it around.
+    let mut sign = 0.0;
 
-    let mut depth = EPSILON;
+    // over-relaxed sphere tracing, from keinert et al: step further than is
+    // safe, and if the spheres at either end of a step stop overlapping the
+    // surface might have been skipped, so go back and step normally from then on
+    let mut relaxation = RELAXATION;
+    let mut previous = (depth, 0.0); // depth and distance before the last step
+
+    for _ in 0..MAX_STEPS {
+        if depth >= ray.t_max { break; }
 
-    for step in 0..MAX_STEPS {
         let point = ray.point_at(&depth);
-        let (distance, material) = sdf(point);
+        let (distance, closest) = sdf(point);
+        count(Counter::MarchSteps, 1);
+
+        // cone tracing: anything closer than half the ray's footprint fills
+        // the pixel anyway, so it's a hit. never tighter than EPSILON.
+        let threshold = EPSILON.max(ray.footprint(depth) * 0.5);
+
+        if sign == 0.0 { sign = if distance < -threshold { -1.0 } else { 1.0 }; }
+        let distance = distance * sign;
+
+        // landing inside is a step too far as well
+        let (last_depth, last_distance) = previous;
+        if relaxation > 1.0 && (distance < -threshold || distance.abs() + last_distance < depth - last_depth) {
+            relaxation = 1.0;
+            depth = last_depth + last_distance;
+            continue;
+        }
+
+        if distance > threshold { escaped = true; }
 
-        if distance <= EPSILON {
-            let normal = normal(point); // quick normal estimation
+        if escaped && distance <= threshold {
+            if let Some(index) = closest {
+                let normal = march[index].normal(point);
 
-            // let mut mat = Material::blank();
-            // mat.color = normal;
+                // let mut mat = Material::blank();
+                // mat.color = normal;
 
-            return CastResult::new(true, depth, normal, material);
+                let mut result = CastResult::new(true, depth, normal, march[index].material_at(point))
+                    .with_surface(march[index].tangent(point, normal), march[index].uv(point));
+                result.catcher = march[index].shadow_catcher();
+                result.object = Some(Handle::March(index));
+                return result;
+            }
         }
 
-        if distance >= MAX_DEPTH.into() {
+        // with nothing this close the ray's gone past the scene
+        if distance >= MAX_DEPTH as Float {
             break;
         }
 
-        depth += distance;
+        previous = (depth, distance.abs());
+        depth += distance.abs().max(threshold) * relaxation;
     }
     return CastResult::worst();
 }
 
-fn hit_trace(trace: &Vec<Arc<dyn Trace>>, ray: Ray) -> CastResult {
+// hit_march for a whole packet in lockstep. every lane takes the same steps
+// it would on its own, but the distance fields are evaluated for all of them
+// at once; lanes that are done just go along for the ride.
+fn hit_march_packet(march: &[Arc<dyn March>], packet: &RayPacket, kind: RayKind) -> [CastResult; LANES] {
+    let mut results = [CastResult::worst(); LANES];
+    let mut done = [false; LANES];
+
+    let (t_max, width, spread) = (packet.t_max.to_array(), packet.width.to_array(), packet.spread.to_array());
+    let mut depth = packet.t_min.to_array();
+    let mut escaped = [false; LANES];
+    let mut sign = [0.0; LANES]; // -1 for lanes starting inside, see hit_march
+    let mut relaxation = [RELAXATION; LANES];
+    let mut previous: [(Float, Float); LANES] = std::array::from_fn(|lane| (depth[lane], 0.0));
+    count(Counter::Marches, LANES as u64);
+
+    for _ in 0..MAX_STEPS {
+        let points = packet.point_at(&Lanes::new(depth));
+
+        let mut min = [Float::MAX; LANES];
+        let mut closest = [None; LANES];
+        for (index, object) in march.iter().enumerate() {
+            if !object.visibility().sees(kind) { continue; }
+            let distances = object.march_packet(&points).to_array();
+
+            for lane in 0..LANES {
+                if distances[lane] <= min[lane] {
+                    min[lane] = distances[lane];
+                    closest[lane] = Some(index);
+                }
+            }
+        }
+
+        for lane in 0..LANES {
+            if done[lane] { continue; }
+            count(Counter::MarchSteps, 1);
+
+            if depth[lane] >= t_max[lane] {
+                done[lane] = true;
+                continue;
+            }
+
+            let threshold = EPSILON.max((width[lane] + spread[lane] * depth[lane]) * 0.5);
+            if sign[lane] == 0.0 { sign[lane] = if min[lane] < -threshold { -1.0 } else { 1.0 }; }
+
+            let distance = min[lane] * sign[lane];
+            let (last_depth, last_distance) = previous[lane];
+            if relaxation[lane] > 1.0 && (distance < -threshold || distance.abs() + last_distance < depth[lane] - last_depth) {
+                relaxation[lane] = 1.0;
+                depth[lane] = last_depth + last_distance;
+                continue;
+            }
+
+            if distance > threshold { escaped[lane] = true; }
+
+            if escaped[lane] && distance <= threshold {
+                if let Some(index) = closest[lane] {
+                    let point = points.at(lane);
+                    let normal = march[index].normal(point);
+                    results[lane] = CastResult::new(true, depth[lane], normal, march[index].material_at(point))
+                        .with_surface(march[index].tangent(point, normal), march[index].uv(point));
+                    results[lane].catcher = march[index].shadow_catcher();
+                    results[lane].object = Some(Handle::March(index));
+                    done[lane] = true;
+                    continue;
+                }
+            }
+
+            if distance >= MAX_DEPTH as Float {
+                done[lane] = true;
+                continue;
+            }
+
+            previous[lane] = (depth[lane], distance.abs());
+            depth[lane] += distance.abs().max(threshold) * relaxation[lane];
+        }
+
+        if done.iter().all(|done| *done) { break; }
+    }
+
+    return results;
+}
+
+// whether anything marched comes within the hit threshold inside the ray's
+// range. no closest object, normal or material, and it stops at the first.
+pub(crate) fn occluded_march(march: &[Arc<dyn March>], ray: Ray) -> bool {
+    if march.is_empty() { return false; }
+
+    let mut depth = ray.t_min;
+    let mut escaped = false;
+    let mut sign = 0.0; // -1 starting inside, see hit_march
+    count(Counter::Marches, 1);
+
+    for _ in 0..MAX_STEPS {
+        let point = ray.point_at(&depth);
+        count(Counter::MarchSteps, 1);
+
+        let mut min = Float::MAX;
+        for object in march {
+            if !object.visibility().sees(RayKind::Shadow) { continue; }
+            min = min.min(object.march(point));
+            // the closest so far can only get closer, but inside out the
+            // whole field is needed first
+            if escaped && sign > 0.0 && min <= EPSILON { return true; }
+        }
+
+        if sign == 0.0 { sign = if min < -EPSILON { -1.0 } else { 1.0 }; }
+        let min = min * sign;
+        if escaped && min <= EPSILON { return true; }
+        if min > EPSILON { escaped = true; }
+
+        depth += min.abs().max(EPSILON);
+        if depth >= ray.t_max || min >= MAX_DEPTH as Float { return false; }
+    }
+
+    return false;
+}
+
+// objects only give back their closest hit, so they're asked from the start
+// of the ray's range. hits closer than EPSILON to it are the surface the ray
+// is leaving. with a top level only the objects near the ray are asked.
+fn hit_trace(trace: &[Arc<dyn Trace>], top: Option<&TopLevel>, ray: Ray, kind: RayKind) -> CastResult {
     let mut best = CastResult::worst();
+    let mut closest = None;
+    let start = ray.from_start();
 
-    for object in trace.iter() {
-        let (hit, distance, normal) = object.trace(ray);
+    // how far along `start` an object's hit is, if it's the closest yet
+    let mut test = |index: usize| {
+        let object = &trace[index];
+        if !object.visibility().sees(kind) { return None; }
+        let (hit, distance, normal) = object.trace(start);
+        let hit = hit && distance > EPSILON && start.contains(distance);
+        let along = distance;
+        let distance = distance + ray.t_min;
 
-        if hit && distance > EPSILON && (best.hit == false || distance <= best.distance) {
-            best = CastResult::new(hit, distance, normal, object.material());
+        if hit && (!best.hit || distance <= best.distance) && !culled(object, &ray, distance, normal) {
+            best = CastResult::new(hit, distance, normal, best.material);
+            closest = Some(index);
+            return Some(along);
         }
+
+        return None;
+    };
+
+    match top {
+        Some(top) => { top.trace(&start, test); },
+        None => (0..trace.len()).for_each(|index| { test(index); }),
+    }
+
+    // only look the material up for the winner
+    if let Some(index) = closest {
+        let point = ray.point_at(&best.distance);
+        best = best.with_surface(trace[index].tangent(point, best.normal), trace[index].uv(point, best.normal));
+        best.material = trace[index].material_at(point, best.normal);
+        best.catcher = trace[index].shadow_catcher();
+        best.object = Some(Handle::Trace(index));
     }
 
     return best;
 }
 
-fn cast_ray(scene: &Scene, ray: Ray) -> CastResult {
-    let march = hit_march(&scene.march, ray);
-    let trace = hit_trace(&scene.trace, ray);
+fn hit_trace_packet(trace: &[Arc<dyn Trace>], packet: &RayPacket, kind: RayKind) -> [CastResult; LANES] {
+    let mut best = [CastResult::worst(); LANES];
+    let mut closest = [None; LANES];
+    let start = RayPacket::new(std::array::from_fn(|lane| packet.ray(lane).from_start()));
+
+    for (index, object) in trace.iter().enumerate() {
+        if !object.visibility().sees(kind) { continue; }
+        let hits = object.trace_packet(&start);
+
+        for lane in 0..LANES {
+            let (hit, distance, normal) = hits[lane];
+            let hit = hit && distance > EPSILON && start.ray(lane).contains(distance);
+            let distance = distance + packet.t_min.as_array_ref()[lane];
+            let ray = packet.ray(lane);
+
+            if hit && (!best[lane].hit || distance <= best[lane].distance) && !culled(object, &ray, distance, normal) {
+                best[lane] = CastResult::new(hit, distance, normal, best[lane].material);
+                closest[lane] = Some(index);
+            }
+        }
+    }
+
+    for lane in 0..LANES {
+        if let Some(index) = closest[lane] {
+            let point = packet.ray(lane).point_at(&best[lane].distance);
+            best[lane] = best[lane].with_surface(trace[index].tangent(point, best[lane].normal), trace[index].uv(point, best[lane].normal));
+            best[lane].material = trace[index].material_at(point, best[lane].normal);
+            best[lane].catcher = trace[index].shadow_catcher();
+            best[lane].object = Some(Handle::Trace(index));
+        }
+    }
+
+    return best;
+}
+
+// whether a hit is on the back of a one sided surface, which rays go
+// straight through, see Material::two_sided
+pub(crate) fn culled(object: &Arc<dyn Trace>, ray: &Ray, distance: Float, normal: Vec3) -> bool {
+    normal.dot(&ray.direction) > 0.0 && !object.material_at(ray.point_at(&distance), normal).two_sided
+}
+
+// the closer of the two, with its normal turned back along the ray so both
+// sides of a surface shade and bounce the same way. front_face says
+// which side it was.
+fn closest(march: CastResult, trace: CastResult, ray: &Ray) -> CastResult {
+    let mut closest = if trace.hit && !march.hit || trace.distance <= march.distance { trace } else { march };
+
+    closest.footprint = ray.footprint(closest.distance);
+    closest.front_face = closest.normal.dot(&ray.direction) <= 0.0;
+    if !closest.front_face {
+        // the bitangent too, so the frame stays right handed
+        closest.normal = closest.normal * -1.0;
+        closest.bitangent = closest.bitangent * -1.0;
+    }
+
+    return closest;
+}
+
+// objects hidden from `kind` are skipped
+pub(crate) fn cast_ray(scene: &Scene, ray: Ray, kind: RayKind) -> CastResult {
+    count(Counter::Rays, 1);
+    let top = scene.top_level.as_ref().filter(|top| top.fits(scene));
+    let march = hit_march(&scene.march, top, ray, kind);
+    let trace = hit_trace(&scene.trace, top, ray, kind);
 
     // nothing was hit, so return the sky
     if !march.hit && !trace.hit {
-        return CastResult::worst();
+        return CastResult::miss(scene.sky(ray.direction));
     }
 
-    if  trace.hit && !march.hit || trace.distance <= march.distance {
-        return trace;
+    return closest(march, trace, &ray);
+}
+
+// cast_ray for up to LANES rays at once, for coherent ones like camera rays.
+// the lanes go through the scene's lists together, so with a top level it's
+// quicker to let each ray find its own way down it.
+pub(crate) fn cast_packet(scene: &Scene, rays: &[Ray], kind: RayKind) -> Vec<CastResult> {
+    if scene.top_level.as_ref().is_some_and(|top| top.fits(scene)) {
+        return rays.iter().map(|ray| cast_ray(scene, *ray, kind)).collect();
     }
 
-    return march;
+    count(Counter::Rays, rays.len() as u64);
+    let packet = RayPacket::from_slice(rays);
+    let march = hit_march_packet(&scene.march, &packet, kind);
+    let trace = hit_trace_packet(&scene.trace, &packet, kind);
+
+    return rays.iter().enumerate().map(|(lane, ray)| {
+        let (march, trace) = (march[lane], trace[lane]);
+        if !march.hit && !trace.hit { return CastResult::miss(scene.sky(ray.direction)); }
+
+        return closest(march, trace, ray);
+    }).collect();
+}
+
+// where to start a ray leaving a surface, pushed off it on the side it's
+// heading. marched hits land anywhere within EPSILON of the surface so
+// this clears twice that, growing far from the origin where floats coarsen.
+pub(crate) fn offset(position: Vec3, normal: Vec3, direction: Vec3) -> Vec3 {
+    let distance = 2.0 * EPSILON * (1.0 + position.length() * 0.0001);
+    let side = if direction.dot(&normal) < 0.0 { -1.0 } else { 1.0 };
+
+    return position + normal * (distance * side);
 }
 
-fn sample_sphere() -> Vec3 {
-    let mut rng = rand::thread_rng();
+fn sample_sphere(rng: &mut impl Rng) -> Vec3 {
     let mut point: Vec3 = Vec3::max();
 
     // sample point in unit cube, check if in unit sphere
     while point.length_squared() >= 1.0 {
         point = Vec3::new(
-            rng.gen::<f64>() * 2.0 - 1.0,
-            rng.gen::<f64>() * 2.0 - 1.0,
-            rng.gen::<f64>() * 2.0 - 1.0,
+            rng.gen::<Float>() * 2.0 - 1.0,
+            rng.gen::<Float>() * 2.0 - 1.0,
+            rng.gen::<Float>() * 2.0 - 1.0,
          );
     }
 
     return point;
 }
 
-fn reflect(v: Vec3, n: Vec3) -> Vec3 {
+pub(crate) fn reflect(v: Vec3, n: Vec3) -> Vec3 {
     return v - 2.0 * v.dot(&n) * n;
 }
 
-fn fresnel(cosine: f64, ri: f64) -> f64 {
-    let mut r0: f64 = (1.0 - ri)/(1.0 + ri);
+pub(crate) fn fresnel(cosine: Float, ri: Float) -> Float {
+    let mut r0: Float = (1.0 - ri)/(1.0 + ri);
     r0 = r0*r0;
     return r0 + (1.0-r0)*(1.0-cosine).powi(5);
 }
 
-fn refract(v: &Vec3, n: &Vec3, ni_over_nt: f64, refracted: &mut Vec3) -> bool {
+pub(crate) fn refract(v: &Vec3, n: &Vec3, ni_over_nt: Float, refracted: &mut Vec3) -> bool {
     let uv: Vec3 = v.unit();
-    let dt: f64 = uv.dot(&n);
+    let dt: Float = uv.dot(n);
 
-    let discriminant: f64 = 1.0 - ni_over_nt*ni_over_nt*(1.0 - dt*dt);
+    let discriminant: Float = 1.0 - ni_over_nt*ni_over_nt*(1.0 - dt*dt);
     if discriminant > 0.0 {
         *refracted = ni_over_nt*(uv - *n*dt) - *n*((discriminant).sqrt());
         return true;
@@ -138,128 +502,1391 @@ fn refract(v: &Vec3, n: &Vec3, ni_over_nt: f64, refracted: &mut Vec3) -> bool {
     }
 }
 
-// simplify
-fn color(scene: &Scene, ray: Ray, bounce: u32, samples: u32) -> Vec3 {
-    let (hit, distance, normal, material) = cast_ray(&scene, ray).unpack();
+// where light that made it back along a path came from, for light groups
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum Source {
+    Object(Handle), // something glowing
+    Environment,    // the sky, or what a shadow catcher shows of it
+    Caustics,       // the photon map, which doesn't keep track
+    Emitter,        // one of scene.emitters with nothing in the scene glowing for it
+}
+
+// follows a single path, picking one way to bounce at each surface and
+// carrying how much of the light makes it back as the throughput.
+// `first` is what the ray hits, if that's been cast already.
+pub(crate) fn color(scene: &Scene, ray: Ray, first: Option<CastResult>, bounces: Bounces, rng: &mut impl Rng) -> Vec3 {
+    let mut radiance = Vec3::new(0.0, 0.0, 0.0);
+    path(scene, ray, first, bounces, rng, &mut |_, light| radiance = radiance + light);
+    return radiance;
+}
 
-    // nothing hit, return the sky
-    if !hit || bounce <= 0 {
-        return material.color * material.emission;
+// color, only handing each bit of light to `emit` along with where it's from
+fn path(scene: &Scene, ray: Ray, first: Option<CastResult>, bounces: Bounces, rng: &mut impl Rng, emit: &mut impl FnMut(Source, Vec3)) {
+    let mut state = PathState::new(ray);
+
+    loop {
+        let cast = match (first, state.bounce) {
+            (Some(first), 0) => first,
+            (_, 0) => cast_ray(scene, state.ray, RayKind::Camera),
+            _ => cast_ray(scene, state.ray, RayKind::Indirect),
+        };
+        if !step(scene, &mut state, cast, bounces, rng, emit) { break; }
     }
+}
 
-    // return (normal + 1.0) * 0.5;
+// how far along a path is between one surface and the next, so it can be
+// put down after a bounce and picked up again once its ray's been cast,
+// see wavefront
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct PathState {
+    pub ray: Ray, // where it goes next
+    pub bounce: u32,
+    throughput: Vec3,
+    // bounces taken so far out of bounces.diffuse, and out of bounces.specular
+    spent: u32,
+    free: u32,
+    // how likely the last bounce was to go the way it did, if the sky and
+    // the emitters were looked for straight from there too, see sky_light
+    // and emitter_light. zero otherwise.
+    lit_pdf: Float,
+}
 
-    let     position     = ray.point_at(&distance);
-    let mut diffuse      = Vec3::new(0.0, 0.0, 0.0);
-    let mut specular     = Vec3::new(0.0, 0.0, 0.0);
-    let mut transmission = Vec3::new(0.0, 0.0, 0.0);
+impl PathState {
+    pub fn new(ray: Ray) -> PathState {
+        PathState { ray: ray, bounce: 0, throughput: Vec3::new(1.0, 1.0, 1.0), spent: 0, free: 0, lit_pdf: 0.0 }
+    }
+}
 
-    // diffuse
-    for _ in 0..samples {
-        let scatter = Ray::through(position, (normal + sample_sphere()) - position);
-        let sample = color(&scene, scatter, (bounce - 1), 1); // (samples / 2).max(1)); // only take one sample
+// one bounce of path: the light `cast`, whatever the path's ray hit, sends
+// back along it, then which way the path goes on. false once it's over.
+pub(crate) fn step(scene: &Scene, state: &mut PathState, cast: CastResult, bounces: Bounces, rng: &mut impl Rng, emit: &mut impl FnMut(Source, Vec3)) -> bool {
+    let PathState { mut ray, bounce, mut throughput, mut spent, mut free, lit_pdf } = *state;
+    let (hit, distance, normal, material) = cast.unpack();
 
-        diffuse = diffuse + material.color * sample;
+    // the ray might scatter in a medium or volume before it gets there. free
+    // flights are sampled in proportion to transmittance, so the attenuation
+    // is accounted for just by how often this happens.
+    let mut nearest = distance;
+    let mut event = None; // (albedo, phase asymmetry)
+
+    if let Some(medium) = &scene.medium {
+        let travel = medium.sample_distance(rng.gen());
+        if travel < nearest {
+            nearest = travel;
+            event = Some((Vec3::new(1.0, 1.0, 1.0) * medium.albedo(), medium.g));
+        }
+    }
+
+    for volume in &scene.volumes {
+        if let Some(travel) = volume.collide(&ray, nearest, rng) {
+            nearest = travel;
+            event = Some((volume.color, volume.g));
+        }
     }
 
-    diffuse = diffuse / (samples as f64);
+    if let Some((albedo, g)) = event {
+        if spent == bounces.diffuse { return false; }
+        spent += 1;
+
+        throughput = throughput * albedo;
+        ray = Ray::new(ray.point_at(&nearest), sample_phase(g, ray.direction, [rng.gen(), rng.gen()]));
+        *state = PathState { ray: ray, bounce: bounce + 1, throughput: throughput, spent: spent, free: free, lit_pdf: 0.0 };
+        return true;
+    }
+
+    // the camera sees through shadow catchers, see catch
+    if bounce == 0 && cast.catcher {
+        emit(Source::Environment, throughput * scene.sky(ray.direction) * catch(scene, ray.point_at(&distance), normal, rng));
+        return false;
+    }
 
-    // specular
-    if material.roughness == 0.0 {
-        let scatter = Ray::new(position, reflect(ray.direction, normal).unit());
-        specular = color(&scene, scatter, (bounce - 1), samples);
+    // the sky, or a light. where it was looked for straight from the last
+    // bounce as well, this is only its share, see sky_light and emitter_light
+    if !hit || material.emission > 0.0 {
+        let share = match (&scene.environment_map, hit) {
+            _ if lit_pdf <= 0.0 => 1.0,
+            (Some(map), false) => power(lit_pdf, map.pdf(ray.direction)),
+            (_, true) => power(lit_pdf, emitter_pdf(scene, ray.origin, ray.point_at(&distance))),
+            _ => 1.0,
+        };
+        emit(cast.object.map_or(Source::Environment, Source::Object), throughput * material.color * material.emitted(cast.front_face) * share);
+    }
+
+    if !hit { return false; }
+
+    let position = ray.point_at(&distance);
+
+    // how much each lobe contributes, the same pbr-ish mix as always:
+    // diffuse under a specular layer, lerped with metal, lerped with emission.
+    // the diffuse only gets what the layer doesn't reflect, see furnace.
+    let surface  = (1.0 - material.emission).max(0.0);
+    let diffuse  = (1.0 - material.transmission) * (1.0 - material.metallic) * (1.0 - material.specular).max(0.0) * surface;
+    let specular = (Vec3::new(1.0, 1.0, 1.0) * material.specular * (1.0 - material.metallic)
+                 + material.color * material.metallic) * surface;
+    // TODO: transmission, it's still black. sppm and the photon map refract,
+    // with the priorities of overlapping glass, see Interiors
+
+    // caustics are only looked up where the camera sees them. paths that
+    // happen to find them on their own get counted twice, but that's rare.
+    if let (Some(caustics), 0) = (&scene.caustics, bounce) {
+        let irradiance = caustics.irradiance(position, normal);
+        emit(Source::Caustics, throughput * material.color * diffuse * irradiance / PI);
+    }
+
+    // follow one lobe, picked in proportion to how much light it
+    // reflects, and divide by that chance so the mix stays the same
+    let diffuse = material.color * diffuse;
+    let weights = [diffuse.luminance().max(0.0), specular.luminance().max(0.0)];
+    let total = weights[0] + weights[1];
+    if total <= 0.0 { return false; }
+
+    let chance = weights[0] / total;
+
+    // a small bright sun in an environment map, or a small light, is rarely
+    // bounced into, so diffuse surfaces look for them as well. media would
+    // need the light through them worked out on the way, so it's left to
+    // bouncing there.
+    let looked = chance > 0.0 && !material.hair && spent < bounces.diffuse && scene.medium.is_none() && scene.volumes.is_empty();
+    if looked {
+        if let Some(map) = &scene.environment_map {
+            emit(Source::Environment, throughput * diffuse * sky_light(scene, map, position, normal, chance, rng));
+        }
+        if !scene.emitters.is_empty() {
+            let (source, light) = emitter_light(scene, position, normal, chance, rng);
+            emit(source, throughput * diffuse * light);
+        }
+    }
+    let mut lit_pdf = 0.0;
+
+    let roughness = match bounces.regularize {
+        Some(after) if bounce >= after => material.roughness.max(REGULARIZED),
+        _ => material.roughness,
+    };
+
+    if material.hair {
+        // fibres scatter along themselves, whatever the mix says
+        if spent == bounces.diffuse { return false; }
+        spent += 1;
+
+        let (direction, weight) = hair::sample(cast.tangent, ray.direction, &material, [rng.gen(), rng.gen(), rng.gen()]);
+        throughput = throughput * weight * surface;
+        ray = Ray::new(offset(position, normal, direction), direction);
+    } else if rng.gen::<Float>() < chance {
+        if spent == bounces.diffuse { return false; }
+        spent += 1;
+
+        // on the sphere around the normal's tip, which is cosine weighted
+        throughput = throughput * diffuse / chance;
+        let direction = (normal + sample_sphere(rng).unit()).unit();
+        ray = Ray::new(offset(position, normal, direction), direction);
+        if looked { lit_pdf = chance * normal.dot(&direction).max(0.0) / PI; }
+    } else if roughness > 0.0 {
+        // rough, the reflection spreads out over the ggx lobe, and it
+        // counts as a bounce like diffuse ones do
+        if spent == bounces.diffuse { return false; }
+        spent += 1;
+
+        let (direction, masking) = match ggx::sample(&cast.frame(), ray.direction * -1.0, roughness, [rng.gen(), rng.gen()]) {
+            Some(sampled) => sampled,
+            None => return false,
+        };
+
+        throughput = throughput * specular * masking / (1.0 - chance);
+        ray = Ray::new(offset(position, normal, direction), direction);
     } else {
-        for _ in 0..samples {
-            let scatter = Ray::new(position, reflect(ray.direction, normal).unit());
-            // let scatter = Ray::through(
-            //     position,
-            //     (reflect(ray.direction, normal) + sample_sphere() * material.roughness) - position,
-            // );
+        // a mirror keeps the cone going from as wide as it got, so what's
+        // seen in it is filtered like what's seen directly. curvature
+        // is left out. diffuse and rough bounces scatter too widely for
+        // a footprint to mean much, so those go on as thin rays.
+        if free < bounces.specular {
+            free += 1;
+        } else if spent < bounces.diffuse {
+            spent += 1;
+        } else {
+            return false;
+        }
+
+        throughput = throughput * specular / (1.0 - chance);
+        let direction = reflect(ray.direction, normal).unit();
+        ray = Ray::new(offset(position, normal, direction), direction)
+            .with_width(cast.footprint)
+            .with_spread(ray.spread);
+    }
+
+    if spent + free > bounces.roulette {
+        let survival = (throughput.x.max(throughput.y).max(throughput.z) * material.importance).min(1.0);
+        if survival <= 0.0 || rng.gen::<Float>() >= survival { return false; }
+        throughput = throughput / survival;
+    }
+
+    *state = PathState { ray: ray, bounce: bounce + 1, throughput: throughput, spent: spent, free: free, lit_pdf: lit_pdf };
+    return true;
+}
+
+// the power heuristic: the share of what two ways of finding the same
+// light found that goes to the one that picks it with density `own`
+pub(crate) fn power(own: Float, other: Float) -> Float {
+    return own * own / (own * own + other * other);
+}
+
+// light from an environment map straight onto `position`, picked by how
+// bright the map is, for a diffuse surface to multiply by its color. its
+// share is what's left by bouncing into it with `chance` times the cosine
+// over pi, see step.
+pub(crate) fn sky_light(scene: &Scene, map: &EnvironmentMap, position: Vec3, normal: Vec3, chance: Float, rng: &mut impl Rng) -> Vec3 {
+    let black = Vec3::new(0.0, 0.0, 0.0);
+    let (direction, light, pdf) = match map.sample([rng.gen(), rng.gen(), rng.gen()]) {
+        Some(sampled) => sampled,
+        None => return black,
+    };
+
+    let cosine = normal.dot(&direction);
+    if cosine <= 0.0 || pdf <= 0.0 { return black; }
+    if scene.occluded(Ray::new(offset(position, normal, direction), direction)) { return black; }
+
+    return light * (cosine / PI * power(pdf, chance * cosine / PI) / pdf);
+}
+
+// which of scene.emitters to look for from `point`, and the chance of it:
+// by scene.light_tree if there's one that fits, evenly otherwise
+pub(crate) fn pick_emitter(scene: &Scene, point: Vec3, u: Float) -> (usize, Float) {
+    let count = scene.emitters.len();
+    return match scene.light_tree.as_ref().filter(|tree| tree.fits(&scene.emitters)) {
+        Some(tree) => tree.sample(point, u),
+        None => (((u * count as Float) as usize).min(count - 1), 1.0 / count as Float),
+    };
+}
+
+// the cosine of the widest angle a sphere takes up seen from `point`, and
+// the density of picking a direction evenly within that cone. none from
+// inside it.
+fn cone(point: Vec3, emitter: &Emitter) -> Option<(Float, Float)> {
+    let d2 = (emitter.position - point).length_squared();
+    let r2 = emitter.radius * emitter.radius;
+    if d2 <= r2 || r2 <= 0.0 { return None; }
+
+    let cos_max = (1.0 - r2 / d2).max(0.0).sqrt();
+    return Some((cos_max, 1.0 / (2.0 * PI * (1.0 - cos_max))));
+}
+
+// the emitter whose sphere `point` is on, if any is near enough to count
+fn emitter_at(scene: &Scene, point: Vec3) -> Option<usize> {
+    let off = |emitter: &Emitter| ((point - emitter.position).length() - emitter.radius).abs();
+    return scene.emitters.iter().enumerate()
+        .filter(|(_, emitter)| emitter.radius > 0.0 && off(emitter) <= touching(emitter))
+        .min_by(|(_, a), (_, b)| off(a).total_cmp(&off(b)))
+        .map(|(index, _)| index);
+}
+
+// how far off an emitter's sphere a surface can be found and still be it
+fn touching(emitter: &Emitter) -> Float {
+    0.01 * emitter.radius + 4.0 * EPSILON
+}
+
+// the density emitter_light finds `point` with from `from`, zero if it's
+// not on any of scene.emitters
+pub(crate) fn emitter_pdf(scene: &Scene, from: Vec3, point: Vec3) -> Float {
+    let index = match emitter_at(scene, point) {
+        Some(index) => index,
+        None => return 0.0,
+    };
+
+    let picked = match scene.light_tree.as_ref().filter(|tree| tree.fits(&scene.emitters)) {
+        Some(tree) => tree.probability(from, index),
+        None => 1.0 / scene.emitters.len() as Float,
+    };
+
+    return cone(from, &scene.emitters[index]).map_or(0.0, |(_, pdf)| picked * pdf);
+}
+
+// light from one of scene.emitters straight onto `position`, for a diffuse
+// surface to multiply by its color, and what it came from. one emitter is
+// picked, see pick_emitter, then a direction evenly within the cone its
+// sphere takes up. an emitter with a glowing object in the scene for it
+// can be bounced into as well, so like sky_light that's only its share,
+// see step. points can't be, and get all of it.
+pub(crate) fn emitter_light(scene: &Scene, position: Vec3, normal: Vec3, chance: Float, rng: &mut impl Rng) -> (Source, Vec3) {
+    let black = (Source::Emitter, Vec3::new(0.0, 0.0, 0.0));
+    if scene.emitters.is_empty() { return black; }
+
+    // from where a bounce would leave, so emitter_pdf agrees
+    let from = offset(position, normal, normal);
+    let (index, picked) = pick_emitter(scene, from, rng.gen());
+    let emitter = scene.emitters[index];
+    if picked <= 0.0 { return black; }
+
+    let to = emitter.position - from;
+    let distance = to.length();
+    if distance <= 0.0 { return black; }
+
+    if emitter.radius <= 0.0 {
+        let direction = to / distance;
+        let cosine = normal.dot(&direction);
+        if cosine <= 0.0 || scene.occluded(Ray::new(from, direction).with_max(distance)) { return black; }
+
+        let intensity = emitter.power / (4.0 * PI);
+        return (Source::Emitter, intensity * (cosine / (PI * distance * distance * picked)));
+    }
+
+    let (cos_max, pdf) = match cone(from, &emitter) {
+        Some(cone) => cone,
+        None => return black,
+    };
+
+    let cos_theta = 1.0 - rng.gen::<Float>() * (1.0 - cos_max);
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * PI * rng.gen::<Float>();
+    let direction = Frame::new(to / distance).to_world(Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta));
+
+    let cosine = normal.dot(&direction);
+    if cosine <= 0.0 { return black; }
+
+    // where the ray meets the sphere, the near side
+    let across = from - emitter.position;
+    let b = across.dot(&direction);
+    let inside = b * b - (across.length_squared() - emitter.radius * emitter.radius);
+    let meets = -b - inside.max(0.0).sqrt();
+
+    // anything short of it is in the way. something glowing right there
+    // is its object, and gives what a bounce into it would.
+    let cast = cast_ray(scene, Ray::new(from, direction), RayKind::Indirect);
+    let (hit, distance, _, material) = cast.unpack();
+    let density = pdf * picked;
+
+    if hit && distance < meets - touching(&emitter) { return black; }
+    if hit && distance <= meets + touching(&emitter) {
+        if material.emission <= 0.0 { return black; }
+        let light = material.color * material.emitted(cast.front_face);
+        let source = cast.object.map_or(Source::Emitter, Source::Object);
+        return (source, light * (cosine / PI * power(density, chance * cosine / PI) / density));
+    }
+
+    // nothing there, it's only an emitter. it glows as evenly as the
+    // sphere Emitter::sphere makes its power from.
+    let radiance = emitter.power / (4.0 * PI * PI * emitter.radius * emitter.radius);
+    return (Source::Emitter, radiance * (cosine / PI / density));
+}
+
+// how much of the light that would reach a shadow catcher from the sky
+// and the lights gets past everything else, per channel. above one where
+// light bounces onto it off the rest of the scene.
+fn catch(scene: &Scene, position: Vec3, normal: Vec3, rng: &mut impl Rng) -> Vec3 {
+    // just the lights, for what would arrive with nothing in the way
+    let march: Vec<Arc<dyn March>> = scene.march.iter()
+        .filter(|object| object.material().emission > 0.0 && !object.shadow_catcher()).cloned().collect();
+    let trace: Vec<Arc<dyn Trace>> = scene.trace.iter()
+        .filter(|object| object.material().emission > 0.0 && !object.shadow_catcher()).cloned().collect();
+
+    let mut lit = Vec3::new(0.0, 0.0, 0.0);
+    let mut open = Vec3::new(0.0, 0.0, 0.0);
+
+    for _ in 0..SAMPLES {
+        let direction = (normal + sample_sphere(rng)).unit();
+        let ray = Ray::new(offset(position, normal, direction), direction);
+
+        // any other catcher is just a surface to bounce off
+        let mut cast = cast_ray(scene, ray, RayKind::Indirect);
+        cast.catcher = false;
+        let bounces = Bounces { diffuse: scene.bounces.diffuse.saturating_sub(1), ..scene.bounces };
+        lit = lit + color(scene, ray, Some(cast), bounces, rng);
+
+        let (march, trace) = (hit_march(&march, None, ray, RayKind::Indirect), hit_trace(&trace, None, ray, RayKind::Indirect));
+        let light = match (march.hit, trace.hit) {
+            (false, false) => None,
+            (true, false) => Some(march),
+            (false, true) => Some(trace),
+            (true, true) => Some(if march.distance < trace.distance { march } else { trace }),
+        };
+        open = open + light.map_or(scene.sky(ray.direction), |light| {
+            light.material.color * light.material.emitted(light.normal.dot(&ray.direction) <= 0.0)
+        });
+    }
+
+    let ratio = |lit: Float, open: Float| if open > 0.0 { lit / open } else { 1.0 };
+    return Vec3::new(ratio(lit.x, open.x), ratio(lit.y, open.y), ratio(lit.z, open.z));
+}
+
+// the ray through `point` on the picture, from 0, 0 at the top left to 1, 1
+// at the bottom right, through the middle of the lens
+fn screen_ray(camera: Camera, point: [Float; 2], resolution: [usize; 2]) -> Ray {
+    let pixel = [point[0] * resolution[0] as Float, (1.0 - point[1]) * resolution[1] as Float];
+    return camera.generate_ray(&CameraSample::center(pixel), resolution).0;
+}
+
+// how far in front of the camera is in focus: whatever's under
+// camera.autofocus if that's set and finds something, or camera.focus.
+// measured straight ahead, like camera.focus is.
+pub fn focus(scene: &Scene, resolution: [usize; 2]) -> Float {
+    let camera = scene.camera;
+    let point = match camera.autofocus {
+        Some(point) => point,
+        None => return camera.focus,
+    };
+
+    let ray = screen_ray(camera, point, resolution);
+    let cast = cast_ray(scene, ray, RayKind::Camera);
+    if !cast.hit { return camera.focus; }
+
+    return cast.distance * ray.direction.dot(&camera.ray.direction);
+}
+
+// one jittered camera ray through a pixel, `focus` being where focus puts
+// it, and what whatever it sees is weighed by, see Camera::generate_ray
+pub(crate) fn camera_ray(scene: &Scene, uv: [Float; 2], resolution: [usize; 2], focus: Float, rng: &mut impl Rng) -> (Ray, Vec3) {
+    let sample = CameraSample {
+        pixel: [uv[0] + rng.gen::<Float>(), uv[1] + rng.gen::<Float>()],
+        lens: [rng.gen(), rng.gen()],
+        time: rng.gen(),
+        color: rng.gen(),
+    };
+    return Camera { focus: focus, ..scene.camera }.generate_ray(&sample, resolution);
+}
+
+// the jittered camera rays through a pixel, with their weights. they're
+// spread over the pixel, the lens and the exposure along the halton
+// sequence, so a few of them cover it all more evenly than at random.
+// every pixel shifts the sequence somewhere else, or they'd all jitter
+// the same way: at random, or by the blue noise tile if the scene wants,
+// so neighbours' errors cancel out instead of clumping.
+pub(crate) fn camera_rays(scene: &Scene, uv: [Float; 2], resolution: [usize; 2], rng: &mut impl Rng) -> Vec<(Ray, Vec3)> {
+    let focus = if scene.camera.aperture > 0.0 { focus(scene, resolution) } else { scene.camera.focus };
+    let camera = Camera { focus: focus, ..scene.camera };
+    let shift: [Float; 6] = match scene.blue_noise {
+        true => blue_noise::offsets([uv[0] as usize, uv[1] as usize], scene.frame),
+        false => rng.gen(),
+    };
 
-            let sample = color(&scene, scatter, (bounce - 1), (samples / 2).max(1));
-            specular = specular + sample;
+    return (0..scene.samples.max(1)).map(|index| {
+        // the first few primes make for the least alike axes
+        let along = |axis: usize, base: u32| (halton(index, base) + shift[axis]).fract();
+        let sample = CameraSample {
+            pixel: [uv[0] + along(0, 2), uv[1] + along(1, 3)],
+            lens: [along(2, 5), along(3, 7)],
+            time: along(4, 11),
+            color: along(5, 13),
+        };
+        camera.generate_ray(&sample, resolution)
+    }).collect();
+}
+
+// a pixel's samples as they come in: what they add up to, and the running
+// mean and spread of their luminance, welford's way, for telling when the
+// average is good enough to stop at, see Scene::noise_threshold
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Estimate {
+    sum: Vec3,
+    pub kept: u32,
+    bad: bool, // one of them was broken, see NanGuard
+    mean: Float,
+    spread: Float, // summed squares of how far each was from the mean
+}
+
+impl Estimate {
+    pub fn new() -> Estimate {
+        Estimate { sum: Vec3::new(0.0, 0.0, 0.0), kept: 0, bad: false, mean: 0.0, spread: 0.0 }
+    }
+
+    // false if `guard` throws it out
+    pub fn add(&mut self, guard: NanGuard, sample: Vec3) -> bool {
+        if guard != NanGuard::Off && !sample.is_finite() {
+            self.bad = true;
+            count(Counter::BadSamples, 1);
+            return false;
         }
 
-        specular = specular / (samples as f64);
+        self.sum = self.sum + sample;
+        self.kept += 1;
+        let luminance = sample.luminance();
+        let last = self.mean;
+        self.mean = last + (luminance - last) / self.kept as Float;
+        self.spread += (luminance - last) * (luminance - self.mean);
+        return true;
     }
 
-    // TODO: transmission
+    // the standard error of the mean is within `threshold` of it. dark
+    // pixels count as a step of an 8 bit png bright, or they'd never be.
+    pub fn converged(&self, threshold: Float) -> bool {
+        if self.kept < 2 { return false; }
+        let kept = self.kept as Float;
+        let error = (self.spread / (kept - 1.0) / kept).sqrt();
+        return error <= threshold * self.mean.max(DARK);
+    }
+
+    pub fn value(&self, guard: NanGuard) -> Vec3 {
+        if self.bad && guard == NanGuard::Mark { return MARKER; }
+        if self.kept == 0 { return Vec3::new(0.0, 0.0, 0.0); }
+        return self.sum / (self.kept as Float);
+    }
+}
+
+// the generator is passed in so callers can keep one per thread, and seed
+// it if they want the same noise every time. each camera ray's light comes
+// from Scene::tracer.
+pub fn render(scene: &Scene, uv: [Float; 2], resolution: [usize; 2], rng: &mut impl Rng) -> Vec3 {
+    render_counted(scene, uv, resolution, rng).0
+}
+
+// the same, along with how many samples went into the average: all of
+// them, bar the ones the nan guard throws out, unless the scene has a
+// noise threshold. then a pixel stops at a packet of camera rays once it's
+// through a quarter of them and sure enough of itself, see Estimate.
+pub fn render_counted(scene: &Scene, uv: [Float; 2], resolution: [usize; 2], rng: &mut impl Rng) -> (Vec3, u32) {
+    let (rays, weights): (Vec<Ray>, Vec<Vec3>) = camera_rays(scene, uv, resolution, rng).into_iter().unzip();
+    let tracer = scene.tracer();
+    let samples = tracer.samples();
+    let mut estimate = Estimate::new();
+
+    for (index, (chunk, weights)) in rays.chunks(LANES).zip(weights.chunks(LANES)).enumerate() {
+        // what the camera rays hit is the same for every path through them, so
+        // it's found once up front, a packet at a time if the scene wants
+        let first: Vec<Option<CastResult>> = match (samples > 1, scene.packets) {
+            (true, true) => cast_packet(scene, chunk, RayKind::Camera).into_iter().map(Some).collect(),
+            (true, false) => chunk.iter().map(|ray| Some(cast_ray(scene, *ray, RayKind::Camera))).collect(),
+            (false, _) => vec![None; chunk.len()],
+        };
+
+        for ((ray, first), weight) in chunk.iter().zip(first).zip(weights) {
+            for _ in 0..samples {
+                // cast ray
+                let sample = *weight * match first {
+                    Some(first) => tracer.li_hit(scene, *ray, first, rng),
+                    None => tracer.li(scene, *ray, rng),
+                };
+
+                if !estimate.add(scene.nan_guard, sample) && scene.nan_guard == NanGuard::Log {
+                    if let Some(told) = &scene.bad_samples { told(uv, sample, *ray); }
+                }
+            }
+        }
+
+        let done = (index + 1) * LANES;
+        if let Some(threshold) = scene.noise_threshold {
+            if done * 4 >= rays.len() && estimate.converged(threshold) { break; }
+        }
+    }
+
+    return (estimate.value(scene.nan_guard), estimate.kept);
+}
+
+// how much of the pixel is covered, for compositing: 1 over objects, 0
+// where the environment shows, and how dark the shadow is on catchers.
+// media and volumes don't count.
+pub fn alpha(scene: &Scene, uv: [Float; 2], resolution: [usize; 2], rng: &mut impl Rng) -> Float {
+    let rays = camera_rays(scene, uv, resolution, rng);
 
-    // combine the samples in a PBR manner
-    return (
-        (
-            ( // for dielectric materials. TODO: fresnel blending
-                (
-                    (transmission *        material.transmission)  // mix transparent
-                  + (diffuse      * (1.0 - material.transmission)) // and diffuse
-                )
-              + (specular * material.specular) // with a specular layer on top
-              // TODO: specular seems off, violating cons. of energy. review.
-            )
-          * (1.0 - material.metallic) // lerp with metal
+    let covered = rays.iter().fold(0.0, |sum, (ray, _)| {
+        let cast = cast_ray(scene, *ray, RayKind::Camera);
+        sum + match (cast.hit, cast.catcher) {
+            (false, _) => 0.0,
+            (true, true) => (1.0 - catch(scene, ray.point_at(&cast.distance), cast.normal, rng).luminance()).clamp(0.0, 1.0),
+            (true, false) => 1.0,
+        }
+    });
+
+    return covered / (rays.len() as Float);
+}
+
+// how far through a render is, as each part of the picture starts: which
+// one out of how many, counting from 1. it's called from every render
+// thread. rendering doesn't print how it's going, main does with this.
+pub type Progress = Arc<dyn Fn(usize, usize) + Send + Sync>;
+
+// a sample NanGuard::Log left out: the pixel it was for, counting rows
+// from the bottom, what it came back as and the camera ray it started
+// along. it's called from every render thread too, and main prints them.
+pub type BadSample = Arc<dyn Fn([Float; 2], Vec3, Ray) + Send + Sync>;
+
+// renders the pixels in `region`, or everywhere, that `wanted` picks. tiles
+// go center out on every core, and threads take the next one off a shared
+// queue when they finish one, so a few slow tiles can't leave the others
+// waiting at the end.
+// `shade` works out each pixel, it's usually render.
+pub(crate) fn render_tiles<T: Send>(
+    scene: &Scene,
+    resolution: [usize; 2],
+    region: Option<Tile>,
+    wanted: impl Fn(usize, usize) -> bool + Sync,
+    shade: impl Fn(&Scene, [Float; 2], [usize; 2], &mut ThreadRng) -> T + Sync,
+) -> (Vec<([usize; 2], T)>, RenderStats) {
+    // a crop just leaves out the tiles, or parts of them, outside it
+    let tiles: Vec<Tile> = Tile::spiral(resolution, TILE).iter()
+        .filter_map(|tile| match region { Some(region) => tile.intersect(&region), None => Some(*tile) })
+        .collect();
+
+    let next = AtomicUsize::new(0);
 
-          + ( // for metallic materials
-                specular * material.color
-            )
-          * material.metallic
-        )
-      * (1.0 - material.emission).max(0.0) // modified lerp with emissive
+    let work = || {
+        let mut rng = rand::thread_rng();
+        let mut pixels = vec![];
+        let mut times = vec![];
 
-      + ( // for emissive materials
-          material.color * material.emission
-        )
-    );
+        // anything counted on this thread before now isn't ours
+        RenderStats::collect();
+
+        loop {
+            let index = next.fetch_add(1, Ordering::Relaxed);
+            if index >= tiles.len() { break; }
+
+            let tile = tiles[index];
+            let timer = Timer::start();
+            if let Some(progress) = &scene.progress { progress(index + 1, tiles.len()); }
+
+            for y in tile.y..tile.y + tile.height {
+                for x in tile.x..tile.x + tile.width {
+                    if !wanted(x, y) { continue; }
+                    let uv = [x as Float, (resolution[1] - y) as Float];
+                    pixels.push(([x, y], shade(scene, uv, resolution, &mut rng)));
+                }
+            }
+
+            times.push(timer.elapsed());
+        }
+
+        let mut stats = RenderStats::collect();
+        stats.tiles = times;
+        (pixels, stats)
+    };
+
+    // wasm32 has no threads, so there the one thread does every tile
+    #[cfg(target_arch = "wasm32")]
+    let finished = vec![work()];
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let finished: Vec<_> = thread::scope(|scope| {
+        let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        let workers: Vec<_> = (0..threads).map(|_| scope.spawn(work)).collect();
+        workers.into_iter().map(|worker| worker.join().unwrap()).collect()
+    });
+
+    let mut pixels = vec![];
+    let mut stats = RenderStats::default();
+    for (done, counted) in finished {
+        pixels.extend(done);
+        stats.merge(&counted);
+    }
+
+    return (pixels, stats);
 }
 
-// camera or scene
-fn make_ray(origin: Vec3, fov: f64, ratio: f64, uv: [f64; 2]) -> Ray {
-    // I apologize for this garbage
-    let xy = [uv[0] - ratio * 0.5, uv[1] - 0.5];
-    let z = 1.0 / (fov.to_radians() / 2.0).tan();
-    return Ray::new(origin, (Vec3::new(xy[0], xy[1], -z)).unit());
+// render_image for whatever's embedding keikan: the scene's checked
+// first, see Scene::check, and what's wrong with it handed back instead of
+// a black or broken picture
+pub fn try_render_image(scene: &Scene, resolution: [usize; 2]) -> error::Result<(Vec<Vec<Vec3>>, RenderStats)> {
+    scene.check(resolution)?;
+    return Ok(render_image(scene, resolution));
 }
 
-fn translate_ray(camera: Camera, ray: Ray) -> Ray {
-    let f = camera.ray.direction;
-    let s = (f.cross(&camera.up)).unit();
-    let u = s.cross(&f);
+// the whole image in one go
+pub fn render_image(scene: &Scene, resolution: [usize; 2]) -> (Vec<Vec<Vec3>>, RenderStats) {
+    if scene.integrator == Integrator::Sppm && scene.custom_integrator.is_none() { return sppm::render_image(scene, resolution); }
+    let start = Timer::start();
+    let (pixels, mut stats) = render_tiles(scene, resolution, scene.region, |_, _| true, render);
+
+    let mut image = vec![vec![Vec3::new(0.0, 0.0, 0.0); resolution[0]]; resolution[1]];
+    for ([x, y], pixel) in pixels {
+        image[y][x] = pixel;
+    }
 
-    let r = ray.direction;
+    stats.total = start.elapsed();
+    return (image, stats);
+}
 
-    return Ray::new(
-        ray.origin,
-        Vec3::new( // cross product
-            (r.x * s.x) + (r.y * u.x) + (r.z * -f.x),
-            (r.x * s.y) + (r.y * u.y) + (r.z * -f.y),
-            (r.x * s.z) + (r.y * u.z) + (r.z * -f.z),
-        ),
-    )
+// render_image through one of scene.cameras instead of scene.camera, or
+// None if it hasn't got one by that name
+pub fn render_camera(scene: &Scene, name: &str, resolution: [usize; 2]) -> Option<(Vec<Vec<Vec3>>, RenderStats)> {
+    let scene = scene.with_camera(name)?;
+    return Some(render_image(&scene, resolution));
 }
 
-pub fn render(scene: &Scene, uv: [f64; 2], resolution: [usize; 2]) -> Vec3 {
-    let mut rng = rand::thread_rng();
-    let mut aliased = Vec3::new(0.0, 0.0, 0.0);
+// every one of scene.cameras in turn, in order of their names, for a set
+// of shots of the same scene lit the same way
+pub fn render_cameras(scene: &Scene, resolution: [usize; 2]) -> Vec<(String, Vec<Vec<Vec3>>, RenderStats)> {
+    return scene.cameras.keys()
+        .filter_map(|name| render_camera(scene, name, resolution).map(|(image, stats)| (name.clone(), image, stats)))
+        .collect();
+}
 
-    for _ in 0..AA {
-        // shake pixel around
-        let mut xy = [(uv[0] as f64) + rng.gen::<f64>(), (uv[1] as f64) + rng.gen::<f64>()];
+// render_image, and how many samples each pixel took, see render_counted.
+// sppm's passes each take one per pixel.
+pub fn render_sample_counts(scene: &Scene, resolution: [usize; 2]) -> (Vec<Vec<Vec3>>, Vec<Vec<u32>>, RenderStats) {
+    if scene.integrator == Integrator::Sppm && scene.custom_integrator.is_none() {
+        let (image, stats) = sppm::render_image(scene, resolution);
+        return (image, vec![vec![scene.samples.max(1); resolution[0]]; resolution[1]], stats);
+    }
 
-        // normalize coordinates
-        xy = [xy[0] / (resolution[0] as f64), xy[1] / (resolution[1] as f64)];
-        xy[0] *= (resolution[0] as f64) / (resolution[1] as f64);
+    let start = Timer::start();
+    let (pixels, mut stats) = render_tiles(scene, resolution, scene.region, |_, _| true, render_counted);
 
-        let mut ray = make_ray(
-            scene.camera.ray.origin,
-            60.0, // standard fov
-            (resolution[0] as f64) / (resolution[1] as f64),
-            xy,
-        );
+    let mut image = vec![vec![Vec3::new(0.0, 0.0, 0.0); resolution[0]]; resolution[1]];
+    let mut counts = vec![vec![0; resolution[0]]; resolution[1]];
+    for ([x, y], (pixel, count)) in pixels {
+        image[y][x] = pixel;
+        counts[y][x] = count;
+    }
+
+    stats.total = start.elapsed();
+    return (image, counts, stats);
+}
+
+// the matte that goes with render_image, see alpha
+pub fn render_alpha(scene: &Scene, resolution: [usize; 2]) -> (Vec<Vec<Float>>, RenderStats) {
+    let start = Timer::start();
+    let (pixels, mut stats) = render_tiles(scene, resolution, scene.region, |_, _| true, alpha);
+
+    let mut matte = vec![vec![0.0; resolution[0]]; resolution[1]];
+    for ([x, y], pixel) in pixels {
+        matte[y][x] = pixel;
+    }
+
+    stats.total = start.elapsed();
+    return (matte, stats);
+}
+
+// which objects and materials each pixel shows, for picking them out in
+// compositing. objects go by their name if they have one, see Matte.
+pub fn render_mattes(scene: &Scene, resolution: [usize; 2]) -> (Matte, Matte, RenderStats) {
+    let start = Timer::start();
+
+    let mut names: HashMap<Handle, String> = (0..scene.march.len()).map(|i| (Handle::March(i), format!("march {}", i)))
+        .chain((0..scene.trace.len()).map(|i| (Handle::Trace(i), format!("trace {}", i))))
+        .collect();
+    for (name, handle) in scene.names() { names.insert(handle, name.to_string()); }
+    let ids: HashMap<Handle, u32> = names.iter().map(|(handle, name)| (*handle, matte::id(name))).collect();
+
+    // adds a share of the pixel to an id, most covered first
+    let add = |seen: &mut Vec<(u32, Float)>, id: u32, share: Float| {
+        match seen.iter_mut().find(|(other, _)| *other == id) {
+            Some(entry) => entry.1 += share,
+            None => seen.push((id, share)),
+        }
+        seen.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    };
+
+    let (pixels, mut stats) = render_tiles(scene, resolution, scene.region, |_, _| true, |scene, uv, resolution, rng| {
+        let rays = camera_rays(scene, uv, resolution, rng);
+        let share = 1.0 / rays.len() as Float;
+        let (mut objects, mut materials) = (vec![], vec![]);
+
+        for (ray, _) in rays {
+            let cast = cast_ray(scene, ray, RayKind::Camera);
+            if let Some(handle) = cast.object {
+                add(&mut objects, ids[&handle], share);
+                add(&mut materials, matte::material_id(&cast.material), share);
+            }
+        }
+
+        (objects, materials)
+    });
+
+    let empty = |names: HashMap<u32, String>| Matte {
+        width: resolution[0],
+        height: resolution[1],
+        pixels: vec![vec![]; resolution[0] * resolution[1]],
+        names: names,
+    };
+    let mut objects = empty(names.into_values().map(|name| (matte::id(&name), name)).collect());
+    let mut materials = empty(HashMap::new());
+
+    for ([x, y], (object, material)) in pixels {
+        for (id, _) in &material { materials.names.insert(*id, format!("material {:08x}", id)); }
+        objects.pixels[y * resolution[0] + x] = object;
+        materials.pixels[y * resolution[0] + x] = material;
+    }
+
+    stats.total = start.elapsed();
+    return (objects, materials, stats);
+}
+
+// how far away what each pixel shows is and which way it faces, through
+// the middle of the pixel and the lens, for telling frames apart, see
+// temporal. the distance is infinite where the environment shows, with
+// the normal all 0.
+pub fn render_aovs(scene: &Scene, resolution: [usize; 2]) -> (Vec<Vec<Float>>, Vec<Vec<Vec3>>, RenderStats) {
+    let start = Timer::start();
+
+    let (pixels, mut stats) = render_tiles(scene, resolution, scene.region, |_, _| true, |scene, uv, resolution, _| {
+        let (ray, _) = scene.camera.generate_ray(&CameraSample::center([uv[0] + 0.5, uv[1] + 0.5]), resolution);
+        let cast = cast_ray(scene, ray, RayKind::Camera);
+        if !cast.hit { return (Float::INFINITY, Vec3::new(0.0, 0.0, 0.0)); }
+        (cast.distance, cast.normal)
+    });
+
+    let mut depth = vec![vec![Float::INFINITY; resolution[0]]; resolution[1]];
+    let mut normal = vec![vec![Vec3::new(0.0, 0.0, 0.0); resolution[0]]; resolution[1]];
+    for ([x, y], (distance, facing)) in pixels {
+        depth[y][x] = distance;
+        normal[y][x] = facing;
+    }
+
+    stats.total = start.elapsed();
+    return (depth, normal, stats);
+}
+
+// each group's name and its image
+pub type LightGroups = Vec<(String, Vec<Vec<Vec3>>)>;
+
+// the image split by where its light came from: one buffer per light group
+// that's tagged in the scene, see LightGroup, then "environment" for the
+// sky and "default" for every other light, photon caustics, and all of it
+// with any integrator but the path tracer. the buffers add up to render_image, so
+// lights can be rebalanced by scaling them before adding them back up.
+// sppm scenes are split from a path traced render, it doesn't keep track.
+pub fn render_light_groups(scene: &Scene, resolution: [usize; 2]) -> (LightGroups, RenderStats) {
+    let start = Timer::start();
+
+    let mut groups = vec!["environment".to_string(), "default".to_string()];
+    let mut lookup: HashMap<Handle, usize> = HashMap::new();
+    let tagged = scene.march.iter().enumerate().map(|(i, object)| (Handle::March(i), object.light_group()))
+        .chain(scene.trace.iter().enumerate().map(|(i, object)| (Handle::Trace(i), object.light_group())));
+    for (handle, group) in tagged {
+        if let Some(group) = group {
+            let index = groups.iter().position(|other| other == group).unwrap_or_else(|| {
+                groups.push(group.to_string());
+                groups.len() - 1
+            });
+            lookup.insert(handle, index);
+        }
+    }
+
+    let group = |source: Source| match source {
+        Source::Environment => 0,
+        Source::Object(handle) => lookup.get(&handle).copied().unwrap_or(1),
+        Source::Caustics | Source::Emitter => 1,
+    };
+
+    let count = groups.len();
+    let (pixels, mut stats) = render_tiles(scene, resolution, scene.region, |_, _| true, |scene, uv, resolution, rng| {
+        let rays = camera_rays(scene, uv, resolution, rng);
+        let mut split = vec![Vec3::new(0.0, 0.0, 0.0); count];
+
+        for (ray, weight) in &rays {
+            if scene.integrator == Integrator::Bidirectional || scene.custom_integrator.is_some() {
+                let tracer = scene.tracer();
+                let share = *weight / (tracer.samples() as Float * rays.len() as Float);
+                for _ in 0..tracer.samples() {
+                    split[1] = split[1] + tracer.li(scene, *ray, rng) * share;
+                }
+                continue;
+            }
+
+            let first = cast_ray(scene, *ray, RayKind::Camera);
+            let share = *weight / (SAMPLES as Float * rays.len() as Float);
+
+            for _ in 0..SAMPLES {
+                path(scene, *ray, Some(first), scene.bounces, rng, &mut |source, light| {
+                    if scene.nan_guard != NanGuard::Off && !light.is_finite() { return; }
+                    split[group(source)] = split[group(source)] + light * share;
+                });
+            }
+        }
+
+        split
+    });
+
+    let mut buffers: LightGroups = groups.into_iter()
+        .map(|name| (name, vec![vec![Vec3::new(0.0, 0.0, 0.0); resolution[0]]; resolution[1]]))
+        .collect();
+    for ([x, y], split) in pixels {
+        for (buffer, light) in buffers.iter_mut().zip(split) { buffer.1[y][x] = light; }
+    }
+
+    stats.total = start.elapsed();
+    return (buffers, stats);
+}
 
-        ray = translate_ray(scene.camera, ray);
+// just the pixels in `region`, row by row, for handing out parts of a frame
+pub fn render_region(scene: &Scene, resolution: [usize; 2], region: Tile) -> (Vec<Vec3>, RenderStats) {
+    let start = Timer::start();
+    let (pixels, mut stats) = render_tiles(scene, resolution, Some(region), |_, _| true, render);
 
-        // cast ray
-        aliased = aliased + color(&scene, ray, MAX_BOUNCES, SAMPLES);
+    let mut out = vec![Vec3::new(0.0, 0.0, 0.0); region.area()];
+    for ([x, y], pixel) in pixels {
+        out[(y - region.y) * region.width + (x - region.x)] = pixel;
     }
 
-    return aliased / (AA as f64);
+    stats.total = start.elapsed();
+    return (out, stats);
+}
+
+// a progressive preview: every 8th pixel, then every 4th, 2nd, and the
+// rest. each level only renders what the coarser ones haven't, so the last
+// image costs the same as render_image. `preview` sees the picture so far
+// after each level, blown up to full size, along with that level's step.
+pub fn render_pyramid(
+    scene: &Scene,
+    resolution: [usize; 2],
+    mut preview: impl FnMut(&[Vec<Vec3>], usize),
+) -> (Vec<Vec<Vec3>>, RenderStats) {
+    let start = Timer::start();
+    let on = |x: usize, y: usize, step: usize| x.is_multiple_of(step) && y.is_multiple_of(step);
+
+    let mut image = vec![vec![Vec3::new(0.0, 0.0, 0.0); resolution[0]]; resolution[1]];
+    let mut stats = RenderStats::default();
+    let mut coarser = None;
+
+    for step in PYRAMID.iter().copied() {
+        let (pixels, counted) = render_tiles(scene, resolution, scene.region, |x, y| {
+            on(x, y, step) && !coarser.is_some_and(|coarser| on(x, y, coarser))
+        }, render);
+        stats.merge(&counted);
+
+        for ([x, y], pixel) in pixels {
+            image[y][x] = pixel;
+        }
+
+        // each pixel shows the sample at the corner of its block
+        let blocky: Vec<Vec<Vec3>> = (0..resolution[1]).map(|y| {
+            (0..resolution[0]).map(|x| image[y - y % step][x - x % step]).collect()
+        }).collect();
+        preview(&blocky, step);
+
+        coarser = Some(step);
+    }
+
+    stats.total = start.elapsed();
+    return (image, stats);
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::sync::{ Arc, Mutex };
+
+    use super::{ render, render_image, render_counted, render_camera, render_cameras, color, focus, camera_rays, cast_ray, cast_packet, Bounces, NanGuard, MARKER, SAMPLES };
+    use crate::structures::float::Float;
+    use crate::structures::float::consts::PI;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::ray::Ray;
+    use crate::structures::camera::Camera;
+    use crate::structures::material::Material;
+    use crate::structures::scene::Scene;
+    use crate::structures::film::Film;
+    use crate::structures::environment_map::EnvironmentMap;
+    use crate::structures::stats::RenderStats;
+    use crate::structures::top_level::TopLevel;
+    use crate::structures::photon_map::Emitter;
+    use crate::objects::sphere::Sphere;
+    use crate::objects::plane::Plane;
+    use crate::objects::quad::Quad;
+    use crate::objects::visible::RayKind;
+
+    #[test]
+    fn test_front_face() {
+        let wall = |two_sided: bool| Scene::builder()
+            .add(Plane::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0), Material { two_sided: two_sided, ..Material::blank() }))
+            .build();
+        let front = Ray::new(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let back = Ray::new(Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 0.0, 1.0));
+
+        let hit = cast_ray(&wall(true), front, RayKind::Camera);
+        assert!(hit.hit && hit.front_face);
+
+        // from behind the normal's turned around to face the ray
+        let hit = cast_ray(&wall(true), back, RayKind::Camera);
+        assert!(hit.hit && !hit.front_face);
+        assert_eq!(hit.normal, Vec3::new(0.0, 0.0, -1.0));
+
+        // and one sided, there's nothing there
+        assert!(cast_ray(&wall(false), front, RayKind::Camera).hit);
+        assert!(!cast_ray(&wall(false), back, RayKind::Camera).hit);
+        assert!(!wall(false).occluded(back.with_max(10.0)));
+    }
+
+    #[test]
+    fn test_interior() {
+        let ball = Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0, Material::blank());
+        let scene = Scene::builder().march(ball).build();
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+
+        // from the middle, the wall is found on the way out
+        let hit = cast_ray(&scene, ray, RayKind::Camera);
+        assert!(hit.hit && !hit.front_face);
+        assert!((hit.distance - 1.0).abs() < 0.01);
+        assert!((hit.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 0.01);
+
+        let packet = cast_packet(&scene, &[ray], RayKind::Camera);
+        assert!(packet[0].hit && !packet[0].front_face);
+
+        assert!(scene.occluded(ray.with_max(2.0)));
+        assert!(!scene.occluded(ray.with_max(0.5)));
+    }
+
+    #[test]
+    fn test_ray_range() {
+        let near = Sphere::new(Vec3::new(0.0, 0.0, -3.0), 1.0, Material::blank());
+        let far = Sphere::new(Vec3::new(0.0, 0.0, -6.0), 1.0, Material::blank());
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+
+        for scene in [Scene::builder().trace(near).trace(far).build(), Scene::builder().march(near).march(far).build()].iter() {
+            assert!((cast_ray(scene, ray, RayKind::Camera).distance - 2.0).abs() < 0.01);
+
+            // starting past the near one, only the far one's there
+            let past = ray.with_range(4.5, Float::MAX);
+            assert!((cast_ray(scene, past, RayKind::Camera).distance - 5.0).abs() < 0.01);
+            assert!((cast_packet(scene, &[past], RayKind::Camera)[0].distance - 5.0).abs() < 0.01);
+
+            // and stopping short of both, nothing is
+            assert!(!cast_ray(scene, ray.with_max(1.5), RayKind::Camera).hit);
+            assert!(!cast_packet(scene, &[ray.with_max(1.5)], RayKind::Camera)[0].hit);
+            assert!(!scene.occluded(ray.with_max(1.5)));
+            assert!(scene.occluded(past));
+        }
+    }
+
+    #[test]
+    fn test_top_level() {
+        // a grid of balls, every other one marched, in front of a wall
+        let mut scene = Scene::builder()
+            .add(Plane::new(Vec3::new(0.0, 0.0, -4.0), Vec3::new(0.0, 0.0, 1.0), Material::blank()))
+            .build();
+        for i in 0..25 {
+            let ball = Sphere::new(Vec3::new((i % 5) as Float - 2.0, (i / 5) as Float - 2.0, -(i % 3) as Float), 0.3, Material::blank());
+            if i % 2 == 0 { scene.add_trace(ball); } else { scene.add_march(ball); }
+        }
+
+        let rays: Vec<Ray> = (0..400).map(|i| {
+            let (u, v) = ((i % 20) as Float / 20.0 - 0.5, (i / 20) as Float / 20.0 - 0.5);
+            Ray::new(Vec3::new(0.0, 0.0, 4.0), Vec3::new(u, v, -1.0).unit())
+        }).collect();
+        let swept: Vec<_> = rays.iter().map(|ray| cast_ray(&scene, *ray, RayKind::Camera)).collect();
+
+        // going down the tree finds the same things as going down the lists
+        scene.top_level = Some(TopLevel::new(&scene));
+        for (ray, swept) in rays.iter().zip(swept.iter()) {
+            let hit = cast_ray(&scene, *ray, RayKind::Camera);
+            assert_eq!((hit.hit, hit.object), (swept.hit, swept.object));
+            assert!((hit.distance - swept.distance).abs() < 0.001);
+            assert_eq!(cast_packet(&scene, &[*ray], RayKind::Camera)[0].object, hit.object);
+        }
+        assert!(scene.occluded(rays[210].with_max(10.0)));
+        assert!(!scene.occluded(rays[210].with_max(1.0)));
+
+        // one built for other lists is left alone, even when they were
+        // changed by hand instead of through the scene
+        scene.trace.push(Arc::new(Sphere::new(Vec3::new(0.0, 0.0, 2.0), 0.5, Material::blank())));
+        assert!(scene.top_level.is_some());
+        assert!((cast_ray(&scene, rays[210], RayKind::Camera).distance - 1.5).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_specular_bounces() {
+        let white = Vec3::new(1.0, 1.0, 1.0);
+        let mirror = |color: Vec3, importance: Float| Scene::builder()
+            .environment(Vec3::new(0.0, 0.0, 0.0))
+            .add(Plane::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0), Material { color: color, emission: 0.0, metallic: 1.0, importance: importance, ..Material::blank() }))
+            .add(Sphere::new(Vec3::new(2.0, 0.0, 2.0), 0.5, Material { color: white, ..Material::blank() }))
+            .build();
+        let ray = Ray::new(Vec3::new(-1.0, 0.0, 1.0), Vec3::new(1.0, 0.0, -1.0).unit());
+        let rng = &mut rand::thread_rng();
+
+        // the light is only seen off the mirror, which needs a bounce
+        let none = Bounces { diffuse: 0, specular: 0, roulette: 0, regularize: None };
+        assert_eq!(color(&mirror(white, 1.0), ray, None, none, rng), Vec3::new(0.0, 0.0, 0.0));
+
+        // and the mirror has its own
+        let mirrors = Bounces { specular: 1, ..none };
+        assert_eq!(color(&mirror(white, 1.0), ray, None, mirrors, rng), white);
+
+        // a dim mirror that's important enough always survives roulette
+        let grey = Vec3::new(0.5, 0.5, 0.5);
+        assert_eq!(color(&mirror(grey, 2.0), ray, None, mirrors, rng), grey);
+    }
+
+    #[test]
+    fn test_regularize() {
+        // a light just off to the side of where a mirror floor reflects straight up
+        let scene = Scene::builder()
+            .environment(Vec3::new(0.0, 0.0, 0.0))
+            .add(Plane::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Material { color: Vec3::new(1.0, 1.0, 1.0), emission: 0.0, metallic: 1.0, ..Material::blank() }))
+            .add(Sphere::new(Vec3::new(0.4, 1.5, 0.0), 0.2, Material { color: Vec3::new(1.0, 1.0, 1.0), ..Material::blank() }))
+            .build();
+        let ray = Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let rng = &mut rand::thread_rng();
+        let seen = |bounces: Bounces, rng: &mut rand::rngs::ThreadRng| (0..512).fold(0.0, |sum, _| sum + color(&scene, ray, None, bounces, rng).x);
+
+        // a perfect mirror never shows it
+        assert_eq!(seen(Bounces::default(), rng), 0.0);
+        assert_eq!(seen(Bounces { regularize: Some(1), ..Bounces::default() }, rng), 0.0);
+
+        // roughened, some of the paths off it find it
+        assert!(seen(Bounces { regularize: Some(0), ..Bounces::default() }, rng) > 0.0);
+    }
+
+    #[test]
+    fn test_autofocus() {
+        let mut camera = Camera::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0));
+        camera.focus = 2.0;
+        let wall = |camera: Camera| Scene::builder()
+            .camera(camera)
+            .add(Quad::new(Vec3::new(-1.0, -1.0, -5.0), Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 2.0, 0.0), Material::blank()))
+            .samples(8)
+            .build();
+
+        // without it, or when it finds nothing, the focus is left alone
+        assert_eq!(focus(&wall(camera), [10, 10]), 2.0);
+        assert_eq!(focus(&wall(Camera { autofocus: Some([0.0, 0.0]), ..camera }), [10, 10]), 2.0);
+
+        // the middle of the picture is the wall
+        let scene = wall(Camera { autofocus: Some([0.5, 0.5]), aperture: 0.2, ..camera });
+        assert!((focus(&scene, [10, 10]) - 5.0).abs() < 0.001);
+
+        // and the rays start all over the lens
+        let rays = camera_rays(&scene, [5.0, 5.0], [10, 10], &mut rand::thread_rng());
+        assert!(rays.iter().all(|(ray, _)| ray.origin.length() <= 0.1 + 0.0001));
+        assert!(rays.iter().any(|(ray, _)| ray.origin != rays[0].0.origin));
+    }
+
+    #[test]
+    fn test_lens_effects() {
+        let camera = Camera::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0));
+        let corner = |camera: Camera| {
+            let scene = Scene::builder().camera(camera).samples(64).build();
+            camera_rays(&scene, [0.0, 10.0], [10, 10], &mut rand::thread_rng())
+        };
+        let widest = |rays: &[(Ray, Vec3)]| rays.iter().map(|(ray, _)| -ray.direction.z).fold(1.0, Float::min);
+        let narrowest = |rays: &[(Ray, Vec3)]| rays.iter().map(|(ray, _)| -ray.direction.z).fold(0.0, Float::max);
+
+        // barrel distortion sees further out at the edges, and every color
+        // goes the same way without aberration
+        let straight = corner(camera);
+        let barrel = corner(Camera { distortion: 0.5, ..camera });
+        assert!(narrowest(&barrel) < widest(&straight));
+        assert!(straight.iter().chain(barrel.iter()).all(|(_, weight)| *weight == Vec3::new(1.0, 1.0, 1.0)));
+
+        // with it, each ray is one color, red further out than blue
+        let fringed = corner(Camera { aberration: 0.2, ..camera });
+        let only = |weight: Vec3| fringed.iter().filter(|(_, other)| *other == weight).copied().collect::<Vec<(Ray, Vec3)>>();
+        let (red, green, blue) = (only(Vec3::new(3.0, 0.0, 0.0)), only(Vec3::new(0.0, 3.0, 0.0)), only(Vec3::new(0.0, 0.0, 3.0)));
+        assert_eq!(red.len() + green.len() + blue.len(), fringed.len());
+        assert!(narrowest(&red) < widest(&blue));
+    }
+
+    #[test]
+    fn test_blue_noise() {
+        // the tile picks the jitter, so a pixel's rays come out the same
+        // every time, where they'd be somewhere else each time at random
+        let directions = |scene: &Scene| camera_rays(scene, [10.0, 20.0], [64, 64], &mut rand::thread_rng())
+            .iter().map(|(ray, _)| ray.direction).collect::<Vec<Vec3>>();
+
+        let tiled = Scene::builder().samples(4).blue_noise(true).build();
+        assert_eq!(directions(&tiled), directions(&tiled));
+        let random = Scene::builder().samples(4).build();
+        assert!(directions(&random) != directions(&random));
+    }
+
+    #[test]
+    fn test_render_cameras() {
+        // a glowing ball in the dark, one camera looking at it and one away
+        let away = Camera::new(Vec3::new(0.0, 0.0, 4.0), Vec3::new(0.0, 0.0, 8.0), Vec3::new(0.0, 1.0, 0.0));
+        let toward = Camera::new(Vec3::new(4.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let scene = Scene::builder()
+            .environment(Vec3::new(0.0, 0.0, 0.0))
+            .add(Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0, Material::blank()))
+            .named_camera("toward", toward)
+            .named_camera("away", away)
+            .samples(1)
+            .build();
+
+        let shots = render_cameras(&scene, [4, 4]);
+        let names: Vec<&str> = shots.iter().map(|(name, _, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["away", "toward"]);
+        assert!(shots[0].1.iter().flatten().all(|pixel| *pixel == Vec3::new(0.0, 0.0, 0.0)));
+        assert!(shots[1].1[2][2].z > 0.5);
+
+        // the scene's own camera is left as it was
+        assert_eq!(scene.camera.ray.origin, Vec3::new(0.0, 0.0, 4.0));
+        assert!(render_camera(&scene, "missing", [4, 4]).is_none());
+    }
+
+    #[test]
+    fn test_progress() {
+        let camera = Camera::new(Vec3::new(0.0, 0.0, 4.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let seen = Arc::new(Mutex::new(vec![]));
+        let told = seen.clone();
+        let scene = Scene::builder()
+            .camera(camera)
+            .samples(1)
+            .progress(move |done, total| told.lock().unwrap().push((done, total)))
+            .build();
+
+        // 40x20 is three tiles across and two down, each told once
+        render_image(&scene, [40, 20]);
+        let mut seen = seen.lock().unwrap().clone();
+        seen.sort();
+        assert_eq!(seen, (1..=6).map(|done| (done, 6)).collect::<Vec<_>>());
+    }
+
+    // a dim sky with a sun a thousand times brighter in one pixel of it,
+    // over a grey floor, and what the floor sends straight up from under it
+    pub fn sunny() -> (Scene, Float) {
+        let mut film = Film::wrap(16, 8, vec![Vec3::new(0.1, 0.1, 0.1); 128]).unwrap();
+        film.set(5, 2, Vec3::new(100.0, 100.0, 100.0));
+
+        // each pixel of the top half times its cosine over its solid angle
+        let mut irradiance = 0.0;
+        for y in 0..4 {
+            let [top, bottom] = [y as Float * PI / 8.0, (y + 1) as Float * PI / 8.0];
+            let band = PI / 16.0 * (bottom.sin().powi(2) - top.sin().powi(2));
+            irradiance += (0..16).map(|x| film.get(x, y).x * band).sum::<Float>();
+        }
+
+        let floor = Material { color: Vec3::new(0.5, 0.5, 0.5), emission: 0.0, ..Material::blank() };
+        let scene = Scene::builder()
+            .environment_map(EnvironmentMap::new(film))
+            .add(Plane::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), floor))
+            .build();
+        return (scene, 0.5 / PI * irradiance);
+    }
+
+    #[test]
+    fn test_environment_map() {
+        let (scene, expected) = sunny();
+        let sun = EnvironmentMap::direction([5.5 / 16.0, 2.5 / 8.0]);
+        assert_eq!(scene.sky(sun), Vec3::new(100.0, 100.0, 100.0));
+        assert_eq!(cast_ray(&scene, Ray::new(Vec3::new(0.0, 1.0, 0.0), sun), RayKind::Camera).material.color, scene.sky(sun));
+
+        // the floor sees the sun whichever way it's found, and adds up to it
+        let down = Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let rng = &mut rand::thread_rng();
+        let n = 4000;
+        let mean = (0..n).map(|_| color(&scene, down, None, scene.bounces, rng).x).sum::<Float>() / n as Float;
+        assert!((mean - expected).abs() < 0.03 * expected, "{} against {}", mean, expected);
+    }
+
+    #[test]
+    fn test_emitter_light() {
+        // a small lamp over a grey floor in the dark, seen from the side
+        let lamp = Material { color: Vec3::new(1.0, 1.0, 1.0), emission: 4.0, ..Material::blank() };
+        let floor = Material { color: Vec3::new(0.5, 0.5, 0.5), emission: 0.0, ..Material::blank() };
+        let dark = || Scene::builder()
+            .environment(Vec3::new(0.0, 0.0, 0.0))
+            .add(Plane::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), floor));
+        let ray = Ray::new(Vec3::new(0.0, 1.0, 1.0), Vec3::new(0.0, -1.0, -1.0).unit());
+        let rng = &mut rand::thread_rng();
+        let mean = |scene: &Scene, rng: &mut rand::rngs::ThreadRng| {
+            (0..2000).map(|_| color(scene, ray, None, scene.bounces, rng).x).sum::<Float>() / 2000.0
+        };
+
+        // a sphere glowing with L lights what's right under it with pi L
+        // r² / d², and the floor sends half of that over pi back
+        let expected = 0.5 * 4.0 * (0.25 as Float / 2.0).powi(2);
+        let center = Vec3::new(0.0, 2.0, 0.0);
+
+        // found by looking for it and by bouncing into it, shared between the two
+        let both = dark()
+            .add(Sphere::new(center, 0.25, lamp))
+            .emitter(Emitter::sphere(center, 0.25, lamp.color * lamp.emission))
+            .build();
+        let found = mean(&both, rng);
+        assert!((found - expected).abs() < 0.03 * expected, "{} against {}", found, expected);
+
+        // with nothing in the scene for it, only by looking
+        let alone = dark().emitter(Emitter::sphere(center, 0.25, lamp.color * lamp.emission)).build();
+        let found = mean(&alone, rng);
+        assert!((found - expected).abs() < 0.03 * expected, "{} against {}", found, expected);
+
+        // and a point, which has nothing to be noisy about. it is a hair
+        // closer than 2 from where the floor looks
+        let point = dark().emitter(Emitter::new(center, 0.0, Vec3::new(1.0, 1.0, 1.0) * (16.0 * PI * PI))).build();
+        let lit = color(&point, ray, None, point.bounces, rng).x;
+        assert!((lit - 0.5).abs() < 0.005, "{}", lit);
+    }
+
+    #[test]
+    fn test_one_sided_emission() {
+        let panel = |two_sided: bool| Scene::builder()
+            .environment(Vec3::new(0.0, 0.0, 0.0))
+            .add(Quad::new(Vec3::new(-1.0, -1.0, 0.0), Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 2.0, 0.0), Material { two_sided_emission: two_sided, ..Material::blank() }))
+            .build();
+        let none = Bounces { diffuse: 0, specular: 0, roulette: 0, regularize: None };
+        let front = Ray::new(Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let back = Ray::new(Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 0.0, 1.0));
+        let rng = &mut rand::thread_rng();
+
+        let glow = Material::blank().color;
+        assert_eq!(color(&panel(true), back, None, none, rng), glow);
+        assert_eq!(color(&panel(false), front, None, none, rng), glow);
+        assert_eq!(color(&panel(false), back, None, none, rng), Vec3::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_footprint() {
+        let scene = Scene::builder().add(Plane::new(Vec3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0), Material::blank())).build();
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0)).with_spread(0.01).with_width(0.1);
+
+        assert!((cast_ray(&scene, ray, RayKind::Camera).footprint - 0.15).abs() < 0.0001);
+        assert!((cast_packet(&scene, &[ray], RayKind::Camera)[0].footprint - 0.15).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_shading_frame() {
+        let ball = Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0, Material::blank());
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+
+        for scene in [Scene::builder().trace(ball).build(), Scene::builder().march(ball).build()].iter() {
+            let hit = cast_ray(scene, ray, RayKind::Camera);
+            assert!(hit.tangent.dot(&hit.normal).abs() < 0.01);
+            assert!((hit.tangent.cross(&hit.bitangent) - hit.normal).length() < 0.01);
+            assert!((hit.uv[0] - 0.75).abs() < 0.01 && (hit.uv[1] - 0.5).abs() < 0.01);
+        }
+
+        // from behind, the frame's turned around with the normal
+        let wall = Scene::builder().add(Quad::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 2.0, 0.0), Material::blank())).build();
+        let hit = cast_ray(&wall, Ray::new(Vec3::new(0.5, 1.5, -1.0), Vec3::new(0.0, 0.0, 1.0)), RayKind::Camera);
+        assert_eq!(hit.uv, [0.25, 0.75]);
+        assert_eq!(hit.tangent, Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(hit.frame().to_world(Vec3::new(0.0, 0.0, 1.0)), Vec3::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn test_nan_guard() {
+        let broken = Material { color: Vec3::new(Float::NAN, 0.0, 0.0), ..Material::blank() };
+        let mut camera = Camera::new(Vec3::new(0.0, 0.0, 4.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        camera.fov = 1.0;
+
+        let scene = |guard: NanGuard| Scene::builder()
+            .camera(camera)
+            .add(Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0, broken))
+            .nan_guard(guard)
+            .samples(1)
+            .build();
+
+        let mut rng = rand::thread_rng();
+        assert!(!render(&scene(NanGuard::Off), [0.0, 0.0], [1, 1], &mut rng).is_finite());
+
+        // every sample is broken, so there's nothing left to average
+        RenderStats::collect();
+        assert_eq!(render(&scene(NanGuard::Discard), [0.0, 0.0], [1, 1], &mut rng), Vec3::new(0.0, 0.0, 0.0));
+        assert!(RenderStats::collect().bad_samples > 0);
+
+        assert_eq!(render(&scene(NanGuard::Mark), [0.0, 0.0], [1, 1], &mut rng), MARKER);
+
+        // logged ones go to the scene's callback, not the terminal
+        let told = Arc::new(Mutex::new(vec![]));
+        let mut logged = scene(NanGuard::Log);
+        let heard = told.clone();
+        logged.bad_samples = Some(Arc::new(move |pixel, sample, _| heard.lock().unwrap().push((pixel, sample))));
+        assert_eq!(render(&logged, [0.0, 0.0], [1, 1], &mut rng), Vec3::new(0.0, 0.0, 0.0));
+        let told = told.lock().unwrap();
+        assert!(!told.is_empty() && told.iter().all(|(pixel, sample)| *pixel == [0.0, 0.0] && !sample.is_finite()));
+
+        // which shows in the sample counts
+        let blank = Scene::builder().camera(camera).nan_guard(NanGuard::Discard).samples(2).build();
+        assert_eq!(render_counted(&blank, [0.0, 0.0], [1, 1], &mut rng).1, 2 * SAMPLES);
+        assert_eq!(render_counted(&scene(NanGuard::Discard), [0.0, 0.0], [1, 1], &mut rng).1, 0);
+
+        // with a noise threshold, the sky's the same every time so it stops
+        // once a quarter of the camera rays are in, and a wall half in the
+        // shade of a ball, which can't be that sure of itself, takes them all
+        let sky = Scene::builder().camera(camera).samples(16).noise_threshold(0.05).build();
+        assert_eq!(render_counted(&sky, [0.0, 0.0], [1, 1], &mut rng).1, 4 * SAMPLES);
+        let floor = Material { color: Vec3::new(0.5, 0.5, 0.5), emission: 0.0, ..Material::blank() };
+        let lit = Scene::builder()
+            .camera(camera)
+            .samples(16)
+            .noise_threshold(1e-6)
+            .add(Plane::new(Vec3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0), floor))
+            .add(Sphere::new(Vec3::new(2.0, 0.0, -4.0), 1.5, Material { color: Vec3::new(0.0, 0.0, 0.0), ..floor }))
+            .build();
+        assert_eq!(render_counted(&lit, [0.0, 0.0], [1, 1], &mut rng).1, 16 * SAMPLES);
+    }
 }
@@ -0,0 +1,162 @@
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::aabb::Aabb;
+use crate::structures::camera::Camera;
+use crate::structures::material::Material;
+use crate::structures::scene::Scene;
+use crate::objects::sphere::Sphere;
+use crate::objects::plane::Plane;
+use crate::objects::quad::Quad;
+use crate::objects::cuboid::Cuboid;
+use crate::objects::mandelbulb::Mandelbulb;
+use crate::objects::metaballs::{ Metaballs, Ball };
+
+// the standard scenes, each made in one call. they're examples to start
+// from, and they're the same from version to version so renders and
+// timings of them can be compared. change one and old numbers stop meaning
+// anything, add a new one instead.
+
+pub const NAMES: [&str; 4] = ["cornell_box", "material_spheres", "fractal", "bunny"];
+
+// by the name in NAMES
+pub fn named(name: &str) -> Option<Scene> {
+    match name {
+        "cornell_box" => Some(cornell_box()),
+        "material_spheres" => Some(material_spheres()),
+        "fractal" => Some(fractal()),
+        "bunny" => Some(bunny()),
+        _ => None,
+    }
+}
+
+// every one of them along with its name, for running them all
+pub fn all() -> Vec<(&'static str, Scene)> {
+    NAMES.iter().map(|name| (*name, named(name).unwrap())).collect()
+}
+
+fn diffuse(color: Vec3) -> Material {
+    Material { color: color, emission: 0.0, ..Material::blank() }
+}
+
+fn light(color: Vec3, emission: Float) -> Material {
+    Material { color: color, emission: emission, ..Material::blank() }
+}
+
+// red wall on the left, green on the right, lit only by a square panel
+// under the ceiling, with a tall box and a short one. all diffuse and all
+// traced, for checking the light bounces around right.
+pub fn cornell_box() -> Scene {
+    let white = diffuse(Vec3::new(0.73, 0.73, 0.73));
+    let red = diffuse(Vec3::new(0.65, 0.05, 0.05));
+    let green = diffuse(Vec3::new(0.12, 0.45, 0.15));
+
+    let mut camera = Camera::new(Vec3::new(0.0, 0.0, 3.4), Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+    camera.fov = 80.0;
+
+    let (x, y, z) = (Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 2.0, 0.0), Vec3::new(0.0, 0.0, 2.0));
+    let corner = Vec3::new(-1.0, -1.0, -1.0);
+
+    return Scene::builder()
+        .camera(camera)
+        .environment(Vec3::new(0.0, 0.0, 0.0))
+        .add(Quad::new(corner, z, y, red))
+        .add(Quad::new(corner + x, y, z, green))
+        .add(Quad::new(corner, x, z, white))
+        .add(Quad::new(corner + y, z, x, white))
+        .add(Quad::new(corner, y, x, white))
+        .add(Quad::new(Vec3::new(-0.25, 0.99, -0.25), Vec3::new(0.0, 0.0, 0.5), Vec3::new(0.5, 0.0, 0.0), light(Vec3::new(1.0, 1.0, 1.0), 15.0)))
+        .add(Cuboid::new(Vec3::new(-0.35, -0.4, -0.3), Vec3::new(0.3, 0.6, 0.3), white))
+        .add(Cuboid::new(Vec3::new(0.35, -0.7, 0.3), Vec3::new(0.3, 0.3, 0.3), white))
+        .build();
+}
+
+// a row of balls on a grey floor under the sky, from rough to shiny to
+// metal to glowing, for seeing what a change to the shading does to each
+pub fn material_spheres() -> Scene {
+    let white = diffuse(Vec3::new(0.8, 0.8, 0.8));
+    let materials = [
+        white,
+        Material { specular: 0.5, ..white },
+        Material { color: Vec3::new(0.9, 0.9, 0.7), metallic: 1.0, ..white },
+        Material { color: Vec3::new(0.5, 0.5, 0.5), metallic: 0.5, ..white },
+        light(Vec3::new(1.0, 0.6, 0.3), 2.0),
+    ];
+
+    let mut camera = Camera::new(Vec3::new(0.0, 1.5, 6.0), Vec3::new(0.0, 0.5, 0.0), Vec3::new(0.0, 1.0, 0.0));
+    camera.fov = 80.0;
+
+    let mut scene = Scene::builder()
+        .camera(camera)
+        .add(Plane::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), diffuse(Vec3::new(0.5, 0.5, 0.5))))
+        .build();
+
+    for (index, material) in materials.iter().enumerate() {
+        let x = (index as Float - 2.0) * 1.2;
+        scene.add(Sphere::new(Vec3::new(x, 0.5, 0.0), 0.5, *material));
+    }
+
+    return scene;
+}
+
+// a gold mandelbulb, lit by the sky. nearly all the time goes on marching,
+// so it's the one to time the marcher with.
+pub fn fractal() -> Scene {
+    let gold = Material { color: Vec3::new(0.9, 0.9, 0.7), metallic: 1.0, ..diffuse(Vec3::new(0.0, 0.0, 0.0)) };
+    let camera = Camera::new(Vec3::new(-2.0, 1.0, 4.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+
+    return Scene::builder()
+        .camera(camera)
+        .march(Mandelbulb::new(Vec3::new(0.0, 0.0, 0.0), 8.0, 10, gold))
+        .add(Plane::new(Vec3::new(0.0, -1.2, 0.0), Vec3::new(0.0, 1.0, 0.0), diffuse(Vec3::new(0.1, 0.1, 0.1))))
+        .build();
+}
+
+// a few thousand smooth triangles in the shape of a rabbit, for timing the
+// traced side and the bvh. there's no scan to ship with the crate, so it's
+// blobs turned into a mesh with Scene::extract_mesh, which comes out the
+// same every time.
+pub fn bunny() -> Scene {
+    let balls = vec![
+        Ball::new(Vec3::new(0.0, 0.45, 0.0), 0.75, 1.0),    // body
+        Ball::new(Vec3::new(-0.3, 0.35, 0.0), 0.6, 1.0),    // haunches
+        Ball::new(Vec3::new(0.45, 0.85, 0.0), 0.5, 1.0),    // head
+        Ball::new(Vec3::new(0.4, 1.25, 0.12), 0.3, 0.8),    // ears
+        Ball::new(Vec3::new(0.45, 1.5, 0.12), 0.25, 0.8),
+        Ball::new(Vec3::new(0.4, 1.25, -0.12), 0.3, 0.8),
+        Ball::new(Vec3::new(0.45, 1.5, -0.12), 0.25, 0.8),
+        Ball::new(Vec3::new(-0.75, 0.45, 0.0), 0.3, 1.0),   // tail
+    ];
+    let clay = diffuse(Vec3::new(0.8, 0.75, 0.7));
+
+    let blobs = Scene::builder().march(Metaballs::new(balls, 0.5, clay)).build();
+    let mesh = blobs.extract_mesh(Aabb::new(Vec3::new(-1.2, -0.2, -0.8), Vec3::new(1.2, 1.9, 0.8)), 48);
+
+    let camera = Camera::new(Vec3::new(0.5, 1.2, 3.5), Vec3::new(0.0, 0.7, 0.0), Vec3::new(0.0, 1.0, 0.0));
+
+    return Scene::builder()
+        .camera(camera)
+        .trace(mesh)
+        .add(Plane::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), diffuse(Vec3::new(0.5, 0.5, 0.5))))
+        .build();
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::{ all, named, NAMES };
+    use crate::structures::validate::Severity;
+    use crate::render::cast_ray;
+    use crate::objects::visible::RayKind;
+
+    #[test]
+    fn test_scenes() {
+        assert!(named("teapot").is_none());
+
+        for (name, scene) in all() {
+            assert!(NAMES.contains(&name));
+            assert!(scene.validate().iter().all(|diagnostic| diagnostic.severity != Severity::Error), "{}", name);
+
+            // the middle of the picture is always something
+            assert!(cast_ray(&scene, scene.camera.ray, RayKind::Camera).object.is_some(), "{}", name);
+        }
+    }
+}
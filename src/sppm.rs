@@ -0,0 +1,339 @@
+use std::collections::HashMap;
+use rand::Rng;
+
+use crate::structures::float::Float;
+use crate::structures::float::consts::PI;
+use crate::structures::vec3::Vec3;
+use crate::structures::ray::Ray;
+use crate::structures::scene::Scene;
+use crate::structures::cast_result::CastResult;
+use crate::structures::interiors::Interiors;
+use crate::structures::photon_map::random_unit;
+use crate::structures::stats::{ RenderStats, Timer };
+use crate::render::{ cast_ray, camera_ray, focus, render_tiles, offset, reflect, refract, fresnel };
+use crate::objects::visible::RayKind;
+
+// stochastic progressive photon mapping, from hachisuka and jensen 2009.
+// every pass, each pixel follows one camera ray through mirrors and glass
+// to the first rough surface, then photons from scene.emitters are shot and
+// added up at the points they land near. each pixel's gather radius shrinks
+// a little every pass it catches any, so the blur photon maps are known for
+// goes away the longer it runs. light that goes through glass, onto
+// something diffuse, and back through glass to the camera is found this way
+// when path tracing never would.
+//
+// the passes are scene.samples, and it's the same pbr-ish mix as the photon
+// map's: perfect mirrors when metallic, glass when transmissive, lambertian
+// otherwise. the sky and glowing objects are only seen straight on, through
+// mirrors and glass, the rest of the light comes from the emitters.
+
+const PHOTONS: usize = 100_000; // shot per pass, over all the emitters
+const BOUNCES: u32 = 8; // surfaces either side can go off before it's dropped
+const RADIUS: Float = 16.0; // starting radius, in pixel footprints where it lands
+const ALPHA: Float = 2.0 / 3.0; // how much of what's caught a pass keeps, see update
+const EPSILON: Float = 0.002;
+
+// where a pixel's camera ray landed this pass, and how it takes light
+#[derive(Debug, Copy, Clone)]
+struct Visible {
+    position: Vec3,
+    normal: Vec3, // facing the camera's side
+    weight: Vec3, // throughput to the camera times the brdf
+}
+
+// what each pixel keeps from pass to pass
+#[derive(Debug, Copy, Clone)]
+struct Pixel {
+    radius: Float,
+    photons: Float, // how many it's caught, after shrinking
+    flux: Vec3,     // and how much light they carried in
+    seen: Vec3,     // light the camera ray found on the way, summed over passes
+}
+
+// the whole image, scene.samples passes of `PHOTONS` photons
+pub fn render_image(scene: &Scene, resolution: [usize; 2]) -> (Vec<Vec<Vec3>>, RenderStats) {
+    return render(scene, resolution, PHOTONS);
+}
+
+// render_image with `photons` a pass
+pub fn render(scene: &Scene, resolution: [usize; 2], photons: usize) -> (Vec<Vec<Vec3>>, RenderStats) {
+    let start = Timer::start();
+    let (width, height) = (resolution[0], resolution[1]);
+    let passes = scene.samples.max(1);
+    let focus = if scene.camera.aperture > 0.0 { focus(scene, resolution) } else { scene.camera.focus };
+
+    let mut pixels = vec![None; width * height];
+    let mut stats = RenderStats::default();
+    let mut rng = rand::thread_rng();
+
+    for _ in 0..passes {
+        let (found, counted) = render_tiles(scene, resolution, scene.region, |_, _| true, |scene, uv, resolution, rng| {
+            let (ray, weight) = camera_ray(scene, uv, resolution, focus, rng);
+            return trace_camera(scene, ray, weight, rng);
+        });
+        stats.merge(&counted);
+
+        // the radius only comes from the first pass to land somewhere
+        let mut points = vec![None; width * height];
+        for ([x, y], (seen, visible)) in found {
+            let index = y * width + x;
+            let pixel = pixels[index].get_or_insert(Pixel { radius: 0.0, photons: 0.0, flux: Vec3::new(0.0, 0.0, 0.0), seen: Vec3::new(0.0, 0.0, 0.0) });
+            pixel.seen = pixel.seen + seen;
+
+            if let Some((visible, radius)) = visible {
+                if pixel.radius <= 0.0 { pixel.radius = radius; }
+                points[index] = Some(visible);
+            }
+        }
+
+        let grid = Grid::build(&points, &pixels);
+        let mut caught = vec![(0.0, Vec3::new(0.0, 0.0, 0.0)); width * height];
+
+        shoot(scene, photons, &mut rng, &mut |position, direction, power| {
+            for &index in grid.near(position) {
+                let (visible, pixel) = match (points[index], pixels[index]) {
+                    (Some(visible), Some(pixel)) => (visible, pixel),
+                    _ => continue,
+                };
+
+                if (visible.position - position).length_squared() > pixel.radius * pixel.radius { continue; }
+                if direction.dot(&visible.normal) >= 0.0 { continue; }
+
+                caught[index].0 += 1.0;
+                caught[index].1 = caught[index].1 + visible.weight * power;
+            }
+        });
+
+        for (pixel, (count, flux)) in pixels.iter_mut().zip(caught) {
+            if let Some(pixel) = pixel { update(pixel, count, flux); }
+        }
+    }
+
+    let image = (0..height).map(|y| (0..width).map(|x| match pixels[y * width + x] {
+        Some(pixel) => {
+            let gathered = if pixel.radius > 0.0 { pixel.flux / (PI * pixel.radius * pixel.radius) } else { Vec3::new(0.0, 0.0, 0.0) };
+            (pixel.seen + gathered) / passes as Float
+        },
+        None => Vec3::new(0.0, 0.0, 0.0),
+    }).collect()).collect();
+
+    stats.total = start.elapsed();
+    return (image, stats);
+}
+
+// keeps ALPHA of the photons caught this pass and shrinks the radius to
+// match, so the density stays the same while the blur goes away
+fn update(pixel: &mut Pixel, count: Float, flux: Vec3) {
+    if count <= 0.0 { return; }
+
+    let photons = pixel.photons + ALPHA * count;
+    let shrink = photons / (pixel.photons + count);
+
+    pixel.flux = (pixel.flux + flux) * shrink;
+    pixel.radius *= shrink.sqrt();
+    pixel.photons = photons;
+}
+
+// follows the camera ray through mirrors and glass, weighed by `weight`,
+// returning what glows along the way and where it lands on something
+// rough, with the radius to start gathering at there
+fn trace_camera(scene: &Scene, mut ray: Ray, weight: Vec3, rng: &mut impl Rng) -> (Vec3, Option<(Visible, Float)>) {
+    let mut throughput = weight;
+    let mut seen = Vec3::new(0.0, 0.0, 0.0);
+    let mut travelled = 0.0;
+    let spread = ray.spread;
+    let mut kind = RayKind::Camera;
+    let mut interiors = Interiors::new();
+
+    for _ in 0..BOUNCES {
+        let cast = cast_ray(scene, ray, kind);
+        let (hit, distance, normal, material) = cast.unpack();
+        kind = RayKind::Indirect;
+
+        if !hit || material.emission > 0.0 {
+            seen = seen + throughput * material.color * material.emitted(cast.front_face);
+        }
+        if !hit { break; }
+
+        let position = ray.point_at(&distance);
+        travelled += distance;
+
+        match bounce(ray, position, &cast, &mut interiors, rng) {
+            Some((next, tint)) => {
+                throughput = throughput * tint;
+                ray = next;
+            },
+            None => {
+                let surface = (1.0 - material.emission).max(0.0);
+                let visible = Visible { position: position, normal: normal, weight: throughput * material.color * surface / PI };
+                return (seen, Some((visible, (RADIUS * spread * travelled).max(EPSILON))));
+            },
+        }
+    }
+
+    return (seen, None);
+}
+
+// off a mirror or through glass, the ray that goes on and what it's tinted
+// by, or none if it's rough there. `interiors` is the glass it's in, kept
+// up to date as it goes in and out.
+fn bounce(ray: Ray, position: Vec3, cast: &CastResult, interiors: &mut Interiors, rng: &mut impl Rng) -> Option<(Ray, Vec3)> {
+    let (normal, material) = (cast.normal, &cast.material);
+    let reflected = |tint: Vec3| {
+        let direction = reflect(ray.direction, normal).unit();
+        Some((Ray::new(offset(position, normal, direction), direction), tint))
+    };
+
+    if rng.gen::<Float>() < material.metallic { return reflected(material.color); }
+    if rng.gen::<Float>() >= material.transmission { return None; }
+
+    // inside something that wins over it, its surface isn't really there
+    let (from, to) = match interiors.crossing(cast.object, material, cast.front_face) {
+        Some(iors) => iors,
+        None => {
+            interiors.cross(cast.object, material, cast.front_face);
+            return Some((Ray::new(offset(position, normal, ray.direction), ray.direction), Vec3::new(1.0, 1.0, 1.0)));
+        },
+    };
+    let cosine = -ray.direction.dot(&normal);
+
+    let mut refracted = Vec3::new(0.0, 0.0, 0.0);
+    if refract(&ray.direction, &normal, from / to, &mut refracted) && rng.gen::<Float>() >= fresnel(cosine, to / from) {
+        interiors.cross(cast.object, material, cast.front_face);
+        let direction = refracted.unit();
+        return Some((Ray::new(offset(position, normal, direction), direction), material.color));
+    }
+    return reflected(Vec3::new(1.0, 1.0, 1.0));
+}
+
+// shoots `photons` from the emitters the way PhotonMap::build does, handing
+// every landing on something rough to `land`, with where it was going and
+// the light it carries. they go on from there in a cosine lobe, as many of
+// them as the surface's color lets through.
+fn shoot(scene: &Scene, photons: usize, rng: &mut impl Rng, land: &mut impl FnMut(Vec3, Vec3, Vec3)) {
+    let per_emitter = photons / scene.emitters.len().max(1);
+
+    for emitter in &scene.emitters {
+        for _ in 0..per_emitter {
+            let normal = random_unit(rng);
+            let origin = emitter.position + normal * emitter.radius;
+            let mut direction = (normal + random_unit(rng)).unit();
+            if direction.dot(&normal) <= 0.0 { direction = normal; }
+
+            let mut ray = Ray::new(origin + normal * EPSILON, direction);
+            let mut power = emitter.power / per_emitter as Float;
+            let mut interiors = Interiors::new();
+
+            for _ in 0..BOUNCES {
+                let cast = cast_ray(scene, ray, RayKind::Indirect);
+                let (hit, distance, normal, material) = cast.unpack();
+                if !hit { break; }
+
+                let position = ray.point_at(&distance);
+
+                if let Some((next, tint)) = bounce(ray, position, &cast, &mut interiors, rng) {
+                    power = power * tint;
+                    ray = next;
+                    continue;
+                }
+
+                land(position, ray.direction, power);
+
+                // russian roulette on the albedo, made up for by the ones that go on
+                let albedo = material.color * (1.0 - material.emission).max(0.0);
+                let survival = albedo.x.max(albedo.y).max(albedo.z).min(1.0);
+                if survival <= 0.0 || rng.gen::<Float>() >= survival { break; }
+                power = power * albedo / survival;
+
+                let direction = (normal + random_unit(rng)).unit();
+                ray = Ray::new(offset(position, normal, direction), direction);
+            }
+        }
+    }
+}
+
+// the pixels' visible points in cells as big as the widest radius, each in
+// every cell its sphere reaches into, so a photon only has to look in its own
+struct Grid {
+    size: Float,
+    cells: HashMap<[i64; 3], Vec<usize>>,
+}
+
+impl Grid {
+    fn build(points: &[Option<Visible>], pixels: &[Option<Pixel>]) -> Grid {
+        let size = pixels.iter().flatten().map(|pixel| pixel.radius).fold(EPSILON, Float::max);
+        let mut grid = Grid { size: size, cells: HashMap::new() };
+
+        for (index, (point, pixel)) in points.iter().zip(pixels).enumerate() {
+            let (point, pixel) = match (point, pixel) {
+                (Some(point), Some(pixel)) => (point, pixel),
+                _ => continue,
+            };
+
+            let extent = Vec3::new(1.0, 1.0, 1.0) * pixel.radius;
+            let (low, high) = (grid.cell(point.position - extent), grid.cell(point.position + extent));
+            for x in low[0]..=high[0] {
+                for y in low[1]..=high[1] {
+                    for z in low[2]..=high[2] {
+                        grid.cells.entry([x, y, z]).or_default().push(index);
+                    }
+                }
+            }
+        }
+
+        return grid;
+    }
+
+    fn cell(&self, point: Vec3) -> [i64; 3] {
+        let local = point / self.size;
+        [local.x.floor() as i64, local.y.floor() as i64, local.z.floor() as i64]
+    }
+
+    fn near(&self, point: Vec3) -> &[usize] {
+        self.cells.get(&self.cell(point)).map_or(&[], |points| points.as_slice())
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::render;
+    use crate::structures::float::Float;
+    use crate::structures::float::consts::PI;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::camera::Camera;
+    use crate::structures::material::Material;
+    use crate::structures::scene::Scene;
+    use crate::structures::photon_map::Emitter;
+    use crate::render::Integrator;
+    use crate::objects::plane::Plane;
+
+    #[test]
+    fn test_sppm() {
+        // a white floor a unit under a tiny light giving out 4π, so the
+        // irradiance straight under it is 1 and the floor looks 1/π there
+        let white = Material { color: Vec3::new(1.0, 1.0, 1.0), emission: 0.0, ..Material::blank() };
+        let mut camera = Camera::new(Vec3::new(0.0, 2.0, 0.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        camera.fov = 10.0;
+
+        let floor = |emitters: &[Emitter]| {
+            let mut scene = Scene::builder()
+                .camera(camera)
+                .environment(Vec3::new(0.0, 0.0, 0.0))
+                .add(Plane::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), white))
+                .integrator(Integrator::Sppm)
+                .samples(4)
+                .build();
+            scene.emitters = emitters.to_vec();
+            scene
+        };
+
+        let light = Emitter::new(Vec3::new(0.0, 1.0, 0.0), 0.0, Vec3::new(4.0 * PI, 4.0 * PI, 4.0 * PI));
+        let (image, _) = render(&floor(&[light]), [16, 16], 20_000);
+        let expected = 1.0 / PI;
+        let mean = image.iter().flatten().map(|pixel| pixel.x).sum::<Float>() / 256.0;
+        assert!((mean - expected).abs() < 0.15 * expected, "{} against {}", mean, expected);
+
+        // nothing to shoot photons from, a black floor under a black sky
+        assert!(render(&floor(&[]), [2, 2], 100).0.iter().flatten().all(|pixel| pixel.x == 0.0));
+    }
+}
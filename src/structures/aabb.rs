@@ -0,0 +1,99 @@
+use serde::{ Serialize, Deserialize };
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::ray::Ray;
+
+// axis aligned bounding box
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Aabb {
+        Aabb { min: min, max: max }
+    }
+
+    // an inside-out box, grows into the first thing added to it
+    pub fn empty() -> Aabb {
+        Aabb {
+            min: Vec3::max(),
+            max: Vec3::max() * -1.0,
+        }
+    }
+
+    pub fn around(points: &[Vec3]) -> Aabb {
+        points.iter().fold(Aabb::empty(), |bounds, point| bounds.grow(point))
+    }
+
+    pub fn grow(&self, point: &Vec3) -> Aabb {
+        Aabb::new(self.min.min_by(point), self.max.max_by(point))
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb::new(self.min.min_by(&other.min), self.max.max_by(&other.max))
+    }
+
+    // bigger by `by` on every side
+    pub fn padded(&self, by: Float) -> Aabb {
+        let by = Vec3::new(by, by, by);
+        Aabb::new(self.min - by, self.max + by)
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn extent(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    // index of the longest side
+    pub fn longest_axis(&self) -> usize {
+        let extent = self.extent();
+
+        if extent.x >= extent.y && extent.x >= extent.z { return 0; }
+        if extent.y >= extent.z { return 1; }
+        return 2;
+    }
+
+    pub fn surface_area(&self) -> Float {
+        let e = self.extent();
+        return 2.0 * (e.x * e.y + e.y * e.z + e.z * e.x);
+    }
+
+    // zero inside the box
+    pub fn distance(&self, point: &Vec3) -> Float {
+        let zero = Vec3::new(0.0, 0.0, 0.0);
+        let outside = (self.min - *point).max_by(&zero).max_by(&(*point - self.max));
+        return outside.length();
+    }
+
+    pub fn contains(&self, point: &Vec3) -> bool {
+        (0..3).all(|axis| point.axis(axis) >= self.min.axis(axis) && point.axis(axis) <= self.max.axis(axis))
+    }
+
+    // slab test, returns the distance the ray enters the box at
+    pub fn hit(&self, ray: &Ray, max: Float) -> Option<Float> {
+        let mut near = 0.0;
+        let mut far = max;
+
+        for axis in 0..3 {
+            let inverse = 1.0 / ray.direction.axis(axis);
+            let mut t0 = (self.min.axis(axis) - ray.origin.axis(axis)) * inverse;
+            let mut t1 = (self.max.axis(axis) - ray.origin.axis(axis)) * inverse;
+
+            if inverse < 0.0 { std::mem::swap(&mut t0, &mut t1); }
+
+            // written this way round so NaNs from 0 * inf are ignored
+            near = if t0 > near { t0 } else { near };
+            far = if t1 < far { t1 } else { far };
+
+            if far < near { return None; }
+        }
+
+        return Some(near);
+    }
+}
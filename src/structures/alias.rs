@@ -0,0 +1,88 @@
+use crate::structures::float::Float;
+
+// picks one of a list of things in proportion to its weight, in constant
+// time however many there are, with walker's alias method (vose's way of
+// building it). every slot holds one thing with some chance and another
+// with the rest, so a pick is one slot and one coin.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AliasTable {
+    chances: Vec<Float>,
+    aliases: Vec<usize>,
+    probabilities: Vec<Float>,
+}
+
+impl AliasTable {
+    // none if there's nothing to pick, all the weights being zero
+    pub fn new(weights: &[Float]) -> Option<AliasTable> {
+        let total: Float = weights.iter().map(|weight| weight.max(0.0)).sum();
+        if weights.is_empty() || total <= 0.0 || !total.is_finite() { return None; }
+
+        let count = weights.len();
+        let probabilities: Vec<Float> = weights.iter().map(|weight| weight.max(0.0) / total).collect();
+        let mut chances: Vec<Float> = probabilities.iter().map(|probability| probability * count as Float).collect();
+        let mut aliases: Vec<usize> = (0..count).collect();
+
+        let (mut small, mut large): (Vec<usize>, Vec<usize>) = (0..count).partition(|&index| chances[index] < 1.0);
+        while let (Some(&under), Some(&over)) = (small.last(), large.last()) {
+            small.pop();
+            aliases[under] = over;
+            chances[over] -= 1.0 - chances[under];
+            if chances[over] < 1.0 {
+                large.pop();
+                small.push(over);
+            }
+        }
+
+        // whatever's left over is only off by rounding
+        for index in small.into_iter().chain(large) {
+            chances[index] = 1.0;
+        }
+
+        return Some(AliasTable { chances: chances, aliases: aliases, probabilities: probabilities });
+    }
+
+    pub fn len(&self) -> usize {
+        self.chances.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chances.is_empty()
+    }
+
+    // which one `u` in [0, 1) lands on, and the chance of picking it
+    pub fn sample(&self, u: Float) -> (usize, Float) {
+        let scaled = u * self.len() as Float;
+        let slot = (scaled as usize).min(self.len() - 1);
+        let coin = scaled - slot as Float;
+
+        let index = if coin < self.chances[slot] { slot } else { self.aliases[slot] };
+        return (index, self.probabilities[index]);
+    }
+
+    pub fn probability(&self, index: usize) -> Float {
+        self.probabilities[index]
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::AliasTable;
+    use crate::structures::float::Float;
+
+    #[test]
+    fn test_alias_table() {
+        assert!(AliasTable::new(&[]).is_none());
+        assert!(AliasTable::new(&[0.0, 0.0]).is_none());
+
+        // picked as often as they weigh, and never if they weigh nothing
+        let table = AliasTable::new(&[1.0, 0.0, 3.0, 4.0]).unwrap();
+        let mut counts = [0; 4];
+        for i in 0..8000 {
+            let (index, probability) = table.sample((i as Float + 0.5) / 8000.0);
+            assert_eq!(probability, table.probability(index));
+            counts[index] += 1;
+        }
+        assert_eq!(counts, [1000, 0, 3000, 4000]);
+        assert_eq!(table.probability(3), 0.5);
+    }
+}
@@ -0,0 +1,187 @@
+use std::sync::OnceLock;
+
+use crate::structures::float::Float;
+
+// a tile of numbers in [0, 1) where close pixels are as unalike as they can
+// be, made once with ulichney's void and cluster. shifting each pixel's
+// samples by its own number instead of a random one leaves the same amount
+// of noise, only fine grained: neighbours go wrong in opposite directions,
+// so what's left looks like grain instead of blotches, and a blur or a
+// denoiser takes it out much more easily.
+
+const SIZE: usize = 64; // pixels along each side, it wraps around
+const SIGMA: Float = 1.5; // how far apart pixels still count as close
+const GOLDEN: Float = 0.618_034; // frame to frame, see offsets
+
+// the tile, ranks over SIZE * SIZE, row by row
+fn mask() -> &'static [Float] {
+    static MASK: OnceLock<Vec<Float>> = OnceLock::new();
+    return MASK.get_or_init(|| {
+        let total = (SIZE * SIZE) as Float;
+        void_and_cluster().iter().map(|rank| (*rank as Float + 0.5) / total).collect()
+    });
+}
+
+// the tile's number at `pixel`
+pub fn value(pixel: [usize; 2]) -> Float {
+    mask()[(pixel[1] % SIZE) * SIZE + pixel[0] % SIZE]
+}
+
+// the shifts for each of a pixel's `N` sample axes. every axis reads the
+// tile somewhere else so they don't all move together, and each frame
+// turns them all a golden ratio further round, so frames averaged together
+// don't keep the same error.
+pub fn offsets<const N: usize>(pixel: [usize; 2], frame: u32) -> [Float; N] {
+    let mut offsets = [0.0; N];
+    for (axis, offset) in offsets.iter_mut().enumerate() {
+        // far apart and not on a line
+        let [dx, dy] = [axis * 23 + axis / 3 * 7, axis * 41 + axis / 2 * 13];
+        *offset = (value([pixel[0] + dx, pixel[1] + dy]) + frame as Float * GOLDEN).fract();
+    }
+    return offsets;
+}
+
+// how much each pixel is crowded by the ones set around it, through a
+// gaussian that wraps around the tile
+struct Energy {
+    falloff: Vec<Float>, // by how far apart, along x and y
+    energy: Vec<Float>,
+}
+
+impl Energy {
+    fn new(pattern: &[bool]) -> Energy {
+        let mut falloff = vec![0.0; SIZE * SIZE];
+        for dy in 0..SIZE {
+            for dx in 0..SIZE {
+                let (x, y) = (dx.min(SIZE - dx) as Float, dy.min(SIZE - dy) as Float);
+                falloff[dy * SIZE + dx] = (-(x * x + y * y) / (2.0 * SIGMA * SIGMA)).exp();
+            }
+        }
+
+        let mut energy = Energy { falloff: falloff, energy: vec![0.0; SIZE * SIZE] };
+        for (index, set) in pattern.iter().enumerate() {
+            if *set { energy.change(index, 1.0); }
+        }
+        return energy;
+    }
+
+    // setting a pixel is `sign` 1, clearing it -1
+    fn change(&mut self, index: usize, sign: Float) {
+        let (px, py) = (index % SIZE, index / SIZE);
+        for y in 0..SIZE {
+            for x in 0..SIZE {
+                let (dx, dy) = ((x + SIZE - px) % SIZE, (y + SIZE - py) % SIZE);
+                self.energy[y * SIZE + x] += sign * self.falloff[dy * SIZE + dx];
+            }
+        }
+    }
+
+    // the most crowded of the set pixels, or the least of the clear ones
+    fn tightest(&self, pattern: &[bool]) -> usize {
+        self.extreme(pattern, true, |a, b| a > b)
+    }
+
+    fn emptiest(&self, pattern: &[bool]) -> usize {
+        self.extreme(pattern, false, |a, b| a < b)
+    }
+
+    fn extreme(&self, pattern: &[bool], set: bool, better: impl Fn(Float, Float) -> bool) -> usize {
+        let mut best = None;
+        for (index, is) in pattern.iter().enumerate() {
+            if *is != set { continue; }
+            if best.is_none_or(|best: usize| better(self.energy[index], self.energy[best])) { best = Some(index); }
+        }
+        return best.expect("there's always one of each while ranking");
+    }
+}
+
+// every pixel's rank, 0 for the first to be set
+fn void_and_cluster() -> Vec<usize> {
+    let count = SIZE * SIZE;
+
+    // a tenth of the pixels set, anywhere, but the same every time
+    let mut state: u32 = 0x9e37_79b9;
+    let mut pattern = vec![false; count];
+    let mut set = 0;
+    while set < count / 10 {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let index = state as usize % count;
+        if !pattern[index] { pattern[index] = true; set += 1; }
+    }
+
+    // spread them out until moving the most crowded one into the biggest
+    // gap would put it straight back
+    let mut energy = Energy::new(&pattern);
+    loop {
+        let cluster = energy.tightest(&pattern);
+        pattern[cluster] = false;
+        energy.change(cluster, -1.0);
+
+        let void = energy.emptiest(&pattern);
+        pattern[void] = true;
+        energy.change(void, 1.0);
+        if void == cluster { break; }
+    }
+
+    let mut ranks = vec![0; count];
+
+    // the set ones are ranked by taking away the most crowded each time
+    let mut taken = pattern.clone();
+    let mut crowd = Energy::new(&taken);
+    for rank in (0..set).rev() {
+        let cluster = crowd.tightest(&taken);
+        taken[cluster] = false;
+        crowd.change(cluster, -1.0);
+        ranks[cluster] = rank;
+    }
+
+    // and the rest by filling in the biggest gap each time
+    for rank in set..count {
+        let void = energy.emptiest(&pattern);
+        pattern[void] = true;
+        energy.change(void, 1.0);
+        ranks[void] = rank;
+    }
+
+    return ranks;
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::{ value, offsets, SIZE };
+    use crate::structures::float::Float;
+
+    #[test]
+    fn test_blue_noise() {
+        // every number once, and it wraps around
+        let mut values: Vec<Float> = (0..SIZE * SIZE).map(|index| value([index % SIZE, index / SIZE])).collect();
+        assert_eq!(value([3, 5]), value([3 + SIZE, 5 + 2 * SIZE]));
+        values.sort_by(|a, b| a.total_cmp(b));
+        assert!(values.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(values[0] > 0.0 && values[values.len() - 1] < 1.0);
+
+        // neighbours are further apart than at random, where it'd be a
+        // third on average, and any 4x4 block averages out close to a half
+        let apart = (0..SIZE * SIZE).map(|index| {
+            let [x, y] = [index % SIZE, index / SIZE];
+            (value([x, y]) - value([x + 1, y])).abs()
+        }).sum::<Float>() / (SIZE * SIZE) as Float;
+        assert!(apart > 0.38);
+
+        for by in 0..SIZE / 4 {
+            for bx in 0..SIZE / 4 {
+                let block = (0..16).map(|i| value([bx * 4 + i % 4, by * 4 + i / 4])).sum::<Float>() / 16.0;
+                assert!((block - 0.5).abs() < 0.12);
+            }
+        }
+
+        // each axis and frame shifts differently
+        let first: [Float; 6] = offsets([10, 20], 0);
+        let next: [Float; 6] = offsets([10, 20], 1);
+        assert!(first.iter().all(|offset| (0.0..1.0).contains(offset)));
+        assert!(first.windows(2).all(|pair| pair[0] != pair[1]));
+        assert!(first.iter().zip(next.iter()).all(|(a, b)| a != b));
+    }
+}
@@ -0,0 +1,212 @@
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::aabb::Aabb;
+use crate::structures::ray::Ray;
+use crate::structures::stats::{ count, Counter };
+
+const LEAF_SIZE: usize = 4;
+
+#[derive(Debug, Copy, Clone)]
+struct Node {
+    bounds: Aabb,
+    // leaves: first primitive in `indices`, branches: index of the left child
+    start: usize,
+    // 0 for branches
+    count: usize,
+}
+
+// bounding volume hierarchy over anything that can be boxed.
+// it only stores indices, the owner keeps the actual primitives.
+#[derive(Debug, Clone)]
+pub struct Bvh {
+    nodes: Vec<Node>,
+    pub indices: Vec<usize>,
+}
+
+impl Bvh {
+    pub fn new(bounds: &[Aabb]) -> Bvh {
+        let mut bvh = Bvh {
+            nodes: vec![],
+            indices: (0..bounds.len()).collect(),
+        };
+
+        if !bounds.is_empty() {
+            bvh.nodes.push(Node { bounds: Aabb::empty(), start: 0, count: bounds.len() });
+            bvh.split(0, bounds);
+        }
+
+        return bvh;
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        match self.nodes.first() {
+            Some(root) => root.bounds,
+            None => Aabb::empty(),
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        fn depth(nodes: &[Node], node: usize) -> usize {
+            let node = nodes[node];
+            if node.count > 0 { return 1; }
+            return 1 + depth(nodes, node.start).max(depth(nodes, node.start + 1));
+        }
+
+        return if self.nodes.is_empty() { 0 } else { depth(&self.nodes, 0) };
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    // what the nodes and indices take up
+    pub fn bytes(&self) -> usize {
+        self.nodes.len() * std::mem::size_of::<Node>() + self.indices.len() * std::mem::size_of::<usize>()
+    }
+
+    // median split along the longest axis of the centroids
+    fn split(&mut self, node: usize, bounds: &[Aabb]) {
+        let Node { start, count, .. } = self.nodes[node];
+        let slice = &mut self.indices[start..start + count];
+
+        let mut boxed = Aabb::empty();
+        let mut centers = Aabb::empty();
+        for index in slice.iter() {
+            boxed = boxed.union(&bounds[*index]);
+            centers = centers.grow(&bounds[*index].center());
+        }
+        self.nodes[node].bounds = boxed;
+
+        if count <= LEAF_SIZE { return; }
+
+        let axis = centers.longest_axis();
+        slice.sort_by(|a, b| {
+            bounds[*a].center().axis(axis)
+                .partial_cmp(&bounds[*b].center().axis(axis))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let half = count / 2;
+        let left = self.nodes.len();
+        self.nodes.push(Node { bounds: Aabb::empty(), start: start,        count: half });
+        self.nodes.push(Node { bounds: Aabb::empty(), start: start + half, count: count - half });
+        self.nodes[node] = Node { bounds: boxed, start: left, count: 0 };
+
+        self.split(left, bounds);
+        self.split(left + 1, bounds);
+    }
+
+    // walks the tree front to back. `test` intersects a single primitive
+    // and returns its distance; the closest (index, distance) is returned.
+    pub fn traverse(&self, ray: &Ray, mut test: impl FnMut(usize) -> Option<Float>) -> Option<(usize, Float)> {
+        let mut best: Option<(usize, Float)> = None;
+        let mut closest = Float::MAX;
+
+        if self.nodes.is_empty() || self.nodes[0].bounds.hit(ray, closest).is_none() {
+            return None;
+        }
+
+        let mut stack = vec![0];
+
+        while let Some(index) = stack.pop() {
+            let node = self.nodes[index];
+            count(Counter::BvhVisits, 1);
+
+            if node.count > 0 {
+                for primitive in &self.indices[node.start..node.start + node.count] {
+                    if let Some(distance) = test(*primitive) {
+                        if distance < closest {
+                            closest = distance;
+                            best = Some((*primitive, distance));
+                        }
+                    }
+                }
+                continue;
+            }
+
+            let left = self.nodes[node.start].bounds.hit(ray, closest);
+            let right = self.nodes[node.start + 1].bounds.hit(ray, closest);
+
+            // push the far child first so the near one is visited first
+            match (left, right) {
+                (Some(l), Some(r)) => {
+                    if l < r {
+                        stack.push(node.start + 1);
+                        stack.push(node.start);
+                    } else {
+                        stack.push(node.start);
+                        stack.push(node.start + 1);
+                    }
+                },
+                (Some(_), None) => stack.push(node.start),
+                (None, Some(_)) => stack.push(node.start + 1),
+                (None, None) => (),
+            }
+        }
+
+        return best;
+    }
+
+    // calls `visit` for every primitive in a leaf the ray passes through
+    pub fn each(&self, ray: &Ray, mut visit: impl FnMut(usize)) {
+        if self.nodes.is_empty() { return; }
+
+        let mut stack = vec![0];
+
+        while let Some(index) = stack.pop() {
+            let node = self.nodes[index];
+            count(Counter::BvhVisits, 1);
+            if node.bounds.hit(ray, Float::MAX).is_none() { continue; }
+
+            if node.count > 0 {
+                self.indices[node.start..node.start + node.count].iter().for_each(|p| visit(*p));
+            } else {
+                stack.push(node.start);
+                stack.push(node.start + 1);
+            }
+        }
+    }
+
+    // closest primitive to a point, `distance` measures a single primitive
+    pub fn nearest(&self, point: &Vec3, mut distance: impl FnMut(usize) -> Float) -> Option<(usize, Float)> {
+        let mut best: Option<(usize, Float)> = None;
+        let mut closest = Float::MAX;
+
+        if self.nodes.is_empty() { return None; }
+
+        let mut stack = vec![0];
+
+        while let Some(index) = stack.pop() {
+            let node = self.nodes[index];
+            // a box around the point can still hold something further in
+            // than a surface already found inside
+            let gap = node.bounds.distance(point);
+            if gap >= closest && gap > 0.0 { continue; }
+
+            if node.count > 0 {
+                for primitive in &self.indices[node.start..node.start + node.count] {
+                    let d = distance(*primitive);
+                    if d < closest {
+                        closest = d;
+                        best = Some((*primitive, d));
+                    }
+                }
+                continue;
+            }
+
+            // visit the nearer child first, it's likely to shrink `closest`
+            let left = self.nodes[node.start].bounds.distance(point);
+            let right = self.nodes[node.start + 1].bounds.distance(point);
+
+            if left < right {
+                stack.push(node.start + 1);
+                stack.push(node.start);
+            } else {
+                stack.push(node.start);
+                stack.push(node.start + 1);
+            }
+        }
+
+        return best;
+    }
+}
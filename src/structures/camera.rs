@@ -1,19 +1,285 @@
+use serde::{ Serialize, Deserialize };
+
+use crate::structures::float::Float;
+use crate::structures::float::consts::PI;
 use crate::structures::vec3::Vec3;
 use crate::structures::ray::Ray;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct Camera {
     pub ray: Ray,
     pub up: Vec3,
+    pub fov: Float,
+
+    // depth of field. light comes in over a lens `aperture` wide, so only
+    // what's `focus` in front of it is sharp. 0 is a pinhole, sharp all over.
+    #[serde(default)]
+    pub aperture: Float,
+    #[serde(default = "focus")]
+    pub focus: Float,
+    // focus on whatever is at this point of the picture instead, from 0, 0
+    // at the top left to 1, 1 at the bottom right, see render::focus
+    #[serde(default)]
+    pub autofocus: Option<[Float; 2]>,
+
+    // lens imperfections, none by default. `distortion` bends straight lines
+    // the further out they are: more than 0 bows them out like a wide angle
+    // lens does (barrel), less than 0 pinches them in (pincushion).
+    // `aberration` fringes the colors towards the edges, red bent that much
+    // more than green and blue that much less, 0.01 being plenty.
+    #[serde(default)]
+    pub distortion: Float,
+    #[serde(default)]
+    pub aberration: Float,
+
+    // how long the shutter's open, camera rays go out at times from 0 to
+    // this, see Ray::time. nothing in a scene moves yet.
+    #[serde(default)]
+    pub shutter: Float,
+
+    // how the picture's fitted around what fov frames, so the same shot
+    // comes out of any resolution, see Framing
+    #[serde(default)]
+    pub framing: Framing,
+}
+
+// fov is always from the bottom of some frame to its top. by default that's
+// the picture's, and a wider picture only shows more at the sides, but a
+// taller one crops them off: a shot set up at 16:9 loses its edges square.
+// fitting or filling instead keeps a frame of its own, the numbers being
+// how many times wider than high it is, usually the resolution the shot
+// was set up at, and the picture's grown or shrunk around it.
+#[derive(Debug, Copy, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub enum Framing {
+    #[default]
+    Height,      // the picture's height is the frame's, whatever its width
+    Fit(Float),  // all of the frame is in the picture, with more of the scene on the long side
+    Fill(Float), // the picture's covered by the frame, with its long side cropped
 }
 
+// where a camera ray goes through the picture, the lens and the exposure,
+// see Camera::generate_ray. all but `pixel` are 0 to 1.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CameraSample {
+    pub pixel: [Float; 2], // in pixels from the bottom left, jitter and all
+    pub lens: [Float; 2],  // where on the lens, for depth of field
+    pub time: Float,       // when while the shutter's open
+    pub color: Float,      // which color it carries with chromatic aberration
+}
+
+impl CameraSample {
+    // through the middle of everything
+    pub fn center(pixel: [Float; 2]) -> CameraSample {
+        CameraSample { pixel: pixel, lens: [0.0, 0.0], time: 0.0, color: 0.5 }
+    }
+}
+
+// the radical inverse of `index` in `base`, the halton sequence's points
+// along one axis. they spread out evenly however many are taken.
+pub fn halton(mut index: u32, base: u32) -> Float {
+    let mut result = 0.0;
+    let mut fraction = 1.0 / base as Float;
+
+    while index > 0 {
+        result += (index % base) as Float * fraction;
+        index /= base;
+        fraction /= base as Float;
+    }
+
+    return result;
+}
+
+fn focus() -> Float { 1.0 }
+
 impl Camera {
-    pub fn new(from: Vec3, to: Vec3, up: Vec3) -> Camera{
+    pub fn new(from: Vec3, to: Vec3, up: Vec3) -> Camera {
         let f = (to - from).unit();
 
         Camera {
             ray: Ray::new(from, f),
             up: up,
+            fov: 60.0, // standard fov
+            aperture: 0.0,
+            focus: 1.0,
+            autofocus: None,
+            distortion: 0.0,
+            aberration: 0.0,
+            shutter: 0.0,
+            framing: Framing::Height,
         }
     }
+
+    // half the picture's width and height at the distance where what fov
+    // frames is one unit high, see Framing
+    pub fn half_size(&self, resolution: [usize; 2]) -> [Float; 2] {
+        let ratio = resolution[0] as Float / resolution[1].max(1) as Float;
+        let height = match self.framing {
+            Framing::Height => 1.0,
+            Framing::Fit(aspect) => (aspect / ratio).max(1.0),
+            Framing::Fill(aspect) => (aspect / ratio).min(1.0),
+        };
+        return [ratio * height * 0.5, height * 0.5];
+    }
+
+    // the camera ray for a sample, and what whatever it sees is weighed
+    // by. the picture's `resolution` pixels across and half_size at as far
+    // from the camera as fov puts it, so the middle goes straight ahead. then the lens bends it, see distortion and aberration, and
+    // moves it to start somewhere on the aperture, still going through the
+    // same point on the plane in focus so only that plane stays sharp. with
+    // chromatic aberration each ray only carries one of the colors, and
+    // three times as much of it to make up for the other two.
+    pub fn generate_ray(&self, sample: &CameraSample, resolution: [usize; 2]) -> (Ray, Vec3) {
+        let [half_x, half_y] = self.half_size(resolution);
+        let z = 1.0 / (self.fov.to_radians() / 2.0).tan();
+
+        let (bend, weight) = if self.aberration == 0.0 {
+            (1.0, Vec3::new(1.0, 1.0, 1.0))
+        } else {
+            match (sample.color * 3.0) as usize {
+                0 => (1.0 + self.aberration, Vec3::new(3.0, 0.0, 0.0)),
+                1 => (1.0, Vec3::new(0.0, 3.0, 0.0)),
+                _ => (1.0 - self.aberration, Vec3::new(0.0, 0.0, 3.0)),
+            }
+        };
+
+        // on the picture, from the middle, bent by the lens
+        let x = (sample.pixel[0] / resolution[0] as Float * 2.0 - 1.0) * half_x;
+        let y = (sample.pixel[1] / resolution[1] as Float * 2.0 - 1.0) * half_y;
+        let scale = (1.0 + self.distortion * (x * x + y * y)) * bend;
+
+        let f = self.ray.direction;
+        let s = f.cross(&self.up).unit();
+        let u = s.cross(&f);
+
+        let direction = (s * (x * scale) + u * (y * scale) + f * z).unit();
+        // the angle one pixel covers, near enough, for cone tracing
+        let spread = 2.0 * half_y / (resolution[1] as Float * z);
+        let ray = Ray::new(self.ray.origin, direction).with_spread(spread).with_time(sample.time * self.shutter);
+
+        if self.aperture <= 0.0 { return (ray, weight); }
+
+        let target = ray.point_at(&(self.focus / direction.dot(&f)));
+
+        // uniform over the disk
+        let radius = self.aperture * 0.5 * sample.lens[0].sqrt();
+        let angle = 2.0 * PI * sample.lens[1];
+        let origin = ray.origin + s * (radius * angle.cos()) + u * (radius * angle.sin());
+
+        return (Ray { origin: origin, direction: (target - origin).unit(), ..ray }, weight);
+    }
+
+    // the other way: where on the picture `point` shows up, in pixels from
+    // the bottom left like CameraSample::pixel, or none if it's behind the
+    // camera. the lens is taken to be perfect, without distortion.
+    pub fn project(&self, point: Vec3, resolution: [usize; 2]) -> Option<[Float; 2]> {
+        let [half_x, half_y] = self.half_size(resolution);
+        let z = 1.0 / (self.fov.to_radians() / 2.0).tan();
+
+        let f = self.ray.direction;
+        let s = f.cross(&self.up).unit();
+        let u = s.cross(&f);
+
+        let d = point - self.ray.origin;
+        let ahead = d.dot(&f);
+        if ahead <= 0.0 { return None; }
+
+        let (x, y) = (d.dot(&s) / ahead * z, d.dot(&u) / ahead * z);
+        return Some([(x / half_x + 1.0) * 0.5 * resolution[0] as Float, (y / half_y + 1.0) * 0.5 * resolution[1] as Float]);
+    }
+
+    // part way from this camera to `other`, `t` from 0 to 1, for moving
+    // one over the frames of an animation. where it looks turns the short
+    // way round, and everything else goes straight across.
+    pub fn lerp(&self, other: &Camera, t: Float) -> Camera {
+        let mix = |a: Float, b: Float| a + (b - a) * t;
+        let direction = self.ray.direction * (1.0 - t) + other.ray.direction * t;
+        let direction = if direction.length() > 1e-6 { direction.unit() } else { other.ray.direction };
+
+        return Camera {
+            ray: Ray::new(self.ray.origin * (1.0 - t) + other.ray.origin * t, direction),
+            up: (self.up * (1.0 - t) + other.up * t).unit(),
+            fov: mix(self.fov, other.fov),
+            aperture: mix(self.aperture, other.aperture),
+            focus: mix(self.focus, other.focus),
+            autofocus: if t < 0.5 { self.autofocus } else { other.autofocus },
+            distortion: mix(self.distortion, other.distortion),
+            aberration: mix(self.aberration, other.aberration),
+            shutter: mix(self.shutter, other.shutter),
+            framing: if t < 0.5 { self.framing } else { other.framing },
+        };
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::{ Camera, CameraSample, Framing, halton };
+    use crate::structures::float::Float;
+    use crate::structures::vec3::Vec3;
+
+    #[test]
+    fn test_generate_ray() {
+        let sequence = [halton(1, 2), halton(2, 2), halton(3, 2), halton(1, 3), halton(4, 3)];
+        assert!(sequence.iter().zip([0.5, 0.25, 0.75, 1.0 / 3.0, 4.0 / 9.0].iter()).all(|(a, b)| (a - b).abs() < 0.0001));
+
+        let camera = Camera::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0));
+        let straight = |ray: Vec3| (ray - Vec3::new(0.0, 0.0, -1.0)).length() < 0.0001;
+
+        // the middle of the picture goes straight ahead, and the right of
+        // it off to the right
+        let (ray, weight) = camera.generate_ray(&CameraSample::center([100.0, 50.0]), [200, 100]);
+        assert!(straight(ray.direction) && weight == Vec3::new(1.0, 1.0, 1.0));
+        assert!(camera.generate_ray(&CameraSample::center([150.0, 50.0]), [200, 100]).0.direction.x > 0.0);
+
+        // off the lens, but still through the same point in focus
+        let lens = Camera { aperture: 0.5, focus: 3.0, ..camera };
+        let sample = CameraSample { lens: [0.9, 0.3], ..CameraSample::center([100.0, 50.0]) };
+        let (ray, _) = lens.generate_ray(&sample, [200, 100]);
+        assert!(ray.origin.length() > 0.1 && !straight(ray.direction));
+        assert!((ray.point_at(&(3.0 / -ray.direction.z)) - Vec3::new(0.0, 0.0, -3.0)).length() < 0.0001);
+
+        // times are over the shutter, and colors split with aberration
+        let slow = Camera { shutter: 0.5, aberration: 0.01, ..camera };
+        let (ray, weight) = slow.generate_ray(&CameraSample { time: 0.5, color: 0.1, ..CameraSample::center([0.0, 0.0]) }, [200, 100]);
+        assert!(ray.time == 0.25 && weight == Vec3::new(3.0, 0.0, 0.0));
+
+        // and projected back onto the picture where it came from
+        let (ray, _) = camera.generate_ray(&CameraSample::center([37.5, 80.25]), [200, 100]);
+        let [x, y] = camera.project(ray.point_at(&4.0), [200, 100]).unwrap();
+        assert!((x - 37.5).abs() < 0.001 && (y - 80.25).abs() < 0.001);
+        assert!(camera.project(Vec3::new(0.0, 0.0, 1.0), [200, 100]).is_none());
+
+        // half way between two cameras is half way
+        let other = Camera::new(Vec3::new(2.0, 0.0, 0.0), Vec3::new(2.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(camera.lerp(&other, 0.5).ray.origin, Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(camera.lerp(&other, 1.0).ray.direction, other.ray.direction);
+    }
+
+    #[test]
+    fn test_framing() {
+        let camera = Camera::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0));
+        let corner = |camera: &Camera, resolution: [usize; 2]| {
+            camera.generate_ray(&CameraSample::center([resolution[0] as Float, resolution[1] as Float]), resolution).0.direction
+        };
+        let apart = |a: Vec3, b: Vec3| (a - b).length();
+
+        // set up at 16:9, the top right corner goes the same way at any
+        // size of it
+        let fitted = Camera { framing: Framing::Fit(16.0 / 9.0), ..camera };
+        assert!(apart(corner(&fitted, [1920, 1080]), corner(&fitted, [640, 360])) < 1e-6);
+        assert!(apart(corner(&fitted, [1920, 1080]), corner(&camera, [1920, 1080])) < 1e-6);
+
+        // square, it's all still in view, with more above and below, and
+        // filling cuts off the sides instead, where by height it would too
+        let wide = corner(&camera, [1920, 1080]);
+        let fit = corner(&fitted, [1080, 1080]);
+        let fill = corner(&Camera { framing: Framing::Fill(16.0 / 9.0), ..camera }, [1080, 1080]);
+        assert!((fit.x / fit.z - wide.x / wide.z).abs() < 1e-6 && fit.y / fit.z < wide.y / wide.z);
+        assert!((fill.y / fill.z - wide.y / wide.z).abs() < 1e-6 && fill.x / fill.z > wide.x / wide.z);
+        assert!(apart(corner(&camera, [1080, 1080]), fill) < 1e-6);
+
+        // and projecting still undoes it
+        let (ray, _) = fitted.generate_ray(&CameraSample::center([30.0, 70.0]), [100, 100]);
+        let [x, y] = fitted.project(ray.point_at(&2.0), [100, 100]).unwrap();
+        assert!((x - 30.0).abs() < 0.001 && (y - 70.0).abs() < 0.001);
+    }
 }
@@ -1,36 +1,83 @@
-use std::f64;
-
+use crate::structures::float::Float;
 use crate::structures::vec3::Vec3;
 use crate::structures::material::Material;
+use crate::structures::frame::Frame;
+use crate::structures::scene::Handle;
 
 #[derive(Debug, Copy, Clone)]
 pub struct CastResult {
     pub hit: bool,
-    pub distance: f64,
+    pub distance: Float,
     pub normal: Vec3,
     pub material: Material,
+    pub catcher: bool, // whether it's a shadow catcher that was hit
+    pub object: Option<Handle>, // which one, for id mattes
+    pub front_face: bool, // whether the ray hit the outside, the normal's been turned to face it either way
+    pub footprint: Float, // how wide the ray's cone is there, see Ray::footprint
+
+    // the surface's own axes around the normal, and where on the surface
+    // the hit is, see March::tangent
+    pub tangent: Vec3,
+    pub bitangent: Vec3,
+    pub uv: [Float; 2],
 }
 
 impl CastResult {
-    pub fn new(hit: bool, distance: f64, normal: Vec3, material: Material) -> CastResult {
+    pub fn new(hit: bool, distance: Float, normal: Vec3, material: Material) -> CastResult {
+        let frame = Frame::new(normal);
+
         CastResult {
             hit: hit,
             distance: distance,
             normal: normal,
             material: material,
+            catcher: false,
+            object: None,
+            front_face: true,
+            footprint: 0.0,
+            tangent: frame.tangent,
+            bitangent: frame.bitangent,
+            uv: [0.0, 0.0],
         }
     }
 
+    // the tangent's straightened up against the normal, see Frame::with_tangent
+    pub fn with_surface(mut self, tangent: Vec3, uv: [Float; 2]) -> CastResult {
+        let frame = Frame::with_tangent(self.normal, tangent);
+        self.tangent = frame.tangent;
+        self.bitangent = frame.bitangent;
+        self.uv = uv;
+        return self;
+    }
+
+    pub fn frame(&self) -> Frame {
+        Frame { normal: self.normal, tangent: self.tangent, bitangent: self.bitangent }
+    }
+
     pub fn worst() -> CastResult {
         CastResult {
             hit: false,
-            distance: f64::MAX,
+            distance: Float::MAX,
             normal: Vec3::new(1.0, 1.0, 1.0),
             material: Material::blank(),
+            catcher: false,
+            object: None,
+            front_face: true,
+            footprint: 0.0,
+            tangent: Vec3::new(1.0, 0.0, 0.0),
+            bitangent: Vec3::new(0.0, 1.0, 0.0),
+            uv: [0.0, 0.0],
         }
     }
 
-    pub fn unpack(&self) -> (bool, f64, Vec3, Material) {
+    // a ray that got away, lit by whatever surrounds the scene
+    pub fn miss(environment: Vec3) -> CastResult {
+        let mut material = Material::sky();
+        material.color = environment;
+        return CastResult { material: material, ..CastResult::worst() };
+    }
+
+    pub fn unpack(&self) -> (bool, Float, Vec3, Material) {
         (self.hit, self.distance, self.normal, self.material)
     }
 }
@@ -0,0 +1,124 @@
+use crate::structures::float::Float;
+use crate::structures::float::consts::PI;
+use crate::structures::vec3::Vec3;
+use crate::structures::film::Film;
+use crate::structures::alias::AliasTable;
+
+// light from all around out of a latitude-longitude picture, the way hdris
+// come: u goes around y the same way it does on a Sphere and v from straight
+// up to straight down. directions can be picked by how bright the picture is
+// there, so a small sun in it gets found directly instead of being hit by
+// chance as rarely as a tiny glowing object would.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvironmentMap {
+    pub film: Film,
+    table: Option<AliasTable>,
+}
+
+impl EnvironmentMap {
+    pub fn new(film: Film) -> EnvironmentMap {
+        // rows near the poles cover less of the sphere, so they count less
+        let weights: Vec<Float> = (0..film.height).flat_map(|y| {
+            let sin = (PI * (y as Float + 0.5) / film.height as Float).sin();
+            let film = &film;
+            (0..film.width).map(move |x| film.get(x, y).luminance() * sin)
+        }).collect();
+
+        return EnvironmentMap { table: AliasTable::new(&weights), film: film };
+    }
+
+    pub fn uv(direction: Vec3) -> [Float; 2] {
+        let d = direction.unit();
+        [d.z.atan2(d.x) / (2.0 * PI) + 0.5, d.y.clamp(-1.0, 1.0).acos() / PI]
+    }
+
+    pub fn direction(uv: [Float; 2]) -> Vec3 {
+        let (phi, theta) = ((uv[0] - 0.5) * 2.0 * PI, uv[1] * PI);
+        Vec3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin())
+    }
+
+    // what comes in from `direction`, nearest pixel
+    pub fn lookup(&self, direction: Vec3) -> Vec3 {
+        if self.film.pixels.is_empty() { return Vec3::new(0.0, 0.0, 0.0); }
+        let [u, v] = EnvironmentMap::uv(direction);
+        let x = ((u * self.film.width as Float) as usize).min(self.film.width - 1);
+        let y = ((v * self.film.height as Float) as usize).min(self.film.height - 1);
+        return self.film.get(x, y);
+    }
+
+    // a direction picked by brightness, what comes from it, and the density
+    // per solid angle it was picked with. u[0] picks the pixel, the other two
+    // where in it. none if the picture is black all over.
+    pub fn sample(&self, u: [Float; 3]) -> Option<(Vec3, Vec3, Float)> {
+        let table = self.table.as_ref()?;
+        let (index, _) = table.sample(u[0]);
+        let (x, y) = (index % self.film.width, index / self.film.width);
+
+        let uv = [
+            (x as Float + u[1]) / self.film.width as Float,
+            (y as Float + u[2]) / self.film.height as Float,
+        ];
+        let direction = EnvironmentMap::direction(uv);
+        return Some((direction, self.film.get(x, y), self.pdf(direction)));
+    }
+
+    // the density per solid angle `sample` picks `direction` with, for
+    // weighing it against picking it some other way
+    pub fn pdf(&self, direction: Vec3) -> Float {
+        let table = match &self.table { Some(table) => table, None => return 0.0 };
+        let [u, v] = EnvironmentMap::uv(direction);
+        let x = ((u * self.film.width as Float) as usize).min(self.film.width - 1);
+        let y = ((v * self.film.height as Float) as usize).min(self.film.height - 1);
+
+        let sin = (v * PI).sin();
+        if sin <= 0.0 { return 0.0; }
+        let pixels = (self.film.width * self.film.height) as Float;
+        return table.probability(y * self.film.width + x) * pixels / (2.0 * PI * PI * sin);
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::EnvironmentMap;
+    use crate::structures::float::Float;
+    use crate::structures::float::consts::PI;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::film::Film;
+
+    #[test]
+    fn test_environment_map() {
+        // a dim sky with a sun in one pixel a thousand times brighter
+        let mut film = Film::wrap(16, 8, vec![Vec3::new(0.1, 0.1, 0.1); 128]).unwrap();
+        film.set(5, 2, Vec3::new(100.0, 100.0, 100.0));
+        let map = EnvironmentMap::new(film);
+
+        let sun = EnvironmentMap::direction([5.5 / 16.0, 2.5 / 8.0]);
+        assert!((EnvironmentMap::direction(EnvironmentMap::uv(sun)) - sun).length() < 0.0001);
+        assert_eq!(map.lookup(sun), Vec3::new(100.0, 100.0, 100.0));
+
+        // most picks go to the sun, and weighed by how they were picked they
+        // add up to all the light there is
+        let (mut suns, mut total) = (0, Vec3::new(0.0, 0.0, 0.0));
+        let n = 4096;
+        for i in 0..n {
+            let u = [(i as Float + 0.5) / n as Float, (((i * 7) % 13) as Float + 0.5) / 13.0, (((i * 5) % 11) as Float + 0.5) / 11.0];
+            let (direction, light, pdf) = map.sample(u).unwrap();
+            assert!((pdf - map.pdf(direction)).abs() < 0.0001 * pdf);
+            if light.x > 1.0 { suns += 1; }
+            total = total + light / pdf;
+        }
+        assert!(suns > n * 9 / 10);
+
+        let mut expected = Vec3::new(0.0, 0.0, 0.0);
+        for y in 0..8 {
+            for x in 0..16 {
+                let solid_angle = 2.0 * PI / 16.0 * ((PI * y as Float / 8.0).cos() - (PI * (y + 1) as Float / 8.0).cos());
+                expected = expected + map.film.get(x, y) * solid_angle;
+            }
+        }
+        assert!(((total / n as Float).x - expected.x).abs() < 0.05 * expected.x);
+
+        // nothing to pick in the dark
+        assert!(EnvironmentMap::new(Film::new(4, 2)).sample([0.5, 0.5, 0.5]).is_none());
+    }
+}
@@ -0,0 +1,110 @@
+use image::{ ImageBuffer, Rgb, RgbImage, Rgb32FImage };
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::post::Post;
+
+// a rendered image, linear radiance in rows top to bottom. converts into
+// the image crate's buffers: RgbImage tone mapped like saved pngs, and
+// Rgb32FImage as it is, for exr and friends.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Film {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Vec3>,
+}
+
+impl Film {
+    // all black
+    pub fn new(width: usize, height: usize) -> Film {
+        Film { width: width, height: height, pixels: vec![Vec3::new(0.0, 0.0, 0.0); width * height] }
+    }
+
+    // takes over a buffer already laid out row by row, if it's the right size
+    pub fn wrap(width: usize, height: usize, pixels: Vec<Vec3>) -> Option<Film> {
+        if pixels.len() != width * height { return None; }
+        return Some(Film { width: width, height: height, pixels: pixels });
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Vec3 {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, pixel: Vec3) {
+        self.pixels[y * self.width + x] = pixel;
+    }
+
+    pub fn rows(&self) -> Vec<Vec<Vec3>> {
+        self.pixels.chunks(self.width.max(1)).map(|row| row.to_vec()).collect()
+    }
+
+    // the same frame `stops` brighter, or darker below 0, like opening up
+    // a camera's aperture a stop doubles the light
+    pub fn expose(&self, stops: Float) -> Film {
+        let scale = stops.exp2();
+        return Film { width: self.width, height: self.height, pixels: self.pixels.iter().map(|pixel| *pixel * scale).collect() };
+    }
+
+    // runs the frame through each effect in turn, see Post
+    pub fn post(&mut self, effects: &[Post]) {
+        for effect in effects { effect.apply(self); }
+    }
+}
+
+// what render_image hands back
+impl From<Vec<Vec<Vec3>>> for Film {
+    fn from(rows: Vec<Vec<Vec3>>) -> Film {
+        let width = rows.first().map_or(0, |row| row.len());
+        let height = rows.len();
+        return Film { width: width, height: height, pixels: rows.into_iter().flatten().collect() };
+    }
+}
+
+impl From<&Film> for RgbImage {
+    fn from(film: &Film) -> RgbImage {
+        ImageBuffer::from_fn(film.width as u32, film.height as u32, |x, y| {
+            Rgb(film.get(x as usize, y as usize).colorize())
+        })
+    }
+}
+
+impl From<&Film> for Rgb32FImage {
+    #[allow(clippy::unnecessary_cast)] // Float may already be f32
+    fn from(film: &Film) -> Rgb32FImage {
+        ImageBuffer::from_fn(film.width as u32, film.height as u32, |x, y| {
+            let pixel = film.get(x as usize, y as usize);
+            Rgb([pixel.x as f32, pixel.y as f32, pixel.z as f32])
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use image::{ RgbImage, Rgb32FImage };
+
+    use super::Film;
+    use crate::structures::vec3::Vec3;
+
+    #[test]
+    fn test_film() {
+        let mut film = Film::new(3, 2);
+        film.set(2, 1, Vec3::new(0.5, 1.0, 100.0));
+
+        assert_eq!(Film::from(film.rows()), film);
+        assert!(Film::wrap(3, 3, film.pixels.clone()).is_none());
+
+        let linear = Rgb32FImage::from(&film);
+        assert_eq!(linear.dimensions(), (3, 2));
+        assert_eq!(linear.get_pixel(2, 1).0, [0.5, 1.0, 100.0]);
+
+        // tone mapped, so the bright channel is clipped rather than wrapped around
+        let mapped = RgbImage::from(&film);
+        assert_eq!(mapped.get_pixel(2, 1).0[2], 255);
+        assert_eq!(mapped.get_pixel(0, 0).0, [0, 0, 0]);
+
+        // a stop either way doubles or halves it, and nothing's left as it was
+        assert_eq!(film.expose(1.0).get(2, 1), Vec3::new(1.0, 2.0, 200.0));
+        assert_eq!(film.expose(-1.0).get(2, 1), Vec3::new(0.25, 0.5, 50.0));
+        assert_eq!(film.expose(0.0), film);
+    }
+}
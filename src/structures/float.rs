@@ -0,0 +1,13 @@
+// the scalar everything is computed in. f64 by default; the `f32` feature
+// trades precision nobody can see in a preview for a lot more speed.
+// the tests pin f64 results, so run them without it.
+
+#[cfg(not(feature = "f32"))]
+pub type Float = f64;
+#[cfg(not(feature = "f32"))]
+pub use std::f64::consts;
+
+#[cfg(feature = "f32")]
+pub type Float = f32;
+#[cfg(feature = "f32")]
+pub use std::f32::consts;
@@ -0,0 +1,75 @@
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+
+// three axes at right angles around a surface normal, for working in the
+// surface's own space: z is the normal, x the tangent and y the bitangent
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Frame {
+    pub normal: Vec3,
+    pub tangent: Vec3,
+    pub bitangent: Vec3,
+}
+
+impl Frame {
+    // for surfaces that don't run any particular way. from duff et al.
+    // 2017, no branches to flip the tangent around near the poles.
+    pub fn new(normal: Vec3) -> Frame {
+        let sign: Float = (1.0 as Float).copysign(normal.z);
+        let a = -1.0 / (sign + normal.z);
+        let b = normal.x * normal.y * a;
+
+        Frame {
+            normal: normal,
+            tangent: Vec3::new(1.0 + sign * normal.x * normal.x * a, sign * b, -sign * normal.x),
+            bitangent: Vec3::new(b, sign + normal.y * normal.y * a, -normal.y),
+        }
+    }
+
+    // the tangent is straightened up against the normal, and made up if
+    // it's parallel to it
+    pub fn with_tangent(normal: Vec3, tangent: Vec3) -> Frame {
+        let straight = tangent - normal * normal.dot(&tangent);
+        if straight.length_squared() < 1e-12 { return Frame::new(normal); }
+
+        let tangent = straight.unit();
+        return Frame { normal: normal, tangent: tangent, bitangent: normal.cross(&tangent) };
+    }
+
+    pub fn to_world(&self, local: Vec3) -> Vec3 {
+        self.tangent * local.x + self.bitangent * local.y + self.normal * local.z
+    }
+
+    pub fn to_local(&self, world: Vec3) -> Vec3 {
+        Vec3::new(world.dot(&self.tangent), world.dot(&self.bitangent), world.dot(&self.normal))
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::Frame;
+    use crate::structures::vec3::Vec3;
+
+    #[test]
+    fn test_frame() {
+        let normals = [
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(1.0, 2.0, -3.0).unit(),
+        ];
+
+        for normal in normals.iter() {
+            let frame = Frame::new(*normal);
+            assert!((frame.tangent.length() - 1.0).abs() < 0.0001);
+            assert!((frame.bitangent.length() - 1.0).abs() < 0.0001);
+            assert!(frame.tangent.dot(normal).abs() < 0.0001);
+            assert!(frame.bitangent.dot(&frame.tangent).abs() < 0.0001);
+
+            let v = Vec3::new(0.3, -0.5, 0.8);
+            assert!((frame.to_world(frame.to_local(v)) - v).length() < 0.0001);
+        }
+
+        let frame = Frame::with_tangent(Vec3::new(0.0, 1.0, 0.0), Vec3::new(1.0, 1.0, 0.0));
+        assert!((frame.tangent - Vec3::new(1.0, 0.0, 0.0)).length() < 0.0001);
+        assert!((frame.bitangent - Vec3::new(0.0, 0.0, -1.0)).length() < 0.0001);
+    }
+}
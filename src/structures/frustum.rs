@@ -0,0 +1,88 @@
+use crate::structures::vec3::Vec3;
+use crate::structures::camera::Camera;
+use crate::structures::aabb::Aabb;
+
+// the part of space a pinhole camera's rays can get to at one resolution:
+// in front of it, between the four planes through it and the edges of the
+// picture. it's widened to fit lens distortion and aberration, and a
+// little more to be safe, so anything outside really can't be seen.
+#[derive(Debug, Copy, Clone)]
+pub struct Frustum {
+    origin: Vec3,
+    // pointing in
+    sides: [Vec3; 4],
+}
+
+impl Frustum {
+    // none with an aperture, the rays don't all start from one point then
+    pub fn new(camera: &Camera, resolution: [usize; 2]) -> Option<Frustum> {
+        if camera.aperture > 0.0 { return None; }
+
+        // the same space Camera::generate_ray works in
+        let [x, y] = camera.half_size(resolution);
+        let z = 1.0 / (camera.fov.to_radians() / 2.0).tan();
+
+        // as far out as the lens can bend the corners, see Camera::distortion
+        let bent = (1.0 + camera.distortion * (x * x + y * y)).abs().max(1.0);
+        let widen = bent * (1.0 + camera.aberration.abs()) * 1.01;
+        let (x, y) = (x * widen, y * widen);
+
+        let f = camera.ray.direction;
+        let s = f.cross(&camera.up).unit();
+        let u = s.cross(&f);
+
+        return Some(Frustum {
+            origin: camera.ray.origin,
+            sides: [s * -z + f * x, s * z + f * x, u * -z + f * y, u * z + f * y],
+        });
+    }
+
+    // whether any of the box might be seen. boxes near the corners of the
+    // frustum, outside it but not wholly outside any one side, count too.
+    pub fn overlaps(&self, bounds: &Aabb) -> bool {
+        return self.sides.iter().all(|n| {
+            // the corner furthest in
+            let corner = Vec3::new(
+                if n.x > 0.0 { bounds.max.x } else { bounds.min.x },
+                if n.y > 0.0 { bounds.max.y } else { bounds.min.y },
+                if n.z > 0.0 { bounds.max.z } else { bounds.min.z },
+            );
+            n.dot(&(corner - self.origin)) >= 0.0
+        });
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::Frustum;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::camera::Camera;
+    use crate::structures::aabb::Aabb;
+
+    #[test]
+    fn test_frustum() {
+        // at 90 degrees the picture's half as wide as it is far, see Camera::generate_ray
+        let mut camera = Camera::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0));
+        camera.fov = 90.0;
+        let frustum = Frustum::new(&camera, [1, 1]).unwrap();
+        let around = |center: Vec3| Aabb::new(center - Vec3::new(0.1, 0.1, 0.1), center + Vec3::new(0.1, 0.1, 0.1));
+
+        // in front, or across an edge, and not behind or off to the side
+        assert!(frustum.overlaps(&around(Vec3::new(0.0, 0.0, -5.0))));
+        assert!(frustum.overlaps(&around(Vec3::new(2.5, 0.0, -5.0))));
+        assert!(!frustum.overlaps(&around(Vec3::new(0.0, 0.0, 5.0))));
+        assert!(!frustum.overlaps(&around(Vec3::new(3.0, 0.0, -5.0))));
+        assert!(!frustum.overlaps(&around(Vec3::new(0.0, -3.0, -5.0))));
+
+        // a wider picture sees further to the sides, but not up and down
+        let wide = Frustum::new(&camera, [2, 1]).unwrap();
+        assert!(wide.overlaps(&around(Vec3::new(4.5, 0.0, -5.0))));
+        assert!(!wide.overlaps(&around(Vec3::new(0.0, 3.0, -5.0))));
+
+        // and so does a lens that bends the edges in
+        let barrel = Frustum::new(&Camera { distortion: 0.5, ..camera }, [1, 1]).unwrap();
+        assert!(barrel.overlaps(&around(Vec3::new(3.0, 0.0, -5.0))));
+
+        assert!(Frustum::new(&Camera { aperture: 0.1, ..camera }, [1, 1]).is_none());
+    }
+}
@@ -0,0 +1,106 @@
+use crate::structures::float::Float;
+use crate::structures::float::consts::PI;
+use crate::structures::vec3::Vec3;
+use crate::structures::frame::Frame;
+
+// rough reflection off microfacets with the ggx (trowbridge-reitz)
+// distribution. directions here are in the surface's own space, z along
+// the normal, see Frame. alpha is roughness squared, which makes roughness
+// look about linear to the eye.
+
+pub fn alpha(roughness: Float) -> Float {
+    roughness * roughness
+}
+
+// smith's masking: how much of the microsurface seen from `v` isn't hidden
+// behind other microfacets
+pub fn g1(v: Vec3, alpha: Float) -> Float {
+    if v.z <= 0.0 { return 0.0; }
+    return 2.0 * v.z / (v.z + (alpha * alpha * (1.0 - v.z * v.z) + v.z * v.z).sqrt());
+}
+
+// a microfacet normal, picked in proportion to how much of it is seen from
+// `view`. from heitz 2018, "sampling the ggx distribution of visible normals".
+pub fn sample_visible_normal(view: Vec3, alpha: Float, u: [Float; 2]) -> Vec3 {
+    // stretched so the distribution's a hemisphere
+    let v = Vec3::new(alpha * view.x, alpha * view.y, view.z).unit();
+
+    let length_squared = v.x * v.x + v.y * v.y;
+    let t1 = if length_squared > 0.0 {
+        Vec3::new(-v.y, v.x, 0.0) / length_squared.sqrt()
+    } else {
+        Vec3::new(1.0, 0.0, 0.0)
+    };
+    let t2 = v.cross(&t1);
+
+    // a point on the disk, squashed onto the half of it that faces the view
+    let radius = u[0].sqrt();
+    let phi = 2.0 * PI * u[1];
+    let p1 = radius * phi.cos();
+    let s = 0.5 * (1.0 + v.z);
+    let p2 = (1.0 - s) * (1.0 - p1 * p1).max(0.0).sqrt() + s * radius * phi.sin();
+
+    let n = t1 * p1 + t2 * p2 + v * (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt();
+
+    // and unstretched
+    return Vec3::new(alpha * n.x, alpha * n.y, n.z.max(0.0)).unit();
+}
+
+// where light coming back along `outgoing`, away from the surface, was
+// reflected from, and what to weigh it by on top of the fresnel tint.
+// picking the microfacet by how visible it is cancels everything in the
+// estimate but the masking on the way in. none when it would come from
+// under the surface, which is the light rough surfaces lose to being hit
+// only once.
+pub fn sample(frame: &Frame, outgoing: Vec3, roughness: Float, u: [Float; 2]) -> Option<(Vec3, Float)> {
+    let alpha = alpha(roughness).max(0.0001);
+    let view = frame.to_local(outgoing);
+    if view.z <= 0.0 { return None; }
+
+    let half = sample_visible_normal(view, alpha, u);
+    let incoming = half * (2.0 * view.dot(&half)) - view;
+    if incoming.z <= 0.0 { return None; }
+
+    return Some((frame.to_world(incoming).unit(), g1(incoming, alpha)));
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::{ sample, g1 };
+    use crate::structures::float::Float;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::frame::Frame;
+
+    #[test]
+    fn test_ggx() {
+        let frame = Frame::new(Vec3::new(0.0, 1.0, 0.0));
+        let outgoing = Vec3::new(1.0, 1.0, 0.0).unit();
+        let mirror = Vec3::new(-1.0, 1.0, 0.0).unit();
+
+        // masking is nothing head on and everything at grazing angles
+        assert!((g1(Vec3::new(0.0, 0.0, 1.0), 0.5) - 1.0).abs() < 0.0001);
+        assert_eq!(g1(Vec3::new(1.0, 0.0, 0.0), 0.5), 0.0);
+
+        // barely rough it's as good as a mirror
+        let (direction, weight) = sample(&frame, outgoing, 0.01, [0.3, 0.7]).unwrap();
+        assert!((direction - mirror).length() < 0.001);
+        assert!((weight - 1.0).abs() < 0.001);
+
+        // rough, it spreads out over the hemisphere around the mirror
+        // direction, never under the surface, and loses a little
+        let mut mean = Vec3::new(0.0, 0.0, 0.0);
+        let mut total = 0.0;
+        for i in 0..32 {
+            for j in 0..32 {
+                let u = [(i as Float + 0.5) / 32.0, (j as Float + 0.5) / 32.0];
+                if let Some((direction, weight)) = sample(&frame, outgoing, 0.5, u) {
+                    assert!(direction.y > 0.0 && weight <= 1.0);
+                    mean = mean + direction;
+                    total += weight;
+                }
+            }
+        }
+        assert!(mean.unit().dot(&mirror) > 0.9);
+        assert!(total / 1024.0 > 0.8 && total / 1024.0 < 1.0);
+    }
+}
@@ -0,0 +1,73 @@
+use crate::structures::float::Float;
+use crate::structures::float::consts::PI;
+use crate::structures::vec3::Vec3;
+use crate::structures::frame::Frame;
+use crate::structures::material::Material;
+
+// light scattering off a fibre, loosely after kajiya and kay. a smooth
+// cylinder sends light off on a cone around itself, at the same angle to
+// it as the light came in, and hair does that too, only blurred by its
+// roughness. some of it bounces off the outside untinted, `specular` of
+// it, the rest goes through the fibre and comes out colored, all around.
+
+// a tent over -1, 1, for blurring the cone
+fn tent(u: Float) -> Float {
+    if u < 0.5 { (2.0 * u).sqrt() - 1.0 } else { 1.0 - (2.0 - 2.0 * u).sqrt() }
+}
+
+// where light travelling along `direction` and landing on a fibre running
+// along `tangent` goes next, and what it's weighed by
+pub fn sample(tangent: Vec3, direction: Vec3, material: &Material, u: [Float; 3]) -> (Vec3, Vec3) {
+    let t = tangent.unit();
+    let d = direction.unit();
+
+    // the incoming direction across the fibre, which azimuths are measured from
+    let across = d - t * d.dot(&t);
+    let forward = if across.length_squared() > 1e-12 { across.unit() } else { Frame::new(t).tangent };
+    let side = t.cross(&forward);
+
+    // the angle to the fibre stays the same, give or take the roughness
+    let spread = material.roughness.max(0.05) * PI * 0.25;
+    let theta = (d.dot(&t).clamp(-1.0, 1.0).asin() + tent(u[0]) * spread).clamp(-PI * 0.5, PI * 0.5);
+
+    let (phi, weight) = if u[1] < material.specular {
+        // off the outside, from wherever across the fibre it landed
+        let offset = (2.0 * u[2] - 1.0).asin();
+        (PI - 2.0 * offset, Vec3::new(1.0, 1.0, 1.0))
+    } else {
+        (2.0 * PI * u[2], material.color)
+    };
+
+    let around = forward * phi.cos() + side * phi.sin();
+    return ((t * theta.sin() + around * theta.cos()).unit(), weight);
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::sample;
+    use crate::structures::float::Float;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::material::Material;
+
+    #[test]
+    fn test_hair() {
+        let tangent = Vec3::new(0.0, 1.0, 0.0);
+        let direction = Vec3::new(1.0, -1.0, 0.0).unit();
+        let fibre = Material { color: Vec3::new(0.5, 0.3, 0.1), specular: 0.25, roughness: 0.0, hair: true, ..Material::blank() };
+
+        let mut shine = 0;
+        for i in 0..64 {
+            let u = [(i as Float + 0.5) / 64.0, ((i * 7) % 64) as Float / 64.0, ((i * 13) % 64) as Float / 64.0];
+            let (out, weight) = sample(tangent, direction, &fibre, u);
+
+            // smooth, it stays on the cone around the fibre, going on the way it was
+            assert!((out.length() - 1.0).abs() < 0.0001);
+            assert!((out.dot(&tangent) - direction.dot(&tangent)).abs() < 0.05);
+
+            if weight == Vec3::new(1.0, 1.0, 1.0) { shine += 1; } else { assert_eq!(weight, fibre.color); }
+        }
+
+        // and some of it's the untinted shine off the outside
+        assert_eq!(shine, 16);
+    }
+}
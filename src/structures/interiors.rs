@@ -0,0 +1,91 @@
+use crate::structures::float::Float;
+use crate::structures::material::Material;
+use crate::structures::scene::Handle;
+
+// the transparent objects a path has gone into and not come out of yet,
+// innermost last. where they overlap, like ice in water, only the one
+// with the highest priority is really there, see Material::priority, and
+// the others' surfaces inside it are passed straight through. kept by
+// sppm and the photon map, the only ones that refract so far.
+#[derive(Debug, Clone, Default)]
+pub struct Interiors {
+    inside: Vec<(Option<Handle>, Material)>, // see CastResult::object
+}
+
+impl Interiors {
+    pub fn new() -> Interiors {
+        Interiors { inside: vec![] }
+    }
+
+    // the ior of whatever's around the path, 1 for air. on a tie the one
+    // gone into last wins.
+    pub fn ior(&self) -> Float {
+        return self.around(None).map_or(1.0, |material| material.ior);
+    }
+
+    // the highest priority one but `object`, if there's any
+    fn around(&self, object: Option<Option<Handle>>) -> Option<&Material> {
+        let mut best: Option<&Material> = None;
+        for (_, material) in self.inside.iter().filter(|(handle, _)| Some(*handle) != object) {
+            if best.is_none_or(|best| material.priority >= best.priority) { best = Some(material); }
+        }
+        return best;
+    }
+
+    // the iors either side of `object`'s surface, going from one to the
+    // other, or none if something with a higher priority is all around it
+    // there and the path should carry straight on. `front` is whether it's
+    // going in.
+    pub fn crossing(&self, object: Option<Handle>, material: &Material, front: bool) -> Option<(Float, Float)> {
+        let outside = self.around(Some(object));
+        if outside.is_some_and(|outside| outside.priority > material.priority) { return None; }
+
+        let outside = outside.map_or(1.0, |outside| outside.ior);
+        return Some(if front { (outside, material.ior) } else { (material.ior, outside) });
+    }
+
+    // keeps track of the path going through `object`'s surface, either way
+    pub fn cross(&mut self, object: Option<Handle>, material: &Material, front: bool) {
+        if front {
+            self.inside.push((object, *material));
+        } else if let Some(index) = self.inside.iter().rposition(|(handle, _)| *handle == object) {
+            self.inside.remove(index);
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::Interiors;
+    use crate::structures::material::Material;
+    use crate::structures::scene::Handle;
+
+    #[test]
+    fn test_interiors() {
+        let water = Material { ior: 1.33, transmission: 1.0, priority: 1, ..Material::blank() };
+        let ice = Material { ior: 1.31, transmission: 1.0, priority: 2, ..Material::blank() };
+        let (pool, cube) = (Some(Handle::Trace(0)), Some(Handle::Trace(1)));
+
+        // into the water from the air
+        let mut path = Interiors::new();
+        assert_eq!(path.crossing(pool, &water, true), Some((1.0, 1.33)));
+        path.cross(pool, &water, true);
+        assert_eq!(path.ior(), 1.33);
+
+        // the ice wins inside the water, from water to ice and back
+        assert_eq!(path.crossing(cube, &ice, true), Some((1.33, 1.31)));
+        path.cross(cube, &ice, true);
+        assert_eq!(path.ior(), 1.31);
+
+        // the water's surface inside the ice isn't there, and leaving the
+        // ice on the other side still finds the water around it
+        assert_eq!(path.crossing(pool, &water, false), None);
+        path.cross(pool, &water, false);
+        assert_eq!(path.crossing(cube, &ice, false), Some((1.31, 1.0)));
+        path.cross(cube, &ice, false);
+        assert_eq!(path.ior(), 1.0);
+
+        // coming out of something it never went into is just into the air
+        assert_eq!(Interiors::new().crossing(cube, &ice, false), Some((1.31, 1.0)));
+    }
+}
@@ -0,0 +1,180 @@
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::aabb::Aabb;
+use crate::structures::photon_map::Emitter;
+
+// picks one of many emitters in proportion to roughly how much of its
+// light reaches a point, instead of every one as often as any other. the
+// emitters go down a binary tree of boxes, and a pick walks from the top
+// taking either side by its power over how far away its box is, so near
+// bright lights get picked a lot and a thousand far off ones hardly at
+// all. it never rules anything out: the box's own size caps how close
+// it counts as, and light that gets there some other way still has a
+// chance. it's a cache like the top level, see Scene::light_tree.
+#[derive(Debug, Clone)]
+pub struct LightTree {
+    nodes: Vec<Node>,  // the first is the top
+    order: Vec<usize>, // emitters by where they are in the tree
+    slots: Vec<usize>, // where each emitter is in order
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Node {
+    bounds: Aabb,
+    power: Float, // the luminance of everything under it
+    start: usize, // the emitters under it, in order
+    end: usize,
+    children: Option<[usize; 2]>,
+}
+
+impl LightTree {
+    // none if there's nothing to pick, none of them giving off any light
+    pub fn new(emitters: &[Emitter]) -> Option<LightTree> {
+        let mut order: Vec<usize> = (0..emitters.len()).filter(|index| emitters[*index].power.luminance() > 0.0).collect();
+        if order.is_empty() { return None; }
+
+        let mut nodes = vec![];
+        let count = order.len();
+        build(emitters, &mut order, 0, count, &mut nodes);
+
+        let mut slots = vec![usize::MAX; emitters.len()];
+        for (slot, index) in order.iter().enumerate() { slots[*index] = slot; }
+
+        return Some(LightTree { nodes: nodes, order: order, slots: slots });
+    }
+
+    // whether it was built for this many emitters
+    pub fn fits(&self, emitters: &[Emitter]) -> bool {
+        self.slots.len() == emitters.len()
+    }
+
+    // which emitter `u` in [0, 1) lands on seen from `point`, and the
+    // chance of picking it
+    pub fn sample(&self, point: Vec3, mut u: Float) -> (usize, Float) {
+        let mut node = self.nodes[0];
+        let mut probability = 1.0;
+
+        while let Some([left, right]) = node.children {
+            let chance = self.chance(point, left, right);
+            if u < chance {
+                u = (u / chance).min(1.0 - Float::EPSILON);
+                probability *= chance;
+                node = self.nodes[left];
+            } else {
+                u = ((u - chance) / (1.0 - chance)).min(1.0 - Float::EPSILON);
+                probability *= 1.0 - chance;
+                node = self.nodes[right];
+            }
+        }
+
+        return (self.order[node.start], probability);
+    }
+
+    // the chance sample picks emitter `index` seen from `point`
+    pub fn probability(&self, point: Vec3, index: usize) -> Float {
+        let slot = match self.slots.get(index) {
+            Some(slot) if *slot != usize::MAX => *slot,
+            _ => return 0.0,
+        };
+
+        let mut node = self.nodes[0];
+        let mut probability = 1.0;
+
+        while let Some([left, right]) = node.children {
+            let chance = self.chance(point, left, right);
+            if slot < self.nodes[left].end {
+                probability *= chance;
+                node = self.nodes[left];
+            } else {
+                probability *= 1.0 - chance;
+                node = self.nodes[right];
+            }
+        }
+
+        return probability;
+    }
+
+    // the chance of going left rather than right from `point`
+    fn chance(&self, point: Vec3, left: usize, right: usize) -> Float {
+        let (left, right) = (importance(&self.nodes[left], point), importance(&self.nodes[right], point));
+        if left + right <= 0.0 || !(left + right).is_finite() { return 0.5; }
+        return left / (left + right);
+    }
+}
+
+// how much light a node's emitters might send to `point`, falling off
+// with the distance to the middle of its box but never closer than the
+// box is big
+fn importance(node: &Node, point: Vec3) -> Float {
+    let half = node.bounds.extent() * 0.5;
+    let distance = (node.bounds.center() - point).length_squared().max(half.length_squared());
+    return node.power / distance.max(Float::EPSILON);
+}
+
+// a node over order[start..end], split down the middle of its longest side
+// until there's one emitter left. returns where it went in nodes.
+fn build(emitters: &[Emitter], order: &mut [usize], start: usize, end: usize, nodes: &mut Vec<Node>) -> usize {
+    let around = |index: &usize| {
+        let emitter = emitters[*index];
+        Aabb::new(emitter.position, emitter.position).padded(emitter.radius)
+    };
+    let bounds = order[start..end].iter().map(around).fold(Aabb::empty(), |a, b| a.union(&b));
+    let power = order[start..end].iter().map(|index| emitters[*index].power.luminance()).sum();
+
+    let at = nodes.len();
+    nodes.push(Node { bounds: bounds, power: power, start: start, end: end, children: None });
+    if end - start == 1 { return at; }
+
+    // the centers' spread, not the spheres', picks the side
+    let centers = Aabb::around(&order[start..end].iter().map(|index| emitters[*index].position).collect::<Vec<_>>());
+    let axis = centers.longest_axis();
+    let component = |index: &usize| { let p = emitters[*index].position; [p.x, p.y, p.z][axis] };
+    order[start..end].sort_by(|a, b| component(a).total_cmp(&component(b)));
+
+    let middle = start + (end - start) / 2;
+    let left = build(emitters, order, start, middle, nodes);
+    let right = build(emitters, order, middle, end, nodes);
+    nodes[at].children = Some([left, right]);
+
+    return at;
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::LightTree;
+    use crate::structures::float::Float;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::photon_map::Emitter;
+
+    #[test]
+    fn test_light_tree() {
+        assert!(LightTree::new(&[]).is_none());
+        assert!(LightTree::new(&[Emitter::new(Vec3::new(0.0, 0.0, 0.0), 0.1, Vec3::new(0.0, 0.0, 0.0))]).is_none());
+
+        // a row of the same lamps, and one dark one
+        let mut emitters: Vec<Emitter> = (0..100)
+            .map(|i| Emitter::new(Vec3::new(i as Float, 0.0, 0.0), 0.1, Vec3::new(1.0, 1.0, 1.0)))
+            .collect();
+        emitters.push(Emitter::new(Vec3::new(50.0, 0.0, 0.0), 0.1, Vec3::new(0.0, 0.0, 0.0)));
+        let tree = LightTree::new(&emitters).unwrap();
+        assert!(tree.fits(&emitters));
+
+        // the chances add up to one and the dark one's never picked
+        let point = Vec3::new(10.0, 1.0, 0.0);
+        let total: Float = (0..emitters.len()).map(|index| tree.probability(point, index)).sum();
+        assert!((total - 1.0).abs() < 1e-4);
+        assert_eq!(tree.probability(point, 100), 0.0);
+
+        // the ones close by are picked far more than the ones far off, but
+        // all of them can be
+        assert!(tree.probability(point, 10) > 10.0 * tree.probability(point, 90));
+        assert!(tree.probability(point, 99) > 0.0);
+
+        // and sample says the same chance as probability
+        for i in 0..50 {
+            let (index, chance) = tree.sample(point, (i as Float + 0.5) / 50.0);
+            assert!(index < 100);
+            assert!((chance - tree.probability(point, index)).abs() < 1e-6);
+        }
+    }
+}
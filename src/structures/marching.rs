@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::aabb::Aabb;
+
+// the six tetrahedra a cube splits into around its 0-7 diagonal.
+// corners are numbered by bits, x = 1, y = 2, z = 4.
+const TETRAHEDRA: [[usize; 4]; 6] = [
+    [0, 1, 3, 7], [0, 3, 2, 7], [0, 2, 6, 7],
+    [0, 6, 4, 7], [0, 4, 5, 7], [0, 5, 1, 7],
+];
+
+// turns the zero level of a distance field into triangles. cells are
+// cubic, `resolution` of them along the longest side of the box.
+// each cube is cut into tetrahedra, which have no ambiguous cases and
+// so never leave holes; vertices on shared edges are welded together.
+pub fn polygonize(sdf: impl Fn(Vec3) -> Float, bounds: &Aabb, resolution: usize) -> (Vec<Vec3>, Vec<[usize; 3]>) {
+    let extent = bounds.extent();
+    let cell = extent.axis(bounds.longest_axis()) / resolution.max(1) as Float;
+    let dimensions = [
+        (extent.x / cell).ceil() as usize + 1,
+        (extent.y / cell).ceil() as usize + 1,
+        (extent.z / cell).ceil() as usize + 1,
+    ];
+    let [nx, ny, nz] = dimensions;
+
+    let position = |i: usize, j: usize, k: usize| bounds.min + Vec3::new(i as Float, j as Float, k as Float) * cell;
+    let index = |i: usize, j: usize, k: usize| i + nx * (j + ny * k);
+
+    let mut values = Vec::with_capacity(nx * ny * nz);
+    for k in 0..nz {
+        for j in 0..ny {
+            for i in 0..nx {
+                values.push(sdf(position(i, j, k)));
+            }
+        }
+    }
+
+    let mut vertices = vec![];
+    let mut triangles = vec![];
+    let mut welded: HashMap<(usize, usize), usize> = HashMap::new();
+
+    for k in 0..nz - 1 {
+        for j in 0..ny - 1 {
+            for i in 0..nx - 1 {
+                let corners: Vec<(usize, Vec3)> = (0..8).map(|c| {
+                    let (ci, cj, ck) = (i + (c & 1), j + ((c >> 1) & 1), k + ((c >> 2) & 1));
+                    (index(ci, cj, ck), position(ci, cj, ck))
+                }).collect();
+
+                for tetrahedron in TETRAHEDRA.iter() {
+                    let points: Vec<(usize, Vec3, Float)> = tetrahedron.iter()
+                        .map(|c| (corners[*c].0, corners[*c].1, values[corners[*c].0]))
+                        .collect();
+
+                    let (inside, outside): (Vec<_>, Vec<_>) = points.iter().partition(|p| p.2 < 0.0);
+                    if inside.is_empty() || outside.is_empty() { continue; }
+
+                    let mut vertex = |a: &(usize, Vec3, Float), b: &(usize, Vec3, Float)| {
+                        let key = (a.0.min(b.0), a.0.max(b.0));
+                        *welded.entry(key).or_insert_with(|| {
+                            vertices.push(a.1 + (b.1 - a.1) * (a.2 / (a.2 - b.2)));
+                            vertices.len() - 1
+                        })
+                    };
+
+                    // one corner cut off is a triangle, two is a quad
+                    let mut faces = vec![];
+                    if inside.len() == 2 {
+                        let (a, b) = (inside[0], inside[1]);
+                        let (c, d) = (outside[0], outside[1]);
+                        let quad = [vertex(a, c), vertex(a, d), vertex(b, d), vertex(b, c)];
+                        faces.push([quad[0], quad[1], quad[2]]);
+                        faces.push([quad[0], quad[2], quad[3]]);
+                    } else {
+                        let (lone, rest) = if inside.len() == 1 { (inside[0], &outside) } else { (outside[0], &inside) };
+                        faces.push([vertex(lone, rest[0]), vertex(lone, rest[1]), vertex(lone, rest[2])]);
+                    }
+
+                    // wind every face so it points out of the surface
+                    let centroid = |points: &[&(usize, Vec3, Float)]| {
+                        points.iter().fold(Vec3::new(0.0, 0.0, 0.0), |sum, p| sum + p.1) / points.len() as Float
+                    };
+                    let outwards = centroid(&outside) - centroid(&inside);
+
+                    for face in faces {
+                        let [a, b, c] = face;
+                        if a == b || b == c || a == c { continue; }
+
+                        let normal = (vertices[b] - vertices[a]).cross(&(vertices[c] - vertices[a]));
+                        triangles.push(if normal.dot(&outwards) < 0.0 { [a, c, b] } else { [a, b, c] });
+                    }
+                }
+            }
+        }
+    }
+
+    return (vertices, triangles);
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::polygonize;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::aabb::Aabb;
+
+    #[test]
+    fn test_polygonize_sphere() {
+        let bounds = Aabb::new(Vec3::new(-1.5, -1.5, -1.5), Vec3::new(1.5, 1.5, 1.5));
+        let (vertices, triangles) = polygonize(|p| p.length() - 1.0, &bounds, 12);
+
+        assert!(!triangles.is_empty());
+        assert!(vertices.iter().all(|v| (v.length() - 1.0).abs() < 0.1));
+
+        // welded and closed, so every edge is shared by exactly two faces
+        let mut edges = std::collections::HashMap::new();
+        for t in &triangles {
+            for (a, b) in [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])].iter() {
+                *edges.entry((*a.min(b), *a.max(b))).or_insert(0) += 1;
+            }
+        }
+        assert!(edges.values().all(|count| *count == 2));
+
+        // and wound outwards
+        let t = triangles[0];
+        let normal = (vertices[t[1]] - vertices[t[0]]).cross(&(vertices[t[2]] - vertices[t[0]]));
+        assert!(normal.dot(&vertices[t[0]]) > 0.0);
+    }
+}
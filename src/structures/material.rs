@@ -1,19 +1,54 @@
+use serde::{ Serialize, Deserialize };
+
+use crate::structures::float::Float;
 use crate::structures::vec3::Vec3;
 
 // TODO: derive debug.. etc. for other structs
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct Material {
     pub color: Vec3, // color
-    pub emission: f64, // how strong?
+    pub emission: Float, // how strong?
+
+    pub metallic: Float,
+    pub specular: Float,
+    pub roughness: Float,
+
+    pub transmission: Float,
+    pub ior: Float,
+
+    // one sided surfaces can only be hit from the front, traced ones are
+    // seen straight through from behind. marched ones are solid, so
+    // there's nothing to see through to.
+    #[serde(default = "two_sided")]
+    pub two_sided: bool,
+
+    // how much paths bouncing off it are worth keeping alive once russian
+    // roulette starts ending them, see render::Bounces. above 1 for mirrors
+    // and glass that lead somewhere that matters.
+    #[serde(default = "importance")]
+    pub importance: Float,
 
-    pub metallic: f64,
-    pub specular: f64,
-    pub roughness: f64,
+    // glowing from the back as well as the front. light panels and the
+    // insides of light boxes only want the one side, see emitted.
+    #[serde(default = "two_sided")]
+    pub two_sided_emission: bool,
 
-    pub transmission: f64,
-    pub ior: f64,
+    // shaded as fibres running along the surface's tangent instead, see
+    // structures::hair. meant for Curves, which run the way they grow.
+    #[serde(default)]
+    pub hair: bool,
+
+    // where transparent objects overlap, like ice in water, the one with
+    // the higher priority is what's there, see structures::interiors.
+    // equal ones each start where the other's surface is. only sppm and
+    // the photon map refract, the path tracers leave transmission black.
+    #[serde(default)]
+    pub priority: u32,
 }
 
+fn two_sided() -> bool { true }
+fn importance() -> Float { 1.0 }
+
 // ior and specular are correlated, remove one or the other?
 
 impl Material {
@@ -30,6 +65,41 @@ impl Material {
 
             transmission: 0.0,
             ior: 0.0,
+
+            two_sided: true,
+            importance: 1.0,
+            two_sided_emission: true,
+            hair: false,
+            priority: 0,
+        }
+    }
+
+    // how strongly it glows toward a ray that hit it on the front or not,
+    // see CastResult::front_face
+    pub fn emitted(&self, front_face: bool) -> Float {
+        if front_face || self.two_sided_emission { self.emission } else { 0.0 }
+    }
+
+    // linear blend, t = 0 is self and t = 1 is other
+    pub fn lerp(&self, other: &Material, t: Float) -> Material {
+        let mix = |a: Float, b: Float| a + (b - a) * t;
+
+        Material {
+            color: self.color + (other.color - self.color) * t,
+            emission: mix(self.emission, other.emission),
+
+            metallic: mix(self.metallic, other.metallic),
+            specular: mix(self.specular, other.specular),
+            roughness: mix(self.roughness, other.roughness),
+
+            transmission: mix(self.transmission, other.transmission),
+            ior: mix(self.ior, other.ior),
+
+            two_sided: if t < 0.5 { self.two_sided } else { other.two_sided },
+            importance: mix(self.importance, other.importance),
+            two_sided_emission: if t < 0.5 { self.two_sided_emission } else { other.two_sided_emission },
+            hair: if t < 0.5 { self.hair } else { other.hair },
+            priority: if t < 0.5 { self.priority } else { other.priority },
         }
     }
 
@@ -45,6 +115,11 @@ impl Material {
         //
         //     transmission: 0.0,
         //     ior: 0.0,
+        //
+        //     two_sided: true,
+        //     importance: 1.0,
+        //     two_sided_emission: true,
+        //     hair: false,
         // }
     }
 }
@@ -0,0 +1,59 @@
+use std::collections::HashMap;
+use std::sync::{ Arc, RwLock };
+use serde::{ Serialize, Deserialize };
+
+use crate::structures::material::Material;
+
+// which material in a MaterialRegistry
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MaterialId(pub usize);
+
+// materials kept in one place for objects to share, see Shared. they're
+// looked up every time one of their objects is hit, so changing one here
+// changes it on every object using it. clones share the same materials.
+#[derive(Debug, Clone, Default)]
+pub struct MaterialRegistry {
+    materials: Arc<RwLock<Vec<Material>>>,
+    names: Arc<RwLock<HashMap<String, MaterialId>>>,
+}
+
+impl MaterialRegistry {
+    pub fn new() -> MaterialRegistry {
+        MaterialRegistry::default()
+    }
+
+    pub fn add(&self, material: Material) -> MaterialId {
+        let mut materials = self.materials.write().unwrap();
+        materials.push(material);
+        return MaterialId(materials.len() - 1);
+    }
+
+    // added under a name to look it up by later, replacing whatever had the
+    // name before for anything looked up from now on
+    pub fn add_named(&self, name: &str, material: Material) -> MaterialId {
+        let id = self.add(material);
+        self.names.write().unwrap().insert(name.to_string(), id);
+        return id;
+    }
+
+    pub fn id(&self, name: &str) -> Option<MaterialId> {
+        self.names.read().unwrap().get(name).copied()
+    }
+
+    // ids only ever come from add, so they're always there
+    pub fn get(&self, id: MaterialId) -> Material {
+        self.materials.read().unwrap()[id.0]
+    }
+
+    pub fn set(&self, id: MaterialId, material: Material) {
+        self.materials.write().unwrap()[id.0] = material;
+    }
+
+    pub fn len(&self) -> usize {
+        self.materials.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
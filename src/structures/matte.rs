@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+use image::{ ImageBuffer, Rgb, RgbImage };
+
+use crate::structures::float::Float;
+use crate::structures::material::Material;
+
+// fnv-1a, so the same name gets the same id on every run and machine
+pub fn id(name: &str) -> u32 {
+    name.bytes().fold(0x811c9dc5, |hash: u32, byte| (hash ^ byte as u32).wrapping_mul(0x01000193))
+}
+
+// materials don't have names, so they go by what's in them
+pub fn material_id(material: &Material) -> u32 {
+    id(&format!("{:?}", material))
+}
+
+// a color to key an id by, from its bits. never black, that's the background.
+pub fn color(id: u32) -> [u8; 3] {
+    let [r, g, b, _] = id.to_le_bytes();
+    [r | 0x40, g | 0x40, b | 0x40]
+}
+
+// which ids each pixel saw, with the share of its samples that saw each,
+// most covered first. where they don't add up to one the environment
+// showed through. `names` says what each id stands for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Matte {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Vec<(u32, Float)>>,
+    pub names: HashMap<u32, String>,
+}
+
+impl Matte {
+    pub fn coverage(&self, x: usize, y: usize, id: u32) -> Float {
+        self.pixels[y * self.width + x].iter().find(|(seen, _)| *seen == id).map_or(0.0, |(_, share)| *share)
+    }
+
+    // how much of each pixel the named thing covers, rows top to bottom
+    pub fn mask(&self, name: &str) -> Vec<Float> {
+        let id = id(name);
+        (0..self.width * self.height).map(|i| self.coverage(i % self.width, i / self.width, id)).collect()
+    }
+
+    // every id in its own color, blended at the edges by coverage
+    pub fn colors(&self) -> RgbImage {
+        ImageBuffer::from_fn(self.width as u32, self.height as u32, |x, y| {
+            let pixel = &self.pixels[y as usize * self.width + x as usize];
+            let mix = pixel.iter().fold([0.0; 3], |sum: [Float; 3], (id, share)| {
+                let c = color(*id);
+                [sum[0] + c[0] as Float * share, sum[1] + c[1] as Float * share, sum[2] + c[2] as Float * share]
+            });
+            Rgb(mix.map(|channel| channel.round().min(255.0) as u8))
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::collections::HashMap;
+
+    use super::{ Matte, id, color };
+
+    #[test]
+    fn test_matte() {
+        // the reference values for fnv-1a
+        assert_eq!(id(""), 0x811c9dc5);
+        assert_eq!(id("a"), 0xe40c292c);
+
+        let (ball, floor) = (id("ball"), id("floor"));
+        let matte = Matte {
+            width: 2,
+            height: 1,
+            pixels: vec![vec![(ball, 0.75), (floor, 0.25)], vec![]],
+            names: HashMap::new(),
+        };
+
+        assert_eq!(matte.mask("ball"), vec![0.75, 0.0]);
+        assert_eq!(matte.coverage(0, 0, floor), 0.25);
+
+        let colors = matte.colors();
+        assert_eq!(colors.get_pixel(1, 0).0, [0, 0, 0]);
+        assert_ne!(colors.get_pixel(0, 0).0, color(ball));
+    }
+}
@@ -0,0 +1,103 @@
+use serde::{ Serialize, Deserialize };
+
+use crate::structures::float::Float;
+use crate::structures::float::consts::PI;
+
+use crate::structures::vec3::Vec3;
+
+// a participating medium filling the whole scene, like fog or haze.
+// coefficients are per unit of distance; `g` is the henyey-greenstein
+// asymmetry, positive scatters forwards, 0 is even in every direction.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Medium {
+    pub absorption: Float,
+    pub scattering: Float,
+    pub g: Float,
+}
+
+impl Medium {
+    pub fn new(absorption: Float, scattering: Float, g: Float) -> Medium {
+        Medium {
+            absorption: absorption,
+            scattering: scattering,
+            g: g.clamp(-0.99, 0.99),
+        }
+    }
+
+    // total extinction
+    pub fn density(&self) -> Float {
+        self.absorption + self.scattering
+    }
+
+    // chance an interaction scatters the light instead of absorbing it
+    pub fn albedo(&self) -> Float {
+        if self.density() == 0.0 { return 0.0; }
+        return self.scattering / self.density();
+    }
+
+    pub fn transmittance(&self, distance: Float) -> Float {
+        (-self.density() * distance).exp()
+    }
+
+    // how far light gets before interacting, exponentially distributed
+    pub fn sample_distance(&self, u: Float) -> Float {
+        if self.density() == 0.0 { return Float::MAX; }
+        return -(1.0 - u).ln() / self.density();
+    }
+
+    pub fn phase(&self, cosine: Float) -> Float {
+        phase(self.g, cosine)
+    }
+
+    pub fn sample_direction(&self, direction: Vec3, u: [Float; 2]) -> Vec3 {
+        sample_phase(self.g, direction, u)
+    }
+}
+
+// henyey-greenstein density of scattering by an angle with the given cosine
+pub fn phase(g: Float, cosine: Float) -> Float {
+    let denom = 1.0 + g * g - 2.0 * g * cosine;
+    return (1.0 - g * g) / (4.0 * PI * denom * denom.sqrt());
+}
+
+// a new direction for light travelling along `direction`, importance sampled
+pub fn sample_phase(g: Float, direction: Vec3, u: [Float; 2]) -> Vec3 {
+    let cosine = if g.abs() < 0.001 {
+        1.0 - 2.0 * u[0]
+    } else {
+        let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * u[0]);
+        (1.0 + g * g - s * s) / (2.0 * g)
+    };
+    let sine = (1.0 - cosine * cosine).max(0.0).sqrt();
+    let phi = 2.0 * PI * u[1];
+
+    // any two axes perpendicular to the direction will do
+    let w = direction.unit();
+    let helper = if w.x.abs() > 0.9 { Vec3::new(0.0, 1.0, 0.0) } else { Vec3::new(1.0, 0.0, 0.0) };
+    let a = w.cross(&helper).unit();
+    let b = w.cross(&a);
+
+    return (a * (sine * phi.cos()) + b * (sine * phi.sin()) + w * cosine).unit();
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::Medium;
+    use crate::structures::vec3::Vec3;
+
+    #[test]
+    fn test_medium() {
+        let fog = Medium::new(0.1, 0.3, 0.6);
+        assert!((fog.albedo() - 0.75).abs() < 1e-9);
+
+        // median free flight is where half the light is left
+        let median = fog.sample_distance(0.5);
+        assert!((fog.transmittance(median) - 0.5).abs() < 1e-9);
+
+        // strongly forward scattering keeps going the same way
+        let forward = Vec3::new(0.0, 0.0, -1.0);
+        let scattered = fog.sample_direction(forward, [0.5, 0.25]);
+        assert!(scattered.dot(&forward) > 0.5);
+        assert!(fog.phase(1.0) > fog.phase(-1.0));
+    }
+}
@@ -1,6 +1,35 @@
+pub mod float;
 pub mod vec3;
 pub mod ray;
+pub mod packet;
 pub mod material;
+pub mod material_registry;
 pub mod camera;
+pub mod resolution;
 pub mod scene;
 pub mod cast_result;
+pub mod aabb;
+pub mod bvh;
+pub mod transform;
+pub mod node;
+pub mod marching;
+pub mod medium;
+pub mod photon_map;
+pub mod stats;
+pub mod tile;
+pub mod film;
+pub mod post;
+pub mod frustum;
+pub mod interiors;
+pub mod top_level;
+pub mod light_tree;
+pub mod blue_noise;
+pub mod noise;
+pub mod validate;
+pub mod report;
+pub mod matte;
+pub mod frame;
+pub mod ggx;
+pub mod hair;
+pub mod alias;
+pub mod environment_map;
@@ -0,0 +1,114 @@
+use std::sync::Arc;
+
+use crate::structures::transform::Transform;
+use crate::objects::traits::{ March, Trace };
+use crate::objects::instance::Instance;
+use crate::objects::sphere::Sphere;
+use crate::objects::plane::Plane;
+use crate::objects::disk::Disk;
+use crate::objects::quad::Quad;
+use crate::objects::triangle::Triangle;
+use crate::objects::cuboid::Cuboid;
+use crate::objects::torus::Torus;
+use crate::objects::cylinder::Cylinder;
+use crate::objects::capsule::Capsule;
+use crate::objects::cone::Cone;
+use crate::objects::hex_prism::HexPrism;
+use crate::objects::mandelbulb::Mandelbulb;
+use crate::objects::julia::Julia;
+use crate::objects::menger::Menger;
+use crate::objects::mesh::Mesh;
+
+pub enum NodeObject {
+    Empty,
+    March(Arc<dyn March>),
+    Trace(Arc<dyn Trace>),
+}
+
+// the built in shapes go wherever they're cheapest to hit: traced if they
+// can be, marched if they only have a distance field
+macro_rules! object {
+    ($variant:ident, $($shape:ident),*) => {
+        $(impl From<$shape> for NodeObject {
+            fn from(shape: $shape) -> NodeObject { NodeObject::$variant(Arc::new(shape)) }
+        })*
+    };
+}
+
+object!(Trace, Sphere, Plane, Cuboid, Disk, Quad, Triangle, Mesh);
+object!(March, Torus, Cylinder, Capsule, Cone, HexPrism, Mandelbulb, Julia, Menger);
+
+// a node in the scene graph. transforms stack from parent to child,
+// so moving a node moves everything underneath it.
+pub struct Node {
+    pub transform: Transform,
+    pub object: NodeObject,
+    pub children: Vec<Node>,
+}
+
+impl Node {
+    // an empty group
+    pub fn new(transform: Transform) -> Node {
+        Node { transform: transform, object: NodeObject::Empty, children: vec![] }
+    }
+
+    pub fn march(transform: Transform, march: impl March + 'static) -> Node {
+        Node { transform: transform, object: NodeObject::March(Arc::new(march)), children: vec![] }
+    }
+
+    pub fn trace(transform: Transform, trace: impl Trace + 'static) -> Node {
+        Node { transform: transform, object: NodeObject::Trace(Arc::new(trace)), children: vec![] }
+    }
+
+    pub fn add(&mut self, child: Node) -> &mut Node {
+        self.children.push(child);
+        return self;
+    }
+
+    // bakes the hierarchy into flat lists, objects are shared, not copied
+    pub fn flatten(
+        &self,
+        parent: &Transform,
+        march: &mut Vec<Arc<dyn March>>,
+        trace: &mut Vec<Arc<dyn Trace>>,
+    ) {
+        let world = *parent * self.transform;
+
+        match &self.object {
+            NodeObject::Empty => (),
+            NodeObject::March(object) if world.is_identity() => march.push(Arc::clone(object)),
+            NodeObject::Trace(object) if world.is_identity() => trace.push(Arc::clone(object)),
+            NodeObject::March(object) => march.push(Arc::new(Instance::new(Arc::clone(object), world))),
+            NodeObject::Trace(object) => trace.push(Arc::new(Instance::new(Arc::clone(object), world))),
+        }
+
+        for child in self.children.iter() {
+            child.flatten(&world, march, trace);
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::Node;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::material::Material;
+    use crate::structures::transform::Transform;
+    use crate::objects::sphere::Sphere;
+
+    #[test]
+    fn test_flatten() {
+        let sphere = Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0, Material::blank());
+
+        let mut group = Node::new(Transform::translate(Vec3::new(0.0, 0.0, -5.0)));
+        group.add(Node::march(Transform::translate(Vec3::new(0.0, 3.0, 0.0)), sphere));
+
+        let mut march = vec![];
+        let mut trace = vec![];
+        group.flatten(&Transform::identity(), &mut march, &mut trace);
+
+        assert_eq!(march.len(), 1);
+        assert!(trace.is_empty());
+        assert_eq!(march[0].march(Vec3::new(0.0, 3.0, 0.0)), 4.0);
+    }
+}
@@ -0,0 +1,283 @@
+use serde::{ Serialize, Deserialize };
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+
+// smooth random patterns over space, the same everywhere for a seed, for
+// anything procedural: bumping a surface, breaking up a volume's density,
+// the grain on a finished frame. every one is continuous, 0 on average and
+// about -1 to 1, bar worley which is distances.
+
+// splitmix64 over a cell and the seed
+fn hash(cell: [i64; 3], seed: u64) -> u64 {
+    let mut h = seed
+        ^ (cell[0] as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+        ^ (cell[1] as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f)
+        ^ (cell[2] as u64).wrapping_mul(0x1656_67b1_9e37_79f9);
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    return h ^ (h >> 31);
+}
+
+// the hash as 0 to 1, from its bits starting at `shift`
+fn unit(h: u64, shift: u32) -> Float {
+    (h >> shift & 0xf_ffff) as Float / 0x10_0000 as Float
+}
+
+fn floor(point: Vec3) -> [i64; 3] {
+    [point.x.floor() as i64, point.y.floor() as i64, point.z.floor() as i64]
+}
+
+fn offset(cell: [i64; 3], by: [i64; 3]) -> [i64; 3] {
+    [cell[0] + by[0], cell[1] + by[1], cell[2] + by[2]]
+}
+
+// one of the twelve edges of a cube, perlin's gradients
+fn gradient(h: u64) -> Vec3 {
+    match h % 12 {
+        0 => Vec3::new(1.0, 1.0, 0.0),
+        1 => Vec3::new(-1.0, 1.0, 0.0),
+        2 => Vec3::new(1.0, -1.0, 0.0),
+        3 => Vec3::new(-1.0, -1.0, 0.0),
+        4 => Vec3::new(1.0, 0.0, 1.0),
+        5 => Vec3::new(-1.0, 0.0, 1.0),
+        6 => Vec3::new(1.0, 0.0, -1.0),
+        7 => Vec3::new(-1.0, 0.0, -1.0),
+        8 => Vec3::new(0.0, 1.0, 1.0),
+        9 => Vec3::new(0.0, -1.0, 1.0),
+        10 => Vec3::new(0.0, 1.0, -1.0),
+        _ => Vec3::new(0.0, -1.0, -1.0),
+    }
+}
+
+fn lerp(a: Float, b: Float, t: Float) -> Float {
+    a + (b - a) * t
+}
+
+// blends the eight corners of the cell `point` is in, `corner` giving each
+// one's value there, with `fade` easing across the cell
+fn trilinear(point: Vec3, fade: impl Fn(Float) -> Float, corner: impl Fn([i64; 3], Vec3) -> Float) -> Float {
+    let cell = floor(point);
+    let local = point - Vec3::new(cell[0] as Float, cell[1] as Float, cell[2] as Float);
+    let (u, v, w) = (fade(local.x), fade(local.y), fade(local.z));
+    let at = |x: i64, y: i64, z: i64| corner(offset(cell, [x, y, z]), local - Vec3::new(x as Float, y as Float, z as Float));
+
+    let bottom = lerp(lerp(at(0, 0, 0), at(1, 0, 0), u), lerp(at(0, 1, 0), at(1, 1, 0), u), v);
+    let top = lerp(lerp(at(0, 0, 1), at(1, 0, 1), u), lerp(at(0, 1, 1), at(1, 1, 1), u), v);
+    return lerp(bottom, top, w);
+}
+
+// random values on the corners of a grid, blended smoothly in between.
+// the cheapest, and it shows the grid more than the others.
+pub fn value(point: Vec3, seed: u64) -> Float {
+    trilinear(point, |t| t * t * (3.0 - 2.0 * t), |cell, _| unit(hash(cell, seed), 11) * 2.0 - 1.0)
+}
+
+// perlin's improved noise: a random slope at each corner instead, so it's 0
+// on the grid and the bumps fall between, and the grid's harder to spot
+pub fn perlin(point: Vec3, seed: u64) -> Float {
+    let fade = |t: Float| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+    return trilinear(point, fade, |cell, local| gradient(hash(cell, seed)).dot(&local));
+}
+
+// simplex noise, gustavson's way: four corners of a tetrahedron around
+// each point instead of eight of a cube, so it's cheaper and doesn't line
+// up along the axes at all. each corner's bump reaches half a unit out
+// squared, any further and it pokes out past the tetrahedra around it
+// and leaves seams.
+pub fn simplex(point: Vec3, seed: u64) -> Float {
+    const SKEW: Float = 1.0 / 3.0;
+    const UNSKEW: Float = 1.0 / 6.0;
+
+    // which skewed cell, and where in it
+    let s = (point.x + point.y + point.z) * SKEW;
+    let cell = floor(point + Vec3::new(s, s, s));
+    let t = (cell[0] + cell[1] + cell[2]) as Float * UNSKEW;
+    let first = point - Vec3::new(cell[0] as Float - t, cell[1] as Float - t, cell[2] as Float - t);
+
+    // down the longest axis first, then the next
+    let (x, y, z) = (first.x, first.y, first.z);
+    let (one, two) = if x >= y {
+        if y >= z { ([1, 0, 0], [1, 1, 0]) } else if x >= z { ([1, 0, 0], [1, 0, 1]) } else { ([0, 0, 1], [1, 0, 1]) }
+    } else if y < z { ([0, 0, 1], [0, 1, 1]) } else if x < z { ([0, 1, 0], [0, 1, 1]) } else { ([0, 1, 0], [1, 1, 0]) };
+
+    let corners = [[0, 0, 0], one, two, [1, 1, 1]];
+    let mut total = 0.0;
+    for (step, corner) in corners.iter().enumerate() {
+        let back = step as Float * UNSKEW;
+        let local = first - Vec3::new(corner[0] as Float - back, corner[1] as Float - back, corner[2] as Float - back);
+        let falloff = 0.5 - local.length_squared();
+        if falloff <= 0.0 { continue; }
+        total += falloff.powi(4) * gradient(hash(offset(cell, *corner), seed)).dot(&local);
+    }
+
+    return total * 76.0;
+}
+
+// cellular noise: a point dropped somewhere in every cell of the grid, and
+// how far `point` is from the nearest of them and the next nearest. F1 on
+// its own is like cells or scales, F2 - F1 like cracks between them.
+pub fn worley(point: Vec3, seed: u64) -> [Float; 2] {
+    let cell = floor(point);
+    let mut nearest = [Float::MAX, Float::MAX];
+
+    for x in -1..=1 {
+        for y in -1..=1 {
+            for z in -1..=1 {
+                let neighbour = offset(cell, [x, y, z]);
+                let h = hash(neighbour, seed);
+                let feature = Vec3::new(
+                    neighbour[0] as Float + unit(h, 4),
+                    neighbour[1] as Float + unit(h, 24),
+                    neighbour[2] as Float + unit(h, 44),
+                );
+
+                let distance = (feature - point).length();
+                if distance < nearest[0] {
+                    nearest = [distance, nearest[0]];
+                } else if distance < nearest[1] {
+                    nearest[1] = distance;
+                }
+            }
+        }
+    }
+
+    return nearest;
+}
+
+// which of them a Fractal's built from
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Basis {
+    Value,
+    #[default]
+    Perlin,
+    Simplex,
+    Worley, // F1, from -1 right on a point to 1 as far as they get from one
+}
+
+impl Basis {
+    pub fn sample(self, point: Vec3, seed: u64) -> Float {
+        match self {
+            Basis::Value => value(point, seed),
+            Basis::Perlin => perlin(point, seed),
+            Basis::Simplex => simplex(point, seed),
+            Basis::Worley => (worley(point, seed)[0] * 2.0 - 1.0).min(1.0),
+        }
+    }
+
+    // the most it changes by per unit moved, measured with a little to spare
+    pub fn steepness(self) -> Float {
+        match self {
+            Basis::Value => 4.0,
+            Basis::Perlin => 4.5,
+            Basis::Simplex => 9.0,
+            Basis::Worley => 2.5,
+        }
+    }
+}
+
+// fractal brownian motion: `octaves` layers of the basis, each `lacunarity`
+// times finer and `gain` times fainter than the last, so there's detail at
+// every scale like rock or cloud. `frequency` is how many of the coarsest
+// bumps fit in a unit. summed, then scaled back to about -1 to 1.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Fractal {
+    pub basis: Basis,
+    pub octaves: u32,
+    pub frequency: Float,
+    pub lacunarity: Float,
+    pub gain: Float,
+    pub seed: u64,
+}
+
+impl Default for Fractal {
+    fn default() -> Fractal {
+        Fractal { basis: Basis::Perlin, octaves: 4, frequency: 1.0, lacunarity: 2.0, gain: 0.5, seed: 0 }
+    }
+}
+
+impl Fractal {
+    pub fn sample(&self, point: Vec3) -> Float {
+        let (mut total, mut most) = (0.0, 0.0);
+        let (mut frequency, mut amplitude) = (self.frequency, 1.0);
+
+        // each octave gets a seed of its own so the layers don't line up
+        for octave in 0..self.octaves.max(1) {
+            total += amplitude * self.basis.sample(point * frequency, self.seed.wrapping_add(octave as u64));
+            most += amplitude;
+            frequency *= self.lacunarity;
+            amplitude *= self.gain;
+        }
+
+        return total / most;
+    }
+
+    // how fast it can change, at most, per unit moved. a distance field
+    // bumped by it has to be marched slower by this, see Displaced.
+    pub fn steepness(&self) -> Float {
+        let (mut total, mut most) = (0.0, 0.0);
+        let (mut frequency, mut amplitude) = (self.frequency, 1.0);
+
+        for _ in 0..self.octaves.max(1) {
+            total += amplitude * frequency;
+            most += amplitude;
+            frequency *= self.lacunarity;
+            amplitude *= self.gain;
+        }
+
+        return self.basis.steepness() * total / most;
+    }
+}
+
+// the usual fbm of perlin noise, for when the defaults will do
+pub fn fbm(point: Vec3, octaves: u32, seed: u64) -> Float {
+    Fractal { octaves: octaves, seed: seed, ..Fractal::default() }.sample(point)
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::{ value, perlin, simplex, worley, fbm, Basis, Fractal };
+    use crate::structures::float::Float;
+    use crate::structures::vec3::Vec3;
+
+    #[test]
+    fn test_noise() {
+        let points: Vec<Vec3> = (0..4000)
+            .map(|i| Vec3::new(i as Float * 0.37, (i % 89) as Float * 0.71 - 20.0, (i % 13) as Float * 1.13 + 0.5))
+            .collect();
+
+        for basis in [Basis::Value, Basis::Perlin, Basis::Simplex, Basis::Worley] {
+            let samples: Vec<Float> = points.iter().map(|point| basis.sample(*point, 7)).collect();
+
+            // in range, about 0 on average, and not flat
+            let mean = samples.iter().sum::<Float>() / samples.len() as Float;
+            let spread = samples.iter().map(|sample| (sample - mean).powi(2)).sum::<Float>() / samples.len() as Float;
+            assert!(samples.iter().all(|sample| sample.abs() <= 1.1), "{:?}", basis);
+            assert!(mean.abs() < 0.1 && spread > 0.01, "{:?} {} {}", basis, mean, spread);
+
+            // the same for a seed, and another for another one
+            assert_eq!(basis.sample(points[5], 7), samples[5]);
+            assert!(points.iter().take(50).any(|point| basis.sample(*point, 8) != basis.sample(*point, 7)));
+
+            // and smooth, near points are near in value
+            let step = Vec3::new(1e-3, 0.0, 0.0);
+            assert!(points.iter().all(|point| (basis.sample(*point + step, 7) - basis.sample(*point, 7)).abs() < 1e-2), "{:?}", basis);
+        }
+
+        // perlin's 0 on the grid, value noise isn't
+        assert_eq!(perlin(Vec3::new(3.0, -2.0, 5.0), 1), 0.0);
+        assert!(value(Vec3::new(3.0, -2.0, 5.0), 1) != 0.0);
+        assert!(simplex(Vec3::new(0.3, 0.2, 0.1), 1) != simplex(Vec3::new(0.3, 0.2, 0.1), 2));
+
+        // the nearest feature point's never further than the next
+        let [f1, f2] = worley(Vec3::new(0.4, 1.7, -3.2), 3);
+        assert!(f1 <= f2 && f1 < 3.0_f64.sqrt() as Float);
+
+        // more octaves add finer detail without going out of range
+        let fractal = Fractal { octaves: 6, ..Fractal::default() };
+        assert!(points.iter().all(|point| fractal.sample(*point).abs() <= 1.1));
+        assert_eq!(fbm(points[3], 4, 0), Fractal::default().sample(points[3]));
+        assert!(fractal.steepness() > Fractal { octaves: 1, ..fractal }.steepness());
+    }
+}
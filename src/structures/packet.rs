@@ -0,0 +1,213 @@
+use std::ops::{ Add, Sub };
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::ray::Ray;
+
+// how many rays go through together. std::simd isn't stable, so lanes are
+// wide's vectors, which are sse or avx registers where the target has them
+// and plain arrays where it doesn't.
+pub const LANES: usize = 4;
+
+#[cfg(not(feature = "f32"))]
+pub type Lanes = wide::f64x4;
+#[cfg(feature = "f32")]
+pub type Lanes = wide::f32x4;
+
+pub fn lanes(f: impl FnMut(usize) -> Float) -> Lanes {
+    Lanes::new(std::array::from_fn(f))
+}
+
+// LANES vectors stored a component at a time, so each component is one register
+#[derive(Debug, Copy, Clone)]
+pub struct WideVec3 {
+    pub x: Lanes,
+    pub y: Lanes,
+    pub z: Lanes,
+}
+
+impl WideVec3 {
+    pub fn new(vectors: [Vec3; LANES]) -> WideVec3 {
+        WideVec3 {
+            x: lanes(|i| vectors[i].x),
+            y: lanes(|i| vectors[i].y),
+            z: lanes(|i| vectors[i].z),
+        }
+    }
+
+    // the same vector in every lane
+    pub fn splat(vector: Vec3) -> WideVec3 {
+        WideVec3::new([vector; LANES])
+    }
+
+    pub fn at(&self, lane: usize) -> Vec3 {
+        Vec3::new(self.x.as_array_ref()[lane], self.y.as_array_ref()[lane], self.z.as_array_ref()[lane])
+    }
+
+    pub fn dot(&self, other: &WideVec3) -> Lanes {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn length(&self) -> Lanes {
+        self.dot(self).sqrt()
+    }
+
+    pub fn scale(&self, by: &Lanes) -> WideVec3 {
+        WideVec3 {
+            x: self.x * *by,
+            y: self.y * *by,
+            z: self.z * *by,
+        }
+    }
+}
+
+impl Add<WideVec3> for WideVec3 {
+    type Output = WideVec3;
+
+    fn add(self, other: WideVec3) -> WideVec3 {
+        WideVec3 {
+            x: self.x + other.x,
+            y: self.y + other.y,
+            z: self.z + other.z,
+        }
+    }
+}
+
+impl Sub<WideVec3> for WideVec3 {
+    type Output = WideVec3;
+
+    fn sub(self, other: WideVec3) -> WideVec3 {
+        WideVec3 {
+            x: self.x - other.x,
+            y: self.y - other.y,
+            z: self.z - other.z,
+        }
+    }
+}
+
+// rays that start close and head the same way, like camera rays through
+// one pixel, so they tend to hit the same things at the same time
+#[derive(Debug, Copy, Clone)]
+pub struct RayPacket {
+    pub origin: WideVec3,
+    pub direction: WideVec3,
+    pub spread: Lanes,
+    pub width: Lanes,
+    pub t_min: Lanes,
+    pub t_max: Lanes,
+    pub time: Lanes,
+}
+
+impl RayPacket {
+    pub fn new(rays: [Ray; LANES]) -> RayPacket {
+        RayPacket {
+            origin: WideVec3::new(rays.map(|ray| ray.origin)),
+            direction: WideVec3::new(rays.map(|ray| ray.direction)),
+            spread: lanes(|i| rays[i].spread),
+            width: lanes(|i| rays[i].width),
+            t_min: lanes(|i| rays[i].t_min),
+            t_max: lanes(|i| rays[i].t_max),
+            time: lanes(|i| rays[i].time),
+        }
+    }
+
+    // up to LANES rays, the last one repeated to fill any lanes left over
+    pub fn from_slice(rays: &[Ray]) -> RayPacket {
+        let last = rays.len().clamp(1, LANES) - 1;
+        RayPacket::new(std::array::from_fn(|i| rays[i.min(last)]))
+    }
+
+    pub fn ray(&self, lane: usize) -> Ray {
+        let at = |lanes: &Lanes| lanes.as_array_ref()[lane];
+        Ray::new(self.origin.at(lane), self.direction.at(lane)).with_spread(at(&self.spread)).with_width(at(&self.width))
+            .with_range(at(&self.t_min), at(&self.t_max)).with_time(at(&self.time))
+    }
+
+    pub fn point_at(&self, distance: &Lanes) -> WideVec3 {
+        self.origin + self.direction.scale(distance)
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::{ RayPacket, WideVec3, LANES, lanes };
+    use crate::structures::float::Float;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::ray::Ray;
+    use crate::structures::material::Material;
+    use crate::structures::camera::Camera;
+    use crate::structures::scene::Scene;
+    use crate::render::{ cast_packet, cast_ray };
+    use crate::objects::visible::RayKind;
+    use crate::objects::sphere::Sphere;
+    use crate::objects::plane::Plane;
+    use crate::objects::cuboid::Cuboid;
+    use crate::objects::traits::{ March, Trace };
+
+    fn rays() -> Vec<Ray> {
+        (0..LANES).map(|i| {
+            let x = i as Float * 0.3 - 0.45;
+            Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(x, -0.2, -1.0).unit())
+        }).collect()
+    }
+
+    // packets answer exactly what the rays would one at a time
+    #[test]
+    fn test_packet() {
+        let shapes: Vec<Box<dyn Trace>> = vec![
+            Box::new(Sphere::new(Vec3::new(0.0, 0.0, -4.0), 1.0, Material::blank())),
+            Box::new(Plane::new(Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Material::blank())),
+        ];
+
+        let rays = rays();
+        let packet = RayPacket::from_slice(&rays);
+
+        for shape in &shapes {
+            let together = shape.trace_packet(&packet);
+            for (lane, ray) in rays.iter().enumerate() {
+                let (hit, distance, normal) = shape.trace(*ray);
+                assert_eq!(together[lane].0, hit);
+                if hit {
+                    assert!((together[lane].1 - distance).abs() < 1e-9);
+                    assert!((together[lane].2 - normal).length() < 1e-9);
+                }
+            }
+        }
+
+        let marched: Vec<Box<dyn March>> = vec![
+            Box::new(Sphere::new(Vec3::new(0.0, 0.0, -4.0), 1.0, Material::blank())),
+            Box::new(Cuboid::new(Vec3::new(1.0, 0.0, -3.0), Vec3::new(0.5, 0.5, 0.5), Material::blank())),
+        ];
+
+        let points = packet.point_at(&lanes(|i| 1.0 + i as Float));
+        for shape in &marched {
+            let together = shape.march_packet(&points);
+            for (lane, distance) in together.to_array().iter().enumerate() {
+                assert!((distance - shape.march(points.at(lane))).abs() < 1e-9);
+            }
+        }
+
+        // and so does marching a whole scene in lockstep
+        let camera = Camera::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0));
+        let mut scene = Scene::new(camera);
+        scene.add_march(Sphere::new(Vec3::new(-0.5, 0.0, -4.0), 1.0, Material::blank()));
+        scene.add_march(Plane::new(Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Material::blank()));
+        scene.add_trace(Cuboid::new(Vec3::new(1.0, 0.0, -3.0), Vec3::new(0.5, 0.5, 0.5), Material::blank()));
+
+        for (together, ray) in cast_packet(&scene, &rays, RayKind::Camera).iter().zip(rays.iter()) {
+            let alone = cast_ray(&scene, *ray, RayKind::Camera);
+            assert_eq!(together.hit, alone.hit);
+            assert_eq!(together.distance, alone.distance);
+        }
+
+        // short packets fill up with the last ray
+        let short = RayPacket::from_slice(&rays[..1]);
+        assert_eq!(short.ray(LANES - 1), rays[0]);
+
+        // and keep the shutter time camera rays were sent at
+        let timed = RayPacket::from_slice(&[rays[0].with_time(0.25), rays[1].with_time(0.75)]);
+        assert_eq!(timed.ray(0).time, 0.25);
+        assert_eq!(timed.ray(1).time, 0.75);
+        assert_eq!(WideVec3::splat(rays[0].origin).at(1), rays[0].origin);
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use serde::{ Serialize, Deserialize };
+
+use crate::structures::float::Float;
+use crate::structures::float::consts::PI;
+use rand::Rng;
+
+use crate::structures::vec3::Vec3;
+use crate::structures::ray::Ray;
+use crate::structures::scene::Scene;
+use crate::structures::interiors::Interiors;
+use crate::render::{ cast_ray, offset, reflect, refract, fresnel };
+use crate::objects::visible::RayKind;
+
+// how many surfaces a photon can bounce off before it's dropped
+const MAX_BOUNCES: u32 = 8;
+const EPSILON: Float = 0.002;
+
+// something photons are shot from. `power` is flux, the total light
+// leaving it; use `sphere` to match an emissive sphere in the scene.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Emitter {
+    pub position: Vec3,
+    pub radius: Float,
+    pub power: Vec3,
+}
+
+impl Emitter {
+    pub fn new(position: Vec3, radius: Float, power: Vec3) -> Emitter {
+        Emitter { position: position, radius: radius, power: power }
+    }
+
+    // a sphere glowing with `radiance` over its surface puts out 4π²r² times that
+    pub fn sphere(position: Vec3, radius: Float, radiance: Vec3) -> Emitter {
+        Emitter::new(position, radius, radiance * (4.0 * PI * PI * radius * radius))
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Photon {
+    pub position: Vec3,
+    pub direction: Vec3, // the way it was travelling when it landed
+    pub power: Vec3,
+}
+
+// caustics only: photons are stored where they land on a diffuse surface
+// after going through at least one mirror or glass bounce. everything
+// else the path tracer handles well enough on its own.
+// stored in a hash grid with cells as big as the gather radius.
+#[derive(Debug, Clone)]
+pub struct PhotonMap {
+    pub radius: Float,
+    cells: HashMap<[i64; 3], Vec<Photon>>,
+    count: usize,
+}
+
+impl PhotonMap {
+    pub fn build(scene: &Scene, emitters: &[Emitter], photons: usize, radius: Float) -> PhotonMap {
+        let mut map = PhotonMap { radius: radius, cells: HashMap::new(), count: 0 };
+        let mut rng = rand::thread_rng();
+
+        let per_emitter = photons / emitters.len().max(1);
+
+        for emitter in emitters {
+            for _ in 0..per_emitter {
+                // leave in a cosine distribution from a random point on the surface
+                let normal = random_unit(&mut rng);
+                let origin = emitter.position + normal * emitter.radius;
+                let mut direction = (normal + random_unit(&mut rng)).unit();
+                if direction.dot(&normal) <= 0.0 { direction = normal; }
+
+                let power = emitter.power / per_emitter as Float;
+                map.trace(scene, Ray::new(origin + normal * EPSILON, direction), power, &mut rng);
+            }
+        }
+
+        return map;
+    }
+
+    fn trace(&mut self, scene: &Scene, mut ray: Ray, mut power: Vec3, rng: &mut impl Rng) {
+        let mut specular = false;
+        let mut interiors = Interiors::new();
+
+        for _ in 0..MAX_BOUNCES {
+            let cast = cast_ray(scene, ray, RayKind::Indirect);
+            let (hit, distance, normal, material) = cast.unpack();
+            if !hit { return; }
+
+            let position = ray.point_at(&distance);
+
+            if rng.gen::<Float>() < material.metallic {
+                let direction = reflect(ray.direction, normal).unit();
+                ray = Ray::new(offset(position, normal, direction), direction);
+                power = power * material.color;
+            } else if rng.gen::<Float>() < material.transmission {
+                // where glass overlaps glass only the one with the higher
+                // priority counts, see Interiors
+                let (from, to) = match interiors.crossing(cast.object, &material, cast.front_face) {
+                    Some(iors) => iors,
+                    None => {
+                        interiors.cross(cast.object, &material, cast.front_face);
+                        ray = Ray::new(offset(position, normal, ray.direction), ray.direction);
+                        continue;
+                    },
+                };
+                let cosine = -ray.direction.dot(&normal);
+
+                let mut refracted = Vec3::new(0.0, 0.0, 0.0);
+                if refract(&ray.direction, &normal, from / to, &mut refracted) && rng.gen::<Float>() >= fresnel(cosine, to / from) {
+                    let direction = refracted.unit();
+                    ray = Ray::new(offset(position, normal, direction), direction);
+                    power = power * material.color;
+                    interiors.cross(cast.object, &material, cast.front_face);
+                } else {
+                    let direction = reflect(ray.direction, normal).unit();
+                    ray = Ray::new(offset(position, normal, direction), direction);
+                }
+            } else {
+                if specular { self.store(Photon { position: position, direction: ray.direction, power: power }); }
+                return;
+            }
+
+            specular = true;
+        }
+    }
+
+    fn cell(&self, point: Vec3) -> [i64; 3] {
+        let local = point / self.radius;
+        [local.x.floor() as i64, local.y.floor() as i64, local.z.floor() as i64]
+    }
+
+    fn store(&mut self, photon: Photon) {
+        let cell = self.cell(photon.position);
+        self.cells.entry(cell).or_default().push(photon);
+        self.count += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn photons(&self) -> impl Iterator<Item = &Photon> {
+        self.cells.values().flatten()
+    }
+
+    // irradiance from the photons landing within the radius of a point,
+    // on the side the normal faces
+    pub fn irradiance(&self, point: Vec3, normal: Vec3) -> Vec3 {
+        let [x, y, z] = self.cell(point);
+        let mut flux = Vec3::new(0.0, 0.0, 0.0);
+
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let photons = match self.cells.get(&[x + dx, y + dy, z + dz]) {
+                        Some(photons) => photons,
+                        None => continue,
+                    };
+
+                    for photon in photons {
+                        if (photon.position - point).length_squared() > self.radius * self.radius { continue; }
+                        if photon.direction.dot(&normal) >= 0.0 { continue; }
+                        flux = flux + photon.power;
+                    }
+                }
+            }
+        }
+
+        return flux / (PI * self.radius * self.radius);
+    }
+}
+
+pub(crate) fn random_unit(rng: &mut impl Rng) -> Vec3 {
+    loop {
+        let point = Vec3::new(
+            rng.gen::<Float>() * 2.0 - 1.0,
+            rng.gen::<Float>() * 2.0 - 1.0,
+            rng.gen::<Float>() * 2.0 - 1.0,
+        );
+        let length = point.length_squared();
+        if length > 1e-6 && length <= 1.0 { return point.unit(); }
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::{ PhotonMap, Emitter };
+    use crate::structures::vec3::Vec3;
+    use crate::structures::camera::Camera;
+    use crate::structures::scene::Scene;
+    use crate::structures::material::Material;
+    use crate::objects::sphere::Sphere;
+    use crate::objects::plane::Plane;
+
+    #[test]
+    fn test_caustics() {
+        let camera = Camera::new(Vec3::new(0.0, 1.0, 5.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let mut scene = Scene::new(camera);
+
+        let mut floor = Material::blank();
+        floor.emission = 0.0;
+        let mut mirror = floor;
+        mirror.metallic = 1.0;
+        mirror.color = Vec3::new(1.0, 1.0, 1.0);
+
+        scene.add_trace(Plane::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), floor));
+        scene.add_trace(Sphere::new(Vec3::new(0.0, 1.0, 0.0), 0.5, mirror));
+
+        let emitters = [Emitter::sphere(Vec3::new(0.0, 3.0, 0.0), 0.1, Vec3::new(1.0, 1.0, 1.0))];
+        let map = PhotonMap::build(&scene, &emitters, 2000, 0.1);
+
+        // only the photons bounced off the mirror are kept, all on the floor
+        assert!(!map.is_empty() && map.len() < 2000);
+        assert!(map.photons().all(|photon| photon.position.y.abs() < 0.01));
+    }
+}
@@ -0,0 +1,83 @@
+use serde::{ Serialize, Deserialize };
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::film::Film;
+use crate::structures::noise;
+
+// effects on a finished frame, for the look of it rather than the light in
+// it. they go on in order, see Film::post, and work on linear radiance, so
+// they come before tone mapping like a real lens and film's would.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Post {
+    // darker towards the edges, `strength` of the light gone in the corners
+    Vignette { strength: Float },
+    // the speckle of film, lightening and darkening pixels by up to
+    // `strength` of themselves, in clumps `size` pixels across. the same
+    // `seed` gives the same grain every time.
+    Grain { strength: Float, size: Float, seed: u64 },
+}
+
+impl Post {
+    pub fn apply(&self, film: &mut Film) {
+        let (width, height) = (film.width as Float, film.height as Float);
+
+        for y in 0..film.height {
+            for x in 0..film.width {
+                let factor = match *self {
+                    Post::Vignette { strength } => {
+                        // 0 in the middle to 1 in the corners
+                        let u = (x as Float + 0.5) / width * 2.0 - 1.0;
+                        let v = (y as Float + 0.5) / height * 2.0 - 1.0;
+                        1.0 - strength * (u * u + v * v) * 0.5
+                    },
+                    Post::Grain { strength, size, seed } => {
+                        let size = size.max(1.0);
+                        1.0 + strength * noise::value(Vec3::new(x as Float / size, y as Float / size, 0.0), seed)
+                    },
+                };
+
+                film.set(x, y, film.get(x, y) * factor.max(0.0));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::Post;
+    use crate::structures::float::Float;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::film::Film;
+
+    #[test]
+    fn test_post() {
+        let white = || Film::wrap(64, 64, vec![Vec3::new(1.0, 1.0, 1.0); 64 * 64]).unwrap();
+
+        // vignetting leaves the middle and takes from the corners
+        let mut film = white();
+        film.post(&[Post::Vignette { strength: 0.5 }]);
+        assert!(film.get(32, 32).x > 0.99);
+        assert!((film.get(0, 0).x - 0.5).abs() < 0.05 && film.get(0, 0) == film.get(63, 63));
+        assert!(film.get(0, 32).x > film.get(0, 0).x);
+
+        // grain moves pixels either way without changing the frame overall,
+        // the same way every time for one seed
+        let grain = |seed: u64| {
+            let mut film = white();
+            film.post(&[Post::Grain { strength: 0.2, size: 2.0, seed: seed }]);
+            film
+        };
+        let (a, b) = (grain(1), grain(2));
+        assert_eq!(a, grain(1));
+        assert!(a != b);
+        assert!(a.pixels.iter().all(|pixel| pixel.x >= 0.8 && pixel.x <= 1.2 && pixel.x == pixel.z));
+        let mean = a.pixels.iter().map(|pixel| pixel.x).sum::<Float>() / a.pixels.len() as Float;
+        assert!((mean - 1.0).abs() < 0.02);
+
+        // and black stays black
+        let mut black = Film::new(4, 4);
+        black.post(&[Post::Grain { strength: 0.2, size: 1.0, seed: 1 }, Post::Vignette { strength: 1.0 }]);
+        assert_eq!(black, Film::new(4, 4));
+    }
+}
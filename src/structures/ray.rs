@@ -1,27 +1,106 @@
+use serde::{ Serialize, Deserialize };
+
+use crate::structures::float::Float;
 use crate::structures::vec3::Vec3;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct Ray {
     pub origin: Vec3,
     pub direction: Vec3,
+    #[serde(default)]
+    pub spread: Float, // how fast the footprint widens per unit travelled, 0 for a thin ray
+    #[serde(default)]
+    pub width: Float,  // how wide the footprint already is at the origin
+    #[serde(default)]
+    pub t_min: Float,  // hits only count between these two distances along it
+    #[serde(default = "t_max")]
+    pub t_max: Float,
+    #[serde(default)]
+    pub time: Float, // when it was sent, see Camera::shutter. only camera rays have one so far
 }
 
+fn t_max() -> Float { Float::MAX }
+
 impl Ray {
     pub fn new(origin: Vec3, direction: Vec3) -> Ray {
         Ray {
             origin: origin,
             direction: direction,
+            spread: 0.0,
+            width: 0.0,
+            t_min: 0.0,
+            t_max: Float::MAX,
+            time: 0.0,
+        }
+    }
+
+    // a cone, like the one a pixel sees
+    pub fn with_spread(mut self, spread: Float) -> Ray {
+        self.spread = spread;
+        return self;
+    }
+
+    // a cone that doesn't start at a point, like one carried on off a mirror
+    pub fn with_width(mut self, width: Float) -> Ray {
+        self.width = width;
+        return self;
+    }
+
+    // only hits between `t_min` and `t_max` along the ray count, like a
+    // shadow ray that stops at its light
+    pub fn with_range(mut self, t_min: Float, t_max: Float) -> Ray {
+        self.t_min = t_min;
+        self.t_max = t_max;
+        return self;
+    }
+
+    pub fn with_time(mut self, time: Float) -> Ray {
+        self.time = time;
+        return self;
+    }
+
+    pub fn with_max(mut self, t_max: Float) -> Ray {
+        self.t_max = t_max;
+        return self;
+    }
+
+    // whether a hit this far along is one that counts
+    pub fn contains(&self, distance: Float) -> bool {
+        distance > self.t_min && distance < self.t_max
+    }
+
+    // the same ray moved up to where its range starts, for asking things
+    // that only give back their closest hit. distances along it are short
+    // by the old t_min.
+    pub fn from_start(&self) -> Ray {
+        Ray {
+            origin: self.point_at(&self.t_min),
+            width: self.footprint(self.t_min),
+            t_min: 0.0,
+            t_max: self.t_max - self.t_min,
+            ..*self
         }
     }
 
+    // how wide the cone is at a distance, for cone tracing and for
+    // filtering whatever's found there
+    pub fn footprint(&self, distance: Float) -> Float {
+        self.width + self.spread * distance
+    }
+
     pub fn through(origin: Vec3, to: Vec3) -> Ray {
         Ray {
             origin: origin,
-            direction: (origin - to).unit()
+            direction: (origin - to).unit(),
+            spread: 0.0,
+            width: 0.0,
+            t_min: 0.0,
+            t_max: Float::MAX,
+            time: 0.0,
         }
     }
 
-    pub fn point_at(&self, distance: &f64) -> Vec3 {
+    pub fn point_at(&self, distance: &Float) -> Vec3 {
         self.origin + self.direction * (*distance)
     }
 }
@@ -0,0 +1,247 @@
+use std::fmt;
+use std::mem::size_of_val;
+use std::collections::BTreeMap;
+
+use crate::structures::float::Float;
+use crate::structures::bvh::Bvh;
+use crate::structures::scene::Scene;
+
+// how many steps a marched ray typically takes. render stops at 128, most
+// rays get there or away well before.
+const MARCH_STEPS: Float = 32.0;
+
+// what one object is and what it takes, see March::footprint. costs are
+// rough and in spheres: how many ray sphere tests, or sphere distances,
+// one ray against it, or one distance to it, takes the time of.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Footprint {
+    pub kind: &'static str, // what it's counted as
+    pub bytes: usize,       // what it keeps outside of itself, vertices, grids, trees
+    pub textures: usize,    // of that, sampled grids, see SdfGrid
+    pub bvh_nodes: usize,   // of all the trees in it
+    pub bvh_depth: usize,   // of the deepest
+    pub cost: Float,
+}
+
+impl Footprint {
+    pub fn new(kind: &'static str) -> Footprint {
+        Footprint { kind: kind, bytes: 0, textures: 0, bvh_nodes: 0, bvh_depth: 0, cost: 1.0 }
+    }
+
+    pub fn with_bytes(mut self, bytes: usize) -> Footprint {
+        self.bytes += bytes;
+        return self;
+    }
+
+    pub fn with_textures(mut self, bytes: usize) -> Footprint {
+        self.bytes += bytes;
+        self.textures += bytes;
+        return self;
+    }
+
+    pub fn with_cost(mut self, cost: Float) -> Footprint {
+        self.cost = cost;
+        return self;
+    }
+
+    // a tree over the rest of it, which a ray goes down testing the boxes
+    // either side at each level, then a few things at the bottom
+    pub fn with_bvh(mut self, bvh: &Bvh) -> Footprint {
+        self.bytes += bvh.bytes();
+        self.bvh_nodes += bvh.node_count();
+        self.bvh_depth = self.bvh_depth.max(bvh.depth());
+        self.cost = 2.0 * bvh.depth() as Float + 4.0;
+        return self;
+    }
+
+    // two objects in one, like the sides of a Union, which both get looked at
+    pub fn and(mut self, other: Footprint) -> Footprint {
+        self.bytes += other.bytes;
+        self.textures += other.textures;
+        self.bvh_nodes += other.bvh_nodes;
+        self.bvh_depth = self.bvh_depth.max(other.bvh_depth);
+        self.cost += other.cost;
+        return self;
+    }
+}
+
+// what's in a scene and what rendering it will take, for knowing what a
+// big imported scene turned out to be before leaving it to render
+// overnight. memory is what the objects keep, counted once per object, so
+// instances of one mesh count it once each.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub march: BTreeMap<&'static str, usize>, // objects of each kind
+    pub trace: BTreeMap<&'static str, usize>,
+    pub emitters: usize,
+    pub volumes: usize,
+    pub bytes: usize,    // objects and the top level tree, with
+    pub textures: usize, // the sampled grids of them
+    pub bvh_nodes: usize, // of the objects' own trees
+    pub bvh_depth: usize, // the deepest of them
+    pub top_level: Option<[usize; 2]>, // nodes and depth, if it fits
+    pub march_cost: Float, // one distance to everything marched
+    pub trace_cost: Float, // one ray against everything traced
+}
+
+impl Report {
+    // one camera ray, traced, and marched for as long as they usually are,
+    // in spheres like Footprint's. every bounce costs about the same again.
+    pub fn ray_cost(&self) -> Float {
+        let marched = if self.march.is_empty() { 0.0 } else { self.march_cost * MARCH_STEPS };
+        return self.trace_cost + marched;
+    }
+}
+
+// what a ray, or a distance, costs over a list, given which of it is in
+// the top level's tree. with one it goes down the tree and looks at a couple
+// of the objects at the bottom, and at all the ones without bounds anyway.
+fn list_cost(footprints: &[(Footprint, bool)], depth: Option<usize>) -> Float {
+    if footprints.is_empty() { return 0.0; }
+
+    let depth = match depth {
+        Some(depth) => depth,
+        None => return footprints.iter().map(|(footprint, _)| footprint.cost).sum(),
+    };
+
+    let aside: Float = footprints.iter().filter(|(_, boxed)| !boxed).map(|(footprint, _)| footprint.cost).sum();
+    let boxed: Vec<Float> = footprints.iter().filter(|(_, boxed)| *boxed).map(|(footprint, _)| footprint.cost).collect();
+    if boxed.is_empty() { return aside; }
+
+    let mean = boxed.iter().sum::<Float>() / boxed.len() as Float;
+    return aside + 2.0 * depth as Float + 2.0 * mean.min(boxed.iter().sum());
+}
+
+impl Scene {
+    // counts and sizes of what's in the scene, and a guess at what each ray
+    // will cost, see Report
+    pub fn report(&self) -> Report {
+        let top_level = self.top_level.as_ref().filter(|top| top.fits(self));
+        let depth = top_level.map(|top| top.bvh().depth());
+
+        let march: Vec<(Footprint, bool)> = self.march.iter().map(|object| {
+            (object.footprint().with_bytes(size_of_val(&**object)), object.bounds().is_some())
+        }).collect();
+        let trace: Vec<(Footprint, bool)> = self.trace.iter().map(|object| {
+            (object.footprint().with_bytes(size_of_val(&**object)), object.bounds().is_some())
+        }).collect();
+
+        let mut report = Report {
+            march: BTreeMap::new(),
+            trace: BTreeMap::new(),
+            emitters: self.emitters.len(),
+            volumes: self.volumes.len(),
+            bytes: top_level.map_or(0, |top| top.bvh().bytes()),
+            textures: 0,
+            bvh_nodes: 0,
+            bvh_depth: 0,
+            top_level: top_level.map(|top| [top.bvh().node_count(), top.bvh().depth()]),
+            march_cost: list_cost(&march, depth),
+            trace_cost: list_cost(&trace, depth),
+        };
+
+        for (counts, footprints) in [(&mut report.march, &march), (&mut report.trace, &trace)] {
+            for (footprint, _) in footprints {
+                *counts.entry(footprint.kind).or_insert(0) += 1;
+                report.bytes += footprint.bytes;
+                report.textures += footprint.textures;
+                report.bvh_nodes += footprint.bvh_nodes;
+                report.bvh_depth = report.bvh_depth.max(footprint.bvh_depth);
+            }
+        }
+
+        return report;
+    }
+}
+
+// bytes as the biggest unit that keeps it over one
+fn size(bytes: usize) -> String {
+    let units = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as Float;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < units.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    return if unit == 0 { format!("{} B", bytes) } else { format!("{:.1} {}", value, units[unit]) };
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let list = |counts: &BTreeMap<&str, usize>| {
+            let kinds: Vec<String> = counts.iter().map(|(kind, count)| format!("{} {}", count, kind)).collect();
+            if kinds.is_empty() { "nothing".to_string() } else { kinds.join(", ") }
+        };
+
+        writeln!(f, "marched: {}", list(&self.march))?;
+        writeln!(f, "traced: {}", list(&self.trace))?;
+        writeln!(f, "{} emitters, {} volumes", self.emitters, self.volumes)?;
+        writeln!(f, "memory: {}, {} of it textures", size(self.bytes), size(self.textures))?;
+        writeln!(f, "object trees: {} nodes, {} deep at most", self.bvh_nodes, self.bvh_depth)?;
+        match self.top_level {
+            Some([nodes, depth]) => writeln!(f, "top level: {} nodes, {} deep", nodes, depth)?,
+            None => writeln!(f, "top level: none")?,
+        }
+        write!(f, "cost: about {:.0} sphere tests a ray, {:.1} a traced ray and {:.1} a distance",
+            self.ray_cost(), self.trace_cost, self.march_cost)
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use crate::structures::float::Float;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::camera::Camera;
+    use crate::structures::material::Material;
+    use crate::structures::scene::Scene;
+    use crate::structures::top_level::TopLevel;
+    use crate::objects::sphere::Sphere;
+    use crate::objects::plane::Plane;
+    use crate::objects::mesh::Mesh;
+    use crate::objects::csg::Union;
+    use crate::objects::transformed::Transformed;
+
+    #[test]
+    fn test_report() {
+        let camera = Camera::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let material = Material::blank();
+
+        // a grid of quads, two triangles each
+        let side = 20;
+        let vertices: Vec<Vec3> = (0..(side + 1) * (side + 1)).map(|i| Vec3::new((i % (side + 1)) as Float, 0.0, (i / (side + 1)) as Float)).collect();
+        let triangles: Vec<[usize; 3]> = (0..side * side).flat_map(|i| {
+            let corner = i / side * (side + 1) + i % side;
+            vec![[corner, corner + 1, corner + side + 1], [corner + 1, corner + side + 2, corner + side + 1]]
+        }).collect();
+
+        let mut scene = Scene::builder().camera(camera).build();
+        for i in 0..50 { scene.add_trace(Sphere::new(Vec3::new(i as Float, 0.0, 0.0), 0.5, material)); }
+        scene.add_trace(Plane::new(Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 1.0, 0.0), material));
+        scene.add_trace(Transformed::translate(Mesh::new(vertices, triangles, material), Vec3::new(0.0, 2.0, 0.0)));
+        scene.add_march(Union::new(Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0, material), Sphere::new(Vec3::new(1.0, 0.0, 0.0), 1.0, material)));
+
+        // objects are counted as what they are, wrapped or not
+        let report = scene.report();
+        assert_eq!(report.trace["sphere"], 50);
+        assert_eq!((report.trace["plane"], report.trace["mesh"], report.march["union"]), (1, 1, 1));
+
+        // the mesh's tree and triangles are most of it
+        assert!(report.bvh_nodes > 1 && report.bvh_depth > 1);
+        assert!(report.bytes > 800 * 3 * 8 && report.textures == 0);
+        assert_eq!(report.march_cost, 2.0);
+        assert!(report.ray_cost() > report.trace_cost);
+        assert_eq!(report.top_level, None);
+
+        // with a top level a ray looks at far fewer of them
+        let alone = report.trace_cost;
+        scene.top_level = Some(TopLevel::new(&scene));
+        let report = scene.report();
+        assert!(report.top_level.unwrap()[0] > 1);
+        assert!(report.trace_cost < alone / 2.0);
+
+        let text = report.to_string();
+        assert!(text.contains("traced: 1 mesh, 1 plane, 50 sphere"));
+        assert!(text.contains("marched: 1 union"));
+    }
+}
@@ -0,0 +1,110 @@
+use std::fmt;
+use serde::{ Serialize, Deserialize };
+
+use crate::structures::float::Float;
+use crate::structures::camera::Framing;
+
+// an image size, for the renders that take a [width, height], with the
+// usual ones by name. the shot stays the same at any of them once the
+// camera's framed for one, see Camera::framing and fit below.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Resolution {
+    pub width: usize,
+    pub height: usize,
+}
+
+// by the names parse knows them by
+pub const PRESETS: [(&str, Resolution); 9] = [
+    ("720p", Resolution::new(1280, 720)),
+    ("1080p", Resolution::new(1920, 1080)),
+    ("1440p", Resolution::new(2560, 1440)),
+    ("4k", Resolution::new(3840, 2160)),
+    ("square", Resolution::new(1080, 1080)),
+    ("instagram", Resolution::new(1080, 1350)), // portrait, 4:5
+    ("instagram-landscape", Resolution::new(1080, 566)),
+    ("instagram-story", Resolution::new(1080, 1920)),
+    ("cinema", Resolution::new(2048, 858)), // 2.39:1 scope
+];
+
+impl Resolution {
+    pub const fn new(width: usize, height: usize) -> Resolution {
+        Resolution { width: width, height: height }
+    }
+
+    // one of PRESETS by name, any case, or a size like 640x480
+    pub fn parse(text: &str) -> Option<Resolution> {
+        let name = text.to_lowercase();
+        if let Some((_, preset)) = PRESETS.iter().find(|(preset, _)| *preset == name) { return Some(*preset); }
+
+        let size: Option<Vec<usize>> = name.split('x').map(|n| n.parse().ok()).collect();
+        return match size.as_deref() {
+            Some([width, height]) if *width > 0 && *height > 0 => Some(Resolution::new(*width, *height)),
+            _ => None,
+        };
+    }
+
+    // how many times wider than high
+    pub fn aspect(&self) -> Float {
+        self.width as Float / self.height.max(1) as Float
+    }
+
+    // the same shape `scale` times the size, never under a pixel, for
+    // previews of a final render
+    pub fn scaled(&self, scale: Float) -> Resolution {
+        let side = |length: usize| ((length as Float * scale).round() as usize).max(1);
+        return Resolution::new(side(self.width), side(self.height));
+    }
+
+    // what a camera set up for this one is framed by, so its shot doesn't
+    // change at other sizes. with Fit the whole of it is kept, with Fill
+    // the picture's always full of it.
+    pub fn fit(&self) -> Framing {
+        Framing::Fit(self.aspect())
+    }
+
+    pub fn fill(&self) -> Framing {
+        Framing::Fill(self.aspect())
+    }
+}
+
+impl From<Resolution> for [usize; 2] {
+    fn from(resolution: Resolution) -> [usize; 2] {
+        [resolution.width, resolution.height]
+    }
+}
+
+impl From<[usize; 2]> for Resolution {
+    fn from(size: [usize; 2]) -> Resolution {
+        Resolution::new(size[0], size[1])
+    }
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::{ Resolution, PRESETS };
+    use crate::structures::camera::Framing;
+
+    #[test]
+    fn test_resolution() {
+        assert_eq!(Resolution::parse("4K"), Some(Resolution::new(3840, 2160)));
+        assert_eq!(Resolution::parse("640x480"), Some(Resolution::new(640, 480)));
+        assert_eq!(Resolution::parse("640x0"), None);
+        assert_eq!(Resolution::parse("huge"), None);
+        assert_eq!(Resolution::parse("640xfoox480"), None);
+        assert_eq!(Resolution::parse("640x480x"), None);
+        assert!(PRESETS.iter().all(|(name, preset)| Resolution::parse(name) == Some(*preset)));
+
+        let hd = Resolution::parse("1080p").unwrap();
+        assert_eq!(hd.scaled(0.25), Resolution::new(480, 270));
+        assert_eq!(hd.scaled(0.0), Resolution::new(1, 1));
+        assert_eq!(hd.fit(), Framing::Fit(16.0 / 9.0));
+        assert_eq!(<[usize; 2]>::from(hd), [1920, 1080]);
+        assert_eq!(hd.to_string(), "1920x1080");
+    }
+}
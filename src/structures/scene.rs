@@ -1,24 +1,759 @@
+use std::collections::{ HashMap, BTreeMap };
+use std::path::Path;
 use std::sync::Arc;
+use serde::{ Serialize, Serializer, Deserialize, Deserializer };
+use serde::ser::Error;
 
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
 use crate::structures::camera::Camera;
+use crate::structures::material::Material;
+use crate::structures::material_registry::{ MaterialRegistry, MaterialId };
+use crate::structures::aabb::Aabb;
+use crate::structures::marching::polygonize;
+use crate::structures::medium::Medium;
+use crate::structures::photon_map::{ PhotonMap, Emitter };
+use crate::structures::node::{ Node, NodeObject };
+use crate::structures::transform::Transform;
+use crate::structures::ray::Ray;
+use crate::structures::tile::Tile;
+use crate::structures::post::Post;
+use crate::structures::frustum::Frustum;
+use crate::structures::top_level::TopLevel;
+use crate::structures::light_tree::LightTree;
+use crate::structures::environment_map::EnvironmentMap;
+use crate::render::{ Integrator, Bounces, NanGuard, Progress, BadSample, occluded_march, culled, AA };
+use crate::structures::stats::{ count, Counter };
+use crate::objects::mesh::Mesh;
+use crate::objects::volume::Volume;
+use crate::objects::primitive::{ MarchPrimitive, TracePrimitive };
 use crate::objects::traits::{ March, Trace };
+use crate::objects::visible::{ RayKind, Visible, Visibility };
+use crate::objects::clipped::{ ClipPlane, Clipped };
+use crate::objects::shared::Shared;
+use crate::import::scene_file;
+use crate::integrator;
 
+#[derive(Clone)]
 pub struct Scene {
     pub march: Vec<Arc<dyn March>>,
     pub trace: Vec<Arc<dyn Trace>>,
     pub camera: Camera,
+    pub cameras: BTreeMap<String, Camera>, // other views of it, see add_camera
+    pub medium: Option<Medium>, // fills all of space, the sky counts as infinitely far
+    pub volumes: Vec<Volume>,
+    pub caustics: Option<PhotonMap>, // gathered at first hits, see PhotonMap::build
+    pub top_level: Option<TopLevel>, // one tree over all the objects, see TopLevel::new
+    pub emitters: Vec<Emitter>,      // lights the bidirectional integrator starts from
+    pub light_tree: Option<LightTree>, // picks emitters by how near and bright, see LightTree::new
+    pub integrator: Integrator,
+    pub custom_integrator: Option<Arc<dyn integrator::Integrator>>, // used instead if it's set, see tracer
+    pub progress: Option<Progress>, // told as each part of the picture starts, see render_tiles
+    pub bad_samples: Option<BadSample>, // told about each sample NanGuard::Log leaves out
+    pub samples: u32, // jittered camera rays per pixel
+    pub noise_threshold: Option<Float>, // pixels sure of themselves to within this stop early, see render_counted
+    pub blue_noise: bool, // jitter each pixel by a blue noise tile instead of at random, see blue_noise
+    pub frame: u32, // of an animation, turns the blue noise from one to the next, see temporal
+    pub packets: bool, // cast camera rays several at a time, see RayPacket
+    pub region: Option<Tile>, // only render these pixels, the rest stay black
+    pub environment: Vec3, // what rays see when they miss everything
+    pub environment_map: Option<Arc<EnvironmentMap>>, // seen instead of environment if it's set, see sky
+    pub nan_guard: NanGuard, // what happens to broken samples
+    pub bounces: Bounces, // how far paths go
+    pub post: Vec<Post>, // effects on the finished frame, see Film::post
+    pub materials: MaterialRegistry, // shared between objects, see add_march_shared
+    names: HashMap<String, Handle>, // kept in step with the lists, see add_named
+}
+
+// where a named object sits, which list and how far down it
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Handle {
+    March(usize),
+    Trace(usize),
+}
+
+// a named object, to be swapped for another in place
+pub enum ObjectMut<'a> {
+    March(&'a mut Arc<dyn March>),
+    Trace(&'a mut Arc<dyn Trace>),
+}
+
+// what's kept of a scene when it's saved. objects go in as primitives, and
+// the caustics, the top level and the light tree are left out since
+// they're caches.
+#[derive(Serialize, Deserialize)]
+struct Saved {
+    camera: Camera,
+    #[serde(default)]
+    cameras: BTreeMap<String, Camera>,
+    #[serde(default)]
+    march: Vec<MarchPrimitive>,
+    #[serde(default)]
+    trace: Vec<TracePrimitive>,
+    #[serde(default)]
+    medium: Option<Medium>,
+    #[serde(default)]
+    emitters: Vec<Emitter>,
+    #[serde(default)]
+    integrator: Integrator,
+    #[serde(default = "samples")]
+    samples: u32,
+    #[serde(default)]
+    noise_threshold: Option<Float>,
+    #[serde(default)]
+    blue_noise: bool,
+    #[serde(default)]
+    frame: u32,
+    #[serde(default = "packets")]
+    packets: bool,
+    #[serde(default)]
+    region: Option<Tile>,
+    #[serde(default = "environment")]
+    environment: Vec3,
+    #[serde(default)]
+    names: HashMap<String, Handle>,
+    #[serde(default)]
+    nan_guard: NanGuard,
+    #[serde(default)]
+    bounces: Bounces,
+    #[serde(default)]
+    post: Vec<Post>,
+}
+
+fn samples() -> u32 { AA }
+fn packets() -> bool { true }
+fn environment() -> Vec3 { Material::sky().color }
+
+impl Serialize for Scene {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if !self.volumes.is_empty() { return Err(S::Error::custom("volumes can't be saved yet")); }
+        if self.custom_integrator.is_some() { return Err(S::Error::custom("custom integrators can't be saved")); }
+        if self.environment_map.is_some() { return Err(S::Error::custom("environment maps can't be saved yet")); }
+
+        let march = self.march.iter().map(|object| object.primitive()).collect::<Option<Vec<_>>>();
+        let trace = self.trace.iter().map(|object| object.primitive()).collect::<Option<Vec<_>>>();
+
+        let (march, trace) = match (march, trace) {
+            (Some(march), Some(trace)) => (march, trace),
+            _ => return Err(S::Error::custom("only the built in shapes can be saved")),
+        };
+
+        return Saved {
+            camera: self.camera,
+            cameras: self.cameras.clone(),
+            march: march,
+            trace: trace,
+            medium: self.medium,
+            emitters: self.emitters.clone(),
+            integrator: self.integrator,
+            samples: self.samples,
+            noise_threshold: self.noise_threshold,
+            blue_noise: self.blue_noise,
+            frame: self.frame,
+            packets: self.packets,
+            region: self.region,
+            environment: self.environment,
+            names: self.names.clone(),
+            nan_guard: self.nan_guard,
+            bounces: self.bounces,
+            post: self.post.clone(),
+        }.serialize(serializer);
+    }
+}
+
+impl<'de> Deserialize<'de> for Scene {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Scene, D::Error> {
+        let saved = Saved::deserialize(deserializer)?;
+        let mut scene = Scene::new(saved.camera);
+        scene.cameras = saved.cameras;
+
+        for object in saved.march { scene.add_march(object); }
+        for object in saved.trace { scene.add_trace(object); }
+        scene.medium = saved.medium;
+        scene.emitters = saved.emitters;
+        scene.integrator = saved.integrator;
+        scene.samples = saved.samples;
+        scene.noise_threshold = saved.noise_threshold;
+        scene.blue_noise = saved.blue_noise;
+        scene.frame = saved.frame;
+        scene.packets = saved.packets;
+        scene.region = saved.region;
+        scene.environment = saved.environment;
+        scene.nan_guard = saved.nan_guard;
+        scene.bounces = saved.bounces;
+        scene.post = saved.post;
+
+        let fits = |handle: &Handle| match *handle {
+            Handle::March(index) => index < scene.march.len(),
+            Handle::Trace(index) => index < scene.trace.len(),
+        };
+        if let Some(name) = saved.names.iter().find(|(_, handle)| !fits(handle)).map(|(name, _)| name) {
+            return Err(serde::de::Error::custom(format!("{} names an object that isn't there", name)));
+        }
+        scene.names = saved.names;
+
+        return Ok(scene);
+    }
 }
 
 impl Scene {
     pub fn new(camera: Camera) -> Scene {
-        Scene { march: vec![], trace: vec![], camera: camera }
+        Scene {
+            march: vec![],
+            trace: vec![],
+            camera: camera,
+            cameras: BTreeMap::new(),
+            medium: None,
+            volumes: vec![],
+            caustics: None,
+            top_level: None,
+            emitters: vec![],
+            light_tree: None,
+            integrator: Integrator::Path,
+            custom_integrator: None,
+            progress: None,
+            bad_samples: None,
+            samples: AA,
+            noise_threshold: None,
+            blue_noise: false,
+            frame: 0,
+            packets: true,
+            region: None,
+            environment: environment(),
+            environment_map: None,
+            nan_guard: NanGuard::default(),
+            bounces: Bounces::default(),
+            post: vec![],
+            materials: MaterialRegistry::new(),
+            names: HashMap::new(),
+        }
+    }
+
+    // what works out the light along camera rays, scene.custom_integrator if
+    // there is one, or else the built in one scene.integrator picks
+    pub fn tracer(&self) -> &dyn integrator::Integrator {
+        match &self.custom_integrator {
+            Some(custom) => custom.as_ref(),
+            None => self.integrator.tracer(),
+        }
+    }
+
+    // the other way to put a scene together, see SceneBuilder
+    pub fn builder() -> SceneBuilder {
+        SceneBuilder::new()
+    }
+
+    // a scene written by hand, see import::scene_file for what goes in one
+    pub fn from_file(path: impl AsRef<Path>) -> crate::error::Result<Scene> {
+        scene_file::load(path)
     }
 
     pub fn add_march(&mut self, march: impl March + 'static) {
+        self.edited();
         self.march.push(Arc::new(march));
     }
 
     pub fn add_trace(&mut self, trace: impl Trace + 'static) {
+        self.edited();
         self.trace.push(Arc::new(trace));
     }
+
+    // the trees were built for the objects as they were, and an object
+    // swapped in place leaves the lists just as long, so they're dropped
+    // rather than trusted. build them again once the edits are done.
+    fn edited(&mut self) {
+        self.top_level = None;
+        self.light_tree = None;
+    }
+
+    // objects taking their material from scene.materials, so changing it
+    // there changes it on all of them
+    pub fn add_march_shared(&mut self, march: impl March + 'static, id: MaterialId) {
+        self.add_march(Shared::new(march, &self.materials, id));
+    }
+
+    pub fn add_trace_shared(&mut self, trace: impl Trace + 'static, id: MaterialId) {
+        self.add_trace(Shared::new(trace, &self.materials, id));
+    }
+
+    // a built in shape, traced if it can be and marched otherwise
+    pub fn add(&mut self, object: impl Into<NodeObject>) {
+        self.edited();
+        match object.into() {
+            NodeObject::Empty => (),
+            NodeObject::March(object) => self.march.push(object),
+            NodeObject::Trace(object) => self.trace.push(object),
+        }
+    }
+
+    // adds an object that can be found again by name, to edit the scene
+    // between frames instead of building it over. a name already in use
+    // moves to the new object.
+    pub fn add_named(&mut self, name: &str, object: impl Into<NodeObject>) {
+        let handle = match object.into() {
+            NodeObject::Empty => return,
+            NodeObject::March(object) => { self.edited(); self.march.push(object); Handle::March(self.march.len() - 1) },
+            NodeObject::Trace(object) => { self.edited(); self.trace.push(object); Handle::Trace(self.trace.len() - 1) },
+        };
+        self.names.insert(name.to_string(), handle);
+    }
+
+    pub fn handle(&self, name: &str) -> Option<Handle> {
+        self.names.get(name).copied()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = (&str, Handle)> + '_ {
+        self.names.iter().map(|(name, handle)| (name.as_str(), *handle))
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<ObjectMut<'_>> {
+        let handle = self.handle(name)?;
+        self.edited();
+        match handle {
+            Handle::March(index) => self.march.get_mut(index).map(ObjectMut::March),
+            Handle::Trace(index) => self.trace.get_mut(index).map(ObjectMut::Trace),
+        }
+    }
+
+    // takes a named object out. everything after it in the same list moves
+    // up one, and the names follow.
+    pub fn remove(&mut self, name: &str) -> Option<NodeObject> {
+        let handle = self.names.remove(name)?;
+        self.edited();
+
+        for other in self.names.values_mut() {
+            match (handle, *other) {
+                (Handle::March(gone), Handle::March(index)) if index > gone => *other = Handle::March(index - 1),
+                (Handle::Trace(gone), Handle::Trace(index)) if index > gone => *other = Handle::Trace(index - 1),
+                _ => (),
+            }
+        }
+
+        return match handle {
+            Handle::March(index) => Some(NodeObject::March(self.march.remove(index))),
+            Handle::Trace(index) => Some(NodeObject::Trace(self.trace.remove(index))),
+        };
+    }
+
+    // swaps a named object for another, keeping the name. hands back the
+    // old one, or None and changes nothing if the name isn't known.
+    pub fn replace(&mut self, name: &str, object: impl Into<NodeObject>) -> Option<NodeObject> {
+        let object = object.into();
+
+        match (self.get_mut(name)?, object) {
+            (ObjectMut::March(slot), NodeObject::March(object)) => Some(NodeObject::March(std::mem::replace(slot, object))),
+            (ObjectMut::Trace(slot), NodeObject::Trace(object)) => Some(NodeObject::Trace(std::mem::replace(slot, object))),
+            (_, object) => {
+                let old = self.remove(name);
+                self.add_named(name, object);
+                old
+            },
+        }
+    }
+
+    // another camera to render the same scene through, for several shots
+    // of it lit the same way, see render::render_cameras. a name already
+    // in use moves to the new camera.
+    pub fn add_camera(&mut self, name: &str, camera: Camera) {
+        self.cameras.insert(name.to_string(), camera);
+    }
+
+    // the scene seen through a named camera instead. the objects are
+    // shared with this one, not copied.
+    pub fn with_camera(&self, name: &str) -> Option<Scene> {
+        let camera = *self.cameras.get(name)?;
+        let mut scene = self.clone();
+        scene.camera = camera;
+        return Some(scene);
+    }
+
+    pub fn add_volume(&mut self, volume: Volume) {
+        self.volumes.push(volume);
+    }
+
+    // slices through everything already in the scene, see ClipPlane.
+    // volumes and the medium aren't cut.
+    pub fn clip(&mut self, plane: ClipPlane) {
+        for object in self.march.iter_mut() {
+            *object = Arc::new(Clipped::new(object.clone(), plane));
+        }
+
+        for object in self.trace.iter_mut() {
+            *object = Arc::new(Clipped::new(object.clone(), plane));
+        }
+    }
+
+    // hides the traced objects the camera can't see at `resolution` from
+    // camera rays, so every pixel doesn't try them, see Frustum. they still
+    // cast shadows and show up in reflections. this is for the camera as
+    // it is now, moving it after could leave things missing. hands back how
+    // many were hidden, none with an aperture.
+    pub fn cull(&mut self, resolution: [usize; 2]) -> usize {
+        let frustum = match Frustum::new(&self.camera, resolution) {
+            Some(frustum) => frustum,
+            None => return 0,
+        };
+
+        let mut hidden = 0;
+        for object in self.trace.iter_mut() {
+            let visibility = object.visibility();
+            if !visibility.camera { continue; }
+
+            if let Some(bounds) = object.bounds() {
+                if frustum.overlaps(&bounds) { continue; }
+                *object = Arc::new(Visible::new(object.clone(), Visibility { camera: false, ..visibility }));
+                hidden += 1;
+            }
+        }
+
+        return hidden;
+    }
+
+    // flattens a scene graph into the march and trace lists.
+    // to animate, re-pose the graph and flatten it into a fresh scene.
+    pub fn add_node(&mut self, node: &Node) {
+        self.edited();
+        node.flatten(&Transform::identity(), &mut self.march, &mut self.trace);
+    }
+
+    // what a ray going off in `direction` sees once it's missed everything
+    pub fn sky(&self, direction: Vec3) -> Vec3 {
+        match &self.environment_map {
+            Some(map) => map.lookup(direction),
+            None => self.environment,
+        }
+    }
+
+    // shadow rays: is there anything at all in the ray's range, see
+    // Ray::with_max. cheaper than casting since any hit will do.
+    pub fn occluded(&self, ray: Ray) -> bool {
+        count(Counter::Rays, 1);
+        let start = ray.from_start();
+
+        let blocks = |object: &Arc<dyn Trace>| {
+            if !object.visibility().sees(RayKind::Shadow) { return false; }
+            let (hit, distance, normal) = object.trace(start);
+            hit && start.contains(distance) && !culled(object, &ray, distance + ray.t_min, normal)
+        };
+
+        let blocked = match self.top_level.as_ref().filter(|top| top.fits(self)) {
+            Some(top) => top.trace(&start, |index| if blocks(&self.trace[index]) { Some(0.0) } else { None }).is_some(),
+            None => self.trace.iter().any(blocks),
+        };
+
+        return blocked || occluded_march(&self.march, ray);
+    }
+
+    // the combined distance field of everything marched
+    pub fn sdf(&self, point: Vec3) -> Float {
+        self.march.iter().fold(Float::MAX, |min, object| min.min(object.march(point)))
+    }
+
+    // polygonizes the march list inside `bounds`, with `resolution` cells
+    // along its longest side. normals come from the field's gradient.
+    // traced objects are left out, they're usually meshes already.
+    pub fn extract_mesh(&self, bounds: Aabb, resolution: usize) -> Mesh {
+        let (vertices, triangles) = polygonize(|p| self.sdf(p), &bounds, resolution);
+
+        let h = 0.0001;
+        let normals = vertices.iter().map(|p| Vec3::new(
+            self.sdf(*p + Vec3::new(h, 0.0, 0.0)) - self.sdf(*p - Vec3::new(h, 0.0, 0.0)),
+            self.sdf(*p + Vec3::new(0.0, h, 0.0)) - self.sdf(*p - Vec3::new(0.0, h, 0.0)),
+            self.sdf(*p + Vec3::new(0.0, 0.0, h)) - self.sdf(*p - Vec3::new(0.0, 0.0, h)),
+        ).unit()).collect();
+
+        let material = self.march.first().map(|m| m.material()).unwrap_or_else(Material::blank);
+        return Mesh::smooth(vertices, normals, triangles, material);
+    }
+}
+
+// sets a scene up in one go, everything left out keeps a default that
+// renders: a camera a few units back looking at the origin, the sky, and
+// the path tracer
+//
+//     let scene = Scene::builder()
+//         .camera(Camera::new(from, to, up))
+//         .environment(Vec3::new(0.0, 0.0, 0.0))
+//         .add(Sphere::new(center, 1.0, Material::blank()).with_material(gold))
+//         .build();
+pub struct SceneBuilder {
+    scene: Scene,
+    clipping: Vec<ClipPlane>, // put in last, so they cut everything
+}
+
+impl SceneBuilder {
+    pub fn new() -> SceneBuilder {
+        let camera = Camera::new(Vec3::new(0.0, 0.0, 4.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        SceneBuilder { scene: Scene::new(camera), clipping: vec![] }
+    }
+
+    pub fn camera(mut self, camera: Camera) -> SceneBuilder {
+        self.scene.camera = camera;
+        return self;
+    }
+
+    pub fn named_camera(mut self, name: &str, camera: Camera) -> SceneBuilder {
+        self.scene.add_camera(name, camera);
+        return self;
+    }
+
+    pub fn environment(mut self, environment: Vec3) -> SceneBuilder {
+        self.scene.environment = environment;
+        return self;
+    }
+
+    pub fn environment_map(mut self, map: EnvironmentMap) -> SceneBuilder {
+        self.scene.environment_map = Some(Arc::new(map));
+        return self;
+    }
+
+    // a built in shape, traced if it can be and marched otherwise
+    #[allow(clippy::should_implement_trait)] // not a sum, nothing to get mixed up with
+    pub fn add(mut self, object: impl Into<NodeObject>) -> SceneBuilder {
+        self.scene.add(object);
+        return self;
+    }
+
+    pub fn named(mut self, name: &str, object: impl Into<NodeObject>) -> SceneBuilder {
+        self.scene.add_named(name, object);
+        return self;
+    }
+
+    pub fn march(mut self, march: impl March + 'static) -> SceneBuilder {
+        self.scene.add_march(march);
+        return self;
+    }
+
+    pub fn trace(mut self, trace: impl Trace + 'static) -> SceneBuilder {
+        self.scene.add_trace(trace);
+        return self;
+    }
+
+    pub fn node(mut self, node: &Node) -> SceneBuilder {
+        self.scene.add_node(node);
+        return self;
+    }
+
+    pub fn volume(mut self, volume: Volume) -> SceneBuilder {
+        self.scene.add_volume(volume);
+        return self;
+    }
+
+    pub fn medium(mut self, medium: Medium) -> SceneBuilder {
+        self.scene.medium = Some(medium);
+        return self;
+    }
+
+    pub fn emitter(mut self, emitter: Emitter) -> SceneBuilder {
+        self.scene.emitters.push(emitter);
+        return self;
+    }
+
+    pub fn integrator(mut self, integrator: Integrator) -> SceneBuilder {
+        self.scene.integrator = integrator;
+        return self;
+    }
+
+    pub fn custom_integrator(mut self, integrator: impl integrator::Integrator + 'static) -> SceneBuilder {
+        self.scene.custom_integrator = Some(Arc::new(integrator));
+        return self;
+    }
+
+    pub fn progress(mut self, progress: impl Fn(usize, usize) + Send + Sync + 'static) -> SceneBuilder {
+        self.scene.progress = Some(Arc::new(progress));
+        return self;
+    }
+
+    pub fn bad_samples(mut self, told: impl Fn([Float; 2], Vec3, Ray) + Send + Sync + 'static) -> SceneBuilder {
+        self.scene.bad_samples = Some(Arc::new(told));
+        return self;
+    }
+
+    pub fn nan_guard(mut self, nan_guard: NanGuard) -> SceneBuilder {
+        self.scene.nan_guard = nan_guard;
+        return self;
+    }
+
+    pub fn bounces(mut self, bounces: Bounces) -> SceneBuilder {
+        self.scene.bounces = bounces;
+        return self;
+    }
+
+    pub fn post(mut self, effect: Post) -> SceneBuilder {
+        self.scene.post.push(effect);
+        return self;
+    }
+
+    pub fn samples(mut self, samples: u32) -> SceneBuilder {
+        self.scene.samples = samples.max(1);
+        return self;
+    }
+
+    pub fn noise_threshold(mut self, threshold: Float) -> SceneBuilder {
+        self.scene.noise_threshold = Some(threshold);
+        return self;
+    }
+
+    pub fn blue_noise(mut self, blue_noise: bool) -> SceneBuilder {
+        self.scene.blue_noise = blue_noise;
+        return self;
+    }
+
+    pub fn region(mut self, region: Tile) -> SceneBuilder {
+        self.scene.region = Some(region);
+        return self;
+    }
+
+    pub fn clip(mut self, plane: ClipPlane) -> SceneBuilder {
+        self.clipping.push(plane);
+        return self;
+    }
+
+    pub fn build(mut self) -> Scene {
+        for plane in self.clipping {
+            self.scene.clip(plane);
+        }
+
+        return self.scene;
+    }
+}
+
+impl Default for SceneBuilder {
+    fn default() -> SceneBuilder { SceneBuilder::new() }
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::sync::Arc;
+
+    use super::{ Scene, Handle, ObjectMut };
+    use crate::structures::float::Float;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::ray::Ray;
+    use crate::structures::camera::Camera;
+    use crate::structures::material::Material;
+    use crate::structures::transform::Transform;
+    use crate::structures::top_level::TopLevel;
+    use crate::objects::sphere::Sphere;
+    use crate::objects::plane::Plane;
+    use crate::objects::mandelbulb::Mandelbulb;
+    use crate::objects::transformed::Transformed;
+
+    #[test]
+    fn test_serde() {
+        let camera = Camera::new(Vec3::new(0.0, 0.0, 4.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let mut scene = Scene::new(camera);
+        scene.add_march(Mandelbulb::new(Vec3::new(0.0, 0.0, 0.0), 8.0, 10, Material::blank()));
+        scene.add_trace(Plane::new(Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Material::blank()));
+
+        let json = serde_json::to_string(&scene).unwrap();
+        assert!(json.contains(r#""type":"Mandelbulb""#));
+
+        let back: Scene = serde_json::from_str(&json).unwrap();
+        assert_eq!((back.march.len(), back.trace.len()), (1, 1));
+        assert_eq!(back.sdf(Vec3::new(0.5, 0.2, 1.5)), scene.sdf(Vec3::new(0.5, 0.2, 1.5)));
+        assert_eq!(back.camera.ray, scene.camera.ray);
+
+        // a ray saved before it had a footprint is still a ray
+        let thin: Ray = serde_json::from_str(r#"{"origin":[0,0,0],"direction":[0,0,-1]}"#).unwrap();
+        assert_eq!(thin.spread, 0.0);
+
+        // anything but the built in shapes is an error, not a silently missing object
+        let sphere = Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0, Material::blank());
+        scene.add_march(Transformed::new(sphere, Transform::identity()));
+        assert!(serde_json::to_string(&scene).is_err());
+    }
+
+    #[test]
+    fn test_builder() {
+        let mut red = Material::blank();
+        red.color = Vec3::new(1.0, 0.0, 0.0);
+
+        let scene = Scene::builder()
+            .environment(Vec3::new(0.0, 0.0, 0.0))
+            .add(Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0, Material::blank()).with_material(red))
+            .add(Mandelbulb::new(Vec3::new(3.0, 0.0, 0.0), 8.0, 10, Material::blank()))
+            .samples(0)
+            .build();
+
+        assert_eq!((scene.march.len(), scene.trace.len()), (1, 1));
+        assert_eq!(scene.trace[0].material().color, red.color);
+        assert_eq!(scene.environment, Vec3::new(0.0, 0.0, 0.0));
+        assert_eq!(scene.samples, 1);
+
+        // the default camera looks at the origin
+        assert_eq!(scene.camera.ray.direction, Vec3::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn test_named() {
+        let mut scene = Scene::builder()
+            .named("ball", Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0, Material::blank()))
+            .named("floor", Plane::new(Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Material::blank()))
+            .named("fractal", Mandelbulb::new(Vec3::new(3.0, 0.0, 0.0), 8.0, 10, Material::blank()))
+            .build();
+        assert_eq!(scene.handle("floor"), Some(Handle::Trace(1)));
+
+        // ball goes, and floor moves up to fill its spot
+        assert!(scene.remove("ball").is_some());
+        assert_eq!(scene.handle("floor"), Some(Handle::Trace(0)));
+        assert!(scene.get_mut("ball").is_none());
+
+        // the same kind stays where it was, another kind changes lists.
+        // a tree built before isn't used after, the lists are as long but
+        // the floor's somewhere else
+        scene.top_level = Some(TopLevel::new(&scene));
+        scene.replace("floor", Plane::new(Vec3::new(0.0, -2.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Material::blank()));
+        assert_eq!(scene.handle("floor"), Some(Handle::Trace(0)));
+        assert!(scene.top_level.is_none());
+        scene.replace("fractal", Sphere::new(Vec3::new(3.0, 0.0, 0.0), 1.0, Material::blank()));
+        assert_eq!(scene.handle("fractal"), Some(Handle::Trace(1)));
+        assert!(scene.march.is_empty());
+
+        if let Some(ObjectMut::Trace(slot)) = scene.get_mut("floor") {
+            *slot = Arc::new(Sphere::new(Vec3::new(0.0, -5.0, 0.0), 1.0, Material::blank()));
+        }
+        assert_eq!(scene.sdf(Vec3::new(0.0, 0.0, 0.0)), Float::MAX);
+        assert!(scene.occluded(Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, -1.0, 0.0)).with_max(10.0)));
+
+        let back: Scene = serde_json::from_str(&serde_json::to_string(&scene).unwrap()).unwrap();
+        assert_eq!(back.handle("fractal"), Some(Handle::Trace(1)));
+    }
+
+    #[test]
+    fn test_occluded() {
+        let camera = Camera::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0));
+        let mut scene = Scene::new(camera);
+        scene.add_trace(Sphere::new(Vec3::new(0.0, 0.0, -5.0), 1.0, Material::blank()));
+        scene.add_march(Sphere::new(Vec3::new(5.0, 0.0, 0.0), 1.0, Material::blank()));
+
+        let ahead = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(scene.occluded(ahead.with_max(10.0)));
+        assert!(!scene.occluded(ahead.with_max(3.0)));
+
+        let right = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(scene.occluded(right.with_max(10.0)));
+        assert!(!scene.occluded(right.with_max(3.0)));
+
+        let up = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        assert!(!scene.occluded(up.with_max(100.0)));
+    }
+
+    #[test]
+    fn test_cull() {
+        let camera = Camera::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0));
+        let mut scene = Scene::new(camera);
+        scene.add_trace(Sphere::new(Vec3::new(0.0, 0.0, -5.0), 1.0, Material::blank()));
+        scene.add_trace(Sphere::new(Vec3::new(0.0, 0.0, 5.0), 1.0, Material::blank()));
+        scene.add_trace(Plane::new(Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Material::blank()));
+
+        // only the one behind goes, the plane has no bounds to go by
+        assert_eq!(scene.cull([16, 16]), 1);
+        assert!(scene.trace[0].visibility().camera && scene.trace[2].visibility().camera);
+        assert!(!scene.trace[1].visibility().camera);
+
+        // it still casts shadows, and culling again doesn't wrap it twice
+        assert!(scene.occluded(Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0)).with_max(10.0)));
+        assert_eq!(scene.cull([16, 16]), 0);
+    }
 }
@@ -0,0 +1,133 @@
+use std::cell::Cell;
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+// counters bumped from the hot paths. they're per thread so counting
+// never contends, and `collect` drains whatever this thread has seen.
+#[derive(Debug, Copy, Clone)]
+pub enum Counter {
+    Rays,
+    Marches,
+    MarchSteps,
+    BvhVisits,
+    BadSamples,
+}
+
+thread_local! {
+    static COUNTERS: Cell<[u64; 5]> = const { Cell::new([0; 5]) };
+}
+
+pub fn count(counter: Counter, amount: u64) {
+    COUNTERS.with(|counters| {
+        let mut values = counters.get();
+        values[counter as usize] += amount;
+        counters.set(values);
+    });
+}
+
+// wall clock time where there is one. wasm32 has no clock std can reach,
+// so there everything takes no time at all.
+#[derive(Debug, Copy, Clone)]
+pub struct Timer {
+    #[cfg(not(target_arch = "wasm32"))]
+    start: Instant,
+}
+
+impl Timer {
+    pub fn start() -> Timer {
+        Timer {
+            #[cfg(not(target_arch = "wasm32"))]
+            start: Instant::now(),
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        #[cfg(not(target_arch = "wasm32"))]
+        return self.start.elapsed();
+
+        #[cfg(target_arch = "wasm32")]
+        return Duration::from_secs(0);
+    }
+}
+
+// what a render spent its time on
+#[derive(Debug, Clone, Default)]
+pub struct RenderStats {
+    pub rays: u64,        // everything cast, camera, bounce and shadow rays alike
+    pub marches: u64,     // rays that went through the marcher
+    pub march_steps: u64, // sdf evaluations of the whole march list, not counting normals
+    pub bvh_visits: u64,  // nodes looked at while walking mesh hierarchies
+    pub bad_samples: u64, // NaN or infinite radiance, see NanGuard
+    pub tiles: Vec<Duration>,
+    pub total: Duration,
+}
+
+impl RenderStats {
+    // drains this thread's counters into a fresh set of stats
+    pub fn collect() -> RenderStats {
+        let values = COUNTERS.with(|counters| counters.replace([0; 5]));
+
+        RenderStats {
+            rays: values[Counter::Rays as usize],
+            marches: values[Counter::Marches as usize],
+            march_steps: values[Counter::MarchSteps as usize],
+            bvh_visits: values[Counter::BvhVisits as usize],
+            bad_samples: values[Counter::BadSamples as usize],
+            tiles: vec![],
+            total: Duration::from_secs(0),
+        }
+    }
+
+    pub fn merge(&mut self, other: &RenderStats) {
+        self.rays += other.rays;
+        self.marches += other.marches;
+        self.march_steps += other.march_steps;
+        self.bvh_visits += other.bvh_visits;
+        self.bad_samples += other.bad_samples;
+        self.tiles.extend(other.tiles.iter());
+        self.total += other.total;
+    }
+
+    pub fn average_steps(&self) -> f64 {
+        if self.marches == 0 { return 0.0; }
+        return self.march_steps as f64 / self.marches as f64;
+    }
+
+    pub fn average_tile(&self) -> Duration {
+        if self.tiles.is_empty() { return Duration::from_secs(0); }
+        return self.tiles.iter().sum::<Duration>() / self.tiles.len() as u32;
+    }
+
+    pub fn slowest_tile(&self) -> Duration {
+        self.tiles.iter().max().copied().unwrap_or_else(|| Duration::from_secs(0))
+    }
+
+    pub fn print(&self) {
+        println!("rays cast:        {}", self.rays);
+        println!("march steps/ray:  {:.1}", self.average_steps());
+        println!("bvh node visits:  {}", self.bvh_visits);
+        if self.bad_samples > 0 { println!("bad samples:      {}", self.bad_samples); }
+        println!("time per tile:    {:?} (slowest {:?})", self.average_tile(), self.slowest_tile());
+        println!("total time:       {:?}", self.total);
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::{ RenderStats, Counter, count };
+
+    #[test]
+    fn test_collect() {
+        RenderStats::collect();
+
+        count(Counter::Marches, 2);
+        count(Counter::MarchSteps, 30);
+
+        let stats = RenderStats::collect();
+        assert_eq!(stats.average_steps(), 15.0);
+
+        // and it starts over
+        assert_eq!(RenderStats::collect().march_steps, 0);
+    }
+}
@@ -0,0 +1,110 @@
+use serde::{ Serialize, Deserialize };
+
+use crate::structures::float::Float;
+
+// a rectangle of pixels rendered as one job. rows count from the top.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tile {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Tile {
+    // cuts the image into tiles of `size`, smaller along the right and
+    // bottom edges, ordered in a spiral out from the middle. the subject
+    // is usually there, so that's what's worth seeing first.
+    pub fn spiral(resolution: [usize; 2], size: usize) -> Vec<Tile> {
+        let size = size.max(1);
+        let columns = resolution[0].div_ceil(size);
+        let rows = resolution[1].div_ceil(size);
+
+        let mut tiles = vec![];
+        for row in 0..rows {
+            for column in 0..columns {
+                tiles.push(Tile {
+                    x: column * size,
+                    y: row * size,
+                    width: size.min(resolution[0] - column * size),
+                    height: size.min(resolution[1] - row * size),
+                });
+            }
+        }
+
+        // rings of tiles around the center, each one walked around by angle
+        let center = [(columns as Float - 1.0) / 2.0, (rows as Float - 1.0) / 2.0];
+        let place = |tile: &Tile| {
+            let dx = (tile.x / size) as Float - center[0];
+            let dy = (tile.y / size) as Float - center[1];
+            (dx.abs().max(dy.abs()), dy.atan2(dx))
+        };
+
+        tiles.sort_by(|a, b| {
+            let (a, b) = (place(a), place(b));
+            a.partial_cmp(&b).unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        return tiles;
+    }
+
+    // a crop given as fractions of the image, from the top left corner
+    // [x, y] to the bottom right one. at least a pixel is always left.
+    pub fn normalized(resolution: [usize; 2], min: [Float; 2], max: [Float; 2]) -> Tile {
+        let pixel = |t: Float, axis: usize| (t.clamp(0.0, 1.0) * resolution[axis] as Float).round() as usize;
+        let (x, y) = (pixel(min[0], 0).min(resolution[0] - 1), pixel(min[1], 1).min(resolution[1] - 1));
+
+        Tile {
+            x: x,
+            y: y,
+            width: pixel(max[0], 0).max(x + 1) - x,
+            height: pixel(max[1], 1).max(y + 1) - y,
+        }
+    }
+
+    pub fn area(&self) -> usize {
+        self.width * self.height
+    }
+
+    // the pixels in both, if there are any
+    pub fn intersect(&self, other: &Tile) -> Option<Tile> {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = (self.x + self.width).min(other.x + other.width);
+        let bottom = (self.y + self.height).min(other.y + other.height);
+
+        if right <= x || bottom <= y { return None; }
+        return Some(Tile { x: x, y: y, width: right - x, height: bottom - y });
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::Tile;
+
+    #[test]
+    fn test_spiral() {
+        let tiles = Tile::spiral([100, 50], 16);
+
+        // every pixel is in exactly one tile
+        assert_eq!(tiles.len(), 7 * 4);
+        assert_eq!(tiles.iter().map(|tile| tile.area()).sum::<usize>(), 100 * 50);
+
+        // the middle comes first and the corners last
+        let first = tiles[0];
+        assert!(first.x >= 32 && first.x <= 48 && first.y >= 16 && first.y <= 32);
+
+        let last = tiles[tiles.len() - 1];
+        assert!(last.x == 0 || last.x == 96);
+    }
+
+    #[test]
+    fn test_crop() {
+        let crop = Tile::normalized([100, 50], [0.25, 0.5], [0.5, 1.0]);
+        assert_eq!(crop, Tile { x: 25, y: 25, width: 25, height: 25 });
+
+        let tile = Tile { x: 16, y: 16, width: 16, height: 16 };
+        assert_eq!(tile.intersect(&crop), Some(Tile { x: 25, y: 25, width: 7, height: 7 }));
+        assert_eq!(tile.intersect(&Tile { x: 0, y: 0, width: 16, height: 16 }), None);
+    }
+}
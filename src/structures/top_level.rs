@@ -0,0 +1,173 @@
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::aabb::Aabb;
+use crate::structures::bvh::Bvh;
+use crate::structures::ray::Ray;
+use crate::structures::scene::{ Scene, Handle };
+
+// one bvh over the boxes of everything in a scene, traced and marched
+// together, so a ray only looks at the objects near it instead of going
+// down both lists, see cast_ray. objects without bounds, like planes, are
+// kept aside and always looked at. it's a cache like the caustics: it only
+// knows where things were when it was built, so build it again after
+// moving or swapping objects. the scene drops it when it's edited through
+// add, remove, replace or get_mut, and one built for lists of other
+// lengths isn't used at all.
+#[derive(Debug, Clone)]
+pub struct TopLevel {
+    bvh: Bvh,
+    handles: Vec<Handle>, // what the bvh's indices point at
+    march: Vec<usize>, // unbounded, in the scene's lists
+    trace: Vec<usize>,
+    lengths: [usize; 2], // of the march and trace lists it was built for
+}
+
+impl TopLevel {
+    pub fn new(scene: &Scene) -> TopLevel {
+        let mut handles = vec![];
+        let mut bounds: Vec<Aabb> = vec![];
+        let (mut march, mut trace) = (vec![], vec![]);
+
+        for (index, object) in scene.march.iter().enumerate() {
+            match object.bounds() {
+                Some(boxed) => { handles.push(Handle::March(index)); bounds.push(boxed); },
+                None => march.push(index),
+            }
+        }
+
+        for (index, object) in scene.trace.iter().enumerate() {
+            match object.bounds() {
+                Some(boxed) => { handles.push(Handle::Trace(index)); bounds.push(boxed); },
+                None => trace.push(index),
+            }
+        }
+
+        TopLevel {
+            bvh: Bvh::new(&bounds),
+            handles: handles,
+            march: march,
+            trace: trace,
+            lengths: [scene.march.len(), scene.trace.len()],
+        }
+    }
+
+    // whether it was built for the scene's lists as they are now
+    pub fn fits(&self, scene: &Scene) -> bool {
+        self.lengths == [scene.march.len(), scene.trace.len()]
+    }
+
+    pub fn bvh(&self) -> &Bvh {
+        &self.bvh
+    }
+
+    // how many objects are in the tree and how many are kept aside
+    pub fn counts(&self) -> (usize, usize) {
+        (self.handles.len(), self.march.len() + self.trace.len())
+    }
+
+    // the closest traced object along the ray and how far, `test` hits
+    // one of them the way Bvh::traverse does
+    pub fn trace(&self, ray: &Ray, mut test: impl FnMut(usize) -> Option<Float>) -> Option<(usize, Float)> {
+        let mut best = self.bvh.traverse(ray, |index| match self.handles[index] {
+            Handle::Trace(index) => test(index),
+            Handle::March(_) => None,
+        }).and_then(|(index, distance)| match self.handles[index] {
+            Handle::Trace(index) => Some((index, distance)),
+            Handle::March(_) => None,
+        });
+
+        for index in self.trace.iter() {
+            if let Some(distance) = test(*index) {
+                if best.is_none_or(|(_, closest)| distance < closest) { best = Some((*index, distance)); }
+            }
+        }
+
+        return best;
+    }
+
+    // the smallest of the marched objects' distances at a point, and which
+    // object that is. boxes further off than something already found are
+    // skipped, the surfaces in them can't be any closer.
+    pub fn march(&self, point: &Vec3, mut distance: impl FnMut(usize) -> Float) -> (Float, Option<usize>) {
+        let (mut min, mut closest) = match self.bvh.nearest(point, |index| match self.handles[index] {
+            Handle::March(index) => distance(index),
+            Handle::Trace(_) => Float::MAX,
+        }) {
+            Some((index, d)) => match self.handles[index] {
+                Handle::March(index) => (d, Some(index)),
+                Handle::Trace(_) => (Float::MAX, None),
+            },
+            None => (Float::MAX, None),
+        };
+
+        for index in self.march.iter() {
+            let d = distance(*index);
+            if d <= min {
+                min = d;
+                closest = Some(*index);
+            }
+        }
+
+        return (min, closest);
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::TopLevel;
+    use crate::structures::float::Float;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::ray::Ray;
+    use crate::structures::camera::Camera;
+    use crate::structures::material::Material;
+    use crate::structures::scene::Scene;
+    use crate::objects::sphere::Sphere;
+    use crate::objects::torus::Torus;
+    use crate::objects::plane::Plane;
+    use crate::objects::traits::{ March, Trace };
+
+    #[test]
+    fn test_top_level() {
+        let camera = Camera::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let mut scene = Scene::new(camera);
+
+        // a row of spheres traced and tori marched, over a floor
+        for i in 0..8 {
+            let x = i as Float * 3.0;
+            scene.add_trace(Sphere::new(Vec3::new(x, 0.0, 0.0), 1.0, Material::blank()));
+            scene.add_march(Torus::new(Vec3::new(x, 0.0, -3.0), 1.0, 0.25, Material::blank()));
+        }
+        scene.add_trace(Plane::new(Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Material::blank()));
+
+        let top = TopLevel::new(&scene);
+        assert!(top.fits(&scene));
+        assert_eq!(top.counts(), (16, 1));
+
+        // the same closest hit as going down the whole list
+        let ray = Ray::new(Vec3::new(9.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let test = |index: usize| {
+            let (hit, distance, _) = scene.trace[index].trace(ray);
+            if hit { Some(distance) } else { None }
+        };
+        assert_eq!(top.trace(&ray, test), Some((3, 4.0)));
+
+        // and the floor's found outside the tree
+        let down = Ray::new(Vec3::new(1.5, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let test = |index: usize| {
+            let (hit, distance, _) = scene.trace[index].trace(down);
+            if hit { Some(distance) } else { None }
+        };
+        assert_eq!(top.trace(&down, test), Some((8, 6.0)));
+
+        // the same field everywhere, inside things too
+        for point in [Vec3::new(6.0, 0.0, -2.0), Vec3::new(7.0, 0.0, -3.0), Vec3::new(-10.0, 4.0, 2.0)].iter() {
+            let (min, closest) = top.march(point, |index| scene.march[index].march(*point));
+            let all = scene.march.iter().map(|object| object.march(*point)).fold(Float::MAX, Float::min);
+            assert_eq!(min, all);
+            assert_eq!(scene.march[closest.unwrap()].march(*point), all);
+        }
+
+        scene.add_march(Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0, Material::blank()));
+        assert!(!top.fits(&scene));
+    }
+}
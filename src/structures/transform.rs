@@ -0,0 +1,267 @@
+use std::ops::Mul;
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::ray::Ray;
+use crate::structures::aabb::Aabb;
+
+type Matrix = [[Float; 4]; 4];
+
+const IDENTITY: Matrix = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+// an affine transformation, stored alongside its inverse.
+// matrices are row major, and `a * b` applies `b` first.
+#[derive(Debug, Copy, Clone)]
+pub struct Transform {
+    pub matrix: Matrix,
+    pub inverse: Matrix,
+}
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut out = [[0.0; 4]; 4];
+
+    for (row, out_row) in out.iter_mut().enumerate() {
+        for (col, cell) in out_row.iter_mut().enumerate() {
+            *cell = (0..4).map(|k| a[row][k] * b[k][col]).sum();
+        }
+    }
+
+    return out;
+}
+
+fn finite(matrix: &Matrix) -> bool {
+    matrix.iter().flatten().all(|cell| cell.is_finite())
+}
+
+// the transpose of the top left 3x3, which is the inverse of a rotation
+fn transpose(matrix: &Matrix) -> Matrix {
+    let mut transposed = *matrix;
+    for (row, transposed_row) in transposed.iter_mut().enumerate().take(3) {
+        for (col, cell) in transposed_row.iter_mut().enumerate().take(3) {
+            *cell = matrix[col][row];
+        }
+    }
+    return transposed;
+}
+
+// gauss-jordan elimination. None for singular matrices, and for ones with
+// a nan or infinity in them, which have no sensible inverse either.
+fn invert(matrix: &Matrix) -> Option<Matrix> {
+    if !finite(matrix) { return None; }
+
+    let mut m = *matrix;
+    let mut inverse = IDENTITY;
+
+    for col in 0..4 {
+        let pivot = (col..4).max_by(|a, b| m[*a][col].abs().total_cmp(&m[*b][col].abs()))?;
+        if m[pivot][col].abs() < 1e-12 { return None; }
+
+        m.swap(col, pivot);
+        inverse.swap(col, pivot);
+
+        let scale = 1.0 / m[col][col];
+        for k in 0..4 {
+            m[col][k] *= scale;
+            inverse[col][k] *= scale;
+        }
+
+        for row in 0..4 {
+            if row == col { continue; }
+            let factor = m[row][col];
+            for k in 0..4 {
+                m[row][k] -= factor * m[col][k];
+                inverse[row][k] -= factor * inverse[col][k];
+            }
+        }
+    }
+
+    return if finite(&inverse) { Some(inverse) } else { None };
+}
+
+impl Transform {
+    // panics if the matrix has no inverse, see try_new for ones from files
+    pub fn new(matrix: Matrix) -> Transform {
+        Transform::try_new(matrix).expect("the matrix has no inverse")
+    }
+
+    pub fn try_new(matrix: Matrix) -> Option<Transform> {
+        Some(Transform { matrix: matrix, inverse: invert(&matrix)? })
+    }
+
+    pub fn identity() -> Transform {
+        Transform { matrix: IDENTITY, inverse: IDENTITY }
+    }
+
+    pub fn translate(offset: Vec3) -> Transform {
+        Transform {
+            matrix: [
+                [1.0, 0.0, 0.0, offset.x],
+                [0.0, 1.0, 0.0, offset.y],
+                [0.0, 0.0, 1.0, offset.z],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+            inverse: [
+                [1.0, 0.0, 0.0, -offset.x],
+                [0.0, 1.0, 0.0, -offset.y],
+                [0.0, 0.0, 1.0, -offset.z],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    // a zero factor has no inverse, and leaves it infinite, see is_finite
+    pub fn scale(factor: Vec3) -> Transform {
+        Transform {
+            matrix: [
+                [factor.x, 0.0, 0.0, 0.0],
+                [0.0, factor.y, 0.0, 0.0],
+                [0.0, 0.0, factor.z, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+            inverse: [
+                [1.0 / factor.x, 0.0, 0.0, 0.0],
+                [0.0, 1.0 / factor.y, 0.0, 0.0],
+                [0.0, 0.0, 1.0 / factor.z, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    // rotation around an axis, in degrees
+    pub fn rotate(axis: Vec3, degrees: Float) -> Transform {
+        let a = axis.unit();
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let t = 1.0 - cos;
+
+        let matrix = [
+            [t * a.x * a.x + cos,       t * a.x * a.y - sin * a.z, t * a.x * a.z + sin * a.y, 0.0],
+            [t * a.x * a.y + sin * a.z, t * a.y * a.y + cos,       t * a.y * a.z - sin * a.x, 0.0],
+            [t * a.x * a.z - sin * a.y, t * a.y * a.z + sin * a.x, t * a.z * a.z + cos,       0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ];
+
+        // rotations are orthonormal, so the inverse is the transpose
+        Transform { matrix: matrix, inverse: transpose(&matrix) }
+    }
+
+    // unit quaternion as [x, y, z, w], a rotation like the one above
+    pub fn quaternion(q: [Float; 4]) -> Transform {
+        let [x, y, z, w] = q;
+
+        let matrix = [
+            [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - z * w),       2.0 * (x * z + y * w),       0.0],
+            [2.0 * (x * y + z * w),       1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - x * w),       0.0],
+            [2.0 * (x * z - y * w),       2.0 * (y * z + x * w),       1.0 - 2.0 * (x * x + y * y), 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ];
+
+        Transform { matrix: matrix, inverse: transpose(&matrix) }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.matrix == IDENTITY
+    }
+
+    // whether it and its inverse are all numbers. built from a nan, a zero
+    // scale or a rotation about nothing they aren't, and anything moved by
+    // it would be lost.
+    pub fn is_finite(&self) -> bool {
+        finite(&self.matrix) && finite(&self.inverse)
+    }
+
+    pub fn inverted(&self) -> Transform {
+        Transform { matrix: self.inverse, inverse: self.matrix }
+    }
+
+    pub fn point(&self, p: Vec3) -> Vec3 {
+        let m = &self.matrix;
+        Vec3::new(
+            m[0][0] * p.x + m[0][1] * p.y + m[0][2] * p.z + m[0][3],
+            m[1][0] * p.x + m[1][1] * p.y + m[1][2] * p.z + m[1][3],
+            m[2][0] * p.x + m[2][1] * p.y + m[2][2] * p.z + m[2][3],
+        )
+    }
+
+    pub fn vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.matrix;
+        Vec3::new(
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+        )
+    }
+
+    // normals transform by the inverse transpose
+    pub fn normal(&self, n: Vec3) -> Vec3 {
+        let m = &self.inverse;
+        Vec3::new(
+            m[0][0] * n.x + m[1][0] * n.y + m[2][0] * n.z,
+            m[0][1] * n.x + m[1][1] * n.y + m[2][1] * n.z,
+            m[0][2] * n.x + m[1][2] * n.y + m[2][2] * n.z,
+        ).unit()
+    }
+
+    // the box around a box once it's moved, from its eight corners
+    pub fn aabb(&self, bounds: &Aabb) -> Aabb {
+        let corners: Vec<Vec3> = (0..8).map(|i| {
+            let pick = |bit: usize, axis: usize| if i & bit == 0 { bounds.min.axis(axis) } else { bounds.max.axis(axis) };
+            self.point(Vec3::new(pick(1, 0), pick(2, 1), pick(4, 2)))
+        }).collect();
+
+        return Aabb::around(&corners);
+    }
+
+    // the direction is left unnormalized so distances, and the ray's range
+    // with them, carry over between spaces
+    pub fn ray(&self, ray: Ray) -> Ray {
+        Ray::new(self.point(ray.origin), self.vector(ray.direction)).with_range(ray.t_min, ray.t_max)
+    }
+}
+
+impl Mul<Transform> for Transform {
+    type Output = Transform;
+
+    fn mul(self, other: Transform) -> Transform {
+        Transform {
+            matrix: multiply(&self.matrix, &other.matrix),
+            inverse: multiply(&other.inverse, &self.inverse),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::Transform;
+    use crate::structures::float::Float;
+    use crate::structures::vec3::Vec3;
+
+    #[test]
+    fn test_inverse() {
+        let matrix = [
+            [2.0, 0.0, 0.0, 1.0],
+            [0.0, 0.0, -1.0, 2.0],
+            [0.0, 3.0, 0.0, 3.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ];
+        let transform = Transform::try_new(matrix).unwrap();
+        let point = Vec3::new(0.5, -1.0, 2.0);
+        assert!((transform.inverted().point(transform.point(point)) - point).length() < 1e-9);
+
+        // flat or broken matrices have none, instead of a made up one
+        let mut flat = matrix;
+        flat[1] = [0.0; 4];
+        assert!(Transform::try_new(flat).is_none());
+        let mut broken = matrix;
+        broken[0][0] = Float::NAN;
+        assert!(Transform::try_new(broken).is_none());
+
+        assert!(!Transform::scale(Vec3::new(0.0, 1.0, 1.0)).is_finite());
+        assert!(!Transform::rotate(Vec3::new(0.0, 0.0, 0.0), 90.0).is_finite());
+        assert!(Transform::rotate(Vec3::new(0.0, 1.0, 0.0), 90.0).is_finite());
+    }
+}
@@ -0,0 +1,223 @@
+use std::fmt;
+
+use crate::error::{ self, Error };
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::material::Material;
+use crate::structures::scene::Scene;
+use crate::render::Integrator;
+use crate::objects::primitive::{ MarchPrimitive, TracePrimitive };
+use crate::objects::traits::{ March, Trace };
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Severity {
+    Warning, // renders, but probably not what was meant
+    Error,   // renders black, or NaN
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity { Severity::Warning => "warning", Severity::Error => "error" };
+        write!(f, "{}: {}", severity, self.message)
+    }
+}
+
+fn finite(v: Vec3) -> bool {
+    v.x.is_finite() && v.y.is_finite() && v.z.is_finite()
+}
+
+fn material_finite(material: &Material) -> bool {
+    finite(material.color) && [
+        material.emission, material.metallic, material.specular,
+        material.roughness, material.transmission, material.ior,
+    ].iter().all(|value| value.is_finite())
+}
+
+// the points and sizes that pin a built in shape down: points have to be
+// finite, sizes finite and above zero
+fn march_measures(shape: &MarchPrimitive) -> (Vec<Vec3>, Vec<Float>) {
+    match shape {
+        MarchPrimitive::Sphere(s) => (vec![s.position], vec![s.radius]),
+        MarchPrimitive::Plane(s) => (vec![s.position, s.normal], vec![s.normal.length()]),
+        MarchPrimitive::Cuboid(s) => (vec![s.position], vec![s.size.x, s.size.y, s.size.z]),
+        MarchPrimitive::Torus(s) => (vec![s.position], vec![s.major, s.minor]),
+        MarchPrimitive::Cylinder(s) => (vec![s.position], vec![s.radius, s.height]),
+        MarchPrimitive::Capsule(s) => (vec![s.start, s.end], vec![s.radius]),
+        MarchPrimitive::Cone(s) => (vec![s.position], vec![s.radius, s.height]),
+        MarchPrimitive::HexPrism(s) => (vec![s.position], vec![s.radius, s.height]),
+        MarchPrimitive::Mandelbulb(s) => (vec![s.position], vec![s.power]),
+        MarchPrimitive::Julia(s) => (vec![s.position], vec![]),
+        MarchPrimitive::Menger(s) => (vec![s.position], vec![s.size]),
+    }
+}
+
+fn trace_measures(shape: &TracePrimitive) -> (Vec<Vec3>, Vec<Float>) {
+    match shape {
+        TracePrimitive::Sphere(s) => (vec![s.position], vec![s.radius]),
+        TracePrimitive::Plane(s) => (vec![s.position, s.normal], vec![s.normal.length()]),
+        TracePrimitive::Cuboid(s) => (vec![s.position], vec![s.size.x, s.size.y, s.size.z]),
+        TracePrimitive::Disk(s) => (vec![s.position, s.normal], vec![s.radius, s.normal.length()]),
+        TracePrimitive::Quad(s) => (vec![s.corner, s.u, s.v], vec![s.u.cross(&s.v).length()]),
+        TracePrimitive::Triangle(s) => (vec![s.a, s.b, s.c], vec![(s.b - s.a).cross(&(s.c - s.a)).length()]),
+    }
+}
+
+impl Scene {
+    // looks for the mistakes that make a render come out black, NaN or
+    // empty without saying why. nothing is fixed, that's up to the caller.
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = vec![];
+        let mut error = |message: String| diagnostics.push(Diagnostic { severity: Severity::Error, message: message });
+
+        let camera = self.camera;
+        if !finite(camera.ray.origin) || !finite(camera.ray.direction) || !finite(camera.up) {
+            error("the camera isn't finite, is it looking at the point it's standing on?".to_string());
+        } else if camera.up.cross(&camera.ray.direction).length() < 1e-6 {
+            error("the camera's up is along the way it looks, pick another up".to_string());
+        }
+        if !(camera.fov > 0.0 && camera.fov < 180.0) {
+            error(format!("a fov of {} degrees can't be projected, it has to be between 0 and 180", camera.fov));
+        }
+
+        if !finite(self.environment) {
+            error("the environment color isn't finite".to_string());
+        }
+        if let Some(threshold) = self.noise_threshold.filter(|threshold| !(threshold.is_finite() && *threshold > 0.0)) {
+            error(format!("a noise threshold of {} can't be reached, it has to be above 0", threshold));
+        }
+
+        for (index, object) in self.march.iter().enumerate() {
+            let (points, sizes) = object.primitive().map_or((vec![], vec![]), |shape| march_measures(&shape));
+
+            if !points.into_iter().all(finite) {
+                error(format!("marched object {} has a position that isn't finite", index));
+            } else if !object.march(camera.ray.origin).is_finite() {
+                error(format!("marched object {} gives a distance that isn't finite", index));
+            }
+            if !sizes.into_iter().all(|size| size > 0.0) {
+                error(format!("marched object {} has no size, it'll never be hit", index));
+            }
+            if !material_finite(&March::material(object)) {
+                error(format!("marched object {} has a material value that isn't finite", index));
+            }
+        }
+
+        for (index, object) in self.trace.iter().enumerate() {
+            let (points, sizes) = object.primitive().map_or((vec![], vec![]), |shape| trace_measures(&shape));
+
+            if !points.into_iter().all(finite) {
+                error(format!("traced object {} has a position that isn't finite", index));
+            }
+            if !sizes.into_iter().all(|size| size > 0.0) {
+                error(format!("traced object {} has no size, it'll never be hit", index));
+            }
+            if !material_finite(&Trace::material(object)) {
+                error(format!("traced object {} has a material value that isn't finite", index));
+            }
+        }
+
+        let mut warning = |message: String| diagnostics.push(Diagnostic { severity: Severity::Warning, message: message });
+
+        if self.march.is_empty() && self.trace.is_empty() && self.volumes.is_empty() {
+            warning("the scene is empty, only the environment will show".to_string());
+        }
+
+        // closed shapes all have a distance field, even the traced ones.
+        // ones the camera can't see don't hide anything.
+        let inside_march = self.march.iter().position(|object| object.visibility().camera && object.march(camera.ray.origin) < 0.0);
+        let inside_trace = self.trace.iter().position(|object| object.visibility().camera && match object.primitive() {
+            Some(TracePrimitive::Sphere(s)) => s.march(camera.ray.origin) < 0.0,
+            Some(TracePrimitive::Cuboid(s)) => s.march(camera.ray.origin) < 0.0,
+            _ => false,
+        });
+        if let Some(index) = inside_march {
+            warning(format!("the camera is inside marched object {}, everything else is hidden", index));
+        }
+        if let Some(index) = inside_trace {
+            warning(format!("the camera is inside traced object {}, everything else is hidden", index));
+        }
+
+        let emissive = self.march.iter().any(|object| March::material(object).emission > 0.0)
+            || self.trace.iter().any(|object| Trace::material(object).emission > 0.0);
+        let sky = self.environment.x > 0.0 || self.environment.y > 0.0 || self.environment.z > 0.0;
+        if !emissive && !sky && self.emitters.is_empty() {
+            warning("nothing gives off light and the environment is black, the render will be too".to_string());
+        }
+
+        if self.integrator == Integrator::Bidirectional && self.emitters.is_empty() {
+            warning("the bidirectional integrator starts from emitters, and there are none".to_string());
+        }
+
+        if self.integrator == Integrator::Sppm && self.emitters.is_empty() {
+            warning("sppm shoots photons from emitters, and there are none".to_string());
+        }
+
+        return diagnostics;
+    }
+
+    // whether it can be rendered at `resolution` at all: there have to be
+    // pixels, and none of validate's errors. the warnings are left to the
+    // caller, see validate.
+    pub fn check(&self, resolution: [usize; 2]) -> error::Result<()> {
+        error::resolution(resolution)?;
+        let errors: Vec<Diagnostic> = self.validate().into_iter().filter(|d| d.severity == Severity::Error).collect();
+        if !errors.is_empty() { return Err(Error::Scene(errors)); }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::Severity;
+    use crate::error::Error;
+    use crate::structures::float::Float;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::camera::Camera;
+    use crate::structures::material::Material;
+    use crate::structures::scene::Scene;
+    use crate::objects::sphere::Sphere;
+    use crate::objects::cuboid::Cuboid;
+
+    #[test]
+    fn test_validate() {
+        let camera = Camera::new(Vec3::new(0.0, 0.0, 4.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let fine = Scene::builder().camera(camera).add(Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0, Material::blank())).build();
+        assert!(fine.validate().is_empty());
+
+        // blank glows like the sky does, so these don't
+        let dark = Material { emission: 0.0, ..Material::blank() };
+        let broken = Material { roughness: Float::NAN, ..dark };
+
+        let scene = Scene::builder()
+            .camera(camera)
+            .environment(Vec3::new(0.0, 0.0, 0.0))
+            .add(Sphere::new(Vec3::new(Float::NAN, 0.0, 0.0), 1.0, dark))
+            .add(Cuboid::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 0.0), broken))
+            .add(Sphere::new(Vec3::new(0.0, 0.0, 4.0), 1.0, dark))
+            .build();
+        let diagnostics = scene.validate();
+        let errors = diagnostics.iter().filter(|d| d.severity == Severity::Error).count();
+
+        // the nan sphere, and the flat cuboid and its material
+        assert_eq!(errors, 3);
+        assert!(diagnostics.iter().any(|d| d.message.contains("inside traced object 2")));
+        assert!(diagnostics.iter().any(|d| d.message.contains("gives off light")));
+
+        assert!(Scene::builder().build().validate().iter().any(|d| d.message.contains("empty")));
+
+        // nothing is ever sure of itself to within 0
+        let never = Scene::builder().camera(camera).noise_threshold(0.0).add(Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0, Material::blank())).build();
+        assert!(never.validate().iter().any(|d| d.severity == Severity::Error && d.message.contains("noise threshold")));
+
+        // rendering it would go wrong, so checking it says so
+        assert!(matches!(scene.check([8, 8]), Err(Error::Scene(errors)) if errors.len() == 3));
+        assert!(matches!(fine.check([8, 0]), Err(Error::Resolution([8, 0]))));
+        assert!(fine.check([8, 8]).is_ok());
+    }
+}
@@ -0,0 +1,157 @@
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::camera::{ Camera, CameraSample };
+use crate::structures::scene::Scene;
+use crate::structures::stats::RenderStats;
+use crate::render::render_aovs;
+
+// frames of an animation built up over each other. every pixel of a new
+// frame finds where what it shows was in the frame before, and if the
+// same surface was there, facing the same way, its color is averaged in
+// with the colors it had before. with a slow camera most pixels carry on
+// from the last frame, so each frame needs far fewer samples for the same
+// noise. where something new comes into view, or the old pixel saw
+// something nearer or further, the history is dropped and it starts over.
+//
+// only the camera's taken to move: lights flickering, or objects moving
+// between frames, leave trails behind them for HISTORY frames.
+
+pub const HISTORY: u32 = 16; // most frames a pixel's color is an average of
+const DEPTH: Float = 0.05; // how far off, relatively, the old pixel's distance can be
+const FACING: Float = 0.9; // and its normal, as a cosine
+
+// what the next frame needs of the last
+#[derive(Debug, Clone)]
+pub struct History {
+    pub camera: Camera,
+    pub resolution: [usize; 2],
+    pub color: Vec<Vec<Vec3>>, // blended, as shown
+    pub depth: Vec<Vec<Float>>, // see render_aovs
+    pub normal: Vec<Vec<Vec3>>,
+    pub frames: Vec<Vec<u32>>, // how many went into each pixel
+}
+
+// the next frame of an animation, rendered with `render` and blended with
+// `previous`, along with what to hand the frame after. without a history,
+// or with one at another resolution, it's just the frame as rendered.
+pub fn render_frame(
+    scene: &Scene,
+    resolution: [usize; 2],
+    previous: Option<&History>,
+    render: impl Fn(&Scene, [usize; 2]) -> (Vec<Vec<Vec3>>, RenderStats),
+) -> (Vec<Vec<Vec3>>, History, RenderStats) {
+    let (mut color, mut stats) = render(scene, resolution);
+    let (depth, normal, aovs) = render_aovs(scene, resolution);
+    stats.merge(&aovs);
+
+    let previous = previous.filter(|history| history.resolution == resolution);
+    let [width, height] = resolution;
+    let mut frames = vec![vec![1; width]; height];
+
+    if let Some(previous) = previous {
+        for y in 0..height {
+            for x in 0..width {
+                let [px, py] = match reproject(scene.camera, previous, [x, y], depth[y][x], normal[y][x]) {
+                    Some(pixel) => pixel,
+                    None => continue,
+                };
+
+                let kept = previous.frames[py][px].min(HISTORY - 1);
+                color[y][x] = (previous.color[py][px] * kept as Float + color[y][x]) / (kept + 1) as Float;
+                frames[y][x] = kept + 1;
+            }
+        }
+    }
+
+    let history = History {
+        camera: scene.camera,
+        resolution: resolution,
+        color: color.clone(),
+        depth: depth,
+        normal: normal,
+        frames: frames,
+    };
+    return (color, history, stats);
+}
+
+// the pixel of the frame before that saw what `pixel` of this one does, if
+// one did: it has to be on the picture, and at the same distance and
+// facing the same way, or the surface there's another one in front or a
+// different one altogether. sky only lines up with sky.
+fn reproject(camera: Camera, previous: &History, pixel: [usize; 2], depth: Float, normal: Vec3) -> Option<[usize; 2]> {
+    let [width, height] = previous.resolution;
+    let (ray, _) = camera.generate_ray(&CameraSample::center([pixel[0] as Float + 0.5, (height - pixel[1]) as Float + 0.5]), previous.resolution);
+
+    let before = previous.camera.ray.origin;
+    let seen = if depth.is_finite() { ray.point_at(&depth) } else { before + ray.direction };
+    let [sx, sy] = previous.camera.project(seen, previous.resolution)?;
+
+    // rows go down from the top, see render::render_tiles
+    if sx < 0.0 || sy < 1.0 || sx >= width as Float || sy >= height as Float + 1.0 { return None; }
+    let (px, py) = (sx as usize, height - sy as usize);
+
+    let (old_depth, old_normal) = (previous.depth[py][px], previous.normal[py][px]);
+    if !depth.is_finite() || !old_depth.is_finite() {
+        return if depth.is_finite() == old_depth.is_finite() { Some([px, py]) } else { None };
+    }
+
+    let expected = (seen - before).length();
+    if (old_depth - expected).abs() > DEPTH * expected { return None; }
+    if old_normal.dot(&normal) < FACING { return None; }
+
+    return Some([px, py]);
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::{ render_frame, HISTORY };
+    use crate::structures::float::Float;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::stats::RenderStats;
+    use crate::structures::camera::Camera;
+    use crate::structures::material::Material;
+    use crate::structures::scene::Scene;
+    use crate::objects::sphere::Sphere;
+    use crate::objects::plane::Plane;
+
+    #[test]
+    fn test_temporal() {
+        let camera = Camera::new(Vec3::new(0.0, 0.0, 6.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let wall = Material { emission: 0.0, ..Material::blank() };
+        let mut scene = Scene::builder()
+            .camera(camera)
+            .add(Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0, Material::blank()))
+            .add(Plane::new(Vec3::new(0.0, 0.0, -3.0), Vec3::new(0.0, 0.0, 1.0), wall))
+            .build();
+
+        // frames that come out all one color, so the blending's easy to see
+        let flat = |value: Float| move |_: &Scene, resolution: [usize; 2]| {
+            (vec![vec![Vec3::new(value, value, value); resolution[0]]; resolution[1]], RenderStats::default())
+        };
+
+        // standing still, every pixel carries on and it's the average
+        let (first, history, _) = render_frame(&scene, [40, 30], None, flat(1.0));
+        assert!(history.frames.iter().flatten().all(|frames| *frames == 1));
+        assert_eq!(first[15][20], Vec3::new(1.0, 1.0, 1.0));
+
+        let (second, again, _) = render_frame(&scene, [40, 30], Some(&history), flat(3.0));
+        assert!(again.frames.iter().flatten().all(|frames| *frames == 2));
+        assert!(second.iter().flatten().all(|pixel| *pixel == Vec3::new(2.0, 2.0, 2.0)));
+
+        // and only so far back
+        let mut history = again;
+        for _ in 0..HISTORY + 2 { history = render_frame(&scene, [40, 30], Some(&history), flat(1.0)).1; }
+        assert!(history.frames.iter().flatten().all(|frames| *frames == HISTORY));
+
+        // moving over, the wall that was behind the ball starts over, but
+        // most of it carries on
+        scene.camera = Camera::new(Vec3::new(0.3, 0.0, 6.0), Vec3::new(0.3, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let (_, moved, _) = render_frame(&scene, [40, 30], Some(&history), flat(1.0));
+        let fresh = moved.frames.iter().flatten().filter(|frames| **frames == 1).count();
+        assert!(fresh > 0 && fresh < 40 * 30 / 4);
+
+        // a history at another size isn't any use
+        let (_, other, _) = render_frame(&scene, [20, 15], Some(&moved), flat(1.0));
+        assert!(other.frames.iter().flatten().all(|frames| *frames == 1));
+    }
+}
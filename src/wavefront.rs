@@ -0,0 +1,256 @@
+use rand::rngs::ThreadRng;
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread;
+
+use crate::structures::float::Float;
+use crate::structures::vec3::Vec3;
+use crate::structures::ray::Ray;
+use crate::structures::packet::LANES;
+use crate::structures::scene::Scene;
+use crate::structures::cast_result::CastResult;
+use crate::structures::stats::{ RenderStats, Timer };
+use crate::structures::tile::Tile;
+use crate::render::{ self, Integrator, NanGuard, PathState, Estimate, cast_ray, cast_packet, camera_rays, step, SAMPLES };
+use crate::objects::visible::RayKind;
+
+// the path tracer again, a wave of pixels at a time instead of a path at a
+// time. every camera ray of the wave is made first, then they're all cast,
+// then every path is shaded, then all the rays they bounce into are cast,
+// and so on until the last path's done. each stage runs the same code over
+// a long queue, so a stage's code and the scene it looks at stay in cache,
+// and the rays come in batches ready for packets or a gpu to take. making
+// paths wait for each other costs memory, so the waves are kept small.
+//
+// it adds up to what render::render_image does, with the same samples,
+// nan guard and noise threshold, only not the same noise. it's path
+// tracing only, the other integrators, and any custom one, go through
+// render_image as usual.
+
+const WAVE: usize = 16; // pixels along each side of a wave
+
+// a path waiting for its ray to be cast, or for what it hit to be shaded
+#[derive(Debug, Copy, Clone)]
+struct Path {
+    pixel: usize, // in the wave
+    camera: Ray,  // it started along, for NanGuard::Log
+    weight: Vec3, // the camera ray's, see camera_ray
+    state: PathState,
+    cast: CastResult, // what state.ray hit
+    radiance: Vec3,   // made it back so far
+    going: bool,
+}
+
+pub fn render_image(scene: &Scene, resolution: [usize; 2]) -> (Vec<Vec<Vec3>>, RenderStats) {
+    let (image, _, stats) = render_sample_counts(scene, resolution);
+    return (image, stats);
+}
+
+// render_image, and how many samples each pixel took, like
+// render::render_sample_counts
+pub fn render_sample_counts(scene: &Scene, resolution: [usize; 2]) -> (Vec<Vec<Vec3>>, Vec<Vec<u32>>, RenderStats) {
+    if scene.integrator != Integrator::Path || scene.custom_integrator.is_some() { return render::render_sample_counts(scene, resolution); }
+
+    let start = Timer::start();
+    let mut image = vec![vec![Vec3::new(0.0, 0.0, 0.0); resolution[0]]; resolution[1]];
+    let mut counts = vec![vec![0; resolution[0]]; resolution[1]];
+    let mut stats = RenderStats::default();
+    let mut times = vec![];
+
+    let waves: Vec<Tile> = Tile::spiral(resolution, WAVE).iter()
+        .filter_map(|tile| match scene.region { Some(region) => tile.intersect(&region), None => Some(*tile) })
+        .collect();
+
+    for (index, wave) in waves.iter().enumerate() {
+        let timer = Timer::start();
+        if let Some(progress) = &scene.progress { progress(index + 1, waves.len()); }
+
+        let pixels: Vec<[usize; 2]> = (wave.y..wave.y + wave.height)
+            .flat_map(|y| (wave.x..wave.x + wave.width).map(move |x| [x, y]))
+            .collect();
+
+        for (pixel, estimate) in pixels.iter().zip(render_wave(scene, resolution, &pixels, &mut stats)) {
+            image[pixel[1]][pixel[0]] = estimate.value(scene.nan_guard);
+            counts[pixel[1]][pixel[0]] = estimate.kept;
+        }
+
+        times.push(timer.elapsed());
+    }
+
+    stats.tiles = times;
+    stats.total = start.elapsed();
+    return (image, counts, stats);
+}
+
+// every path through `pixels`, stage by stage, see the top
+fn render_wave(scene: &Scene, resolution: [usize; 2], pixels: &[[usize; 2]], stats: &mut RenderStats) -> Vec<Estimate> {
+    // generate: the jittered camera rays through every pixel
+    let mut generated: Vec<(usize, Vec<(Ray, Vec3)>)> = (0..pixels.len()).map(|pixel| (pixel, vec![])).collect();
+    stats.merge(&parallel(&mut generated, |items, rng| {
+        for (pixel, rays) in items.iter_mut() {
+            let [x, y] = pixels[*pixel];
+            *rays = camera_rays(scene, [x as Float, (resolution[1] - y) as Float], resolution, rng);
+        }
+    }));
+
+    // with a noise threshold they go a quarter to begin with, then a packet
+    // at a time, and pixels sure enough of themselves take no more, just as
+    // they stop in render::render_counted
+    let rays = scene.samples.max(1) as usize;
+    let mut film = vec![Estimate::new(); pixels.len()];
+    let mut first = 0;
+    let mut end = match scene.noise_threshold {
+        Some(_) => rays.div_ceil(4).next_multiple_of(LANES).min(rays),
+        None => rays,
+    };
+
+    while first < rays {
+        let paths: Vec<Path> = generated.iter()
+            .filter(|(pixel, _)| !scene.noise_threshold.is_some_and(|threshold| film[*pixel].converged(threshold)))
+            .flat_map(|(pixel, rays)| rays[first..end].iter().map(move |(ray, weight)| Path {
+                pixel: *pixel,
+                camera: *ray,
+                weight: *weight,
+                state: PathState::new(*ray),
+                cast: CastResult::worst(),
+                radiance: Vec3::new(0.0, 0.0, 0.0),
+                going: true,
+            }))
+            .collect();
+
+        if paths.is_empty() { break; }
+        follow(scene, resolution, pixels, paths, &mut film, stats);
+        first = end;
+        end = (end + LANES).min(rays);
+    }
+
+    return film;
+}
+
+// `paths` through `pixels` to their ends, into `film`
+fn follow(scene: &Scene, resolution: [usize; 2], pixels: &[[usize; 2]], mut paths: Vec<Path>, film: &mut [Estimate], stats: &mut RenderStats) {
+    // intersect the camera rays, a packet at a time if the scene wants. the
+    // first hit's the same for every path through a camera ray, so it's
+    // only cast once before they're split off.
+    stats.merge(&parallel(&mut paths, |items, _| {
+        for chunk in items.chunks_mut(LANES) {
+            let rays: Vec<Ray> = chunk.iter().map(|path| path.state.ray).collect();
+            let casts = if scene.packets {
+                cast_packet(scene, &rays, RayKind::Camera)
+            } else {
+                rays.iter().map(|ray| cast_ray(scene, *ray, RayKind::Camera)).collect()
+            };
+
+            for (path, cast) in chunk.iter_mut().zip(casts) { path.cast = cast; }
+        }
+    }));
+
+    let mut paths: Vec<Path> = paths.into_iter().flat_map(|path| std::iter::repeat_n(path, SAMPLES as usize)).collect();
+
+    while !paths.is_empty() {
+        // shade: light from what they hit, and which way they go on
+        stats.merge(&parallel(&mut paths, |items, rng| {
+            for path in items.iter_mut() {
+                let mut radiance = path.radiance;
+                path.going = step(scene, &mut path.state, path.cast, scene.bounces, rng, &mut |_, light| radiance = radiance + light);
+                path.radiance = radiance;
+            }
+        }));
+
+        // the finished paths go into their pixels
+        for path in paths.iter().filter(|path| !path.going) {
+            let sample = path.weight * path.radiance;
+            if film[path.pixel].add(scene.nan_guard, sample) { continue; }
+
+            if let (NanGuard::Log, Some(told)) = (scene.nan_guard, &scene.bad_samples) {
+                let [x, y] = pixels[path.pixel];
+                told([x as Float, (resolution[1] - y) as Float], sample, path.camera);
+            }
+        }
+        paths.retain(|path| path.going);
+
+        // intersect: where the rest bounced to
+        stats.merge(&parallel(&mut paths, |items, _| {
+            for path in items.iter_mut() {
+                path.cast = cast_ray(scene, path.state.ray, RayKind::Indirect);
+            }
+        }));
+    }
+}
+
+// `work` over the whole queue, split in one piece per core. hands back
+// what was counted doing it.
+fn parallel<T: Send>(items: &mut [T], work: impl Fn(&mut [T], &mut ThreadRng) + Sync) -> RenderStats {
+    let run = |items: &mut [T]| {
+        let mut rng = rand::thread_rng();
+        // anything counted on this thread before now isn't ours
+        RenderStats::collect();
+        work(items, &mut rng);
+        RenderStats::collect()
+    };
+
+    // wasm32 has no threads, so there the one thread does the lot
+    #[cfg(target_arch = "wasm32")]
+    let finished = vec![run(items)];
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let finished: Vec<RenderStats> = thread::scope(|scope| {
+        let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        let size = items.len().div_ceil(threads).max(1);
+        let run = &run;
+        let workers: Vec<_> = items.chunks_mut(size).map(|chunk| scope.spawn(move || run(chunk))).collect();
+        workers.into_iter().map(|worker| worker.join().unwrap()).collect()
+    });
+
+    let mut stats = RenderStats::default();
+    for counted in finished.iter() { stats.merge(counted); }
+    return stats;
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::{ render_image, render_sample_counts };
+    use crate::structures::float::Float;
+    use crate::structures::vec3::Vec3;
+    use crate::structures::material::Material;
+    use crate::structures::scene::Scene;
+    use crate::structures::tile::Tile;
+    use crate::objects::sphere::Sphere;
+    use crate::objects::plane::Plane;
+    use crate::render::{ self, SAMPLES };
+
+    #[test]
+    fn test_wavefront() {
+        let mut floor = Material::blank();
+        floor.emission = 0.0;
+        floor.color = Vec3::new(0.8, 0.8, 0.8);
+        let scene = Scene::builder()
+            .add(Sphere::new(Vec3::new(0.0, 0.0, 0.0), 1.0, floor))
+            .add(Plane::new(Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 1.0, 0.0), floor))
+            .samples(4)
+            .build();
+
+        // the same light as a path at a time, only with different noise
+        let mean = |image: &Vec<Vec<Vec3>>| image.iter().flatten().map(|pixel| pixel.luminance()).sum::<Float>() / 1200.0;
+        let (waves, stats) = render_image(&scene, [40, 30]);
+        let (paths, _) = render::render_image(&scene, [40, 30]);
+        assert!((mean(&waves) - mean(&paths)).abs() < 0.02 * mean(&paths));
+        assert!(stats.rays > 40 * 30 * 4 && stats.tiles.len() == 6);
+
+        // and only inside the region, if there's one
+        let region = Tile { x: 10, y: 5, width: 8, height: 4 };
+        let mut scene = scene;
+        scene.region = Some(region);
+        let (cropped, _) = render_image(&scene, [40, 30]);
+        assert!(cropped[6][12].luminance() > 0.0);
+        assert_eq!(cropped[0][0], Vec3::new(0.0, 0.0, 0.0));
+
+        // with a noise threshold, pixels that only see the sky stop after
+        // the first packet of camera rays that's a quarter of them, the same
+        // as a path at a time
+        let sky = Scene::builder().samples(32).noise_threshold(0.05).build();
+        let (_, counts, _) = render_sample_counts(&sky, [8, 8]);
+        assert!(counts.iter().flatten().all(|count| *count == 8 * SAMPLES));
+        let (_, counts, _) = render::render_sample_counts(&sky, [8, 8]);
+        assert!(counts.iter().flatten().all(|count| *count == 8 * SAMPLES));
+    }
+}
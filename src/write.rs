@@ -1,18 +1,27 @@
-use image::{ ImageBuffer, Rgb, ImageRgb8 };
+use image::{ ImageBuffer, Rgb, Rgba, RgbImage, Rgb32FImage, DynamicImage };
+use std::fs::File;
+use std::io::{ BufWriter, Write };
 use std::path::Path;
 
+use crate::error::{ self, Result };
+use crate::structures::float::Float;
 use crate::structures::vec3::Vec3;
+use crate::structures::matte::{ self, Matte };
+use crate::structures::film::Film;
+use crate::objects::mesh::Mesh;
 
-pub fn png(image: Vec<Vec<Vec3>>, file: String) {
-    let path = Path::new(&file);
+// the width and height of rows of pixels, which can't be none
+fn size<T>(rows: &[Vec<T>]) -> Result<[u32; 2]> {
+    let [width, height] = error::resolution([rows.first().map_or(0, |row| row.len()), rows.len()])?;
+    return Ok([width as u32, height as u32]);
+}
 
-    // TODO: error handling
+pub fn png(image: Vec<Vec<Vec3>>, file: String) -> Result<()> {
+    let path = Path::new(&file);
 
     // new buffer the width and height of the render
-    let mut buffer = ImageBuffer::new(
-        image[0].len() as u32,
-        image.len() as u32,
-    );
+    let [width, height] = size(&image)?;
+    let mut buffer = ImageBuffer::new(width, height);
 
     for (y, row) in image.iter().enumerate() {
         for (x, pixel) in row.iter().enumerate() {
@@ -20,6 +29,143 @@ pub fn png(image: Vec<Vec<Vec3>>, file: String) {
         }
     }
 
-    ImageRgb8(buffer).save(&path);
-    println!("Render saved to {}", path.display())
+    DynamicImage::ImageRgb8(buffer).save(path)?;
+    println!("Render saved to {}", path.display());
+    return Ok(());
+}
+
+// a png with `alpha` as its alpha channel, see render::render_alpha. over a
+// black environment the shadows on catchers come out black, so they can
+// go straight over a photograph.
+pub fn png_alpha(image: Vec<Vec<Vec3>>, alpha: Vec<Vec<Float>>, file: String) -> Result<()> {
+    let path = Path::new(&file);
+
+    let [width, height] = size(&image)?;
+    let buffer = ImageBuffer::from_fn(width, height, |x, y| {
+        let [r, g, b] = image[y as usize][x as usize].colorize();
+        Rgba([r, g, b, (alpha[y as usize][x as usize].clamp(0.0, 1.0) * 255.0).round() as u8])
+    });
+
+    DynamicImage::ImageRgba8(buffer).save(path)?;
+    println!("Render saved to {}", path.display());
+    return Ok(());
+}
+
+// linear radiance as it is, for adding light groups back up in
+// compositing. nothing's tone mapped or clipped.
+pub fn exr(image: Vec<Vec<Vec3>>, file: String) -> Result<()> {
+    let path = Path::new(&file);
+    size(&image)?;
+    Rgb32FImage::from(&Film::from(image)).save(path)?;
+    println!("Render saved to {}", path.display());
+    return Ok(());
+}
+
+// the same render at several exposures, `stops` brighter or darker each,
+// see Film::expose. render.png at -1 and 2 goes to render.ev-1.png and
+// render.ev+2.png, for picking one or merging them back into hdr.
+pub fn bracket(film: &Film, stops: &[Float], file: String) -> Result<()> {
+    let path = Path::new(&file);
+
+    for stop in stops {
+        let exposed = path.with_extension(format!("ev{:+}.png", stop));
+        RgbImage::from(&film.expose(*stop)).save(&exposed)?;
+        println!("Exposure {:+} saved to {}", stop, exposed.display())
+    }
+    return Ok(());
+}
+
+// how many samples each pixel took, see render::render_sample_counts, as
+// a png going from black through red and yellow to white for the most
+// any pixel took. the range gets printed, the picture doesn't say.
+pub fn heatmap(counts: Vec<Vec<u32>>, file: String) -> Result<()> {
+    let path = Path::new(&file);
+    let [width, height] = size(&counts)?;
+    let most = counts.iter().flatten().copied().max().unwrap_or(0).max(1);
+    let fewest = counts.iter().flatten().copied().min().unwrap_or(0);
+
+    let buffer = ImageBuffer::from_fn(width, height, |x, y| {
+        let t = counts[y as usize][x as usize] as Float / most as Float;
+        let channel = |from: Float| ((t * 3.0 - from).clamp(0.0, 1.0) * 255.0).round() as u8;
+        Rgb([channel(0.0), channel(1.0), channel(2.0)])
+    });
+
+    DynamicImage::ImageRgb8(buffer).save(path)?;
+    println!("Sample counts, {} to {}, saved to {}", fewest, most, path.display());
+    return Ok(());
+}
+
+// an id matte as a png with every id in its own color, see Matte::colors,
+// and a json manifest next to it saying which name has which id and color
+pub fn matte(matte: &Matte, file: String) -> Result<()> {
+    let path = Path::new(&file);
+    matte.colors().save(path)?;
+
+    // sorted by name
+    let manifest: serde_json::Map<String, serde_json::Value> = matte.names.iter().map(|(id, name)| {
+        let [r, g, b] = matte::color(*id);
+        let entry = serde_json::json!({ "id": format!("{:08x}", id), "color": format!("#{:02x}{:02x}{:02x}", r, g, b) });
+        (name.clone(), entry)
+    }).collect();
+
+    let json = serde_json::to_string_pretty(&manifest).expect("a map of strings is always json");
+    std::fs::write(path.with_extension("json"), json)?;
+    println!("Matte saved to {}", path.display());
+    return Ok(());
+}
+
+// wavefront obj, with normals when the mesh has them
+pub fn obj(mesh: &Mesh, file: String) -> Result<()> {
+    let path = Path::new(&file);
+    let mut out = BufWriter::new(File::create(path)?);
+    let smooth = !mesh.normals.is_empty();
+
+    let mut write = || -> std::io::Result<()> {
+        for v in &mesh.vertices { writeln!(out, "v {} {} {}", v.x, v.y, v.z)?; }
+        for n in &mesh.normals { writeln!(out, "vn {} {} {}", n.x, n.y, n.z)?; }
+
+        // obj counts from 1
+        for t in &mesh.triangles {
+            let [a, b, c] = [t[0] + 1, t[1] + 1, t[2] + 1];
+            if smooth {
+                writeln!(out, "f {}//{} {}//{} {}//{}", a, a, b, b, c, c)?;
+            } else {
+                writeln!(out, "f {} {} {}", a, b, c)?;
+            }
+        }
+        return out.flush();
+    };
+
+    write()?;
+    println!("Mesh saved to {}", path.display());
+    return Ok(());
+}
+
+// binary stl, which only knows about flat triangles
+pub fn stl(mesh: &Mesh, file: String) -> Result<()> {
+    let path = Path::new(&file);
+    let mut out = BufWriter::new(File::create(path)?);
+
+    let mut write = || -> std::io::Result<()> {
+        out.write_all(&[0u8; 80])?;
+        out.write_all(&(mesh.triangles.len() as u32).to_le_bytes())?;
+
+        for t in &mesh.triangles {
+            let [a, b, c] = [mesh.vertices[t[0]], mesh.vertices[t[1]], mesh.vertices[t[2]]];
+            let normal = (b - a).cross(&(c - a)).unit();
+
+            for v in [normal, a, b, c].iter() {
+                for component in [v.x, v.y, v.z].iter() {
+                    #[allow(clippy::unnecessary_cast)] // not a no-op unless built with f32
+                    out.write_all(&(*component as f32).to_le_bytes())?;
+                }
+            }
+            out.write_all(&[0u8; 2])?;
+        }
+        return out.flush();
+    };
+
+    write()?;
+    println!("Mesh saved to {}", path.display());
+    return Ok(());
 }
@@ -0,0 +1,115 @@
+// explicit returns and field names are the house style
+#![allow(clippy::needless_return, clippy::redundant_field_names)]
+
+use std::path::Path;
+use rand::rngs::ThreadRng;
+use wasm_bindgen::prelude::*;
+
+use keikan::structures::float::Float;
+use keikan::structures::vec3::Vec3;
+use keikan::structures::scene::Scene;
+use keikan::structures::tile::Tile;
+use keikan::import::scene_file;
+use keikan::render::render;
+
+// renders a scene a little at a time into rgba pixels for a canvas. the
+// page calls `step` once a frame and draws `pixels`, so the picture fills
+// in without ever freezing the page: every 8th pixel first, drawn as
+// blocks, then every 4th, 2nd, and the rest.
+
+const LEVELS: [usize; 4] = [8, 4, 2, 1]; // pixel steps, coarse to fine
+const TILE: usize = 32;
+
+#[wasm_bindgen]
+pub struct Renderer {
+    scene: Scene,
+    resolution: [usize; 2],
+    tiles: Vec<Tile>,
+    level: usize, // into LEVELS
+    next: usize,  // into tiles
+    image: Vec<Vec3>,
+    rng: ThreadRng,
+}
+
+#[wasm_bindgen]
+impl Renderer {
+    // `scene` is the text of a scene file, see keikan::import::scene_file
+    #[wasm_bindgen(constructor)]
+    pub fn new(scene: &str, width: usize, height: usize) -> Result<Renderer, JsValue> {
+        let scene = scene_file::parse(scene, Path::new("."))
+            .map_err(|error| JsValue::from_str(&error.to_string()))?;
+        // a scene that would render black or broken is turned down up front
+        let resolution = [width, height];
+        scene.check(resolution).map_err(|error| JsValue::from_str(&error.to_string()))?;
+
+        return Ok(Renderer {
+            scene: scene,
+            resolution: resolution,
+            tiles: Tile::spiral(resolution, TILE),
+            level: 0,
+            next: 0,
+            image: vec![Vec3::new(0.0, 0.0, 0.0); resolution[0] * resolution[1]],
+            rng: rand::thread_rng(),
+        });
+    }
+
+    // renders up to `tiles` more tiles, and says whether there's more to do
+    pub fn step(&mut self, tiles: usize) -> bool {
+        for _ in 0..tiles {
+            if self.done() { return false; }
+
+            let tile = self.tiles[self.next];
+            self.render_tile(tile, LEVELS[self.level], LEVELS.get(self.level.wrapping_sub(1)).copied());
+
+            self.next += 1;
+            if self.next == self.tiles.len() {
+                self.next = 0;
+                self.level += 1;
+            }
+        }
+
+        return !self.done();
+    }
+
+    pub fn done(&self) -> bool {
+        self.level == LEVELS.len()
+    }
+
+    // rows top to bottom, ready for an ImageData
+    pub fn pixels(&self) -> Vec<u8> {
+        let mut rgba = Vec::with_capacity(self.image.len() * 4);
+        for pixel in &self.image {
+            rgba.extend_from_slice(&pixel.colorize());
+            rgba.push(255);
+        }
+        return rgba;
+    }
+
+    pub fn width(&self) -> usize { self.resolution[0] }
+    pub fn height(&self) -> usize { self.resolution[1] }
+}
+
+impl Renderer {
+    // samples the tile every `step` pixels, skipping what a coarser level
+    // already did, and paints each sample over its block
+    fn render_tile(&mut self, tile: Tile, step: usize, coarser: Option<usize>) {
+        let [width, height] = self.resolution;
+        let on = |x: usize, y: usize, step: usize| x.is_multiple_of(step) && y.is_multiple_of(step);
+
+        for y in (tile.y..tile.y + tile.height).filter(|y| y.is_multiple_of(step)) {
+            for x in (tile.x..tile.x + tile.width).filter(|x| x.is_multiple_of(step)) {
+                if !coarser.is_some_and(|coarser| on(x, y, coarser)) {
+                    let uv = [x as Float, (height - y) as Float];
+                    self.image[y * width + x] = render(&self.scene, uv, self.resolution, &mut self.rng);
+                }
+
+                let sample = self.image[y * width + x];
+                for by in y..(y + step).min(height) {
+                    for bx in x..(x + step).min(width) {
+                        self.image[by * width + bx] = sample;
+                    }
+                }
+            }
+        }
+    }
+}